@@ -0,0 +1,4 @@
+-- 再起動ポリシー対応（ポリシー自体は暗号化済み設定に含まれる）
+
+ALTER TABLE tunnels ADD COLUMN restart_count INTEGER NOT NULL DEFAULT 0;  -- 監視による再起動回数
+ALTER TABLE tunnels ADD COLUMN last_exit_code INTEGER DEFAULT NULL;       -- 直前の終了コード（再起動後も保持）
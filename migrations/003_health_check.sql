@@ -0,0 +1,3 @@
+-- gRPC生存確認の結果（unknown, healthy, unhealthy）
+
+ALTER TABLE tunnels ADD COLUMN health TEXT NOT NULL DEFAULT 'unknown';
@@ -0,0 +1,37 @@
+-- 監査ログの改ざん検知（ハッシュチェーン）
+-- entry_hash = SHA-256(prev_hash + 行の内容)。SQLiteにはSHA-256が無いためSqliteRegistryが計算して封印する
+-- 導入前の行はハッシュを持たず、conduit audit verifyでは検証対象外として扱う
+
+ALTER TABLE audit_log ADD COLUMN prev_hash TEXT;
+ALTER TABLE audit_log ADD COLUMN entry_hash TEXT;
+
+-- 封印済みのエントリは変更不可
+CREATE TRIGGER IF NOT EXISTS audit_log_immutable
+BEFORE UPDATE ON audit_log
+FOR EACH ROW
+WHEN OLD.entry_hash IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log entries are immutable');
+END;
+
+-- 削除は保存期間（auto_cleanup_audit_log）を過ぎたエントリのみ
+CREATE TRIGGER IF NOT EXISTS audit_log_retention_only
+BEFORE DELETE ON audit_log
+FOR EACH ROW
+WHEN OLD.timestamp >= unixepoch('now', '-365 days')
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log entries cannot be deleted before retention expires');
+END;
+
+-- トリガーからの挿入はハッシュを計算できずチェーンを壊すため、タイムアウト時の監査記録はやめる
+DROP TRIGGER IF EXISTS session_timeout_check;
+CREATE TRIGGER IF NOT EXISTS session_timeout_check
+AFTER UPDATE OF last_activity ON clients
+FOR EACH ROW
+WHEN NEW.status = 'active'
+AND NEW.last_activity < unixepoch('now', '-' || NEW.session_timeout || ' seconds')
+BEGIN
+    UPDATE clients
+    SET status = 'timeout', disconnected_at = unixepoch('now')
+    WHERE id = NEW.id;
+END;
@@ -0,0 +1,3 @@
+-- 監視による再起動の待機中（別のCLIプロセスからのdownで取り消せるようRegistryに記録する）
+
+ALTER TABLE tunnels ADD COLUMN restart_pending INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,221 @@
+// APIハンドラー
+
+use super::response::{ApiError, ApiResponse, ApiResult};
+use super::server::ApiState;
+use crate::protocol::messages::TargetHealth;
+use crate::router::state::{ClientSummary, ServiceSummary, TrafficStats, TunnelStats};
+use axum::extract::{Path, Query, State};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Pagination {
+    #[serde(default)]
+    offset: usize,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+fn paginate<T>(items: Vec<T>, page: &Pagination) -> Vec<T> {
+    items.into_iter().skip(page.offset).take(page.limit.min(1000)).collect()
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RouterInfo {
+    name: &'static str,
+    version: &'static str,
+    bind_addr: SocketAddr,
+    started_at: DateTime<Utc>,
+    uptime_seconds: i64,
+}
+
+pub(crate) async fn info(State(state): State<ApiState>) -> ApiResponse<RouterInfo> {
+    ApiResponse::ok(RouterInfo {
+        name: crate::NAME,
+        version: crate::VERSION,
+        bind_addr: state.bind_addr,
+        started_at: state.started_at,
+        uptime_seconds: (Utc::now() - state.started_at).num_seconds(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct HealthView {
+    status: &'static str,
+    targets: Vec<TargetHealth>,
+}
+
+pub(crate) async fn health(State(state): State<ApiState>) -> ApiResponse<HealthView> {
+    ApiResponse::ok(HealthView {
+        status: "ok",
+        targets: state.health_monitor.snapshot(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ClientView {
+    #[serde(flatten)]
+    summary: ClientSummary,
+    session_ids: Vec<String>,
+}
+
+// AuthManagerのセッションをClient IDごとにまとめる
+async fn sessions_by_client(state: &ApiState) -> HashMap<String, Vec<String>> {
+    let auth = state.auth_manager.read().await;
+    let mut sessions: HashMap<String, Vec<String>> = HashMap::new();
+    for session in auth.list_sessions() {
+        sessions
+            .entry(session.client_info.client_id.clone())
+            .or_default()
+            .push(session.session_id.clone());
+    }
+    sessions
+}
+
+pub(crate) async fn list_clients(
+    State(state): State<ApiState>,
+    Query(page): Query<Pagination>,
+) -> ApiResponse<Vec<ClientView>> {
+    let mut sessions = sessions_by_client(&state).await;
+    let clients = state
+        .router_state
+        .list_clients()
+        .into_iter()
+        .map(|summary| ClientView {
+            session_ids: sessions.remove(&summary.client_id).unwrap_or_default(),
+            summary,
+        })
+        .collect();
+    ApiResponse::ok(paginate(clients, &page))
+}
+
+pub(crate) async fn get_client(State(state): State<ApiState>, Path(client_id): Path<String>) -> ApiResult<ClientView> {
+    let summary = state
+        .router_state
+        .get_client(&client_id)
+        .ok_or_else(|| ApiError::not_found(format!("Client {} not found", client_id)))?;
+    let session_ids = sessions_by_client(&state).await.remove(&client_id).unwrap_or_default();
+    Ok(ApiResponse::ok(ClientView { summary, session_ids }))
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct DisconnectResult {
+    client_id: String,
+    disconnected: bool,
+    sessions_closed: usize,
+    revoked: bool,
+}
+
+// 切断のみ。鍵は有効なままなのでClientは再接続できる
+pub(crate) async fn disconnect_client(
+    State(state): State<ApiState>,
+    Path(client_id): Path<String>,
+) -> ApiResult<DisconnectResult> {
+    let session_ids = sessions_by_client(&state).await.remove(&client_id).unwrap_or_default();
+    if session_ids.is_empty() && !state.router_state.is_client_connected(&client_id) {
+        return Err(ApiError::not_found(format!("Client {} not found", client_id)));
+    }
+
+    // 切断後に古いセッションで再利用されないようログアウトさせる
+    {
+        let mut auth = state.auth_manager.write().await;
+        for session_id in &session_ids {
+            let _ = auth.logout(session_id);
+        }
+    }
+    let disconnected = state.router_state.disconnect_client(&client_id);
+    info!("Client {} disconnected via management API", client_id);
+
+    Ok(ApiResponse::ok(DisconnectResult {
+        client_id,
+        disconnected,
+        sessions_closed: session_ids.len(),
+        revoked: false,
+    }))
+}
+
+// 認可リストから鍵を外し、接続中なら切断する
+pub(crate) async fn revoke_client(
+    State(state): State<ApiState>,
+    Path(client_id): Path<String>,
+) -> ApiResponse<DisconnectResult> {
+    let sessions_closed = sessions_by_client(&state).await.remove(&client_id).map_or(0, |s| s.len());
+    state.auth_manager.write().await.revoke_client(&client_id);
+    let disconnected = state.router_state.disconnect_client(&client_id);
+    info!("Client {} revoked via management API", client_id);
+
+    ApiResponse::ok(DisconnectResult {
+        client_id,
+        disconnected,
+        sessions_closed,
+        revoked: true,
+    })
+}
+
+pub(crate) async fn list_tunnels(
+    State(state): State<ApiState>,
+    Query(page): Query<Pagination>,
+) -> ApiResponse<Vec<TunnelStats>> {
+    ApiResponse::ok(paginate(state.router_state.list_tunnels(), &page))
+}
+
+// 同じサービス名のトンネル（負荷分散の単位）ごとの集計
+pub(crate) async fn list_services(State(state): State<ApiState>) -> ApiResponse<Vec<ServiceSummary>> {
+    ApiResponse::ok(state.router_state.list_services())
+}
+
+pub(crate) async fn get_tunnel(State(state): State<ApiState>, Path(tunnel_id): Path<String>) -> ApiResult<TunnelStats> {
+    state
+        .router_state
+        .list_tunnels()
+        .into_iter()
+        .find(|t| t.tunnel_id == tunnel_id)
+        .map(ApiResponse::ok)
+        .ok_or_else(|| ApiError::not_found(format!("Tunnel {} not found", tunnel_id)))
+}
+
+pub(crate) async fn traffic_stats(State(state): State<ApiState>) -> ApiResponse<TrafficStats> {
+    ApiResponse::ok(state.router_state.traffic_stats())
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ConnectionStats {
+    active_connections: u64,
+    total_connections: u64,
+    tunnels: Vec<TunnelConnectionStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct TunnelConnectionStats {
+    tunnel_id: String,
+    name: String,
+    active_connections: u64,
+    total_connections: u64,
+}
+
+pub(crate) async fn connection_stats(State(state): State<ApiState>) -> ApiResponse<ConnectionStats> {
+    let tunnels: Vec<TunnelConnectionStats> = state
+        .router_state
+        .list_tunnels()
+        .into_iter()
+        .map(|t| TunnelConnectionStats {
+            tunnel_id: t.tunnel_id,
+            name: t.name,
+            active_connections: t.active_connections,
+            total_connections: t.total_connections,
+        })
+        .collect();
+
+    ApiResponse::ok(ConnectionStats {
+        active_connections: tunnels.iter().map(|t| t.active_connections).sum(),
+        total_connections: tunnels.iter().map(|t| t.total_connections).sum(),
+        tunnels,
+    })
+}
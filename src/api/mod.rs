@@ -0,0 +1,149 @@
+// Router管理用REST API
+//
+// 接続中Client・トンネル別トラフィックの参照と、Clientの切断・鍵失効を提供する。
+// 管理操作を含むため既定では無効で、有効化にはBearerトークンかmTLSのどちらかを必須とする
+
+#[cfg(feature = "api")]
+mod auth;
+#[cfg(feature = "api")]
+mod handlers;
+#[cfg(feature = "api")]
+mod response;
+#[cfg(feature = "api")]
+mod server;
+
+#[cfg(feature = "api")]
+pub use response::ApiResponse;
+#[cfg(feature = "api")]
+pub use server::{build_router, serve, ApiState};
+
+use crate::common::error::{Error, Result};
+use crate::security::TlsConfig;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+// 管理API設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    // 既定はループバックのみ。外部公開する場合は明示的に指定する
+    #[serde(default = "default_bind")]
+    pub bind: SocketAddr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    // 設定ファイルにトークンを直書きしないための代替
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<PathBuf>,
+    // require_client_cert = true でmTLS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+}
+
+fn default_bind() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 9998))
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_bind(),
+            token: None,
+            token_file: None,
+            tls: None,
+        }
+    }
+}
+
+impl ApiConfig {
+    // mTLSでClient証明書を必須にしているか
+    pub fn uses_mtls(&self) -> bool {
+        self.tls.as_ref().is_some_and(|tls| tls.require_client_cert)
+    }
+
+    // トークンの解決（token_fileが指定されていればそちらを優先）
+    pub fn resolve_token(&self) -> Result<Option<String>> {
+        if let Some(ref path) = self.token_file {
+            let token = std::fs::read_to_string(path)
+                .map_err(|e| Error::config(format!("Failed to read API token file {}: {}", path.display(), e)))?;
+            let token = token.trim().to_string();
+            if token.is_empty() {
+                return Err(Error::config(format!("API token file {} is empty", path.display())));
+            }
+            return Ok(Some(token));
+        }
+        Ok(self.token.clone().filter(|t| !t.is_empty()))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        // 認証なしでの公開は許可しない
+        if self.resolve_token()?.is_none() && !self.uses_mtls() {
+            return Err(Error::config(
+                "Management API requires a bearer token (token/token_file) or mTLS (tls.require_client_cert)",
+            ));
+        }
+
+        if let Some(ref tls) = self.tls {
+            if tls.cert_file.is_none() || tls.key_file.is_none() {
+                return Err(Error::config("Management API TLS requires cert_file and key_file"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_disabled_by_default() {
+        let config = ApiConfig::default();
+        assert!(!config.enabled);
+        assert!(config.bind.ip().is_loopback());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_api_requires_authentication() {
+        let mut config = ApiConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        config.token = Some("s3cret".to_string());
+        assert!(config.validate().is_ok());
+
+        config.token = None;
+        config.tls = Some(TlsConfig {
+            cert_file: Some("router.crt".to_string()),
+            key_file: Some("router.key".to_string()),
+            ca_cert_file: Some("clients-ca.crt".to_string()),
+            require_client_cert: true,
+            ..Default::default()
+        });
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_token_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"file-token\n").unwrap();
+
+        let config = ApiConfig {
+            enabled: true,
+            token: Some("inline".to_string()),
+            token_file: Some(file.path().to_path_buf()),
+            ..Default::default()
+        };
+        assert_eq!(config.resolve_token().unwrap().as_deref(), Some("file-token"));
+    }
+}
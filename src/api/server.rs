@@ -0,0 +1,240 @@
+// 管理APIサーバー
+//
+// 平文HTTP（トークン認証）とTLS（mTLS可）の両方に対応する。
+// axum::serveはTLSを扱えないため、TLS時はhyperで接続ごとに処理する
+
+use super::{auth, handlers, ApiConfig};
+use crate::common::error::{Error, Result};
+use crate::router::health::HealthMonitor;
+use crate::router::state::RouterState;
+use crate::security::{AuthManager, TlsServerConfig};
+use axum::routing::{get, post};
+use axum::Router;
+use chrono::{DateTime, Utc};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{Notify, RwLock};
+use tracing::{debug, info, warn};
+
+// ハンドラ間で共有する状態
+#[derive(Clone)]
+pub struct ApiState {
+    pub router_state: Arc<RouterState>,
+    pub auth_manager: Arc<RwLock<AuthManager>>,
+    pub health_monitor: Arc<HealthMonitor>,
+    pub started_at: DateTime<Utc>,
+    pub bind_addr: SocketAddr,
+}
+
+// /api/v1 以下のルーティング
+pub fn build_router(state: ApiState, token: Option<String>) -> Router {
+    let api = Router::new()
+        .route("/info", get(handlers::info))
+        .route("/health", get(handlers::health))
+        .route("/clients", get(handlers::list_clients))
+        .route("/clients/:id", get(handlers::get_client))
+        .route("/clients/:id/disconnect", post(handlers::disconnect_client))
+        .route("/clients/:id/revoke", post(handlers::revoke_client))
+        .route("/tunnels", get(handlers::list_tunnels))
+        .route("/tunnels/:id", get(handlers::get_tunnel))
+        .route("/services", get(handlers::list_services))
+        .route("/stats/traffic", get(handlers::traffic_stats))
+        .route("/stats/connections", get(handlers::connection_stats))
+        .route_layer(axum::middleware::from_fn_with_state(
+            token.map(Arc::new),
+            auth::require_bearer_token,
+        ))
+        .with_state(state);
+
+    Router::new().nest("/api/v1", api)
+}
+
+// 管理APIの待ち受け（shutdownが通知されるまで）
+pub async fn serve(config: &ApiConfig, state: ApiState, shutdown: Arc<Notify>) -> Result<()> {
+    config.validate()?;
+    let app = build_router(state, config.resolve_token()?);
+
+    let listener = TcpListener::bind(config.bind)
+        .await
+        .map_err(|e| Error::network(format!("Failed to bind management API on {}: {}", config.bind, e)))?;
+
+    match config.tls {
+        Some(ref tls) => {
+            let acceptor = TlsServerConfig::new(tls)
+                .map_err(|e| Error::tls(format!("Management API TLS setup failed: {}", e)))?
+                .acceptor();
+            info!(
+                "Management API listening on https://{} (mTLS: {})",
+                config.bind,
+                config.uses_mtls()
+            );
+
+            loop {
+                let (stream, peer) = tokio::select! {
+                    _ = shutdown.notified() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            warn!("Management API accept error: {}", e);
+                            continue;
+                        }
+                    },
+                };
+
+                let acceptor = acceptor.clone();
+                let service = TowerToHyperService::new(app.clone());
+                tokio::spawn(async move {
+                    // Client証明書の検証はハンドシェイク内でrustlsが行う
+                    let stream = match acceptor.accept(stream).await {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            debug!("Management API TLS handshake with {} failed: {}", peer, e);
+                            return;
+                        }
+                    };
+                    if let Err(e) = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        debug!("Management API connection error from {}: {}", peer, e);
+                    }
+                });
+            }
+        }
+        None => {
+            info!("Management API listening on http://{}", config.bind);
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async move { shutdown.notified().await })
+                .await
+                .map_err(|e| Error::network(format!("Management API server error: {}", e)))?;
+        }
+    }
+
+    info!("Management API stopped");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::health::HealthCheckConfig;
+    use crate::security::{KeyManager, KeyRotationConfig};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn test_state(dir: &std::path::Path) -> ApiState {
+        let key_manager = KeyManager::new(dir, KeyRotationConfig::default()).unwrap();
+        ApiState {
+            router_state: Arc::new(RouterState::new()),
+            auth_manager: Arc::new(RwLock::new(AuthManager::new(
+                key_manager,
+                Duration::from_secs(3600),
+                Duration::from_secs(1800),
+            ))),
+            health_monitor: Arc::new(HealthMonitor::new(HealthCheckConfig::default())),
+            started_at: Utc::now(),
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+        }
+    }
+
+    async fn start_server(state: ApiState, token: &str) -> (SocketAddr, Arc<Notify>) {
+        // ポート確保のため一度バインドしてから解放する
+        let addr = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let config = ApiConfig {
+            enabled: true,
+            bind: addr,
+            token: Some(token.to_string()),
+            ..Default::default()
+        };
+        let shutdown = Arc::new(Notify::new());
+        let server_shutdown = Arc::clone(&shutdown);
+        tokio::spawn(async move { serve(&config, state, server_shutdown).await.unwrap() });
+
+        for _ in 0..50 {
+            if tokio::net::TcpStream::connect(addr).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        (addr, shutdown)
+    }
+
+    async fn request(addr: SocketAddr, method: &str, path: &str, token: Option<&str>) -> (u16, serde_json::Value) {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let auth = token.map_or(String::new(), |t| format!("Authorization: Bearer {}\r\n", t));
+        let req = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+            method, path, addr, auth
+        );
+        stream.write_all(req.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let status = response[9..12].parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or("{}");
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, shutdown) = start_server(test_state(dir.path()), "s3cret").await;
+
+        let (status, body) = request(addr, "GET", "/api/v1/info", None).await;
+        assert_eq!(status, 401);
+        assert_eq!(body["success"], false);
+
+        let (status, _) = request(addr, "GET", "/api/v1/info", Some("wrong")).await;
+        assert_eq!(status, 401);
+
+        let (status, body) = request(addr, "GET", "/api/v1/info", Some("s3cret")).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"]["name"], "conduit");
+        assert!(body["request_id"].is_string());
+
+        shutdown.notify_waiters();
+    }
+
+    #[tokio::test]
+    async fn test_clients_and_disconnect() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+        let signal = state
+            .router_state
+            .register_client("client-a", "laptop", "192.168.1.10:50000".parse().unwrap());
+        let counters = state
+            .router_state
+            .register_tunnel("t1", "client-a", "web", "web", &["10.2.0.2:8080".parse().unwrap()])
+            .unwrap();
+        counters.connection_opened();
+        counters.add_bytes_out(512);
+        let (addr, shutdown) = start_server(state, "s3cret").await;
+
+        let (status, body) = request(addr, "GET", "/api/v1/clients", Some("s3cret")).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"][0]["client_id"], "client-a");
+        assert_eq!(body["data"][0]["bytes_out"], 512);
+
+        let (_, body) = request(addr, "GET", "/api/v1/stats/traffic", Some("s3cret")).await;
+        assert_eq!(body["data"]["active_connections"], 1);
+
+        let (_, body) = request(addr, "GET", "/api/v1/services", Some("s3cret")).await;
+        assert_eq!(body["data"][0]["service"], "web");
+        assert_eq!(body["data"][0]["available"], 1);
+
+        let (status, _) = request(addr, "POST", "/api/v1/clients/unknown/disconnect", Some("s3cret")).await;
+        assert_eq!(status, 404);
+
+        let (status, body) = request(addr, "POST", "/api/v1/clients/client-a/revoke", Some("s3cret")).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["revoked"], true);
+        assert_eq!(body["data"]["disconnected"], true);
+        tokio::time::timeout(Duration::from_secs(1), signal.notified()).await.unwrap();
+
+        shutdown.notify_waiters();
+    }
+}
@@ -0,0 +1,208 @@
+// auditコマンドの実装
+// Registryの監査ログ（トンネルの作成・状態変更・拒否したRPCなど）の検索と、ハッシュチェーンによる改ざん検証
+
+use crate::cli::{AuditArgs, AuditCommand};
+use crate::cli::commands::CommandResult;
+use crate::cli::commands::list::format_timestamp;
+use crate::common::error::Error;
+use crate::registry::{ProcessRegistry, models::{AuditLogEntry, AuditQuery, AuditVerifyReport}};
+use comfy_table::{Table, Cell, Color, Attribute};
+use serde_json::json;
+use tracing::debug;
+
+// audit_logのCHECK制約で許可されている操作種別
+const AUDIT_ACTIONS: [&str; 4] = ["CREATE", "UPDATE", "DELETE", "SELECT_SENSITIVE"];
+
+pub async fn execute(args: AuditArgs) -> CommandResult {
+    let registry = ProcessRegistry::new(None).await
+        .map_err(|e| Error::generic(format!("Failed to connect to registry: {}", e)))?;
+
+    match args.command {
+        Some(AuditCommand::Verify { ref anchor, ref format }) => verify(&registry, anchor.as_deref(), format).await,
+        None => query(&registry, &args).await,
+    }
+}
+
+async fn query(registry: &ProcessRegistry, args: &AuditArgs) -> CommandResult {
+    let now = chrono::Utc::now().timestamp();
+    let since = args.since.as_deref()
+        .map(|value| parse_since(value, now))
+        .transpose()
+        .map_err(Error::config)?;
+    let action = args.action.as_deref()
+        .map(|value| {
+            let action = value.to_ascii_uppercase();
+            if AUDIT_ACTIONS.contains(&action.as_str()) {
+                Ok(action)
+            } else {
+                Err(Error::config(format!(
+                    "Unknown audit action '{}' (expected one of: {})",
+                    value,
+                    AUDIT_ACTIONS.join(", ").to_ascii_lowercase()
+                )))
+            }
+        })
+        .transpose()?;
+    let target_id = match args.tunnel {
+        Some(ref tunnel) => Some(resolve_tunnel_id(registry, tunnel).await?),
+        None => None,
+    };
+
+    let query = AuditQuery { since, action, target_id, limit: Some(args.limit) };
+    debug!("Querying audit log: {:?}", query);
+    let entries = registry.list_audit_entries(&query).await
+        .map_err(|e| Error::generic(format!("Failed to query audit log: {}", e)))?;
+
+    match args.format.as_str() {
+        "json" => {
+            let output = json!({ "entries": entries, "total": entries.len() });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        _ => output_table(&entries),
+    }
+    Ok(())
+}
+
+// 削除済みのトンネルも監査ログには残るため、名前で見つからなければIDとして扱う
+async fn resolve_tunnel_id(registry: &ProcessRegistry, tunnel: &str) -> Result<String, Error> {
+    let tunnels = registry.list_all_tunnels().await
+        .map_err(|e| Error::generic(format!("Failed to list tunnels: {}", e)))?;
+    Ok(tunnels.into_iter()
+        .find(|t| t.name == tunnel)
+        .map_or_else(|| tunnel.to_string(), |t| t.id))
+}
+
+fn output_table(entries: &[AuditLogEntry]) {
+    if entries.is_empty() {
+        println!("No audit log entries found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        Cell::new("ID").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("TIME").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("ACTION").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("TARGET").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("USER").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("RESULT").add_attribute(Attribute::Bold).fg(Color::Blue),
+    ]);
+
+    for entry in entries {
+        let target = match entry.target_id {
+            Some(ref id) => format!("{}/{}", entry.target_table, id),
+            None => entry.target_table.clone(),
+        };
+        let result = if entry.success {
+            Cell::new("ok").fg(Color::Green)
+        } else {
+            Cell::new(format!("failed: {}", entry.error_message.as_deref().unwrap_or("-"))).fg(Color::Red)
+        };
+        table.add_row(vec![
+            Cell::new(entry.id),
+            Cell::new(format_timestamp(entry.timestamp)),
+            Cell::new(&entry.action),
+            Cell::new(target),
+            Cell::new(entry.user_context.as_deref().unwrap_or("-")),
+            result,
+        ]);
+    }
+
+    println!("{}", table);
+    println!("\nTotal entries: {}", entries.len());
+}
+
+async fn verify(registry: &ProcessRegistry, anchor: Option<&str>, format: &str) -> CommandResult {
+    let report = registry.verify_audit_chain(anchor).await
+        .map_err(|e| Error::generic(format!("Failed to read audit log: {}", e)))?;
+
+    if format == "json" {
+        let output = json!({ "intact": report.is_intact(), "report": report });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        output_verify_report(&report);
+    }
+
+    // スクリプトから判定できるよう、改ざんを検出したら失敗として終了する
+    if report.is_intact() {
+        Ok(())
+    } else {
+        Err(Error::generic("Audit log verification failed"))
+    }
+}
+
+fn output_verify_report(report: &AuditVerifyReport) {
+    println!("🔐 Audit log hash chain");
+    println!("📜 Entries: {} ({} verified)", report.total_entries, report.verified_entries);
+    if report.unsealed_legacy_entries > 0 {
+        println!("ℹ️  {} entries predate the hash chain and are not covered", report.unsealed_legacy_entries);
+    }
+    if report.starts_after_retention {
+        println!("ℹ️  The chain starts after entries removed by the retention policy");
+    }
+    if let Some(ref head) = report.head_hash {
+        println!("🔗 Head hash: {}", head);
+        println!("   Keep this value elsewhere and pass it as --anchor later to detect rewrites of the whole chain");
+    }
+
+    if report.is_intact() {
+        println!("✅ Audit log is intact");
+        return;
+    }
+    println!("❌ Audit log has been tampered with:");
+    for violation in &report.violations {
+        println!("   - entry #{}: {}", violation.id, violation.reason);
+    }
+    if report.anchor_found == Some(false) {
+        println!("   - anchor hash is no longer part of the chain");
+    }
+}
+
+// --sinceの解釈（相対時間・日付・RFC 3339・UNIX時刻）
+fn parse_since(value: &str, now: i64) -> Result<i64, String> {
+    let value = value.trim();
+    if let Some(seconds) = parse_relative(value) {
+        return Ok(now - seconds);
+    }
+    if let Ok(timestamp) = value.parse::<i64>() {
+        return Ok(timestamp);
+    }
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.timestamp());
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).map_or(0, |dt| dt.and_utc().timestamp()));
+    }
+    Err(format!("Invalid --since value '{}' (use e.g. 30m, 24h, 7d, 2024-01-31 or RFC 3339)", value))
+}
+
+fn parse_relative(value: &str) -> Option<i64> {
+    let unit = value.chars().last()?;
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let amount: i64 = value[..value.len() - 1].parse().ok()?;
+    amount.checked_mul(multiplier).filter(|seconds| *seconds >= 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        let now = 1_700_000_000;
+        assert_eq!(parse_since("30m", now), Ok(now - 1800));
+        assert_eq!(parse_since("7d", now), Ok(now - 7 * 86400));
+        assert_eq!(parse_since("1699990000", now), Ok(1_699_990_000));
+        assert_eq!(parse_since("2024-01-31", now), Ok(1_706_659_200));
+        assert_eq!(parse_since("2024-01-31T09:00:00+09:00", now), Ok(1_706_659_200));
+        assert!(parse_since("yesterday", now).is_err());
+        assert!(parse_since("-5h", now).is_err());
+    }
+}
@@ -45,7 +45,7 @@ async fn validate_config(file: Option<PathBuf>) -> CommandResult {
         Ok(config) => {
             println!("✅ Configuration is valid");
             println!("📊 Found {} tunnel(s) configured", config.tunnels.len());
-            println!("🔗 Router: {}:{}", config.router.host, config.router.port);
+            println!("🔗 Router: {}", config.router_addrs());
             
             for tunnel in &config.tunnels {
                 println!("   - {}: {} -> {}", tunnel.name, tunnel.bind, tunnel.source);
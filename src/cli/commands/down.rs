@@ -4,8 +4,10 @@
 use crate::cli::DownArgs;
 use crate::cli::commands::CommandResult;
 use crate::common::{config::Config, error::Error};
+use crate::cli::commands::kill::format_stop_outcome;
 use crate::registry::ProcessRegistry;
 use dialoguer::Confirm;
+use std::time::Duration;
 use tracing::{debug, info};
 
 pub async fn execute(args: DownArgs) -> CommandResult {
@@ -59,20 +61,21 @@ pub async fn execute(args: DownArgs) -> CommandResult {
         return Ok(());
     }
     
+    let timeout = Duration::from_secs(args.timeout);
     let mut stopped_count = 0;
     let mut error_count = 0;
     
     // 各トンネルを停止
     for tunnel in matching_tunnels {
-        println!("🔄 Stopping tunnel: {}", tunnel.name);
+        println!("🔄 Stopping tunnel: {} (draining up to {}s)", tunnel.name, timeout.as_secs());
         
-        match registry.stop_tunnel(&tunnel.id, false).await {
-            Ok(true) => {
-                println!("✅ Stopped tunnel: {}", tunnel.name);
+        match registry.stop_tunnel(&tunnel.id, false, timeout).await {
+            Ok(Some(outcome)) => {
+                println!("✅ Stopped tunnel: {}{}", tunnel.name, format_stop_outcome(&outcome));
                 info!("Stopped tunnel: {} ({})", tunnel.name, tunnel.id);
                 stopped_count += 1;
             }
-            Ok(false) => {
+            Ok(None) => {
                 println!("⚠️  Tunnel {} was already stopped", tunnel.name);
                 stopped_count += 1;
             }
@@ -105,6 +108,20 @@ pub async fn execute(args: DownArgs) -> CommandResult {
         }
     }
     
+    // 停止済みトンネルの残したソケットの削除
+    match registry.check_socket_consistency().await {
+        Ok(report) if !report.orphaned_sockets.is_empty() => {
+            match registry.remove_orphaned_sockets(&report).await {
+                Ok(removed) => println!("✅ Removed {} orphaned socket(s)", removed),
+                Err(e) => println!("⚠️  Failed to remove orphaned sockets: {}", e),
+            }
+        }
+        Ok(_) => {}
+        Err(e) => {
+            println!("⚠️  Failed to check socket consistency: {}", e);
+        }
+    }
+    
     if error_count > 0 {
         Err(Error::generic("Some tunnels failed to stop"))
     } else {
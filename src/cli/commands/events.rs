@@ -0,0 +1,138 @@
+// eventsコマンドの実装
+// 全Tunnel Processの接続イベント（open/close/error）をUDS経由で購読し、到着順に表示する
+
+use crate::cli::EventsArgs;
+use crate::cli::commands::CommandResult;
+use crate::cli::commands::list::format_timestamp;
+use crate::common::error::Error;
+use crate::ipc::client::ParallelUdsClient;
+use crate::ipc::protocol::ConnectionEvent;
+use crate::registry::{ProcessRegistry, models::TunnelInfo};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::debug;
+
+// 購読開始時の接続待ち。応答しないTunnel Processは見切って他のトンネルの表示を続ける
+const WATCH_CONNECT_TIMEOUT_MS: u64 = 1000;
+
+pub async fn execute(args: EventsArgs) -> CommandResult {
+    debug!("Executing events command");
+
+    let registry = ProcessRegistry::new(None).await
+        .map_err(|e| Error::generic(format!("Failed to connect to registry: {}", e)))?;
+
+    let tunnels: Vec<_> = registry.list_active_tunnels().await
+        .map_err(|e| Error::generic(format!("Failed to list tunnels: {}", e)))?
+        .into_iter()
+        .filter(|t| args.tunnel.is_none() || args.tunnel.as_deref() == Some(t.name.as_str()))
+        .collect();
+
+    if tunnels.is_empty() {
+        println!("No active tunnels found.");
+        return Ok(());
+    }
+
+    if args.format != "json" {
+        println!("📡 Watching connection events from {} tunnel(s). Press Ctrl+C to stop", tunnels.len());
+    }
+    tail_events(&tunnels, args.existing, &args.format).await
+}
+
+// 各トンネルのイベントストリームを合流して表示し続ける（Ctrl+Cまたは全ストリーム終了まで）
+pub(crate) async fn tail_events(tunnels: &[TunnelInfo], include_existing: bool, format: &str) -> CommandResult {
+    let names: HashMap<PathBuf, String> = tunnels.iter()
+        .map(|t| (t.socket_path.clone(), t.name.clone()))
+        .collect();
+    let mut events = ParallelUdsClient::watch_multiple_connections(
+        tunnels.iter().map(|t| t.socket_path.clone()).collect(),
+        include_existing,
+        WATCH_CONNECT_TIMEOUT_MS,
+    );
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+            received = events.recv() => match received {
+                Some((socket_path, result)) => {
+                    let tunnel = names.get(&socket_path).map(String::as_str).unwrap_or("unknown");
+                    match result {
+                        Ok(event) => println!("{}", format_event(tunnel, &event, format)),
+                        // 1つのトンネルが落ちても他のトンネルの表示は続ける
+                        Err(e) => eprintln!("⚠️  Stopped watching tunnel '{}': {}", tunnel, e),
+                    }
+                }
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+fn format_event(tunnel: &str, event: &ConnectionEvent, format: &str) -> String {
+    let connection = event.connection.clone().unwrap_or_default();
+
+    // 1行1イベントのJSON（jq等でそのまま処理できる）
+    if format == "json" {
+        return json!({
+            "timestamp": event.timestamp,
+            "tunnel": tunnel,
+            "tunnel_id": event.tunnel_id,
+            "kind": event.kind,
+            "connection_id": connection.id,
+            "client_addr": connection.client_addr,
+            "target_addr": connection.target_addr,
+            "bytes_sent": connection.bytes_sent,
+            "bytes_received": connection.bytes_received,
+            "error": Some(&event.error).filter(|e| !e.is_empty()),
+        }).to_string();
+    }
+
+    let mut line = format!(
+        "{}  {:<20} {:<5}  {}  {} -> {}  sent={} recv={}",
+        format_timestamp(event.timestamp),
+        tunnel,
+        event.kind,
+        connection.id,
+        connection.client_addr,
+        connection.target_addr,
+        connection.bytes_sent,
+        connection.bytes_received,
+    );
+    if !event.error.is_empty() {
+        line.push_str(&format!("  error={}", event.error));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::protocol::ConnectionInfo;
+
+    #[test]
+    fn test_format_event() {
+        let event = ConnectionEvent {
+            tunnel_id: "web-1".to_string(),
+            kind: "error".to_string(),
+            connection: Some(ConnectionInfo {
+                id: "conn-1".to_string(),
+                client_addr: "127.0.0.1:50000".to_string(),
+                target_addr: "10.2.0.2:8080".to_string(),
+                bytes_sent: 10,
+                bytes_received: 20,
+                ..Default::default()
+            }),
+            error: "reset by peer".to_string(),
+            timestamp: 0,
+        };
+
+        let text = format_event("web", &event, "text");
+        assert!(text.contains("conn-1  127.0.0.1:50000 -> 10.2.0.2:8080  sent=10 recv=20"));
+        assert!(text.ends_with("error=reset by peer"));
+
+        let value: serde_json::Value = serde_json::from_str(&format_event("web", &event, "json")).unwrap();
+        assert_eq!(value["tunnel"], "web");
+        assert_eq!(value["kind"], "error");
+        assert_eq!(value["bytes_received"], 20);
+    }
+}
@@ -4,6 +4,7 @@
 use crate::cli::InitArgs;
 use crate::cli::commands::CommandResult;
 use crate::common::{config::Config, error::Error};
+use crate::common::paths::{create_conduit_dir, migrate_legacy_layout, ConduitPaths};
 use crate::registry::{ProcessRegistry, sqlite::SqliteRegistry};
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
@@ -16,8 +17,11 @@ pub async fn execute(args: InitArgs) -> CommandResult {
     
     info!("Initializing Conduit in directory: {}", work_dir.display());
     
-    // architecture.mdの仕様に基づいて~/.config/conduit/ディレクトリを作成
-    create_conduit_directories(args.force).await?;
+    let paths = ConduitPaths::from_env();
+    for (from, to) in migrate_legacy_layout()? {
+        println!("📦 Migrated {} -> {}", from.display(), to.display());
+    }
+    create_conduit_directories(&paths, args.force).await?;
     
     // プロジェクトディレクトリにkeysディレクトリを作成
     create_directories(&work_dir, args.force)?;
@@ -25,14 +29,15 @@ pub async fn execute(args: InitArgs) -> CommandResult {
     create_sample_config(&work_dir, args.force)?;
     
     // SQLiteデータベースの初期化
-    initialize_sqlite_registry().await?;
+    initialize_sqlite_registry(&paths).await?;
     
     println!("✅ Conduit initialization completed successfully!");
     println!("📁 Working directory: {}", work_dir.display());
     println!("🔑 Keys generated in: {}/keys/", work_dir.display());
     println!("⚙️  Sample configuration created: {}/conduit.toml", work_dir.display());
-    println!("🗃️  SQLite registry initialized: ~/.config/conduit/registry.db");
-    println!("📂 Conduit directories created: ~/.config/conduit/");
+    println!("🗃️  SQLite registry initialized: {}", paths.registry_db().display());
+    println!("📂 Conduit directories created: {}", paths.state_dir.display());
+    println!("🔌 Socket directory: {}", paths.socket_dir.display());
     println!("");
     println!("Next steps:");
     println!("1. Edit conduit.toml to configure your tunnels");
@@ -42,46 +47,19 @@ pub async fn execute(args: InitArgs) -> CommandResult {
     Ok(())
 }
 
-// architecture.mdの仕様に従い~/.config/conduit/ディレクトリ構造を作成
-async fn create_conduit_directories(force: bool) -> CommandResult {
-    let conduit_dir = dirs::home_dir()
-        .ok_or_else(|| Error::config("Could not determine home directory"))?
-        .join(".config")
-        .join("conduit");
-    
-    let sockets_dir = conduit_dir.join("sockets");
-    let tunnels_dir = conduit_dir.join("tunnels");
-    
-    if conduit_dir.exists() && !force {
-        info!("Conduit directories already exist: {}", conduit_dir.display());
-    } else {
-        fs::create_dir_all(&conduit_dir)?;
-        fs::create_dir_all(&sockets_dir)?;
-        fs::create_dir_all(&tunnels_dir)?;
-        
-        info!("Created Conduit directory structure: {}", conduit_dir.display());
-        info!("Created sockets directory: {}", sockets_dir.display());
-        info!("Created tunnels directory: {}", tunnels_dir.display());
-        
-        // セキュリティのためUnixでディレクトリ権限を適切に設定
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            
-            let mut perms = fs::metadata(&conduit_dir)?.permissions();
-            perms.set_mode(0o700); // ユーザーのみアクセス可能
-            fs::set_permissions(&conduit_dir, perms)?;
-            
-            let mut perms = fs::metadata(&sockets_dir)?.permissions();
-            perms.set_mode(0o700);
-            fs::set_permissions(&sockets_dir, perms)?;
-            
-            let mut perms = fs::metadata(&tunnels_dir)?.permissions();
-            perms.set_mode(0o700);
-            fs::set_permissions(&tunnels_dir, perms)?;
-        }
+// Registry・ログ・ソケットの置き場を作成（場所はcommon::pathsで決まる）
+async fn create_conduit_directories(paths: &ConduitPaths, force: bool) -> CommandResult {
+    if paths.state_dir.exists() && paths.socket_dir.exists() && !force {
+        info!("Conduit directories already exist: {}", paths.state_dir.display());
+        return Ok(());
     }
-    
+
+    // セキュリティのためUnixではユーザー（システムスコープではconduitグループ）のみアクセス可能にする
+    for dir in [&paths.state_dir, &paths.socket_dir, &paths.logs_dir] {
+        create_conduit_dir(dir)?;
+        info!("Created Conduit directory: {}", dir.display());
+    }
+
     Ok(())
 }
 
@@ -147,16 +125,11 @@ fn generate_keypair(work_dir: &Path, force: bool) -> CommandResult {
 }
 
 // SQLite Registryの初期化
-async fn initialize_sqlite_registry() -> CommandResult {
-    let db_path = dirs::home_dir()
-        .ok_or_else(|| Error::config("Could not determine home directory"))?
-        .join(".config")
-        .join("conduit")
-        .join("registry.db");
-    
-    match ProcessRegistry::new(Some(db_path.clone())).await {
+async fn initialize_sqlite_registry(paths: &ConduitPaths) -> CommandResult {
+    // 既定の場所で開き、他のコマンドと同じRegistry・ソケットディレクトリを使わせる
+    match ProcessRegistry::new(None).await {
         Ok(_) => {
-            info!("SQLite registry initialized successfully: {}", db_path.display());
+            info!("SQLite registry initialized successfully: {}", paths.registry_db().display());
             Ok(())
         }
         Err(e) => {
@@ -4,29 +4,38 @@
 use crate::cli::KillArgs;
 use crate::cli::commands::CommandResult;
 use crate::common::error::Error;
-use crate::registry::ProcessRegistry;
+use crate::ipc::client::{ParallelUdsClient, UdsGrpcClient};
+use crate::registry::{ProcessRegistry, manager::StopOutcome};
 use dialoguer::Confirm;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing::{debug, info, warn};
 
+// 接続の所属トンネルを探す際、応答しないTunnel Processは短時間で見切る
+const CONNECTION_QUERY_TIMEOUT_MS: u64 = 1000;
+
 pub async fn execute(args: KillArgs) -> CommandResult {
     debug!("Executing kill command");
     
     let registry = ProcessRegistry::new(None).await
         .map_err(|e| Error::generic(&format!("Failed to connect to registry: {}", e)))?;
     
+    let timeout = Duration::from_secs(args.timeout);
     if args.all {
-        kill_all_tunnels(&registry).await
-    } else if let Some(tunnel_name) = args.tunnel {
-        kill_tunnel_by_name(&registry, &tunnel_name).await
+        kill_all_tunnels(&registry, args.force, timeout).await
     } else if let Some(connection_id) = args.connection {
-        kill_connection(&registry, &connection_id).await
+        // --tunnelと併用した場合は検索対象をそのトンネルに絞る
+        kill_connection(&registry, &connection_id, args.tunnel.as_deref()).await
+    } else if let Some(tunnel_name) = args.tunnel {
+        kill_tunnel_by_name(&registry, &tunnel_name, args.force, timeout).await
     } else {
         Err(Error::generic("Please specify --all, --tunnel <name>, or --connection <id>"))
     }
 }
 
 // 全トンネル終了
-async fn kill_all_tunnels(registry: &ProcessRegistry) -> CommandResult {
+async fn kill_all_tunnels(registry: &ProcessRegistry, force: bool, timeout: Duration) -> CommandResult {
     let tunnels = registry.list_active_tunnels().await
         .map_err(|e| Error::generic(&format!("Failed to list tunnels: {}", e)))?;
     
@@ -47,12 +56,16 @@ async fn kill_all_tunnels(registry: &ProcessRegistry) -> CommandResult {
         return Ok(());
     }
     
+    if !force {
+        println!("🔄 Draining connections (timeout: {}s)...", timeout.as_secs());
+    }
+    
     // Process Registryの一括停止機能を使用
-    match registry.stop_all_tunnels(true).await {
+    match registry.stop_all_tunnels(force, timeout).await {
         Ok(stopped_tunnels) => {
             println!("✅ Successfully killed {} tunnel(s)", stopped_tunnels.len());
-            for tunnel_id in stopped_tunnels {
-                println!("  - {}", tunnel_id);
+            for (tunnel_id, outcome) in stopped_tunnels {
+                println!("  - {}{}", tunnel_id, format_stop_outcome(&outcome));
             }
             info!("Killed all tunnels");
         }
@@ -65,7 +78,7 @@ async fn kill_all_tunnels(registry: &ProcessRegistry) -> CommandResult {
 }
 
 // 名前指定でトンネル終了
-async fn kill_tunnel_by_name(registry: &ProcessRegistry, tunnel_name: &str) -> CommandResult {
+async fn kill_tunnel_by_name(registry: &ProcessRegistry, tunnel_name: &str, force: bool, timeout: Duration) -> CommandResult {
     let tunnels = registry.list_active_tunnels().await
         .map_err(|e| Error::generic(&format!("Failed to list tunnels: {}", e)))?;
     
@@ -84,13 +97,18 @@ async fn kill_tunnel_by_name(registry: &ProcessRegistry, tunnel_name: &str) -> C
         return Ok(());
     }
     
-    match registry.stop_tunnel(&tunnel.id, true).await {
-        Ok(true) => {
-            println!("✅ Successfully killed tunnel: {} (PID: {})", tunnel_name,
-                tunnel.pid.map_or("N/A".to_string(), |p| p.to_string()));
+    if !force {
+        println!("🔄 Draining connections of '{}' (timeout: {}s)...", tunnel_name, timeout.as_secs());
+    }
+    
+    match registry.stop_tunnel(&tunnel.id, force, timeout).await {
+        Ok(Some(outcome)) => {
+            println!("✅ Successfully killed tunnel: {} (PID: {}){}", tunnel_name,
+                tunnel.pid.map_or("N/A".to_string(), |p| p.to_string()),
+                format_stop_outcome(&outcome));
             info!("Killed tunnel: {} ({})", tunnel_name, tunnel.id);
         }
-        Ok(false) => {
+        Ok(None) => {
             println!("⚠️  Tunnel '{}' was already stopped", tunnel_name);
         }
         Err(e) => {
@@ -101,11 +119,99 @@ async fn kill_tunnel_by_name(registry: &ProcessRegistry, tunnel_name: &str) -> C
     Ok(())
 }
 
-// 接続ID指定で終了（簡易実装）
-async fn kill_connection(_registry: &ProcessRegistry, connection_id: &str) -> CommandResult {
-    // TODO: 実際の接続管理実装時に詳細化
-    warn!("Connection killing not yet implemented for ID: {}", connection_id);
-    println!("⚠️  Connection killing feature is not yet implemented.");
-    println!("Connection ID: {}", connection_id);
+// 接続ID指定で終了（所属するTunnel Processを探してCloseConnectionを送る）
+async fn kill_connection(registry: &ProcessRegistry, connection_id: &str, tunnel_name: Option<&str>) -> CommandResult {
+    let tunnels: Vec<_> = registry.list_active_tunnels().await
+        .map_err(|e| Error::generic(format!("Failed to list tunnels: {}", e)))?
+        .into_iter()
+        .filter(|t| tunnel_name.is_none() || tunnel_name == Some(t.name.as_str()))
+        .collect();
+    
+    if tunnels.is_empty() {
+        return Err(Error::generic(&match tunnel_name {
+            Some(name) => format!("Tunnel '{}' not found", name),
+            None => "No active tunnels found".to_string(),
+        }));
+    }
+    
+    let names: HashMap<PathBuf, String> = tunnels.iter()
+        .map(|t| (t.socket_path.clone(), t.name.clone()))
+        .collect();
+    let results = ParallelUdsClient::list_multiple_connections(
+        tunnels.into_iter().map(|t| t.socket_path).collect(),
+        CONNECTION_QUERY_TIMEOUT_MS,
+    ).await;
+    
+    // 完全一致を優先し、なければ一意に決まる先頭一致を使う
+    let mut matches = Vec::new();
+    for (socket_path, result) in results {
+        match result {
+            Ok(list) => {
+                for connection in list.connections {
+                    if connection.id.starts_with(connection_id) {
+                        matches.push((socket_path.clone(), connection.id));
+                    }
+                }
+            }
+            Err(e) => debug!("Failed to list connections on {}: {}", socket_path.display(), e),
+        }
+    }
+    if let Some(exact) = matches.iter().position(|(_, id)| id == connection_id) {
+        matches = vec![matches.swap_remove(exact)];
+    }
+    
+    let (socket_path, full_id) = match matches.len() {
+        0 => return Err(Error::generic(format!("Connection '{}' not found", connection_id))),
+        1 => matches.remove(0),
+        _ => {
+            let ids: Vec<_> = matches.iter().map(|(_, id)| id.as_str()).collect();
+            return Err(Error::generic(format!(
+                "Connection ID '{}' is ambiguous: {}", connection_id, ids.join(", ")
+            )));
+        }
+    };
+    let tunnel = names.get(&socket_path).cloned().unwrap_or_default();
+    
+    // 安全性チェック（確認プロンプト）
+    let confirmation = Confirm::new()
+        .with_prompt(format!("Are you sure you want to close connection '{}' on tunnel '{}'?", full_id, tunnel))
+        .default(false)
+        .interact()
+        .map_err(|e| Error::generic(format!("Failed to get user confirmation: {}", e)))?;
+    
+    if !confirmation {
+        println!("Operation cancelled.");
+        return Ok(());
+    }
+    
+    let mut client = UdsGrpcClient::connect(&socket_path).await
+        .map_err(|e| Error::generic(format!("Failed to connect to tunnel '{}': {}", tunnel, e)))?;
+    match client.close_connection(&full_id).await {
+        Ok(_) => {
+            println!("✅ Successfully closed connection: {} (tunnel: {})", full_id, tunnel);
+            info!("Closed connection {} on tunnel {}", full_id, tunnel);
+        }
+        Err(e) => {
+            warn!("Failed to close connection {}: {}", full_id, e);
+            return Err(Error::generic(format!("Failed to close connection '{}': {}", full_id, e)));
+        }
+    }
+    
     Ok(())
-}
\ No newline at end of file
+}
+
+// 停止結果の補足（ドレインした接続数・切断した接続数・送ったシグナル）
+pub(crate) fn format_stop_outcome(outcome: &StopOutcome) -> String {
+    let mut details = Vec::new();
+    if let (Some(drained), Some(closed)) = (outcome.drained, outcome.closed) {
+        details.push(format!("{} connection(s) drained, {} cut", drained, closed));
+    }
+    if let Some(signal) = outcome.escalated {
+        details.push(format!("escalated to {}", signal));
+    }
+    if details.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", details.join("; "))
+    }
+}
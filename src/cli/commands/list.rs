@@ -4,11 +4,17 @@
 use crate::cli::ListArgs;
 use crate::cli::commands::CommandResult;
 use crate::common::error::Error;
-use crate::registry::{ProcessRegistry, models::{TunnelInfo, TunnelStatus}};
+use crate::ipc::client::ParallelUdsClient;
+use crate::ipc::protocol::RouterReconnect;
+use crate::registry::{ProcessRegistry, models::{HealthStatus, TunnelInfo, TunnelStatus}};
 use comfy_table::{Table, Cell, Color, Attribute};
 use serde_json::json;
+use std::collections::HashMap;
 use tracing::debug;
 
+// 一覧表示を待たせないよう、応答しないTunnel Processは短時間で見切る
+const HEALTH_QUERY_TIMEOUT_MS: u64 = 500;
+
 pub async fn execute(args: ListArgs) -> CommandResult {
     debug!("Executing list command with format: {}", args.format);
     
@@ -27,11 +33,20 @@ pub async fn execute(args: ListArgs) -> CommandResult {
     // フィルタリング適用
     let filtered_tunnels = apply_filters(tunnels, &args);
     
+    // トンネル断とターゲット断を区別するため、各Tunnel Processに問い合わせる
+    let health = collect_tunnel_health(&filtered_tunnels).await;
+    
     // 出力形式に応じて表示
     match args.format.as_str() {
-        "json" => output_json(&filtered_tunnels)?,
-        "yaml" => output_yaml(&filtered_tunnels)?,
-        "table" | _ => output_table(&filtered_tunnels, &args)?,
+        "json" => output_json(&filtered_tunnels, &health)?,
+        "yaml" => output_yaml(&filtered_tunnels, &health)?,
+        _ => output_table(&filtered_tunnels, &health, &args)?,
+    }
+    
+    // 一覧の後に接続イベントを追記表示し続ける
+    if args.watch {
+        let format = if args.format == "json" { "json" } else { "text" };
+        crate::cli::commands::events::tail_events(&filtered_tunnels, false, format).await?;
     }
     
     Ok(())
@@ -54,8 +69,107 @@ fn apply_filters(tunnels: Vec<TunnelInfo>, args: &ListArgs) -> Vec<TunnelInfo> {
         .collect()
 }
 
+// トンネルとRouter側ターゲットの疎通状態
+#[derive(Debug, Clone, serde::Serialize)]
+pub(crate) struct TunnelHealth {
+    pub tunnel: &'static str,
+    pub target: String,
+    pub target_error: Option<String>,
+    // Routerへの再接続待ち（例: "next attempt in 42s"）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect: Option<String>,
+}
+
+impl TunnelHealth {
+    fn tunnel_down() -> Self {
+        Self {
+            tunnel: "down",
+            // トンネルが落ちているとRouterからの通知も届かないため判定不能
+            target: "-".to_string(),
+            target_error: None,
+            reconnect: None,
+        }
+    }
+}
+
+// 再接続待ちの表示（次の試行までの残り秒数とサーキットブレーカーの状態）
+fn reconnect_detail(reconnect: &RouterReconnect) -> String {
+    let remaining = (reconnect.next_attempt_at - chrono::Utc::now().timestamp()).max(0);
+    let detail = if reconnect.circuit == "open" {
+        format!("circuit open after {} failures, next attempt in {}s", reconnect.consecutive_failures, remaining)
+    } else {
+        format!("next attempt in {}s", remaining)
+    };
+    if reconnect.last_error.is_empty() {
+        detail
+    } else {
+        format!("{} ({})", detail, reconnect.last_error)
+    }
+}
+
+// 各Tunnel ProcessのgRPC状態からトンネル/ターゲットの疎通状態を集計
+pub(crate) async fn collect_tunnel_health(tunnels: &[TunnelInfo]) -> HashMap<String, TunnelHealth> {
+    let socket_paths = tunnels.iter()
+        .filter(|t| t.status == TunnelStatus::Running)
+        .map(|t| t.socket_path.clone())
+        .collect();
+    
+    let responses: HashMap<_, _> = ParallelUdsClient::get_multiple_status(socket_paths, HEALTH_QUERY_TIMEOUT_MS)
+        .await
+        .into_iter()
+        .collect();
+    
+    tunnels.iter()
+        .map(|tunnel| {
+            let health = match responses.get(&tunnel.socket_path) {
+                // Routerとの接続が切れている間はターゲットの状態も古い
+                Some(Ok(status)) if status.router_reconnect.is_some() => TunnelHealth {
+                    tunnel: "reconnecting",
+                    target: "-".to_string(),
+                    target_error: None,
+                    reconnect: status.router_reconnect.as_ref().map(reconnect_detail),
+                },
+                Some(Ok(status)) => match &status.target_health {
+                    Some(target) => TunnelHealth {
+                        tunnel: "up",
+                        target: target.state.clone(),
+                        target_error: Some(target.error.clone()).filter(|e| !e.is_empty()),
+                        reconnect: None,
+                    },
+                    None => TunnelHealth {
+                        tunnel: "up",
+                        target: "unknown".to_string(),
+                        target_error: None,
+                        reconnect: None,
+                    },
+                },
+                _ => TunnelHealth::tunnel_down(),
+            };
+            (tunnel.id.clone(), health)
+        })
+        .collect()
+}
+
+// 疎通状態に応じた色付きセル
+pub(crate) fn health_cell(state: &str) -> Cell {
+    match state {
+        "up" => Cell::new(state).fg(Color::Green),
+        "down" => Cell::new(state).fg(Color::Red),
+        _ => Cell::new(state).fg(Color::Yellow),
+    }
+}
+
+// 監視による生存確認の結果セル
+fn liveness_cell(health: HealthStatus) -> Cell {
+    match health {
+        HealthStatus::Healthy => Cell::new(health.as_str()).fg(Color::Green),
+        HealthStatus::Unhealthy => Cell::new(health.as_str()).fg(Color::Red),
+        HealthStatus::Unknown => Cell::new(health.as_str()).fg(Color::Yellow),
+    }
+}
+
 // 表形式での出力
-fn output_table(tunnels: &[TunnelInfo], args: &ListArgs) -> CommandResult {
+fn output_table(tunnels: &[TunnelInfo], health: &HashMap<String, TunnelHealth>, args: &ListArgs) -> CommandResult {
     let mut table = Table::new();
     
     // ヘッダー設定
@@ -63,6 +177,9 @@ fn output_table(tunnels: &[TunnelInfo], args: &ListArgs) -> CommandResult {
         table.set_header(vec![
             Cell::new("NAME").add_attribute(Attribute::Bold).fg(Color::Blue),
             Cell::new("STATUS").add_attribute(Attribute::Bold).fg(Color::Blue),
+            Cell::new("HEALTH").add_attribute(Attribute::Bold).fg(Color::Blue),
+            Cell::new("TUNNEL").add_attribute(Attribute::Bold).fg(Color::Blue),
+            Cell::new("TARGET").add_attribute(Attribute::Bold).fg(Color::Blue),
             Cell::new("PID").add_attribute(Attribute::Bold).fg(Color::Blue),
             Cell::new("SOCKET").add_attribute(Attribute::Bold).fg(Color::Blue),
             Cell::new("CREATED").add_attribute(Attribute::Bold).fg(Color::Blue),
@@ -71,6 +188,9 @@ fn output_table(tunnels: &[TunnelInfo], args: &ListArgs) -> CommandResult {
         table.set_header(vec![
             Cell::new("NAME").add_attribute(Attribute::Bold).fg(Color::Blue),
             Cell::new("STATUS").add_attribute(Attribute::Bold).fg(Color::Blue),
+            Cell::new("HEALTH").add_attribute(Attribute::Bold).fg(Color::Blue),
+            Cell::new("TUNNEL").add_attribute(Attribute::Bold).fg(Color::Blue),
+            Cell::new("TARGET").add_attribute(Attribute::Bold).fg(Color::Blue),
             Cell::new("PID").add_attribute(Attribute::Bold).fg(Color::Blue),
             Cell::new("CREATED").add_attribute(Attribute::Bold).fg(Color::Blue),
         ]);
@@ -89,11 +209,15 @@ fn output_table(tunnels: &[TunnelInfo], args: &ListArgs) -> CommandResult {
         
         let created_str = format_timestamp(tunnel.created_at);
         let pid_str = tunnel.pid.map_or("N/A".to_string(), |p| p.to_string());
+        let tunnel_health = health.get(&tunnel.id).cloned().unwrap_or_else(TunnelHealth::tunnel_down);
         
         if args.connections {
             table.add_row(vec![
                 Cell::new(&tunnel.name),
                 status_cell,
+                liveness_cell(tunnel.health),
+                health_cell(tunnel_health.tunnel),
+                health_cell(&tunnel_health.target),
                 Cell::new(&pid_str),
                 Cell::new(&tunnel.socket_path.display().to_string()),
                 Cell::new(&created_str),
@@ -102,6 +226,9 @@ fn output_table(tunnels: &[TunnelInfo], args: &ListArgs) -> CommandResult {
             table.add_row(vec![
                 Cell::new(&tunnel.name),
                 status_cell,
+                liveness_cell(tunnel.health),
+                health_cell(tunnel_health.tunnel),
+                health_cell(&tunnel_health.target),
                 Cell::new(&pid_str),
                 Cell::new(&created_str),
             ]);
@@ -115,9 +242,10 @@ fn output_table(tunnels: &[TunnelInfo], args: &ListArgs) -> CommandResult {
 }
 
 // JSON形式での出力
-fn output_json(tunnels: &[TunnelInfo]) -> CommandResult {
+fn output_json(tunnels: &[TunnelInfo], health: &HashMap<String, TunnelHealth>) -> CommandResult {
     let json_output = json!({
         "tunnels": tunnels,
+        "health": health,
         "total": tunnels.len()
     });
     
@@ -126,13 +254,21 @@ fn output_json(tunnels: &[TunnelInfo]) -> CommandResult {
 }
 
 // YAML形式での出力
-fn output_yaml(tunnels: &[TunnelInfo]) -> CommandResult {
+fn output_yaml(tunnels: &[TunnelInfo], health: &HashMap<String, TunnelHealth>) -> CommandResult {
     println!("tunnels:");
     for tunnel in tunnels {
         println!("  - id: {}", tunnel.id);
         println!("    name: {}", tunnel.name);
         println!("    pid: {}", tunnel.pid.map_or("N/A".to_string(), |p| p.to_string()));
         println!("    status: {}", tunnel.status.as_str());
+        println!("    health: {}", tunnel.health.as_str());
+        if let Some(h) = health.get(&tunnel.id) {
+            println!("    tunnel_health: {}", h.tunnel);
+            println!("    target_health: {}", h.target);
+            if let Some(ref reconnect) = h.reconnect {
+                println!("    reconnect: {}", reconnect);
+            }
+        }
         println!("    socket_path: {}", tunnel.socket_path.display());
         println!("    created_at: {}", tunnel.created_at);
         println!();
@@ -142,7 +278,7 @@ fn output_yaml(tunnels: &[TunnelInfo]) -> CommandResult {
 }
 
 // タイムスタンプを人間が読みやすい形式に変換
-fn format_timestamp(timestamp: i64) -> String {
+pub(crate) fn format_timestamp(timestamp: i64) -> String {
     use chrono::{TimeZone, Utc};
     
     match Utc.timestamp_opt(timestamp, 0) {
@@ -0,0 +1,111 @@
+// logsコマンドの実装
+// Tunnel Processのログファイル（<状態ディレクトリ>/logs/tunnels/<name>.log）の表示と追従
+
+use crate::cli::LogsArgs;
+use crate::cli::commands::CommandResult;
+use crate::common::error::Error;
+use crate::common::logging;
+use crate::registry::ProcessRegistry;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+use tracing::debug;
+
+// -f時のファイル監視間隔
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub async fn execute(args: LogsArgs) -> CommandResult {
+    debug!("Executing logs command for tunnel: {}", args.tunnel);
+
+    let tunnel_name = resolve_tunnel_name(&args.tunnel).await;
+    let log_path = logging::tunnel_log_path(&tunnel_name);
+
+    if !log_path.exists() {
+        return Err(Error::generic(format!(
+            "No logs found for tunnel '{}' ({})",
+            tunnel_name,
+            log_path.display()
+        )));
+    }
+
+    let content = std::fs::read(&log_path)
+        .map_err(|e| Error::generic(format!("Failed to read {}: {}", log_path.display(), e)))?;
+    for line in tail_lines(&String::from_utf8_lossy(&content), args.tail) {
+        println!("{}", line);
+    }
+
+    if args.follow {
+        follow(&log_path, content.len() as u64).await?;
+    }
+
+    Ok(())
+}
+
+// IDまたはID先頭一致でも指定できるようにする。終了済みトンネルのログも見られるよう全件から探す
+async fn resolve_tunnel_name(tunnel: &str) -> String {
+    let tunnels = match ProcessRegistry::new(None).await {
+        Ok(registry) => registry.list_all_tunnels().await.unwrap_or_default(),
+        Err(e) => {
+            debug!("Registry unavailable, using '{}' as tunnel name: {}", tunnel, e);
+            return tunnel.to_string();
+        }
+    };
+
+    tunnels.iter()
+        .find(|t| t.name == tunnel)
+        .or_else(|| tunnels.iter().find(|t| t.id.starts_with(tunnel)))
+        .map(|t| t.name.clone())
+        .unwrap_or_else(|| tunnel.to_string())
+}
+
+fn tail_lines(content: &str, count: usize) -> Vec<&str> {
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(count)..].to_vec()
+}
+
+// 追記分を表示し続ける（Ctrl+Cで終了）
+async fn follow(path: &Path, mut offset: u64) -> CommandResult {
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+            _ = tokio::time::sleep(FOLLOW_POLL_INTERVAL) => {}
+        }
+
+        let len = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            // ローテーション直後は一時的に存在しないことがある
+            Err(_) => continue,
+        };
+        if len < offset {
+            // ローテーションで新しいファイルに切り替わった
+            offset = 0;
+        }
+        if len == offset {
+            continue;
+        }
+
+        let mut file = std::fs::File::open(path)
+            .map_err(|e| Error::generic(format!("Failed to open {}: {}", path.display(), e)))?;
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| Error::generic(format!("Failed to read {}: {}", path.display(), e)))?;
+        let mut appended = Vec::new();
+        file.read_to_end(&mut appended)
+            .map_err(|e| Error::generic(format!("Failed to read {}: {}", path.display(), e)))?;
+
+        offset += appended.len() as u64;
+        print!("{}", String::from_utf8_lossy(&appended));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_lines() {
+        let content = "one\ntwo\nthree\n";
+        assert_eq!(tail_lines(content, 2), vec!["two", "three"]);
+        assert_eq!(tail_lines(content, 10), vec!["one", "two", "three"]);
+        assert!(tail_lines(content, 0).is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+// metricsコマンドの実装
+// 全Tunnel ProcessのメトリクスをUDS経由で集め、Prometheus形式で公開する
+
+use crate::cli::{MetricsArgs, MetricsAction};
+use crate::cli::commands::CommandResult;
+use crate::common::error::Error;
+use std::net::SocketAddr;
+
+pub async fn execute(args: MetricsArgs) -> CommandResult {
+    match args.action {
+        MetricsAction::Serve { bind, path, timeout } => serve(bind, path, timeout).await,
+    }
+}
+
+#[cfg(feature = "metrics")]
+async fn serve(bind: SocketAddr, path: String, timeout_ms: u64) -> CommandResult {
+    use crate::metrics::MetricsConfig;
+    use crate::registry::ProcessRegistry;
+    use std::sync::Arc;
+    use tokio::sync::Notify;
+
+    let config = MetricsConfig { enabled: true, bind, path };
+    config.validate()?;
+
+    let registry = ProcessRegistry::new(None).await
+        .map_err(|e| Error::generic(format!("Failed to connect to registry: {}", e)))?;
+    let source: Arc<dyn crate::metrics::MetricsSource> = Arc::new(aggregator::TunnelAggregator {
+        registry,
+        timeout_ms,
+    });
+
+    println!("📊 Serving tunnel metrics on http://{}{}", config.bind, config.path);
+    println!("Press Ctrl+C to stop");
+
+    let shutdown = Arc::new(Notify::new());
+    let signal_shutdown = Arc::clone(&shutdown);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            signal_shutdown.notify_waiters();
+        }
+    });
+
+    crate::metrics::serve(&config, source, shutdown).await
+}
+
+#[cfg(not(feature = "metrics"))]
+async fn serve(_bind: SocketAddr, _path: String, _timeout_ms: u64) -> CommandResult {
+    Err(Error::config("This build does not include the 'metrics' feature"))
+}
+
+#[cfg(feature = "metrics")]
+mod aggregator {
+    use crate::ipc::client::ParallelUdsClient;
+    use crate::metrics::{MetricsSnapshot, MetricsSource, TunnelSample};
+    use crate::registry::ProcessRegistry;
+    use std::collections::HashMap;
+    use tracing::debug;
+
+    // スクレイプのたびにRegistryから稼働中のトンネルを引き、各UDSへ並列に問い合わせる
+    pub(super) struct TunnelAggregator {
+        pub registry: ProcessRegistry,
+        pub timeout_ms: u64,
+    }
+
+    #[async_trait::async_trait]
+    impl MetricsSource for TunnelAggregator {
+        async fn snapshot(&self) -> MetricsSnapshot {
+            let tunnels = match self.registry.list_active_tunnels().await {
+                Ok(tunnels) => tunnels,
+                Err(e) => {
+                    debug!("Failed to list tunnels for metrics: {}", e);
+                    return MetricsSnapshot::default();
+                }
+            };
+
+            let names: HashMap<_, _> = tunnels
+                .iter()
+                .map(|t| (t.socket_path.clone(), t.name.clone()))
+                .collect();
+            let results = ParallelUdsClient::get_multiple_status(
+                tunnels.into_iter().map(|t| t.socket_path).collect(),
+                self.timeout_ms,
+            ).await;
+
+            let mut samples: Vec<TunnelSample> = results
+                .into_iter()
+                .map(|(socket_path, result)| {
+                    let name = names.get(&socket_path).cloned().unwrap_or_default();
+                    match result.ok().and_then(|status| status.metrics) {
+                        Some(metrics) => metrics.to_sample(name),
+                        // 応答しないプロセスもup=0として出し、アラートで拾えるようにする
+                        None => TunnelSample { tunnel: name, ..Default::default() },
+                    }
+                })
+                .collect();
+            samples.sort_by(|a, b| a.tunnel.cmp(&b.tunnel));
+
+            MetricsSnapshot { tunnels: samples, router: None }
+        }
+    }
+}
@@ -8,10 +8,17 @@ pub mod up;
 pub mod down;
 pub mod router;
 pub mod list;
+pub mod events;
 pub mod kill;
 pub mod status;
+pub mod logs;
 pub mod config;
+pub mod metrics;
+pub mod top;
+pub mod audit;
+pub mod registry;
 pub mod version;
+pub mod tunnel_process;
 
 use crate::common::error::Result;
 
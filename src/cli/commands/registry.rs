@@ -0,0 +1,185 @@
+// registryコマンドの実装
+// Registryデータベースの保守（スキーマのマイグレーション、バンドルへのエクスポート・インポート）
+
+use crate::cli::{RegistryArgs, RegistryAction};
+use crate::cli::commands::CommandResult;
+use crate::common::error::Error;
+use crate::common::paths;
+use crate::registry::bundle::{BundleFormat, RegistryBundle};
+use crate::registry::schema::{self, SchemaMigration};
+use crate::registry::sqlite::SqliteRegistry;
+use crate::security::crypto::Ed25519KeyPair;
+use base64::Engine;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub async fn execute(args: RegistryArgs) -> CommandResult {
+    match args.action {
+        RegistryAction::Migrate { dry_run, db } => migrate(dry_run, db).await,
+        RegistryAction::Export { output, format, recipient, db } => export(&output, format.as_deref(), recipient.as_deref(), db).await,
+        RegistryAction::Import { input, format, identity, db } => import(&input, format.as_deref(), identity.as_deref(), db).await,
+    }
+}
+
+async fn migrate(dry_run: bool, db: Option<PathBuf>) -> CommandResult {
+    let db_path = db.clone().unwrap_or_else(paths::registry_db_path);
+
+    // dry-runでは存在しないRegistryを作らない
+    if dry_run && !db_path.exists() {
+        println!("🗄️  Registry: {}", db_path.display());
+        println!("ℹ️  Registry does not exist yet; it will be created at schema version {}", schema::latest_version());
+        println!("🔍 Dry run: no changes made");
+        return Ok(());
+    }
+
+    // SqliteRegistry::newは暗号化キーの作成などで書き込むため、接続プールだけを開く
+    // dry-runではWALへの切り替えなどでファイルを変えないよう読み取り専用で開く
+    let opened = if dry_run {
+        SqliteRegistry::open_read_only(db).await
+    } else {
+        SqliteRegistry::open(db).await.map(|(pool, db_path, _)| (pool, db_path))
+    };
+    let (pool, db_path) = opened
+        .map_err(|e| Error::generic(format!("Failed to open registry: {}", e)))?;
+    let status = schema::inspect(&pool).await
+        .map_err(|e| Error::generic(format!("{}", e)))?;
+
+    println!("🗄️  Registry: {}", db_path.display());
+    println!("📌 Schema version: {} (this build: {})", format_version(status.current_version), status.latest_version);
+
+    if status.is_up_to_date() {
+        println!("✅ Registry schema is up to date");
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("📋 Pending migrations:");
+        print_migrations(&status.pending);
+        if let Some(version) = status.current_version {
+            println!("💾 A backup would be written to {}", schema::backup_path(&db_path, version).display());
+        }
+        println!("🔍 Dry run: no changes made");
+        return Ok(());
+    }
+
+    let outcome = schema::upgrade(&pool, &db_path).await
+        .map_err(|e| Error::generic(format!("Failed to migrate registry: {}", e)))?;
+    println!("📋 Applied migrations:");
+    print_migrations(&outcome.applied);
+    if let Some(ref backup) = outcome.backup {
+        println!("💾 Backup: {}", backup.display());
+    }
+    println!(
+        "✅ Migrated registry schema from {} to {}",
+        format_version(outcome.from_version),
+        outcome.to_version
+    );
+    Ok(())
+}
+
+async fn export(output: &Path, format: Option<&str>, recipient: Option<&str>, db: Option<PathBuf>) -> CommandResult {
+    let format = bundle_format(output, format)?;
+    let recipient = recipient.map(read_public_key).transpose()?;
+
+    let registry = SqliteRegistry::new(db).await
+        .map_err(|e| Error::generic(format!("Failed to open registry: {}", e)))?;
+    let (bundle, skipped) = registry.export_bundle(recipient.as_deref()).await
+        .map_err(|e| Error::generic(format!("Failed to export registry: {}", e)))?;
+    let text = bundle.serialize_as(format)
+        .map_err(|e| Error::generic(format!("{}", e)))?;
+    write_private_file(output, text.as_bytes())?;
+
+    println!("📦 Exported registry to {}", output.display());
+    println!("   Tunnels: {}, sessions: {}, audit entries: {}",
+             bundle.tunnels.len(), bundle.sessions.len(), bundle.audit_log.len());
+    match bundle.recipient {
+        Some(ref key) => println!("🔐 Tunnel configs are encrypted to {}", key),
+        None => println!("⚠️  Tunnel configs are stored in plain text; keep the bundle private or use --recipient"),
+    }
+    for id in &skipped {
+        println!("⚠️  Skipped tunnel {}: its config could not be decrypted with this registry's key", id);
+    }
+    Ok(())
+}
+
+async fn import(input: &Path, format: Option<&str>, identity: Option<&Path>, db: Option<PathBuf>) -> CommandResult {
+    let format = bundle_format(input, format)?;
+    let text = std::fs::read_to_string(input)
+        .map_err(|e| Error::config(format!("Failed to read bundle '{}': {}", input.display(), e)))?;
+    let bundle = RegistryBundle::parse(&text, format)
+        .map_err(|e| Error::config(format!("{}", e)))?;
+    let identity = identity
+        .map(|path| Ed25519KeyPair::from_file(path)
+            .map_err(|e| Error::config(format!("Failed to load identity '{}': {}", path.display(), e))))
+        .transpose()?;
+
+    let registry = SqliteRegistry::new(db).await
+        .map_err(|e| Error::generic(format!("Failed to open registry: {}", e)))?;
+    let report = registry.import_bundle(&bundle, identity.as_ref()).await
+        .map_err(|e| Error::generic(format!("Failed to import bundle: {}", e)))?;
+
+    println!("✅ Imported {} tunnel(s) from {}", report.imported_tunnels.len(), input.display());
+    for name in &report.imported_tunnels {
+        println!("   - {}", name);
+    }
+    for name in &report.skipped_tunnels {
+        println!("⚠️  Skipped tunnel {}: a tunnel with the same ID already exists", name);
+    }
+    println!("📊 Sessions: {}", report.imported_sessions);
+    if report.restored_audit_entries > 0 {
+        println!("📜 Restored {} audit log entries", report.restored_audit_entries);
+    }
+    if report.skipped_audit_entries > 0 {
+        println!("ℹ️  {} audit log entries were not restored because this registry already has its own audit log",
+                 report.skipped_audit_entries);
+    }
+    Ok(())
+}
+
+fn bundle_format(path: &Path, format: Option<&str>) -> Result<BundleFormat, Error> {
+    match format {
+        Some(format) => format.parse().map_err(Error::config),
+        None => Ok(BundleFormat::from_path(path)),
+    }
+}
+
+// --recipientは公開鍵ファイル（conduit initのclient.pub）かbase64の公開鍵
+fn read_public_key(value: &str) -> Result<Vec<u8>, Error> {
+    let path = Path::new(value);
+    let encoded = if path.is_file() {
+        std::fs::read_to_string(path)
+            .map_err(|e| Error::config(format!("Failed to read public key '{}': {}", value, e)))?
+    } else {
+        value.to_string()
+    };
+    let key = base64::engine::general_purpose::STANDARD.decode(encoded.trim())
+        .map_err(|e| Error::config(format!("Invalid recipient public key: {}", e)))?;
+    if key.len() != ed25519_dalek::PUBLIC_KEY_LENGTH {
+        return Err(Error::config(format!("Invalid recipient public key length: {} bytes", key.len())));
+    }
+    Ok(key)
+}
+
+// バンドルには復号した設定や監査ログが含まれるため所有者のみ読めるように作成する
+fn write_private_file(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    Ok(())
+}
+
+fn print_migrations(migrations: &[SchemaMigration]) {
+    for migration in migrations {
+        println!("   - {:03} {}", migration.version, migration.description);
+    }
+}
+
+fn format_version(version: Option<i64>) -> String {
+    version.map_or_else(|| "none".to_string(), |v| v.to_string())
+}
@@ -1,22 +1,120 @@
 // routerコマンドの実装
+//
+// 設定ファイルとCLI引数からRouterを構成し、シグナルを受けるまでClient接続を受け付ける
 
 use crate::cli::RouterArgs;
 use crate::cli::commands::CommandResult;
 use crate::common::error::Error;
+use crate::common::{logging, paths};
+use crate::router::{Router, RouterConfig};
+use crate::security::{AuthManager, KeyManager, KeyRotationConfig};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
 
+// 認証セッションと発行するトークンの有効期間（Clientと同じ値）
+const SESSION_TIMEOUT: Duration = Duration::from_secs(3600);
+const TOKEN_DURATION: Duration = Duration::from_secs(1800);
+
 pub async fn execute(args: RouterArgs) -> CommandResult {
-    info!("Starting router server on: {}", args.bind);
-    
+    if args.daemon {
+        return spawn_daemon(&args);
+    }
+
+    let config = router_config(&args)?;
+    info!("Starting router server on: {}", config.bind_addr);
+
+    // 鍵はClientと同じく、指定された秘密鍵のディレクトリ（既定は./keys）で管理する
+    let key_dir = config.private_key_path.as_deref()
+        .and_then(Path::parent)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("./keys"));
+    let key_manager = KeyManager::new(key_dir, KeyRotationConfig::default())
+        .map_err(|e| Error::Security(format!("Failed to load keys from {}: {}", key_dir.display(), e)))?;
+    let auth_manager = AuthManager::new(key_manager, SESSION_TIMEOUT, TOKEN_DURATION);
+
     println!("🚀 Starting Conduit Router");
-    println!("🔗 Bind address: {}", args.bind);
-    if let Some(key_path) = &args.key {
-        println!("🔑 Private key: {}", key_path.display());
+    println!("🔗 Bind address: {}", config.bind_addr);
+    if config.api.enabled {
+        println!("🛠️  Management API: {}", config.api.bind);
     }
-    if args.daemon {
-        println!("👻 Running in daemon mode");
+    if config.metrics.enabled {
+        println!("📈 Metrics: http://{}{}", config.metrics.bind, config.metrics.path);
+    }
+
+    let router = Arc::new(Router::new(config, auth_manager));
+    router.start().await?;
+
+    #[cfg(unix)]
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .map_err(|e| Error::generic(format!("Failed to install SIGTERM handler: {}", e)))?;
+    #[cfg(unix)]
+    let terminated = sigterm.recv();
+    #[cfg(not(unix))]
+    let terminated = std::future::pending::<Option<()>>();
+
+    tokio::select! {
+        _ = terminated => info!("Received SIGTERM"),
+        _ = tokio::signal::ctrl_c() => info!("Received Ctrl-C"),
+    }
+
+    println!("🛑 Stopping Conduit Router");
+    router.stop().await
+}
+
+// 設定ファイル（指定があれば）にCLI引数を重ねる
+fn router_config(args: &RouterArgs) -> Result<RouterConfig, Error> {
+    let mut config = match &args.config {
+        Some(path) => RouterConfig::from_file(path)?,
+        None => RouterConfig::default(),
+    };
+    if let Some(bind) = args.bind {
+        config.bind_addr = bind;
+    }
+    if let Some(key) = &args.key {
+        config.private_key_path = Some(key.clone());
+    }
+    config.validate()?;
+    Ok(config)
+}
+
+// 同じ引数で--daemonを除いて起動し直し、端末から切り離す
+fn spawn_daemon(args: &RouterArgs) -> CommandResult {
+    // 起動後に失敗しないよう、設定の誤りはここで返す
+    router_config(args)?;
+
+    let log_path = logging::logs_dir().join("router.log");
+    let log_file = logging::open_tunnel_log(&log_path)?;
+
+    let mut cmd = std::process::Command::new(std::env::current_exe()?);
+    if paths::scope() == paths::Scope::System {
+        cmd.arg("--system");
+    }
+    cmd.arg("router");
+    if let Some(config) = &args.config {
+        cmd.arg("--config").arg(std::fs::canonicalize(config)?);
+    }
+    if let Some(bind) = args.bind {
+        cmd.arg("--bind").arg(bind.to_string());
     }
-    
-    // TODO: TLS 1.3 + Ed25519認証によるRouterサーバー実装
-    Err(Error::generic("Router command not yet implemented"))
-}
\ No newline at end of file
+    if let Some(key) = &args.key {
+        cmd.arg("--key").arg(std::fs::canonicalize(key)?);
+    }
+    cmd.stdin(Stdio::null())
+       .stdout(Stdio::from(log_file.try_clone()?))
+       .stderr(Stdio::from(log_file));
+    // 端末のCtrl-Cが届かないよう別のプロセスグループにする
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let child = cmd.spawn()
+        .map_err(|e| Error::generic(format!("Failed to start router: {}", e)))?;
+    println!("🚀 Started Conduit Router in the background (PID: {})", child.id());
+    println!("  Log: {}", log_path.display());
+    Ok(())
+}
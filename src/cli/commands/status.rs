@@ -3,17 +3,17 @@
 
 use crate::cli::StatusArgs;
 use crate::cli::commands::CommandResult;
+use crate::cli::commands::list::{collect_tunnel_health, health_cell};
 use crate::common::error::Error;
 use crate::registry::{ProcessRegistry, models::TunnelStatus};
 use comfy_table::{Table, Cell, Color, Attribute};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::fs;
 use tracing::debug;
 
 pub async fn execute(args: StatusArgs) -> CommandResult {
     debug!("Executing status command with format: {}", args.format);
     
-    let status = collect_system_status().await?;
+    let status = collect_system_status(args.detailed).await?;
     
     match args.format.as_str() {
         "json" => output_json(&status)?,
@@ -25,7 +25,7 @@ pub async fn execute(args: StatusArgs) -> CommandResult {
 }
 
 // システム全体の状況を収集
-async fn collect_system_status() -> Result<SystemStatus, Error> {
+async fn collect_system_status(detailed: bool) -> Result<SystemStatus, Error> {
     let mut status = SystemStatus::default();
     
     // Process Registry統計取得
@@ -33,11 +33,44 @@ async fn collect_system_status() -> Result<SystemStatus, Error> {
         Ok(registry) => {
             status.registry_status = "Connected".to_string();
             
+            // UDSソケット数とRegistryとの整合性
+            match registry.check_socket_consistency().await {
+                Ok(report) => {
+                    status.uds_sockets = report.sockets as u32;
+                    status.orphaned_sockets = report.orphaned_sockets.iter()
+                        .map(|p| p.display().to_string())
+                        .collect();
+                    status.missing_sockets = report.missing_sockets.into_iter()
+                        .map(|(_, name)| name)
+                        .collect();
+                    status.socket_hash_mismatches = report.hash_mismatches;
+                }
+                Err(e) => debug!("Socket consistency check failed: {}", e),
+            }
+            
             if let Ok(tunnels) = registry.list_active_tunnels().await {
                 status.total_tunnels = tunnels.len() as u32;
                 status.active_tunnels = tunnels.iter()
                     .filter(|t| t.status == TunnelStatus::Running)
                     .count() as u32;
+                
+                if detailed {
+                    let health = collect_tunnel_health(&tunnels).await;
+                    status.tunnels = tunnels.iter()
+                        .map(|t| {
+                            let h = health.get(&t.id);
+                            TunnelDetail {
+                                name: t.name.clone(),
+                                status: t.status.as_str().to_string(),
+                                source_addr: t.config.source_addr.clone(),
+                                tunnel_health: h.map_or("down", |h| h.tunnel).to_string(),
+                                target_health: h.map_or("-".to_string(), |h| h.target.clone()),
+                                target_error: h.and_then(|h| h.target_error.clone()),
+                                reconnect: h.and_then(|h| h.reconnect.clone()),
+                            }
+                        })
+                        .collect();
+                }
             }
         }
         Err(e) => {
@@ -45,19 +78,6 @@ async fn collect_system_status() -> Result<SystemStatus, Error> {
         }
     }
     
-    // UDSソケット数確認
-    let sockets_dir = dirs::home_dir()
-        .unwrap_or_default()
-        .join(".config/conduit/sockets");
-    
-    if let Ok(mut entries) = fs::read_dir(&sockets_dir).await {
-        while let Ok(Some(entry)) = entries.next_entry().await {
-            if entry.path().extension().map_or(false, |ext| ext == "sock") {
-                status.uds_sockets += 1;
-            }
-        }
-    }
-    
     // Conduitプロセス数
     #[cfg(unix)]
     {
@@ -112,9 +132,15 @@ fn output_table(status: &SystemStatus) -> CommandResult {
         Cell::new(&status.active_tunnels.to_string()),
     ]);
     
+    // 孤立ソケット・ソケットのないトンネルがあれば不整合として表示する
+    let socket_issues = status.orphaned_sockets.len() + status.missing_sockets.len() + status.socket_hash_mismatches.len();
     table.add_row(vec![
         Cell::new("UDS Sockets"),
-        Cell::new("Available").fg(Color::Green),
+        if socket_issues == 0 {
+            Cell::new("Available").fg(Color::Green)
+        } else {
+            Cell::new(format!("{} inconsistencies", socket_issues)).fg(Color::Yellow)
+        },
         Cell::new(&status.uds_sockets.to_string()),
     ]);
     
@@ -126,6 +152,12 @@ fn output_table(status: &SystemStatus) -> CommandResult {
     
     println!("{}", table);
     
+    output_socket_issues(status);
+    
+    if !status.tunnels.is_empty() {
+        output_tunnel_details(&status.tunnels);
+    }
+    
     // タイムスタンプ表示
     let timestamp_str = chrono::DateTime::from_timestamp(status.timestamp as i64, 0)
         .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
@@ -136,6 +168,47 @@ fn output_table(status: &SystemStatus) -> CommandResult {
     Ok(())
 }
 
+// ソケットディレクトリとRegistryの不整合
+fn output_socket_issues(status: &SystemStatus) {
+    for path in &status.orphaned_sockets {
+        println!("⚠️  Orphaned socket (no active tunnel): {}", path);
+    }
+    for name in &status.missing_sockets {
+        println!("⚠️  Tunnel '{}' is active but its socket is missing", name);
+    }
+    for id in &status.socket_hash_mismatches {
+        println!("⚠️  Socket path of tunnel {} does not match the registry", id);
+    }
+}
+
+// トンネルごとの疎通状態（トンネル断とターゲット断を分けて表示）
+fn output_tunnel_details(tunnels: &[TunnelDetail]) {
+    println!("\n🔗 Tunnels");
+    
+    let mut table = Table::new();
+    table.set_header(vec![
+        Cell::new("Name").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("Status").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("Source").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("Tunnel").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("Target").add_attribute(Attribute::Bold).fg(Color::Blue),
+        Cell::new("Detail").add_attribute(Attribute::Bold).fg(Color::Blue),
+    ]);
+    
+    for tunnel in tunnels {
+        table.add_row(vec![
+            Cell::new(&tunnel.name),
+            Cell::new(&tunnel.status),
+            Cell::new(&tunnel.source_addr),
+            health_cell(&tunnel.tunnel_health),
+            health_cell(&tunnel.target_health),
+            Cell::new(tunnel.reconnect.as_deref().or(tunnel.target_error.as_deref()).unwrap_or("")),
+        ]);
+    }
+    
+    println!("{}", table);
+}
+
 // JSON形式での出力
 fn output_json(status: &SystemStatus) -> CommandResult {
     println!("{}", serde_json::to_string_pretty(status)?);
@@ -148,7 +221,35 @@ fn output_yaml(status: &SystemStatus) -> CommandResult {
     println!("total_tunnels: {}", status.total_tunnels);
     println!("active_tunnels: {}", status.active_tunnels);
     println!("uds_sockets: {}", status.uds_sockets);
+    for (key, values) in [
+        ("orphaned_sockets", &status.orphaned_sockets),
+        ("missing_sockets", &status.missing_sockets),
+        ("socket_hash_mismatches", &status.socket_hash_mismatches),
+    ] {
+        if !values.is_empty() {
+            println!("{}:", key);
+            for value in values {
+                println!("  - {}", value);
+            }
+        }
+    }
     println!("conduit_processes: {}", status.conduit_processes);
+    if !status.tunnels.is_empty() {
+        println!("tunnels:");
+        for tunnel in &status.tunnels {
+            println!("  - name: {}", tunnel.name);
+            println!("    status: {}", tunnel.status);
+            println!("    source_addr: {}", tunnel.source_addr);
+            println!("    tunnel_health: {}", tunnel.tunnel_health);
+            println!("    target_health: {}", tunnel.target_health);
+            if let Some(ref error) = tunnel.target_error {
+                println!("    target_error: {}", error);
+            }
+            if let Some(ref reconnect) = tunnel.reconnect {
+                println!("    reconnect: {}", reconnect);
+            }
+        }
+    }
     println!("timestamp: {}", status.timestamp);
     Ok(())
 }
@@ -160,6 +261,29 @@ struct SystemStatus {
     total_tunnels: u32,
     active_tunnels: u32,
     uds_sockets: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    orphaned_sockets: Vec<String>,
+    // ソケットが見つからない稼働中トンネルの名前
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing_sockets: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    socket_hash_mismatches: Vec<String>,
     conduit_processes: u32,
     timestamp: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tunnels: Vec<TunnelDetail>,
+}
+
+// --detailed 時のトンネル別状態
+#[derive(Debug, Clone, serde::Serialize)]
+struct TunnelDetail {
+    name: String,
+    status: String,
+    source_addr: String,
+    tunnel_health: String,
+    target_health: String,
+    target_error: Option<String>,
+    // Routerへの再接続待ち（例: "next attempt in 42s"）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reconnect: Option<String>,
 }
\ No newline at end of file
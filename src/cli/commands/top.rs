@@ -0,0 +1,231 @@
+// topコマンドの実装
+// 全Tunnel ProcessのGetMetricsStreamを購読し、スループット・接続数・レイテンシ・状態を端末上に表示し続ける
+
+use crate::cli::TopArgs;
+use crate::cli::commands::CommandResult;
+use crate::common::error::Error;
+
+#[cfg(feature = "tui")]
+mod app;
+#[cfg(feature = "tui")]
+mod ui;
+
+#[cfg(feature = "tui")]
+pub async fn execute(args: TopArgs) -> CommandResult {
+    use crate::registry::ProcessRegistry;
+
+    let sort = args.sort.parse::<app::SortKey>().map_err(Error::config)?;
+    let registry = ProcessRegistry::new(None).await
+        .map_err(|e| Error::generic(format!("Failed to connect to registry: {}", e)))?;
+
+    let terminal = dashboard::TerminalGuard::enter()
+        .map_err(|e| Error::generic(format!("Failed to initialize terminal: {}", e)))?;
+    let result = dashboard::run(terminal, &registry, app::App::new(sort, args.filter.unwrap_or_default())).await;
+    result.map_err(|e| Error::generic(format!("Dashboard failed: {}", e)))
+}
+
+#[cfg(not(feature = "tui"))]
+pub async fn execute(_args: TopArgs) -> CommandResult {
+    Err(Error::config("This build does not include the 'tui' feature"))
+}
+
+#[cfg(feature = "tui")]
+mod dashboard {
+    use super::app::{Action, App, KillTarget};
+    use super::ui;
+    use crate::ipc::client::UdsGrpcClient;
+    use crate::ipc::protocol::{ConnectionInfo, MetricsResponse};
+    use crate::registry::ProcessRegistry;
+    use crossterm::event::{self, Event, KeyEvent, KeyEventKind};
+    use crossterm::execute;
+    use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+    use ratatui::backend::CrosstermBackend;
+    use ratatui::Terminal;
+    use std::io::Stdout;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio_stream::StreamExt;
+    use tracing::debug;
+
+    // 画面の再描画間隔
+    const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+    // Registryからトンネル一覧と状態を取り直す間隔（新規トンネルの購読・切れたストリームの再購読もここで行う）
+    const REGISTRY_REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+    // 選択中トンネルの接続一覧の更新間隔
+    const CONNECTIONS_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+    // キー入力を待つ間隔（終了フラグの確認もこの間隔で行う）
+    const KEY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    type DashboardTerminal = Terminal<CrosstermBackend<Stdout>>;
+
+    // rawモードと代替画面を有効にし、エラーやパニックで抜けても端末を元に戻す
+    pub(super) struct TerminalGuard {
+        terminal: DashboardTerminal,
+    }
+
+    impl TerminalGuard {
+        pub(super) fn enter() -> std::io::Result<Self> {
+            terminal::enable_raw_mode()?;
+            let mut stdout = std::io::stdout();
+            if let Err(e) = execute!(stdout, EnterAlternateScreen) {
+                let _ = terminal::disable_raw_mode();
+                return Err(e);
+            }
+            let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+            Ok(Self { terminal })
+        }
+    }
+
+    impl Drop for TerminalGuard {
+        fn drop(&mut self) {
+            let _ = terminal::disable_raw_mode();
+            let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+            let _ = self.terminal.show_cursor();
+        }
+    }
+
+    pub(super) async fn run(mut guard: TerminalGuard, registry: &ProcessRegistry, mut app: App) -> anyhow::Result<()> {
+        let (metrics_tx, mut metrics_rx) = mpsc::channel::<(String, Option<MetricsResponse>)>(256);
+        let (key_tx, mut key_rx) = mpsc::channel::<KeyEvent>(64);
+        // 応答の遅いトンネルでキー操作と再描画が止まらないよう、接続一覧は別タスクで取得する
+        let (connections_tx, mut connections_rx) = mpsc::channel::<(PathBuf, Vec<ConnectionInfo>)>(1);
+        let mut connections_pending = false;
+        let stop = Arc::new(AtomicBool::new(false));
+        let key_reader = spawn_key_reader(key_tx, Arc::clone(&stop));
+
+        let mut redraw = tokio::time::interval(REDRAW_INTERVAL);
+        let mut refresh = tokio::time::interval(REGISTRY_REFRESH_INTERVAL);
+        let mut connections_refresh = tokio::time::interval(CONNECTIONS_REFRESH_INTERVAL);
+
+        let result = loop {
+            tokio::select! {
+                _ = refresh.tick() => {
+                    match registry.list_active_tunnels().await {
+                        Ok(tunnels) => {
+                            for (id, socket_path) in app.sync_tunnels(&tunnels) {
+                                spawn_metrics_stream(id, socket_path, metrics_tx.clone());
+                            }
+                        }
+                        Err(e) => app.message = Some(format!("Failed to list tunnels: {}", e)),
+                    }
+                }
+                _ = connections_refresh.tick(), if !connections_pending => {
+                    if let Some(socket_path) = app.selected_tunnel().map(|t| t.socket_path.clone()) {
+                        connections_pending = true;
+                        let tx = connections_tx.clone();
+                        tokio::spawn(async move {
+                            let connections = fetch_connections(&socket_path).await;
+                            let _ = tx.send((socket_path, connections)).await;
+                        });
+                    }
+                }
+                Some((socket_path, connections)) = connections_rx.recv() => {
+                    connections_pending = false;
+                    // 取得中に選択が変わっていれば古い結果は捨てる
+                    if app.selected_tunnel().is_some_and(|t| t.socket_path == socket_path) {
+                        app.set_connections(connections);
+                    }
+                }
+                Some((id, response)) = metrics_rx.recv() => match response {
+                    Some(response) => app.record_metrics(&id, &response),
+                    None => app.mark_disconnected(&id),
+                },
+                Some(key) = key_rx.recv() => match app.handle_key(key) {
+                    Action::Quit => break Ok(()),
+                    Action::Kill(target) => {
+                        app.message = Some(kill(registry, &target).await);
+                        refresh.reset_immediately();
+                    }
+                    Action::None => {}
+                },
+                _ = redraw.tick() => {
+                    if let Err(e) = guard.terminal.draw(|frame| ui::draw(frame, &app)) {
+                        break Err(e.into());
+                    }
+                }
+            }
+        };
+
+        stop.store(true, Ordering::SeqCst);
+        let _ = key_reader.join();
+        result
+    }
+
+    // crosstermのイベント読み取りはブロッキングのため専用スレッドで行う
+    fn spawn_key_reader(tx: mpsc::Sender<KeyEvent>, stop: Arc<AtomicBool>) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::SeqCst) {
+                match event::poll(KEY_POLL_INTERVAL) {
+                    Ok(true) => {
+                        // Windows等で押下と離上の両方が届くため押下のみ扱う
+                        if let Ok(Event::Key(key)) = event::read() {
+                            if key.kind == KeyEventKind::Press && tx.blocking_send(key).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(_) => return,
+                }
+            }
+        })
+    }
+
+    // 1トンネルのメトリクスストリームを中継する。終了時はNoneを送り、次の更新で再購読させる
+    fn spawn_metrics_stream(tunnel_id: String, socket_path: PathBuf, tx: mpsc::Sender<(String, Option<MetricsResponse>)>) {
+        tokio::spawn(async move {
+            match UdsGrpcClient::connect_with_timeout(&socket_path, CONNECT_TIMEOUT).await {
+                Ok(mut client) => match client.get_metrics_stream().await {
+                    Ok(stream) => {
+                        tokio::pin!(stream);
+                        while let Some(Ok(response)) = stream.next().await {
+                            if tx.send((tunnel_id.clone(), Some(response))).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => debug!("Metrics stream unavailable for {}: {}", tunnel_id, e),
+                },
+                Err(e) => debug!("Failed to connect to {}: {}", socket_path.display(), e),
+            }
+            let _ = tx.send((tunnel_id, None)).await;
+        });
+    }
+
+    async fn fetch_connections(socket_path: &std::path::Path) -> Vec<ConnectionInfo> {
+        match UdsGrpcClient::connect_with_timeout(socket_path, CONNECT_TIMEOUT).await {
+            Ok(mut client) => client.list_connections().await
+                .map(|response| response.connections)
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // killコマンドと同じ経路（Registry経由のプロセス停止・CloseConnection）で終了させる
+    async fn kill(registry: &ProcessRegistry, target: &KillTarget) -> String {
+        let result = match target {
+            KillTarget::Tunnel { id, .. } => registry.stop_tunnel(id, true, Duration::ZERO).await
+                .map_err(|e| e.to_string())
+                .and_then(|outcome| outcome.map(|_| ()).ok_or_else(|| "not running".to_string())),
+            KillTarget::Connection { socket_path, id } => {
+                match UdsGrpcClient::connect_with_timeout(socket_path, CONNECT_TIMEOUT).await {
+                    Ok(mut client) => match client.close_connection(id).await {
+                        Ok(response) if response.success => Ok(()),
+                        Ok(response) => Err(response.message),
+                        Err(e) => Err(e.to_string()),
+                    },
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+        };
+
+        match result {
+            Ok(()) => format!("Killed {}", target.describe()),
+            Err(e) => format!("Failed to kill {}: {}", target.describe(), e),
+        }
+    }
+}
@@ -0,0 +1,487 @@
+// conduit topの画面状態
+// 描画・端末操作から切り離し、並び替え・絞り込み・スループット計算・キー操作だけを扱う
+
+use crate::ipc::protocol::{ConnectionInfo, MetricsResponse};
+use crate::registry::models::{HealthStatus, TunnelInfo, TunnelStatus};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+
+// スパークラインに保持するサンプル数（GetMetricsStreamは1秒ごと）
+pub(super) const THROUGHPUT_HISTORY: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SortKey {
+    Name,
+    Throughput,
+    Connections,
+    Latency,
+}
+
+impl SortKey {
+    pub(super) fn label(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Throughput => "throughput",
+            Self::Connections => "connections",
+            Self::Latency => "latency",
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            Self::Name => Self::Throughput,
+            Self::Throughput => Self::Connections,
+            Self::Connections => Self::Latency,
+            Self::Latency => Self::Name,
+        }
+    }
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Self::Name),
+            "throughput" => Ok(Self::Throughput),
+            "connections" => Ok(Self::Connections),
+            "latency" => Ok(Self::Latency),
+            _ => Err(format!(
+                "Invalid sort key '{}' (expected name, throughput, connections or latency)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Focus {
+    Tunnels,
+    Connections,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum KillTarget {
+    Tunnel { id: String, name: String },
+    Connection { socket_path: PathBuf, id: String },
+}
+
+impl KillTarget {
+    pub(super) fn describe(&self) -> String {
+        match self {
+            Self::Tunnel { name, .. } => format!("tunnel '{}'", name),
+            Self::Connection { id, .. } => format!("connection '{}'", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Mode {
+    Normal,
+    // '/'で絞り込み文字列を入力中
+    Filter,
+    // 'K'で終了対象を選び、y/nの確認待ち
+    ConfirmKill(KillTarget),
+}
+
+// キー操作の結果、メインループ側で行う処理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Action {
+    None,
+    Quit,
+    Kill(KillTarget),
+}
+
+// 1トンネル分の表示状態
+#[derive(Debug, Clone)]
+pub(super) struct TunnelView {
+    pub id: String,
+    pub name: String,
+    pub socket_path: PathBuf,
+    pub status: TunnelStatus,
+    pub health: HealthStatus,
+    // メトリクスストリームを受信中か（切れたら次の更新で再購読する）
+    pub streaming: bool,
+    pub active_connections: u32,
+    pub avg_latency_ms: f64,
+    // 1秒あたりの送受信バイト数の履歴（古い順）
+    pub throughput: VecDeque<u64>,
+    last_total: Option<(u64, i64)>,
+}
+
+impl TunnelView {
+    fn new(info: &TunnelInfo) -> Self {
+        Self {
+            id: info.id.clone(),
+            name: info.name.clone(),
+            socket_path: info.socket_path.clone(),
+            status: info.status,
+            health: info.health,
+            streaming: false,
+            active_connections: info.metrics.active_connections,
+            avg_latency_ms: info.metrics.avg_latency_ms,
+            throughput: VecDeque::with_capacity(THROUGHPUT_HISTORY),
+            last_total: None,
+        }
+    }
+
+    pub(super) fn current_throughput(&self) -> u64 {
+        self.throughput.back().copied().unwrap_or(0)
+    }
+
+    fn record(&mut self, response: &MetricsResponse) {
+        let Some(metrics) = &response.metrics else {
+            return;
+        };
+        self.active_connections = metrics.active_connections.max(0) as u32;
+        self.avg_latency_ms = metrics.avg_latency_ms;
+
+        let total = (metrics.total_bytes_sent.max(0) + metrics.total_bytes_received.max(0)) as u64;
+        if let Some((last_total, last_timestamp)) = self.last_total {
+            let elapsed = (response.timestamp - last_timestamp).max(1) as u64;
+            // 再起動でカウンタが戻った場合は0として扱う
+            let rate = total.saturating_sub(last_total) / elapsed;
+            if self.throughput.len() == THROUGHPUT_HISTORY {
+                self.throughput.pop_front();
+            }
+            self.throughput.push_back(rate);
+        }
+        self.last_total = Some((total, response.timestamp));
+    }
+}
+
+pub(super) struct App {
+    tunnels: HashMap<String, TunnelView>,
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub filter: String,
+    pub mode: Mode,
+    pub focus: Focus,
+    pub selected: usize,
+    // 選択中トンネルの接続一覧（定期的にListConnectionsで更新）
+    pub connections: Vec<ConnectionInfo>,
+    pub selected_connection: usize,
+    pub message: Option<String>,
+}
+
+impl App {
+    pub(super) fn new(sort: SortKey, filter: String) -> Self {
+        Self {
+            tunnels: HashMap::new(),
+            sort,
+            reverse: false,
+            filter,
+            mode: Mode::Normal,
+            focus: Focus::Tunnels,
+            selected: 0,
+            connections: Vec::new(),
+            selected_connection: 0,
+            message: None,
+        }
+    }
+
+    // Registryの一覧と同期し、メトリクスストリームを(再)購読すべきトンネルを返す
+    pub(super) fn sync_tunnels(&mut self, infos: &[TunnelInfo]) -> Vec<(String, PathBuf)> {
+        self.tunnels.retain(|id, _| infos.iter().any(|info| &info.id == id));
+
+        let mut subscribe = Vec::new();
+        for info in infos {
+            let view = self.tunnels
+                .entry(info.id.clone())
+                .or_insert_with(|| TunnelView::new(info));
+            view.status = info.status;
+            view.health = info.health;
+            view.socket_path = info.socket_path.clone();
+            if !view.streaming && info.status == TunnelStatus::Running {
+                view.streaming = true;
+                subscribe.push((view.id.clone(), view.socket_path.clone()));
+            }
+        }
+
+        self.clamp_selection();
+        subscribe
+    }
+
+    pub(super) fn record_metrics(&mut self, tunnel_id: &str, response: &MetricsResponse) {
+        if let Some(view) = self.tunnels.get_mut(tunnel_id) {
+            view.record(response);
+        }
+    }
+
+    pub(super) fn mark_disconnected(&mut self, tunnel_id: &str) {
+        if let Some(view) = self.tunnels.get_mut(tunnel_id) {
+            view.streaming = false;
+        }
+    }
+
+    // 絞り込み・並び替え済みの表示対象
+    pub(super) fn visible(&self) -> Vec<&TunnelView> {
+        let filter = self.filter.to_lowercase();
+        let mut views: Vec<&TunnelView> = self.tunnels.values()
+            .filter(|v| filter.is_empty()
+                || v.name.to_lowercase().contains(&filter)
+                || v.id.starts_with(&filter))
+            .collect();
+
+        views.sort_by(|a, b| {
+            let ordering = match self.sort {
+                SortKey::Name => a.name.cmp(&b.name),
+                SortKey::Throughput => b.current_throughput().cmp(&a.current_throughput()),
+                SortKey::Connections => b.active_connections.cmp(&a.active_connections),
+                SortKey::Latency => b.avg_latency_ms.total_cmp(&a.avg_latency_ms),
+            };
+            // 同値の行が更新のたびに入れ替わらないよう名前で安定させる
+            let ordering = ordering.then_with(|| a.name.cmp(&b.name));
+            if self.reverse { ordering.reverse() } else { ordering }
+        });
+        views
+    }
+
+    pub(super) fn selected_tunnel(&self) -> Option<&TunnelView> {
+        self.visible().get(self.selected).copied()
+    }
+
+    fn clamp_selection(&mut self) {
+        let count = self.visible().len();
+        self.selected = self.selected.min(count.saturating_sub(1));
+        self.selected_connection = self.selected_connection.min(self.connections.len().saturating_sub(1));
+    }
+
+    pub(super) fn set_connections(&mut self, connections: Vec<ConnectionInfo>) {
+        self.connections = connections;
+        self.clamp_selection();
+    }
+
+    pub(super) fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        match self.mode.clone() {
+            Mode::Filter => {
+                match key.code {
+                    KeyCode::Enter => self.mode = Mode::Normal,
+                    KeyCode::Esc => {
+                        self.filter.clear();
+                        self.mode = Mode::Normal;
+                    }
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Char(c) => self.filter.push(c),
+                    _ => {}
+                }
+                self.selected = 0;
+                Action::None
+            }
+            Mode::ConfirmKill(target) => {
+                self.mode = Mode::Normal;
+                if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                    Action::Kill(target)
+                } else {
+                    self.message = Some("Kill cancelled".to_string());
+                    Action::None
+                }
+            }
+            Mode::Normal => self.handle_normal_key(key),
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Tunnels => Focus::Connections,
+                    Focus::Connections => Focus::Tunnels,
+                };
+            }
+            KeyCode::Char('s') => self.sort = self.sort.next(),
+            KeyCode::Char('r') => self.reverse = !self.reverse,
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('K') => {
+                match self.kill_target() {
+                    Some(target) => self.mode = Mode::ConfirmKill(target),
+                    None => self.message = Some("Nothing selected".to_string()),
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let tunnel_count = self.visible().len();
+        let (selected, count) = match self.focus {
+            Focus::Tunnels => (&mut self.selected, tunnel_count),
+            Focus::Connections => (&mut self.selected_connection, self.connections.len()),
+        };
+        if count == 0 {
+            return;
+        }
+        *selected = selected.saturating_add_signed(delta).min(count - 1);
+
+        // 別のトンネルを選んだら前のトンネルの接続一覧は表示しない
+        if self.focus == Focus::Tunnels {
+            self.connections.clear();
+            self.selected_connection = 0;
+        }
+    }
+
+    fn kill_target(&self) -> Option<KillTarget> {
+        let tunnel = self.selected_tunnel()?;
+        match self.focus {
+            Focus::Tunnels => Some(KillTarget::Tunnel {
+                id: tunnel.id.clone(),
+                name: tunnel.name.clone(),
+            }),
+            Focus::Connections => self.connections.get(self.selected_connection).map(|c| KillTarget::Connection {
+                socket_path: tunnel.socket_path.clone(),
+                id: c.id.clone(),
+            }),
+        }
+    }
+}
+
+// 値の推移を1行のブロック文字で表す（表のセル内に描けるようにするため）
+pub(super) fn sparkline(values: &VecDeque<u64>, width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let start = values.len().saturating_sub(width);
+    let recent: Vec<u64> = values.iter().skip(start).copied().collect();
+    let max = recent.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return BARS[0].to_string().repeat(recent.len());
+    }
+    recent.iter()
+        .map(|&v| BARS[((v * (BARS.len() as u64 - 1)) / max) as usize])
+        .collect()
+}
+
+// バイト数を人間が読みやすい単位に変換
+pub(super) fn format_rate(bytes_per_sec: u64) -> String {
+    const UNITS: [&str; 4] = ["B/s", "KiB/s", "MiB/s", "GiB/s"];
+
+    let mut value = bytes_per_sec as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes_per_sec, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::protocol::TunnelMetrics;
+    use crate::registry::models::{TunnelConfig, TunnelMetrics as RegistryTunnelMetrics, DEFAULT_UNHEALTHY_THRESHOLD};
+
+    fn tunnel(id: &str, name: &str) -> TunnelInfo {
+        TunnelInfo {
+            id: id.to_string(),
+            name: name.to_string(),
+            pid: Some(1),
+            socket_path: PathBuf::from(format!("/tmp/{}.sock", id)),
+            status: TunnelStatus::Running,
+            config: TunnelConfig {
+                router_addr: "10.2.0.1:9999".to_string(),
+                source_addr: "10.2.0.2:8080".to_string(),
+                bind_addr: "0.0.0.0:80".to_string(),
+                protocol: "tcp".to_string(),
+                timeout_seconds: 30,
+                max_connections: 100,
+                restart_policy: Default::default(),
+                restart_on_unhealthy: false,
+                unhealthy_threshold: DEFAULT_UNHEALTHY_THRESHOLD,
+                compression: None,
+                webhooks: Vec::new(),
+                retry: Default::default(),
+            },
+            created_at: 0,
+            updated_at: 0,
+            last_activity: 0,
+            exit_code: None,
+            restart_count: 0,
+            last_exit_code: None,
+            health: Default::default(),
+            metrics: RegistryTunnelMetrics::default(),
+        }
+    }
+
+    fn sample(bytes: i64, connections: i32, timestamp: i64) -> MetricsResponse {
+        MetricsResponse {
+            metrics: Some(TunnelMetrics {
+                active_connections: connections,
+                total_bytes_sent: bytes,
+                ..Default::default()
+            }),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_throughput_and_sort() {
+        let mut app = App::new(SortKey::Name, String::new());
+        let subscribe = app.sync_tunnels(&[tunnel("a1", "api"), tunnel("w1", "web")]);
+        assert_eq!(subscribe.len(), 2);
+        // 購読中のトンネルは再購読しない
+        assert!(app.sync_tunnels(&[tunnel("a1", "api"), tunnel("w1", "web")]).is_empty());
+
+        app.record_metrics("w1", &sample(0, 1, 100));
+        app.record_metrics("w1", &sample(4096, 3, 102));
+        app.record_metrics("a1", &sample(0, 5, 100));
+
+        let names: Vec<_> = app.visible().iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["api", "web"]);
+
+        app.sort = SortKey::Throughput;
+        let top = app.visible()[0];
+        assert_eq!(top.name, "web");
+        assert_eq!(top.current_throughput(), 2048);
+
+        app.sort = SortKey::Connections;
+        assert_eq!(app.visible()[0].name, "api");
+
+        app.mark_disconnected("w1");
+        assert_eq!(app.sync_tunnels(&[tunnel("w1", "web")]), vec![("w1".to_string(), PathBuf::from("/tmp/w1.sock"))]);
+        assert_eq!(app.visible().len(), 1);
+    }
+
+    #[test]
+    fn test_filter_and_kill_keys() {
+        let mut app = App::new(SortKey::Name, String::new());
+        app.sync_tunnels(&[tunnel("a1", "api"), tunnel("w1", "web")]);
+
+        for key in [KeyCode::Char('/'), KeyCode::Char('w'), KeyCode::Enter] {
+            app.handle_key(KeyEvent::from(key));
+        }
+        assert_eq!(app.visible().len(), 1);
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('K')));
+        assert!(matches!(app.mode, Mode::ConfirmKill(_)));
+        assert_eq!(
+            app.handle_key(KeyEvent::from(KeyCode::Char('y'))),
+            Action::Kill(KillTarget::Tunnel { id: "w1".to_string(), name: "web".to_string() })
+        );
+        assert_eq!(app.handle_key(KeyEvent::from(KeyCode::Char('q'))), Action::Quit);
+    }
+
+    #[test]
+    fn test_sparkline_and_rate() {
+        let values: VecDeque<u64> = [0, 50, 100].into_iter().collect();
+        assert_eq!(sparkline(&values, 10), "▁▄█");
+        assert_eq!(sparkline(&values, 2), "▄█");
+        assert_eq!(sparkline(&VecDeque::from(vec![0, 0]), 10), "▁▁");
+
+        assert_eq!(format_rate(512), "512 B/s");
+        assert_eq!(format_rate(1536), "1.5 KiB/s");
+    }
+}
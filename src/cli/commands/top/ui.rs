@@ -0,0 +1,165 @@
+// conduit topの描画
+// 上段にトンネル一覧（スパークライン付き）、下段に選択中トンネルの接続一覧、最下行に操作説明とメッセージを表示する
+
+use super::app::{format_rate, sparkline, App, Focus, Mode};
+use crate::registry::models::{HealthStatus, TunnelStatus};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+
+// スパークライン列の幅（文字数）
+const SPARKLINE_WIDTH: usize = 30;
+
+pub(super) fn draw(frame: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(60),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+
+    draw_tunnels(frame, app, chunks[0]);
+    draw_connections(frame, app, chunks[1]);
+    draw_footer(frame, app, chunks[2]);
+}
+
+fn draw_tunnels(frame: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let header = Row::new(["NAME", "STATUS", "HEALTH", "CONNS", "LATENCY", "THROUGHPUT", "HISTORY"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = app.visible().into_iter().map(|view| {
+        // ストリームが切れている間は最後の値が古いことが分かるよう淡色にする
+        let style = if view.streaming {
+            Style::default()
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        Row::new(vec![
+            Cell::from(view.name.clone()),
+            Cell::from(view.status.as_str()).style(status_style(view.status)),
+            Cell::from(view.health.as_str()).style(health_style(view.health)),
+            Cell::from(view.active_connections.to_string()),
+            Cell::from(format!("{:.1} ms", view.avg_latency_ms)),
+            Cell::from(format_rate(view.current_throughput())),
+            Cell::from(sparkline(&view.throughput, SPARKLINE_WIDTH)).style(Style::default().fg(Color::Cyan)),
+        ]).style(style)
+    }).collect();
+
+    let title = format!(
+        " Tunnels ({}) - sort: {}{}{} ",
+        rows.len(),
+        app.sort.label(),
+        if app.reverse { " (reversed)" } else { "" },
+        if app.filter.is_empty() { String::new() } else { format!(" - filter: {}", app.filter) },
+    );
+    let widths = [
+        Constraint::Min(12),
+        Constraint::Length(9),
+        Constraint::Length(10),
+        Constraint::Length(6),
+        Constraint::Length(11),
+        Constraint::Length(12),
+        Constraint::Length(SPARKLINE_WIDTH as u16),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(focused_block(title, app.focus == Focus::Tunnels))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default();
+    state.select(Some(app.selected));
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_connections(frame: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let header = Row::new(["ID", "CLIENT", "TARGET", "SENT", "RECEIVED", "STATUS"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = app.connections.iter().map(|conn| {
+        Row::new(vec![
+            conn.id.clone(),
+            conn.client_addr.clone(),
+            conn.target_addr.clone(),
+            conn.bytes_sent.to_string(),
+            conn.bytes_received.to_string(),
+            conn.status.clone(),
+        ])
+    }).collect();
+
+    let title = match app.selected_tunnel() {
+        Some(tunnel) => format!(" Connections of '{}' ({}) ", tunnel.name, rows.len()),
+        None => " Connections ".to_string(),
+    };
+    let widths = [
+        Constraint::Length(12),
+        Constraint::Min(21),
+        Constraint::Min(21),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(focused_block(title, app.focus == Focus::Connections))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default();
+    if app.focus == Focus::Connections && !app.connections.is_empty() {
+        state.select(Some(app.selected_connection));
+    }
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_footer(frame: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let line = match &app.mode {
+        Mode::Filter => Line::from(vec![
+            Span::styled("Filter: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{}_", app.filter)),
+            Span::styled("  (Enter: apply, Esc: clear)", Style::default().fg(Color::DarkGray)),
+        ]),
+        Mode::ConfirmKill(target) => Line::from(Span::styled(
+            format!("Kill {}? (y/n)", target.describe()),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Mode::Normal => {
+            let mut spans = vec![Span::styled(
+                "q:quit  ↑↓/jk:select  Tab:focus  s:sort  r:reverse  /:filter  K:kill",
+                Style::default().fg(Color::DarkGray),
+            )];
+            if let Some(message) = &app.message {
+                spans.push(Span::raw(format!("  {}", message)));
+            }
+            Line::from(spans)
+        }
+    };
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+fn focused_block(title: String, focused: bool) -> Block<'static> {
+    let border_style = if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    };
+    Block::default().borders(Borders::ALL).title(title).border_style(border_style)
+}
+
+fn status_style(status: TunnelStatus) -> Style {
+    match status {
+        TunnelStatus::Running => Style::default().fg(Color::Green),
+        TunnelStatus::Created | TunnelStatus::Stopping => Style::default().fg(Color::Yellow),
+        TunnelStatus::Exited | TunnelStatus::Error => Style::default().fg(Color::Red),
+    }
+}
+
+fn health_style(health: HealthStatus) -> Style {
+    match health {
+        HealthStatus::Healthy => Style::default().fg(Color::Green),
+        HealthStatus::Unhealthy => Style::default().fg(Color::Red),
+        HealthStatus::Unknown => Style::default().fg(Color::DarkGray),
+    }
+}
@@ -0,0 +1,243 @@
+// internal-tunnel-processコマンドの実装
+//
+// ProcessManagerが起動するTunnel Process。1つのトンネルをRouterへ接続し、
+// CLIからの状態取得と制御をトンネルごとのUDS gRPCで受け付ける
+
+use crate::cli::TunnelProcessArgs;
+use crate::cli::commands::CommandResult;
+use crate::client::config::{ConnectionSettings, TunnelSettings};
+use crate::client::{Client, ClientConfig, ClientInfo, ConnectionEvent, ConnectionStats, RouterConfig, TunnelConfig};
+use crate::common::error::{Error, Result};
+use crate::ipc::control::{ConnectionControl, LimitsUpdate};
+use crate::ipc::server::{TunnelControlService, TunnelProcessServer};
+use crate::notifier::WebhookConfig;
+use crate::protocol::CompressionSettings;
+use crate::registry::manager::WEBHOOKS_ENV;
+use crate::registry::models::{TunnelMetrics, TunnelStatus};
+use crate::registry::sqlite::SqliteRegistry;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+// CLIから見えるメトリクスを更新する間隔
+const METRICS_INTERVAL: Duration = Duration::from_secs(5);
+
+pub async fn execute(args: TunnelProcessArgs) -> CommandResult {
+    info!("Starting tunnel process {} ({})", args.name, args.id);
+    let config = client_config(&args)?;
+
+    // 拒否したRPCの記録先。起動したProcessManagerと同じスコープのRegistryを使う
+    let registry = Arc::new(SqliteRegistry::new(None).await
+        .map_err(|e| Error::generic(format!("Failed to open registry: {}", e)))?);
+    let mut server = TunnelProcessServer::new(&args.socket, args.id.clone(), Arc::clone(&registry)).await
+        .map_err(|e| Error::generic(format!("Failed to create control server: {}", e)))?;
+
+    let mut client = Client::new(config)?;
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+    client.set_event_channel(event_tx);
+    // CLIからの一覧・切断・制限の変更がデータプレーンの接続に効くよう、制御状態を共有する
+    let control = server.get_service().connection_control();
+    control.update_limits(LimitsUpdate {
+        max_connections: Some(args.max_connections),
+        ..LimitsUpdate::default()
+    });
+    client.set_connection_control(control);
+    client.start().await?;
+    tokio::spawn(forward_events(event_rx, server.get_service()));
+
+    if let Err(e) = registry.update_tunnel_status(&args.id, TunnelStatus::Running, None).await {
+        warn!("Failed to mark tunnel {} as running: {}", args.id, e);
+    }
+    if let Ok(Some(info)) = registry.get_tunnel(&args.id).await {
+        server.get_service().update_tunnel_info(info).await;
+    }
+
+    #[cfg(unix)]
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .map_err(|e| Error::generic(format!("Failed to install SIGTERM handler: {}", e)))?;
+    #[cfg(unix)]
+    let terminated = sigterm.recv();
+    #[cfg(not(unix))]
+    let terminated = std::future::pending::<Option<()>>();
+
+    // Shutdown RPCを受けるか、ProcessManagerからSIGTERMが届くまで動かす
+    let service = server.get_service();
+    let started_at = Instant::now();
+    let mut metrics_interval = tokio::time::interval(METRICS_INTERVAL);
+    let serve = server.serve_with_shutdown();
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(serve, terminated, ctrl_c);
+    let result = loop {
+        tokio::select! {
+            result = &mut serve => break result
+                .map_err(|e| Error::generic(format!("Control server failed: {}", e))),
+            _ = &mut terminated => break Ok(()),
+            _ = &mut ctrl_c => break Ok(()),
+            _ = metrics_interval.tick() => publish_metrics(&client, &service, started_at).await,
+        }
+    };
+
+    info!("Stopping tunnel process {} ({})", args.name, args.id);
+    client.stop().await?;
+    result
+}
+
+// 接続イベントのうちCLIから見える状態をTunnelControlServiceへ反映する
+async fn forward_events(
+    mut events: mpsc::UnboundedReceiver<ConnectionEvent>,
+    service: Arc<TunnelControlService>,
+) {
+    while let Some(event) = events.recv().await {
+        match event {
+            ConnectionEvent::TargetHealthChanged(health) => service.update_target_health(health).await,
+            ConnectionEvent::Reconnecting(status) => service.update_reconnect_status(Some(status)).await,
+            // 切断・接続失敗の原因を再接続待ちの表示に添える（ログはClientが出力する）
+            ConnectionEvent::Error(error) => service.update_router_error(Some(error)).await,
+            // 切断時点でターゲットの状態は分からなくなる。原因のエラーは再接続まで残す
+            ConnectionEvent::Disconnected => service.clear_target_health().await,
+            // 接続できたら待機中の状態は表示しない
+            ConnectionEvent::Connected => {
+                service.update_reconnect_status(None).await;
+                service.update_router_error(None).await;
+            }
+            _ => {}
+        }
+    }
+}
+
+// データプレーンの集計をTunnelControlServiceへ反映する
+async fn publish_metrics(client: &Client, service: &TunnelControlService, started_at: Instant) {
+    let compression = client
+        .list_tunnels()
+        .iter()
+        .filter_map(|tunnel| client.compression_stats(&tunnel.id))
+        .next()
+        .unwrap_or_default();
+    let mut metrics = tunnel_metrics(&service.connection_control(), &client.connection_stats().await);
+    metrics.uptime_seconds = started_at.elapsed().as_secs();
+    metrics.compression = compression;
+    service.update_metrics(metrics).await;
+}
+
+// 接続制御の累計とRouter接続の統計からCLIへ見せるメトリクスを組み立てる
+fn tunnel_metrics(control: &ConnectionControl, connection_stats: &[ConnectionStats]) -> TunnelMetrics {
+    let totals = control.totals();
+    TunnelMetrics {
+        active_connections: control.active_connections() as u32,
+        total_connections: totals.connections,
+        total_bytes_sent: totals.bytes_sent,
+        total_bytes_received: totals.bytes_received,
+        avg_latency_ms: totals.latency.average_ms(),
+        latency: totals.latency,
+        reconnects: connection_stats.iter().map(|stats| stats.reconnect_count).sum(),
+        auth_failures: connection_stats.iter().map(|stats| stats.auth_failures).sum(),
+        ..TunnelMetrics::default()
+    }
+}
+
+// ProcessManagerが環境変数で渡したconduit.tomlの[[webhooks]]
+fn webhooks_from_env() -> Result<Vec<WebhookConfig>> {
+    match std::env::var(WEBHOOKS_ENV) {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|e| Error::config(format!("Invalid {}: {}", WEBHOOKS_ENV, e))),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+// 引数の1トンネル分のClient設定
+fn client_config(args: &TunnelProcessArgs) -> Result<ClientConfig> {
+    // 並び順をそのまま優先度とし、先頭のRouterが使えなければ次へフェイルオーバーする
+    let routers = args.router.split(',')
+        .map(str::trim)
+        .enumerate()
+        .map(|(priority, addr)| {
+            let (host, port) = addr.rsplit_once(':')
+                .ok_or_else(|| Error::config(format!("Invalid router address: {}", addr)))?;
+            let port = port.parse()
+                .map_err(|_| Error::config(format!("Invalid router port: {}", addr)))?;
+            Ok(RouterConfig {
+                host: host.to_string(),
+                port,
+                priority: priority as u32,
+                ..RouterConfig::default()
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let config = ClientConfig {
+        client: ClientInfo {
+            name: args.name.clone(),
+            ..ClientInfo::default()
+        },
+        routers,
+        connection: ConnectionSettings {
+            retry: args.retry.clone().unwrap_or_default(),
+            ..ConnectionSettings::default()
+        },
+        tunnels: vec![TunnelConfig {
+            name: args.name.clone(),
+            source: args.source.parse()?,
+            bind: args.bind,
+            protocol: args.protocol.clone(),
+            enabled: true,
+            settings: TunnelSettings {
+                max_connections: args.max_connections,
+                connection_timeout_seconds: args.timeout,
+                compression_enabled: args.compression.is_some(),
+                compression: CompressionSettings {
+                    algorithm: args.compression.unwrap_or_default(),
+                    level: args.compression_level,
+                    ..CompressionSettings::default()
+                },
+                ..TunnelSettings::default()
+            },
+            router_group: None,
+        }],
+        webhooks: webhooks_from_env()?,
+        ..ClientConfig::default()
+    };
+    config.validate()?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::models::ConnectionInfo;
+
+    #[test]
+    fn test_metrics_count_traffic() {
+        let control = Arc::new(ConnectionControl::new(10));
+        let _closed = control.admit().unwrap().register(ConnectionInfo::open(
+            "conn-1".to_string(),
+            "t1".to_string(),
+            "127.0.0.1:50000".to_string(),
+            "10.2.0.2:8080".to_string(),
+            30,
+        ));
+        control.record_traffic("conn-1", 512, 2048);
+        control.record_latency(Duration::from_millis(8));
+        let stats = ConnectionStats {
+            reconnect_count: 2,
+            auth_failures: 1,
+            ..ConnectionStats::default()
+        };
+
+        let metrics = tunnel_metrics(&control, &[stats.clone(), stats]);
+        assert_eq!(metrics.active_connections, 1);
+        assert_eq!(metrics.total_connections, 1);
+        assert_eq!(metrics.total_bytes_sent, 512);
+        assert_eq!(metrics.total_bytes_received, 2048);
+        assert_eq!(metrics.latency.count, 1);
+        assert!(metrics.avg_latency_ms > 0.0);
+        assert_eq!(metrics.reconnects, 4);
+        assert_eq!(metrics.auth_failures, 2);
+
+        // 閉じた接続の転送量も累計に残る
+        control.unregister("conn-1");
+        let metrics = tunnel_metrics(&control, &[]);
+        assert_eq!(metrics.active_connections, 0);
+        assert_eq!(metrics.total_bytes_received, 2048);
+    }
+}
@@ -3,15 +3,23 @@
 
 use crate::cli::UpArgs;
 use crate::cli::commands::CommandResult;
-use crate::common::{config::Config, error::Error};
+use crate::common::{config::Config, error::Error, logging, paths};
+use crate::notifier::Notifier;
 use crate::registry::ProcessRegistry;
 use crate::registry::models::TunnelConfig;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::path::Path;
 use std::process::Stdio;
-use tokio::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, info, error};
 use uuid::Uuid;
 
+// 監視対象のトンネルが残っているかを確認する間隔
+const SUPERVISE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// 監視プロセスの終了時に既存接続の終了を待つ時間
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub async fn execute(args: UpArgs) -> CommandResult {
     debug!("Executing up command with config file: {}", args.file.display());
     
@@ -24,6 +32,10 @@ pub async fn execute(args: UpArgs) -> CommandResult {
         return Ok(());
     }
     
+    if args.daemon {
+        return spawn_supervisor(&args.file);
+    }
+    
     println!("🚀 Starting {} tunnel(s) from configuration...", config.tunnels.len());
     
     // Process Registry接続
@@ -75,12 +87,21 @@ pub async fn execute(args: UpArgs) -> CommandResult {
     
     if !started_tunnels.is_empty() {
         println!("\n🔗 Active Tunnels:");
-        for (tunnel_id, name) in started_tunnels {
+        for (tunnel_id, name) in &started_tunnels {
             println!("  {} (ID: {})", name, tunnel_id);
         }
         
         println!("\nTo stop all tunnels, run:");
         println!("  conduit down -f {}", args.file.display());
+        
+        // 再起動ポリシー・ヘルスチェック・終了通知はこのプロセスが生きている間だけ働くため常駐する
+        if !config.webhooks.is_empty() {
+            registry.set_notifier(Arc::new(Notifier::from_config(&config.webhooks))).await;
+        }
+        registry.supervise().await
+            .map_err(|e| Error::generic(format!("Failed to start process monitoring: {}", e)))?;
+        println!("\n👀 Supervising {} tunnel(s). Press Ctrl-C to stop them.", started_tunnels.len());
+        supervise_until_stopped(&registry).await?;
     }
     
     if error_count > 0 {
@@ -102,12 +123,20 @@ async fn start_tunnel(
     
     // Registry設定作成
     let registry_config = TunnelConfig {
-        router_addr: format!("{}:{}", config.router.host, config.router.port),
-        source_addr: tunnel_config.source.clone(),
+        // フェイルオーバー先のRouterも優先順のカンマ区切りで渡す
+        router_addr: config.router_addrs(),
+        // 複数の転送先はカンマ区切りでTunnel Processへ渡す
+        source_addr: tunnel_config.source.to_string(),
         bind_addr: tunnel_config.bind.clone(),
         protocol: tunnel_config.protocol.clone(),
         timeout_seconds: 30,
         max_connections: 1000,
+        restart_policy: tunnel_config.restart,
+        restart_on_unhealthy: tunnel_config.restart_on_unhealthy,
+        unhealthy_threshold: tunnel_config.unhealthy_threshold,
+        compression: tunnel_config.compression_settings(),
+        webhooks: config.webhooks.clone(),
+        retry: config.retry.clone(),
     };
     
     // Process Registryを使用してトンネルを作成・起動
@@ -120,4 +149,67 @@ async fn start_tunnel(
     info!("Started tunnel process: {} (PID: {})", tunnel_config.name, pid);
     
     Ok(tunnel_id)
-}
\ No newline at end of file
+}
+
+// Ctrl-C・SIGTERMを受けるか、監視中のトンネルがすべてdown/killで停止されるまで待つ
+async fn supervise_until_stopped(registry: &ProcessRegistry) -> CommandResult {
+    #[cfg(unix)]
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .map_err(|e| Error::generic(format!("Failed to install SIGTERM handler: {}", e)))?;
+    #[cfg(unix)]
+    let terminated = sigterm.recv();
+    #[cfg(not(unix))]
+    let terminated = std::future::pending::<Option<()>>();
+    tokio::pin!(terminated);
+    
+    let mut check_timer = tokio::time::interval(SUPERVISE_POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = &mut terminated => break,
+            _ = check_timer.tick() => {
+                if registry.list_running_processes().await.is_empty() {
+                    println!("All supervised tunnels have stopped.");
+                    return Ok(());
+                }
+            }
+        }
+    }
+    
+    println!("\n🛑 Stopping tunnels (draining up to {}s)...", SHUTDOWN_DRAIN_TIMEOUT.as_secs());
+    let stopped = registry.stop_all_tunnels(false, SHUTDOWN_DRAIN_TIMEOUT).await
+        .map_err(|e| Error::generic(format!("Failed to stop tunnels: {}", e)))?;
+    println!("✅ Stopped {} tunnel(s)", stopped.len());
+    Ok(())
+}
+
+// 監視プロセスを端末から切り離して起動し直す
+// 出力は読み手がいないため、トンネルと同じログ置き場のファイルへ向ける
+fn spawn_supervisor(config_file: &Path) -> CommandResult {
+    let config_file = std::fs::canonicalize(config_file)?;
+    let log_path = logging::logs_dir().join("supervisor.log");
+    let log_file = logging::open_tunnel_log(&log_path)?;
+    
+    let mut cmd = std::process::Command::new(std::env::current_exe()?);
+    if paths::scope() == paths::Scope::System {
+        cmd.arg("--system");
+    }
+    cmd.arg("up").arg("--file").arg(&config_file);
+    cmd.stdin(Stdio::null())
+       .stdout(Stdio::from(log_file.try_clone()?))
+       .stderr(Stdio::from(log_file));
+    // 端末のCtrl-Cが届かないよう別のプロセスグループにする
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    
+    let child = cmd.spawn()
+        .map_err(|e| Error::generic(format!("Failed to start supervisor: {}", e)))?;
+    println!("🚀 Started supervisor in the background (PID: {})", child.id());
+    println!("  Log: {}", log_path.display());
+    println!("\nTo stop all tunnels, run:");
+    println!("  conduit down -f {}", config_file.display());
+    Ok(())
+}
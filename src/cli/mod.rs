@@ -2,6 +2,9 @@
 //
 // コマンドライン引数の解析とコマンド実行機能を提供
 
+use crate::common::logging::{LogFormat, LogRotation};
+use crate::common::retry::RetryPolicy;
+use crate::protocol::CompressionAlgorithm;
 use clap::{Parser, Subcommand};
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -18,6 +21,22 @@ pub mod commands;
 pub struct CliArgs {
     #[command(subcommand)]
     pub command: Commands,
+    
+    /// Log output format (text, json)
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub log_format: Option<LogFormat>,
+    
+    /// Write logs to a file instead of stdout
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    
+    /// Log file rotation (daily, hourly, size, never)
+    #[arg(long, global = true, value_name = "POLICY")]
+    pub log_rotation: Option<LogRotation>,
+    
+    /// Use the system-wide registry (/var/lib/conduit) and sockets (/run/conduit)
+    #[arg(long, global = true)]
+    pub system: bool,
 }
 
 #[derive(Subcommand)]
@@ -40,17 +59,39 @@ pub enum Commands {
     /// List active tunnels and connections
     List(ListArgs),
     
+    /// Stream connection events from all tunnels
+    Events(EventsArgs),
+    
     /// Kill specific tunnels or connections
     Kill(KillArgs),
     
     /// Show system status
     Status(StatusArgs),
     
+    /// Show logs of a tunnel process
+    Logs(LogsArgs),
+    
     /// Manage configuration
     Config(ConfigArgs),
     
+    /// Export metrics in Prometheus format
+    Metrics(MetricsArgs),
+    
+    /// Interactive dashboard of tunnel metrics
+    Top(TopArgs),
+    
+    /// Query and verify the registry audit log
+    Audit(AuditArgs),
+    
+    /// Maintain the process registry database
+    Registry(RegistryArgs),
+    
     /// Show version information
     Version,
+    
+    /// Run one tunnel (spawned by the process manager)
+    #[command(name = "internal-tunnel-process", hide = true)]
+    InternalTunnelProcess(TunnelProcessArgs),
 }
 
 #[derive(Parser)]
@@ -99,13 +140,21 @@ pub struct DownArgs {
     /// Configuration file path
     #[arg(short, long, value_name = "FILE", default_value = "conduit.toml")]
     pub file: PathBuf,
+    
+    /// Seconds to wait for in-flight connections before cutting them
+    #[arg(long, value_name = "SECONDS", default_value = "30", value_parser = clap::value_parser!(u64).range(0..=300))]
+    pub timeout: u64,
 }
 
 #[derive(Parser)]
 pub struct RouterArgs {
-    /// Address to bind the router server
-    #[arg(short, long, value_name = "HOST:PORT", default_value = "0.0.0.0:9999")]
-    pub bind: SocketAddr,
+    /// Router configuration file (TLS, health checks, API, metrics, resumption)
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    
+    /// Address to bind the router server (default: bind_addr in the config file, or 0.0.0.0:9999)
+    #[arg(short, long, value_name = "HOST:PORT")]
+    pub bind: Option<SocketAddr>,
     
     /// Private key file path
     #[arg(short, long, value_name = "PATH")]
@@ -129,6 +178,25 @@ pub struct ListArgs {
     /// Output format (table, json, yaml)
     #[arg(short, long, default_value = "table")]
     pub format: String,
+    
+    /// Keep running and print connection events as they happen
+    #[arg(short, long)]
+    pub watch: bool,
+}
+
+#[derive(Parser)]
+pub struct EventsArgs {
+    /// Only watch the tunnel with this name
+    #[arg(short, long, value_name = "NAME")]
+    pub tunnel: Option<String>,
+    
+    /// Also print an open event for every connection that is already active
+    #[arg(short, long)]
+    pub existing: bool,
+    
+    /// Output format (text, json)
+    #[arg(short, long, default_value = "text")]
+    pub format: String,
 }
 
 #[derive(Parser)]
@@ -141,9 +209,17 @@ pub struct KillArgs {
     #[arg(short, long, value_name = "NAME")]
     pub tunnel: Option<String>,
     
-    /// Connection ID to kill
+    /// Connection ID (or unique prefix) to close; combine with --tunnel to limit the search
     #[arg(short, long, value_name = "ID")]
     pub connection: Option<String>,
+    
+    /// Kill immediately with SIGKILL instead of draining connections first
+    #[arg(long)]
+    pub force: bool,
+    
+    /// Seconds to wait for in-flight connections before cutting them
+    #[arg(long, value_name = "SECONDS", default_value = "30", value_parser = clap::value_parser!(u64).range(0..=300))]
+    pub timeout: u64,
 }
 
 #[derive(Parser)]
@@ -157,6 +233,21 @@ pub struct StatusArgs {
     pub detailed: bool,
 }
 
+#[derive(Parser)]
+pub struct LogsArgs {
+    /// Tunnel name or ID
+    #[arg(value_name = "TUNNEL")]
+    pub tunnel: String,
+    
+    /// Follow log output
+    #[arg(short, long)]
+    pub follow: bool,
+    
+    /// Number of lines to show from the end of the log
+    #[arg(short = 'n', long, value_name = "LINES", default_value = "100")]
+    pub tail: usize,
+}
+
 #[derive(Parser)]
 pub struct ConfigArgs {
     #[command(subcommand)]
@@ -181,4 +272,194 @@ pub enum ConfigAction {
         #[arg(short, long, value_name = "FILE")]
         output: Option<PathBuf>,
     },
-}
\ No newline at end of file
+}
+
+#[derive(Parser)]
+pub struct MetricsArgs {
+    #[command(subcommand)]
+    pub action: MetricsAction,
+}
+
+#[derive(Subcommand)]
+pub enum MetricsAction {
+    /// Serve aggregated metrics of all running tunnels
+    Serve {
+        /// Address to bind the metrics endpoint
+        #[arg(short, long, value_name = "HOST:PORT", default_value = "127.0.0.1:9090")]
+        bind: SocketAddr,
+        
+        /// HTTP path to serve metrics on
+        #[arg(short, long, default_value = "/metrics")]
+        path: String,
+        
+        /// Per-tunnel query timeout in milliseconds
+        #[arg(short, long, value_name = "MS", default_value = "1000")]
+        timeout: u64,
+    },
+}
+
+#[derive(Parser)]
+pub struct AuditArgs {
+    #[command(subcommand)]
+    pub command: Option<AuditCommand>,
+    
+    /// Only show entries since this time (30m, 24h, 7d, 2024-01-31 or RFC 3339)
+    #[arg(long, value_name = "TIME")]
+    pub since: Option<String>,
+    
+    /// Only show entries with this action (create, update, delete, select_sensitive)
+    #[arg(short, long, value_name = "ACTION")]
+    pub action: Option<String>,
+    
+    /// Only show entries for this tunnel (name or ID)
+    #[arg(short, long, value_name = "TUNNEL")]
+    pub tunnel: Option<String>,
+    
+    /// Maximum number of most recent entries to show
+    #[arg(short = 'n', long, value_name = "COUNT", default_value = "100")]
+    pub limit: u32,
+    
+    /// Output format (table, json)
+    #[arg(short, long, default_value = "table")]
+    pub format: String,
+}
+
+#[derive(Subcommand)]
+pub enum AuditCommand {
+    /// Verify the hash chain of the audit log
+    Verify {
+        /// Previously recorded hash that must still be part of the chain
+        #[arg(long, value_name = "HASH")]
+        anchor: Option<String>,
+        
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+}
+
+#[derive(Parser)]
+pub struct RegistryArgs {
+    #[command(subcommand)]
+    pub action: RegistryAction,
+}
+
+#[derive(Subcommand)]
+pub enum RegistryAction {
+    /// Upgrade the registry schema to the version of this build
+    Migrate {
+        /// Show pending migrations without changing the registry
+        #[arg(long)]
+        dry_run: bool,
+        
+        /// Registry database path (default: registry of the current scope)
+        #[arg(long, value_name = "PATH")]
+        db: Option<PathBuf>,
+    },
+    
+    /// Export tunnels, sessions and the audit log to a portable bundle
+    Export {
+        /// Output file (format from the extension: .json or .toml)
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+        
+        /// Bundle format (json, toml); overrides the file extension
+        #[arg(short, long)]
+        format: Option<String>,
+        
+        /// Encrypt tunnel configs to this Ed25519 public key (file or base64)
+        #[arg(short, long, value_name = "KEY")]
+        recipient: Option<String>,
+        
+        /// Registry database path (default: registry of the current scope)
+        #[arg(long, value_name = "PATH")]
+        db: Option<PathBuf>,
+    },
+    
+    /// Import a bundle written by 'registry export'
+    Import {
+        /// Bundle file
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+        
+        /// Bundle format (json, toml); overrides the file extension
+        #[arg(short, long)]
+        format: Option<String>,
+        
+        /// Secret key of the recipient for bundles with encrypted configs
+        #[arg(short, long, value_name = "FILE")]
+        identity: Option<PathBuf>,
+        
+        /// Registry database path (default: registry of the current scope)
+        #[arg(long, value_name = "PATH")]
+        db: Option<PathBuf>,
+    },
+}
+
+#[derive(Parser)]
+pub struct TopArgs {
+    /// Initial sort column (name, throughput, connections, latency)
+    #[arg(short, long, default_value = "name")]
+    pub sort: String,
+    
+    /// Only show tunnels whose name contains this text
+    #[arg(short, long, value_name = "TEXT")]
+    pub filter: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct TunnelProcessArgs {
+    /// Tunnel ID in the registry
+    #[arg(long)]
+    pub id: String,
+    
+    /// Tunnel name
+    #[arg(long)]
+    pub name: String,
+    
+    /// Router addresses to connect to, in order of preference (comma separated)
+    #[arg(long, value_name = "HOST:PORT[,...]")]
+    pub router: String,
+    
+    /// Source service addresses on router side (comma separated)
+    #[arg(long, value_name = "HOST:PORT[,...]")]
+    pub source: String,
+    
+    /// Local bind address for incoming connections
+    #[arg(long, value_name = "HOST:PORT")]
+    pub bind: SocketAddr,
+    
+    /// UDS path of the control socket
+    #[arg(long, value_name = "PATH")]
+    pub socket: PathBuf,
+    
+    /// Tunnel protocol (tcp, udp)
+    #[arg(long, default_value = "tcp")]
+    pub protocol: String,
+    
+    /// Connection timeout in seconds
+    #[arg(long, value_name = "SECONDS", default_value = "30")]
+    pub timeout: u64,
+    
+    /// Maximum concurrent connections
+    #[arg(long, default_value = "100")]
+    pub max_connections: u32,
+    
+    /// Compress tunnel data when the router agrees (zstd, lz4)
+    #[arg(long, value_name = "ALGORITHM")]
+    pub compression: Option<CompressionAlgorithm>,
+    
+    /// zstd compression level (1-22)
+    #[arg(long, value_name = "LEVEL", requires = "compression")]
+    pub compression_level: Option<i32>,
+    
+    /// Reconnect backoff policy as JSON (the [retry] section of conduit.toml)
+    #[arg(long, value_name = "JSON", value_parser = parse_retry_policy)]
+    pub retry: Option<RetryPolicy>,
+}
+
+fn parse_retry_policy(s: &str) -> Result<RetryPolicy, String> {
+    let policy: RetryPolicy = serde_json::from_str(s).map_err(|e| e.to_string())?;
+    policy.validate()?;
+    Ok(policy)
+}
@@ -8,9 +8,13 @@ use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 
 use crate::security::SecurityConfig;
-use crate::protocol::ProtocolConfig;
+use crate::protocol::{CompressionSettings, ProtocolConfig};
 use crate::client::connection::ConnectionConfig;
+use crate::client::failover::{FailoverSettings, RouterEndpoint};
+use crate::common::config::SourceAddrs;
 use crate::common::error::Result;
+use crate::common::retry::RetryPolicy;
+use crate::notifier::WebhookConfig;
 
 /// Clientメイン設定
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,8 +22,9 @@ pub struct ClientConfig {
     /// Client基本情報
     pub client: ClientInfo,
     
-    /// Router接続設定
-    pub router: RouterConfig,
+    /// Router接続設定（`[router]`1つ、または優先度付きの`[[router]]`の並び）
+    #[serde(rename = "router", deserialize_with = "deserialize_routers")]
+    pub routers: Vec<RouterConfig>,
     
     /// セキュリティ設定
     pub security: SecurityConfig,
@@ -32,6 +37,10 @@ pub struct ClientConfig {
     
     /// トンネル設定リスト
     pub tunnels: Vec<TunnelConfig>,
+    
+    /// 状態遷移の通知先Webhook
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
 }
 
 /// Client基本情報
@@ -61,6 +70,13 @@ impl Default for ClientInfo {
     }
 }
 
+/// トンネルがRouterグループを指定しない場合のグループ
+pub const DEFAULT_ROUTER_GROUP: &str = "default";
+
+fn default_router_group() -> String {
+    DEFAULT_ROUTER_GROUP.to_string()
+}
+
 /// Router接続設定
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouterConfig {
@@ -72,6 +88,14 @@ pub struct RouterConfig {
     
     /// 接続タイムアウト（秒）
     pub connect_timeout_seconds: u64,
+    
+    /// 優先度（小さいほど優先。同じグループ内で到達できなければ次の優先度へ切り替える）
+    #[serde(default)]
+    pub priority: u32,
+    
+    /// 所属するRouterグループ（サイトごとの冗長構成など）
+    #[serde(default = "default_router_group")]
+    pub group: String,
 }
 
 impl Default for RouterConfig {
@@ -80,37 +104,90 @@ impl Default for RouterConfig {
             host: "0.0.0.0".to_string(),
             port: 9999,
             connect_timeout_seconds: 30,
+            priority: 0,
+            group: default_router_group(),
         }
     }
 }
 
+impl RouterConfig {
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+// 単一Routerの旧形式（`[router]`）も受け付ける
+fn deserialize_routers<'de, D>(deserializer: D) -> std::result::Result<Vec<RouterConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(RouterConfig),
+        Many(Vec<RouterConfig>),
+    }
+    
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(router) => vec![router],
+        OneOrMany::Many(routers) => routers,
+    })
+}
+
 /// 接続設定
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionSettings {
     /// 自動再接続有効
     pub auto_reconnect: bool,
     
-    /// 再接続間隔（秒）
-    pub reconnect_interval_seconds: u64,
+    /// 再接続の待ち時間（指数バックオフ・ジッター）とサーキットブレーカー
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    
+    /// 初回の再接続間隔（秒）。旧形式の設定で、指定があればretry.initial_delay_msより優先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconnect_interval_seconds: Option<u64>,
     
-    /// 最大再接続回数（0で無制限）
-    pub max_reconnect_attempts: usize,
+    /// 連続して失敗できる再接続回数（0で無制限）。旧形式の設定で、指定があればretry.max_attemptsより優先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_reconnect_attempts: Option<u32>,
     
     /// ハートビート有効
     pub heartbeat_enabled: bool,
     
     /// ハートビート間隔（秒）
     pub heartbeat_interval_seconds: u64,
+    
+    /// 同じグループの別Routerへの切り替えとフェイルバック
+    #[serde(default)]
+    pub failover: FailoverSettings,
+}
+
+impl ConnectionSettings {
+    /// 旧形式の設定を反映した再接続ポリシー
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let mut policy = self.retry.clone();
+        if let Some(seconds) = self.reconnect_interval_seconds {
+            policy.initial_delay_ms = seconds.saturating_mul(1000);
+            policy.max_delay_ms = policy.max_delay_ms.max(policy.initial_delay_ms);
+        }
+        if let Some(attempts) = self.max_reconnect_attempts {
+            policy.max_attempts = attempts;
+        }
+        policy
+    }
 }
 
 impl Default for ConnectionSettings {
     fn default() -> Self {
         Self {
             auto_reconnect: true,
-            reconnect_interval_seconds: 5,
-            max_reconnect_attempts: 0,
+            retry: RetryPolicy::default(),
+            reconnect_interval_seconds: None,
+            max_reconnect_attempts: None,
             heartbeat_enabled: true,
             heartbeat_interval_seconds: 60,
+            failover: FailoverSettings::default(),
         }
     }
 }
@@ -121,8 +198,8 @@ pub struct TunnelConfig {
     /// トンネル名
     pub name: String,
     
-    /// 転送先サービスアドレス（Router側）
-    pub source: SocketAddr,
+    /// 転送先サービスアドレス（Router側）。レプリカは配列で並べ、Routerが振り分ける
+    pub source: SourceAddrs,
     
     /// バインドアドレス（Client側）
     pub bind: SocketAddr,
@@ -135,6 +212,16 @@ pub struct TunnelConfig {
     
     /// トンネル固有設定
     pub settings: TunnelSettings,
+    
+    /// 使用するRouterグループ（省略時は"default"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub router_group: Option<String>,
+}
+
+impl TunnelConfig {
+    pub fn router_group(&self) -> &str {
+        self.router_group.as_deref().unwrap_or(DEFAULT_ROUTER_GROUP)
+    }
 }
 
 /// トンネル固有設定
@@ -151,6 +238,14 @@ pub struct TunnelSettings {
     
     /// 圧縮有効
     pub compression_enabled: bool,
+    
+    /// 圧縮アルゴリズムとレベル
+    #[serde(default)]
+    pub compression: CompressionSettings,
+    
+    /// Routerで負荷分散をまとめるサービス名（省略時はトンネル名）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
 }
 
 impl Default for TunnelSettings {
@@ -160,6 +255,8 @@ impl Default for TunnelSettings {
             connection_timeout_seconds: 30,
             buffer_size: 65536,
             compression_enabled: false,
+            compression: CompressionSettings::default(),
+            service: None,
         }
     }
 }
@@ -168,11 +265,12 @@ impl Default for ClientConfig {
     fn default() -> Self {
         Self {
             client: ClientInfo::default(),
-            router: RouterConfig::default(),
+            routers: vec![RouterConfig::default()],
             security: SecurityConfig::default(),
             protocol: ProtocolConfig::default(),
             connection: ConnectionSettings::default(),
             tunnels: Vec::new(),
+            webhooks: Vec::new(),
         }
     }
 }
@@ -201,14 +299,29 @@ impl ClientConfig {
     /// 設定を検証
     pub fn validate(&self) -> Result<()> {
         // Router設定の検証
-        if self.router.host.is_empty() {
-            return Err(crate::common::error::Error::Config("Router host cannot be empty".to_string()));
+        if self.routers.is_empty() {
+            return Err(crate::common::error::Error::Config("At least one router must be configured".to_string()));
         }
         
-        if self.router.port == 0 {
-            return Err(crate::common::error::Error::Config("Router port must be greater than 0".to_string()));
+        for router in &self.routers {
+            if router.host.is_empty() {
+                return Err(crate::common::error::Error::Config("Router host cannot be empty".to_string()));
+            }
+            
+            if router.port == 0 {
+                return Err(crate::common::error::Error::Config("Router port must be greater than 0".to_string()));
+            }
+            
+            if router.group.is_empty() {
+                return Err(crate::common::error::Error::Config(
+                    format!("Router {} has an empty group name", router.addr())
+                ));
+            }
         }
         
+        self.connection.retry_policy().validate()
+            .map_err(|e| crate::common::error::Error::Config(format!("Invalid connection.retry: {}", e)))?;
+        
         // Client設定の検証
         if self.client.name.is_empty() {
             return Err(crate::common::error::Error::Config("Client name cannot be empty".to_string()));
@@ -225,21 +338,64 @@ impl ClientConfig {
                     format!("Invalid tunnel protocol: {}", tunnel.protocol)
                 ));
             }
+            
+            tunnel.source.parse()
+                .map_err(|e| crate::common::error::Error::Config(
+                    format!("Tunnel {}: {}", tunnel.name, e)
+                ))?;
+            
+            tunnel.settings.compression.validate()
+                .map_err(|e| crate::common::error::Error::Config(
+                    format!("Tunnel {}: {}", tunnel.name, e)
+                ))?;
+            
+            if self.group_routers(tunnel.router_group()).is_empty() {
+                return Err(crate::common::error::Error::Config(
+                    format!("Tunnel {}: no router in group '{}'", tunnel.name, tunnel.router_group())
+                ));
+            }
         }
         
         Ok(())
     }
     
-    /// ConnectionConfigに変換
-    pub fn to_connection_config(&self) -> ConnectionConfig {
+    /// グループに属するRouter（設定順）
+    pub fn group_routers(&self, group: &str) -> Vec<&RouterConfig> {
+        self.routers.iter().filter(|r| r.group == group).collect()
+    }
+    
+    /// 接続が必要なRouterグループ（有効なトンネルが使うグループ。トンネルが無ければ全グループ）
+    pub fn router_groups(&self) -> Vec<String> {
+        let mut groups: Vec<String> = self.tunnels.iter()
+            .filter(|t| t.enabled)
+            .map(|t| t.router_group().to_string())
+            .collect();
+        if groups.is_empty() {
+            groups = self.routers.iter().map(|r| r.group.clone()).collect();
+        }
+        groups.sort();
+        groups.dedup();
+        groups
+    }
+    
+    /// グループのRouterへ接続するConnectionConfigに変換
+    pub fn to_connection_config(&self, group: &str) -> ConnectionConfig {
+        let routers = self.group_routers(group);
         ConnectionConfig {
-            router_addr: format!("{}:{}", self.router.host, self.router.port),
+            routers: routers.iter()
+                .map(|r| RouterEndpoint { addr: r.addr(), priority: r.priority })
+                .collect(),
+            router_group: group.to_string(),
+            failover: self.connection.failover.clone(),
             client_id: self.client.id,
             client_name: self.client.name.clone(),
             auto_reconnect: self.connection.auto_reconnect,
-            reconnect_interval_seconds: self.connection.reconnect_interval_seconds,
-            max_reconnect_attempts: self.connection.max_reconnect_attempts,
-            connection_timeout_seconds: self.router.connect_timeout_seconds,
+            retry: self.connection.retry_policy(),
+            // 接続タイムアウトはグループ内で最も長いものに揃える
+            connection_timeout_seconds: routers.iter()
+                .map(|r| r.connect_timeout_seconds)
+                .max()
+                .unwrap_or(30),
             heartbeat_enabled: self.connection.heartbeat_enabled,
             heartbeat_interval_seconds: self.connection.heartbeat_interval_seconds,
         }
@@ -255,8 +411,8 @@ mod tests {
     fn test_client_config_default() {
         let config = ClientConfig::default();
         assert_eq!(config.client.name, "conduit-client");
-        assert_eq!(config.router.host, "0.0.0.0");
-        assert_eq!(config.router.port, 9999);
+        assert_eq!(config.routers[0].host, "0.0.0.0");
+        assert_eq!(config.routers[0].port, 9999);
         assert!(config.connection.auto_reconnect);
     }
 
@@ -266,14 +422,32 @@ mod tests {
         assert!(config.validate().is_ok());
         
         // 無効な設定をテスト
-        config.router.host = "".to_string();
+        config.routers[0].host = "".to_string();
         assert!(config.validate().is_err());
         
-        config.router.host = "localhost".to_string();
-        config.router.port = 0;
+        config.routers[0].host = "localhost".to_string();
+        config.routers[0].port = 0;
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_legacy_reconnect_settings() {
+        let settings: ConnectionSettings = toml::from_str(
+            "auto_reconnect = true\n\
+             reconnect_interval_seconds = 10\n\
+             max_reconnect_attempts = 5\n\
+             heartbeat_enabled = true\n\
+             heartbeat_interval_seconds = 60\n",
+        ).unwrap();
+        
+        let policy = settings.retry_policy();
+        assert_eq!(policy.initial_delay_ms, 10_000);
+        assert_eq!(policy.max_attempts, 5);
+        // 指定のない項目は既定値のまま
+        assert_eq!(policy.max_delay_ms, RetryPolicy::default().max_delay_ms);
+        assert!(policy.validate().is_ok());
+    }
+
     #[test]
     fn test_file_operations() {
         let config = ClientConfig::default();
@@ -286,16 +460,67 @@ mod tests {
         // 読み込み
         let loaded_config = ClientConfig::from_file(&config_path).unwrap();
         assert_eq!(config.client.name, loaded_config.client.name);
-        assert_eq!(config.router.host, loaded_config.router.host);
+        assert_eq!(config.routers[0].host, loaded_config.routers[0].host);
     }
 
     #[test]
     fn test_connection_config_conversion() {
         let client_config = ClientConfig::default();
-        let connection_config = client_config.to_connection_config();
+        let connection_config = client_config.to_connection_config(DEFAULT_ROUTER_GROUP);
         
         assert_eq!(connection_config.client_id, client_config.client.id);
         assert_eq!(connection_config.client_name, client_config.client.name);
-        assert_eq!(connection_config.router_addr, "0.0.0.0:9999");
+        assert_eq!(connection_config.routers[0].addr, "0.0.0.0:9999");
+    }
+
+    #[test]
+    fn test_router_groups() {
+        let mut config = ClientConfig::default();
+        // 単一Routerの旧形式
+        let content = toml::to_string(&config).unwrap().replace("[[router]]", "[router]");
+        let parsed: ClientConfig = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.routers.len(), 1);
+        assert_eq!(parsed.routers[0].group, DEFAULT_ROUTER_GROUP);
+        
+        config.routers = vec![
+            RouterConfig { host: "tokyo-a".to_string(), priority: 10, group: "tokyo".to_string(), ..Default::default() },
+            RouterConfig { host: "tokyo-b".to_string(), priority: 20, group: "tokyo".to_string(), ..Default::default() },
+            RouterConfig { host: "osaka".to_string(), ..Default::default() },
+        ];
+        config.tunnels.push(TunnelConfig {
+            name: "wiki".to_string(),
+            source: "10.0.0.5:80".parse().unwrap(),
+            bind: "127.0.0.1:8080".parse().unwrap(),
+            protocol: "tcp".to_string(),
+            enabled: true,
+            settings: TunnelSettings::default(),
+            router_group: Some("tokyo".to_string()),
+        });
+        assert!(config.validate().is_ok());
+        assert_eq!(config.router_groups(), vec!["tokyo".to_string()]);
+        
+        // レプリカは配列で書け、重複した転送先は設定エラーにする
+        let tunnel = toml::to_string(&config.tunnels[0]).unwrap();
+        let replicas: TunnelConfig = toml::from_str(
+            &tunnel.replace(r#"source = "10.0.0.5:80""#, r#"source = ["10.0.0.5:80", "10.0.0.6:80"]"#)
+        ).unwrap();
+        assert_eq!(replicas.source.parse().unwrap().len(), 2);
+        let duplicated: TunnelConfig = toml::from_str(
+            &tunnel.replace(r#"source = "10.0.0.5:80""#, r#"source = ["10.0.0.5:80", "10.0.0.5:80"]"#)
+        ).unwrap();
+        let original = std::mem::replace(&mut config.tunnels[0], duplicated);
+        assert!(config.validate().is_err());
+        config.tunnels[0] = original;
+        
+        let connection_config = config.to_connection_config("tokyo");
+        let addrs: Vec<&str> = connection_config.routers.iter().map(|r| r.addr.as_str()).collect();
+        assert_eq!(addrs, vec!["tokyo-a:9999", "tokyo-b:9999"]);
+        
+        // 保存すると[[router]]の並びになる
+        let content = toml::to_string(&config).unwrap();
+        assert_eq!(content.matches("[[router]]").count(), 3);
+        
+        config.tunnels[0].router_group = Some("nagoya".to_string());
+        assert!(config.validate().is_err());
     }
 }
\ No newline at end of file
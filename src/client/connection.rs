@@ -2,27 +2,49 @@
 //
 // Router接続管理、自動再接続機能、接続状態監視、Heartbeat処理を提供します
 
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpStream;
-use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::sync::{mpsc, RwLock};
 use tokio::time::{sleep, Instant};
-use tokio_rustls::client::TlsStream as ClientTlsStream;
 use uuid::Uuid;
 use tracing::{debug, info, warn, error, instrument};
 
 use crate::protocol::{
     ProtocolHandler, ProtocolHandlerConfig, ConnectionState,
-    Message, MessageType, MessagePayload, Heartbeat,
+    Message, MessageType, MessagePayload, Heartbeat, HeartbeatResponse, TargetHealth, TargetState,
+    Negotiated, TunnelCreate, TunnelData,
 };
+use crate::protocol::handler::MessageHandler;
 use crate::security::{TlsClientConfig, AuthManager, TlsConfig, KeyManager, KeyRotationConfig};
 use crate::common::error::Result;
+use crate::common::retry::{Backoff, CircuitState, RetryPolicy};
+use crate::client::failover::{FailoverEvent, FailoverReason, FailoverSettings, RouterEndpoint, RouterPool};
+use crate::notifier::{Alert, AlertKind, Notifier};
+
+/// 再接続の連続失敗をアラートとして通知する回数
+const RECONNECT_ALERT_THRESHOLD: usize = 3;
+
+/// 1つのRouterへの接続試行で再試行する回数（使い切ったら次のRouterへ切り替える）
+const CONNECT_ATTEMPT_RETRIES: u32 = 3;
+
+/// 書き込みタスクへ渡す送信キューの長さ
+const OUTBOUND_QUEUE_SIZE: usize = 256;
 
 /// 接続管理設定
 #[derive(Debug, Clone)]
 pub struct ConnectionConfig {
-    /// Router接続アドレス
-    pub router_addr: String,
+    /// 接続先のRouter（同じグループ内で優先度順に切り替える）
+    pub routers: Vec<RouterEndpoint>,
+    
+    /// Routerグループ名
+    pub router_group: String,
+    
+    /// フェイルオーバー・フェイルバック設定
+    pub failover: FailoverSettings,
     
     /// Client ID
     pub client_id: Uuid,
@@ -33,11 +55,8 @@ pub struct ConnectionConfig {
     /// 自動再接続有効
     pub auto_reconnect: bool,
     
-    /// 再接続間隔（秒）
-    pub reconnect_interval_seconds: u64,
-    
-    /// 最大再接続回数（0で無制限）
-    pub max_reconnect_attempts: usize,
+    /// 再接続の待ち時間・回数とサーキットブレーカー
+    pub retry: RetryPolicy,
     
     /// 接続タイムアウト（秒）
     pub connection_timeout_seconds: u64,
@@ -52,12 +71,13 @@ pub struct ConnectionConfig {
 impl Default for ConnectionConfig {
     fn default() -> Self {
         Self {
-            router_addr: "127.0.0.1:9999".to_string(),
+            routers: vec![RouterEndpoint { addr: "127.0.0.1:9999".to_string(), priority: 0 }],
+            router_group: "default".to_string(),
+            failover: FailoverSettings::default(),
             client_id: Uuid::new_v4(),
             client_name: "conduit-client".to_string(),
             auto_reconnect: true,
-            reconnect_interval_seconds: 5,
-            max_reconnect_attempts: 0, // 無制限
+            retry: RetryPolicy::default(),
             connection_timeout_seconds: 30,
             heartbeat_enabled: true,
             heartbeat_interval_seconds: 60,
@@ -80,6 +100,9 @@ pub struct ConnectionStats {
     /// 再接続回数
     pub reconnect_count: u64,
     
+    /// 認証失敗回数
+    pub auth_failures: u64,
+    
     /// 送信メッセージ数
     pub messages_sent: u64,
     
@@ -93,25 +116,82 @@ pub struct ConnectionStats {
     pub bytes_received: u64,
 }
 
+/// 再接続の待機状態
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectStatus {
+    /// 連続失敗回数（接続できていた後の切断では0）
+    pub failures: u32,
+    
+    /// 次の接続試行の時刻
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+    
+    /// サーキットブレーカーの状態
+    pub circuit: CircuitState,
+}
+
+impl ReconnectStatus {
+    /// 次の試行までの残り時間
+    pub fn next_attempt_in(&self) -> Duration {
+        (self.next_attempt_at - chrono::Utc::now()).to_std().unwrap_or_default()
+    }
+}
+
 /// 接続イベント
 #[derive(Debug, Clone)]
 pub enum ConnectionEvent {
     Connected,
     Disconnected,
-    Reconnecting,
+    /// 次の接続試行を待っている
+    Reconnecting(ReconnectStatus),
     Authenticated,
     Error(String),
     HeartbeatSent,
     HeartbeatReceived,
+    /// Router側サービスの疎通状態が変化した（トンネル自体は接続中）
+    TargetHealthChanged(TargetHealth),
+    /// 登録時にRouterとプロトコルのバージョン・機能を合意した
+    ProtocolNegotiated(Negotiated),
+    /// 切断前のセッションを引き継ぎ、Routerが受信していなかったデータを再送した
+    SessionResumed { replayed: usize },
+    /// 同じグループの別のRouterへ切り替えた（フェイルオーバー・フェイルバック）
+    Failover(FailoverEvent),
+}
+
+// 1回の接続で使うRouterと、切り替えの判断材料
+struct SessionTarget<'a> {
+    router: &'a RouterEndpoint,
+    // 優先度の低いRouterに接続している間、復旧を確かめる優先Router
+    failback_to: Option<RouterEndpoint>,
+    // この巡回でまだ切り替え先があるか
+    can_fail_over: bool,
+}
+
+// 接続が終わった理由
+enum SessionEnd {
+    Closed,
+    TargetDown,
+    Failback,
 }
 
 /// 接続マネージャー
+#[derive(Clone)]
 pub struct ConnectionManager {
     config: ConnectionConfig,
     protocol_handler: Arc<ProtocolHandler>,
-    connection: Arc<Mutex<Option<ClientTlsStream<TcpStream>>>>,
+    // 接続中のセッションの送信キュー（書き込みタスクが順にRouterへ書き出す）
+    outbound: Arc<RwLock<Option<mpsc::Sender<Message>>>>,
+    // Routerに登録するトンネル。認証のたびに送り直す（切り替え先のRouterはトンネルを知らない）
+    tunnels: Arc<RwLock<Vec<TunnelCreate>>>,
+    // 今の接続でRouterが受け入れたトンネル（合意した機能に合わせて調整済み）
+    accepted_tunnels: Arc<RwLock<HashMap<Uuid, TunnelCreate>>>,
     stats: Arc<RwLock<ConnectionStats>>,
+    target_health: Arc<RwLock<HashMap<SocketAddr, TargetHealth>>>,
+    reconnect_status: Arc<RwLock<Option<ReconnectStatus>>>,
+    active_router: Arc<RwLock<Option<String>>>,
+    // ターゲットのdownが報告され、別のRouterへの切り替えを求めている
+    failover_requested: Arc<AtomicBool>,
     event_tx: Option<mpsc::UnboundedSender<ConnectionEvent>>,
+    notifier: Option<Arc<Notifier>>,
     shutdown_tx: Option<mpsc::Sender<()>>,
 }
 
@@ -125,6 +205,7 @@ impl ConnectionManager {
         let protocol_config = ProtocolHandlerConfig {
             connect_timeout_seconds: config.connection_timeout_seconds,
             heartbeat_interval_seconds: config.heartbeat_interval_seconds,
+            retry: config.retry.within_attempt(CONNECT_ATTEMPT_RETRIES),
             ..Default::default()
         };
         
@@ -137,23 +218,114 @@ impl ConnectionManager {
         Self {
             config,
             protocol_handler,
-            connection: Arc::new(Mutex::new(None)),
+            outbound: Arc::new(RwLock::new(None)),
+            tunnels: Arc::new(RwLock::new(Vec::new())),
+            accepted_tunnels: Arc::new(RwLock::new(HashMap::new())),
             stats: Arc::new(RwLock::new(ConnectionStats::default())),
+            target_health: Arc::new(RwLock::new(HashMap::new())),
+            reconnect_status: Arc::new(RwLock::new(None)),
+            active_router: Arc::new(RwLock::new(None)),
+            failover_requested: Arc::new(AtomicBool::new(false)),
             event_tx: None,
+            notifier: None,
             shutdown_tx: None,
         }
     }
     
+    /// Routerから届いた要求以外のメッセージ（トンネルデータなど）の処理先を設定（startより前に呼ぶ）
+    pub fn set_message_handler(&mut self, handler: Arc<dyn MessageHandler>) -> Result<()> {
+        Arc::get_mut(&mut self.protocol_handler)
+            .ok_or_else(|| crate::common::error::Error::Config(
+                "Message handler must be set before the connection manager starts".to_string()
+            ))?
+            .set_message_handler(handler);
+        Ok(())
+    }
+    
     /// イベント通知チャンネルを設定
     pub fn set_event_channel(&mut self, tx: mpsc::UnboundedSender<ConnectionEvent>) {
         self.event_tx = Some(tx);
     }
     
+    /// 切断・再接続失敗を外部へ通知するNotifierを設定
+    pub fn set_notifier(&mut self, notifier: Arc<Notifier>) {
+        self.notifier = Some(notifier);
+    }
+    
     /// 接続統計情報を取得
     pub async fn stats(&self) -> ConnectionStats {
         self.stats.read().await.clone()
     }
     
+    /// Routerから通知された最新のターゲットヘルス情報を取得
+    pub async fn target_health(&self) -> Vec<TargetHealth> {
+        let mut health: Vec<TargetHealth> = self.target_health.read().await.values().cloned().collect();
+        health.sort_by_key(|h| h.target_addr);
+        health
+    }
+    
+    /// ハートビート応答を処理し、状態が変化したターゲットをイベント通知
+    pub async fn handle_heartbeat_response(&self, response: &HeartbeatResponse) {
+        {
+            let mut stats_guard = self.stats.write().await;
+            stats_guard.last_heartbeat = Some(Instant::now());
+            stats_guard.messages_received += 1;
+        }
+        
+        if let Some(ref tx) = self.event_tx {
+            let _ = tx.send(ConnectionEvent::HeartbeatReceived);
+        }
+        
+        // このClientのトンネルが使う転送先だけを扱う（他のトンネルのターゲットで切り替えない）
+        let sources: HashSet<SocketAddr> = self
+            .tunnels
+            .read()
+            .await
+            .iter()
+            .flat_map(|t| std::iter::once(t.source_addr).chain(t.source_addrs.iter().copied()))
+            .collect();
+        let target_health: Vec<&TargetHealth> = response
+            .target_health
+            .iter()
+            .filter(|h| sources.contains(&h.target_addr))
+            .collect();
+        
+        let mut known = self.target_health.write().await;
+        for health in target_health.iter().copied() {
+            let changed = known
+                .get(&health.target_addr)
+                .is_none_or(|previous| previous.state != health.state);
+            known.insert(health.target_addr, health.clone());
+            
+            if changed {
+                if let Some(ref tx) = self.event_tx {
+                    let _ = tx.send(ConnectionEvent::TargetHealthChanged(health.clone()));
+                }
+            }
+        }
+        
+        // 接続自体は保たれていても、ターゲットに届かないRouterは使わない
+        let target_down = target_health.iter().any(|h| h.state == TargetState::Down);
+        if target_down && self.config.failover.on_target_down && self.config.routers.len() > 1 {
+            self.failover_requested.store(true, Ordering::Relaxed);
+        }
+    }
+    
+    /// Routerと合意したプロトコルのバージョンと機能
+    pub async fn negotiated(&self) -> Option<Negotiated> {
+        self.protocol_handler.negotiated().await
+    }
+    
+    /// 接続中・接続試行中のRouterのアドレス
+    pub async fn active_router(&self) -> Option<String> {
+        self.active_router.read().await.clone()
+    }
+    
+    /// 再接続を待っている間の状態（接続試行中・接続中はNone）
+    pub async fn reconnect_status(&self) -> Option<ReconnectStatus> {
+        self.reconnect_status.read().await.clone()
+    }
+    
     /// 接続状態を取得
     pub async fn connection_state(&self) -> ConnectionState {
         self.protocol_handler.connection_state().await
@@ -164,117 +336,270 @@ impl ConnectionManager {
     pub async fn start(&mut self) -> Result<()> {
         info!("Starting connection manager for client: {}", self.config.client_name);
         
+        // 接続ループ用の複製。停止チャンネルの送信側を持たせると、破棄しても止まらなくなるため先に作る
+        let manager = self.clone();
+        
         // シャットダウンチャンネル
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
         self.shutdown_tx = Some(shutdown_tx);
         
         let config = self.config.clone();
-        let protocol_handler = self.protocol_handler.clone();
-        let connection = self.connection.clone();
         let stats = self.stats.clone();
         let event_tx = self.event_tx.clone();
+        let notifier = self.notifier.clone();
+        let reconnect_status = self.reconnect_status.clone();
+        let active_router = self.active_router.clone();
         
         // メイン接続ループ
         tokio::spawn(async move {
-            let mut reconnect_count = 0;
+            // 一度でも接続できたらリセットし、連続失敗のみを数える
+            let mut backoff = Backoff::new(config.retry.clone());
+            let mut pool = RouterPool::new(config.router_group.clone(), config.routers.clone());
             
             loop {
+                backoff.begin_attempt();
+                *reconnect_status.write().await = None;
+                let router = pool.active().clone();
+                *active_router.write().await = Some(router.addr.clone());
+                let target = SessionTarget {
+                    router: &router,
+                    failback_to: (!pool.is_preferred()).then(|| pool.preferred().clone()),
+                    can_fail_over: pool.has_alternative(),
+                };
+                
                 tokio::select! {
                     _ = shutdown_rx.recv() => {
                         info!("Shutdown signal received");
                         break;
                     }
-                    result = Self::connection_loop(
-                        &config,
-                        Arc::clone(&protocol_handler),
-                        &connection,
-                        &stats,
-                        &event_tx,
-                    ) => {
-                        if let Err(e) = result {
-                            error!("Connection loop error: {}", e);
-                            
-                            if let Some(ref tx) = event_tx {
-                                let _ = tx.send(ConnectionEvent::Error(e.to_string()));
+                    result = manager.connection_loop(target) => {
+                        let decision = match result {
+                            Ok(end) => {
+                                backoff.record_success();
+                                pool.connected();
+                                
+                                // 別のRouterへ移る場合は待たずに接続する
+                                let switched = match end {
+                                    SessionEnd::Closed => None,
+                                    SessionEnd::TargetDown => pool.fail_over(FailoverReason::TargetDown),
+                                    SessionEnd::Failback => pool.fail_back(),
+                                };
+                                if let Some(event) = switched {
+                                    Self::announce_failover(&event, &event_tx, &notifier);
+                                    continue;
+                                }
+                                
+                                if let Some(ref notifier) = notifier {
+                                    notifier.notify(Alert::new(
+                                        AlertKind::RouterDisconnected,
+                                        &router.addr,
+                                        "Connection to router was closed",
+                                    ));
+                                }
+                                // 接続できていた後の切断は初回の待ち時間で再接続する
+                                Some((0, backoff.policy().delay(1)))
                             }
-                        }
+                            Err(e) => {
+                                error!("Connection loop error ({}): {}", router.addr, e);
+                                
+                                if let Some(ref tx) = event_tx {
+                                    let _ = tx.send(ConnectionEvent::Error(e.to_string()));
+                                }
+                                if let Some(ref notifier) = notifier {
+                                    notifier.notify(Alert::new(
+                                        AlertKind::ConnectionError,
+                                        &router.addr,
+                                        e.to_string(),
+                                    ));
+                                }
+                                
+                                // 同じグループのRouterを一巡するまでは待たずに切り替える
+                                if let Some(event) = pool.fail_over(FailoverReason::Unreachable) {
+                                    Self::announce_failover(&event, &event_tx, &notifier);
+                                    continue;
+                                }
+                                
+                                // 一巡しても接続できなければ、最優先のRouterからバックオフしてやり直す
+                                let decision = backoff.record_failure();
+                                let consecutive_failures = backoff.failures() as usize;
+                                let group = pool.group().to_string();
+                                if let Some(event) = pool.restart_round() {
+                                    Self::announce_failover(&event, &event_tx, &None);
+                                }
+                                if let Some(ref notifier) = notifier {
+                                    if consecutive_failures == RECONNECT_ALERT_THRESHOLD {
+                                        notifier.notify(Alert::new(
+                                            AlertKind::ReconnectFailed,
+                                            &group,
+                                            format!("{} consecutive reconnect attempts failed for every router: {}", consecutive_failures, e),
+                                        ));
+                                    }
+                                }
+                                decision.map(|d| (d.failures, d.delay))
+                            }
+                        };
                         
                         // 再接続処理
-                        if config.auto_reconnect {
-                            reconnect_count += 1;
-                            
-                            if config.max_reconnect_attempts > 0 && reconnect_count > config.max_reconnect_attempts {
-                                error!("Maximum reconnect attempts reached");
-                                break;
-                            }
-                            
-                            info!("Reconnecting in {} seconds (attempt {})", 
-                                config.reconnect_interval_seconds, reconnect_count);
-                            
-                            if let Some(ref tx) = event_tx {
-                                let _ = tx.send(ConnectionEvent::Reconnecting);
+                        if !config.auto_reconnect {
+                            break;
+                        }
+                        let Some((failures, delay)) = decision else {
+                            error!("Maximum reconnect attempts reached");
+                            if let Some(ref notifier) = notifier {
+                                notifier.notify(Alert::new(
+                                    AlertKind::ReconnectFailed,
+                                    pool.group(),
+                                    format!("Giving up after {} reconnect attempts", backoff.failures()),
+                                ));
                             }
-                            
-                            sleep(Duration::from_secs(config.reconnect_interval_seconds)).await;
-                        } else {
                             break;
+                        };
+                        
+                        let status = ReconnectStatus {
+                            failures,
+                            next_attempt_at: chrono::Utc::now()
+                                + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::zero()),
+                            circuit: backoff.circuit(),
+                        };
+                        if status.circuit == CircuitState::Open {
+                            warn!(
+                                "Circuit breaker open after {} consecutive failures; next attempt in {:?}",
+                                failures, delay
+                            );
+                        } else {
+                            info!("Reconnecting in {:?} (consecutive failures: {})", delay, failures);
+                        }
+                        stats.write().await.reconnect_count += 1;
+                        *reconnect_status.write().await = Some(status.clone());
+                        
+                        if let Some(ref tx) = event_tx {
+                            let _ = tx.send(ConnectionEvent::Reconnecting(status));
+                        }
+                        
+                        // 回路が開いている間は長く待つため、停止要求で待機を打ち切れるようにする
+                        tokio::select! {
+                            _ = shutdown_rx.recv() => {
+                                info!("Shutdown signal received");
+                                break;
+                            }
+                            _ = sleep(delay) => {}
                         }
                     }
                 }
             }
+            
+            *active_router.write().await = None;
         });
         
         Ok(())
     }
     
-    /// 接続ループ
-    async fn connection_loop(
-        config: &ConnectionConfig,
-        protocol_handler: Arc<ProtocolHandler>,
-        connection: &Arc<Mutex<Option<ClientTlsStream<TcpStream>>>>,
-        stats: &Arc<RwLock<ConnectionStats>>,
+    // 接続先の切り替えをログ・イベント・通知で知らせる
+    fn announce_failover(
+        event: &FailoverEvent,
         event_tx: &Option<mpsc::UnboundedSender<ConnectionEvent>>,
-    ) -> Result<()> {
+        notifier: &Option<Arc<Notifier>>,
+    ) {
+        warn!(
+            "Switching router for group {}: {} -> {} ({})",
+            event.group, event.from, event.to, event.reason
+        );
+        if let Some(ref tx) = event_tx {
+            let _ = tx.send(ConnectionEvent::Failover(event.clone()));
+        }
+        if let Some(ref notifier) = notifier {
+            notifier.notify(Alert::new(
+                AlertKind::ConnectionError,
+                &event.from,
+                format!("Failed over to {} ({})", event.to, event.reason),
+            ));
+        }
+    }
+    
+    // フェイルバック前に優先Routerが接続を受け付けるか確かめる
+    async fn probe_router(addr: &str, timeout_seconds: u64) -> bool {
+        matches!(
+            tokio::time::timeout(Duration::from_secs(timeout_seconds), TcpStream::connect(addr)).await,
+            Ok(Ok(_))
+        )
+    }
+    
+    /// 接続ループ
+    async fn connection_loop(&self, target: SessionTarget<'_>) -> Result<SessionEnd> {
+        let config = &self.config;
+        let protocol_handler = &self.protocol_handler;
+        
         // Router接続
-        let mut stream = protocol_handler.connect(&config.router_addr).await
+        let mut stream = protocol_handler.connect(&target.router.addr).await
             .map_err(|e| crate::common::error::Error::Network(e.to_string()))?;
+        // 前のRouterで報告されたdownは持ち越さない
+        self.failover_requested.store(false, Ordering::Relaxed);
         
         // 接続統計更新
         {
-            let mut stats_guard = stats.write().await;
+            let mut stats_guard = self.stats.write().await;
             stats_guard.connected_at = Some(Instant::now());
             stats_guard.total_connections += 1;
         }
         
-        if let Some(ref tx) = event_tx {
+        if let Some(ref tx) = self.event_tx {
             let _ = tx.send(ConnectionEvent::Connected);
         }
         
         // 認証実行
-        protocol_handler.authenticate(&mut stream, config.client_id, config.client_name.clone()).await
-            .map_err(|e| crate::common::error::Error::Authentication(e.to_string()))?;
+        let registration = match protocol_handler.authenticate(&mut stream, config.client_id, config.client_name.clone()).await {
+            Ok(registration) => registration,
+            Err(e) => {
+                self.stats.write().await.auth_failures += 1;
+                return Err(crate::common::error::Error::Authentication(e.to_string()));
+            }
+        };
         
-        if let Some(ref tx) = event_tx {
+        // 切断中にRouterへ届かなかったデータを、新しいデータより先に送り直す
+        let replayed = registration.replay.len();
+        for data in registration.replay {
+            protocol_handler.send_tunnel_data(&mut stream, data).await
+                .map_err(|e| crate::common::error::Error::Network(e.to_string()))?;
+        }
+        
+        if let Some(ref tx) = self.event_tx {
             let _ = tx.send(ConnectionEvent::Authenticated);
+            if let Some(negotiated) = protocol_handler.negotiated().await {
+                let _ = tx.send(ConnectionEvent::ProtocolNegotiated(negotiated));
+            }
+            if registration.resumed {
+                let _ = tx.send(ConnectionEvent::SessionResumed { replayed });
+            }
         }
         
-        // 接続を保存
-        *connection.lock().await = Some(stream);
+        // 送信は書き込みタスクに集め、受信ループが応答を待機中の要求へ振り分ける
+        let (reader, writer) = tokio::io::split(stream);
+        let (outbound_tx, outbound_rx) = mpsc::channel(OUTBOUND_QUEUE_SIZE);
+        let responses = outbound_tx.clone();
+        let writer_handle = tokio::spawn({
+            let protocol_handler = Arc::clone(protocol_handler);
+            async move {
+                if let Err(e) = protocol_handler.start_writer_loop(writer, outbound_rx).await {
+                    error!("Writer loop error: {}", e);
+                }
+            }
+        });
+        let reader_handle = tokio::spawn({
+            let protocol_handler = Arc::clone(protocol_handler);
+            async move {
+                if let Err(e) = protocol_handler.start_message_loop(reader, responses).await {
+                    error!("Message loop error: {}", e);
+                }
+            }
+        });
+        *self.outbound.write().await = Some(outbound_tx);
+        self.register_tunnels().await;
         
         // ハートビートループを開始（有効な場合）
         let heartbeat_handle = if config.heartbeat_enabled {
-            let connection_clone = connection.clone();
-            let protocol_handler_clone = Arc::clone(&protocol_handler);
-            let client_id = config.client_id;
-            let event_tx_clone = event_tx.clone();
-            
+            let manager = self.clone();
             Some(tokio::spawn(async move {
-                if let Err(e) = Self::heartbeat_loop(
-                    &protocol_handler_clone,
-                    connection_clone,
-                    client_id,
-                    &event_tx_clone,
-                ).await {
+                if let Err(e) = manager.heartbeat_loop().await {
                     error!("Heartbeat loop error: {}", e);
                 }
             }))
@@ -282,119 +607,221 @@ impl ConnectionManager {
             None
         };
         
-        // メッセージ受信ループ
-        let connection_guard = connection.lock().await;
-        if let Some(stream) = connection_guard.as_ref() {
-            // ストリームのクローンを作成（実際にはArcでラップする必要があります）
-            drop(connection_guard);
+        // 接続状態の監視（受信ループが切断を検知すると状態が変わる）
+        let mut end = SessionEnd::Closed;
+        let failback_interval = Duration::from_secs(config.failover.failback_interval_seconds);
+        let mut next_failback_check = Instant::now() + failback_interval;
+        loop {
+            sleep(Duration::from_secs(1)).await;
             
-            // メッセージループ開始（簡略化）
-            loop {
-                sleep(Duration::from_secs(1)).await;
-                
-                // 接続状態チェック
-                let state = protocol_handler.connection_state().await;
-                if !matches!(state, ConnectionState::Connected | ConnectionState::Authenticated) {
-                    break;
+            // 接続状態チェック
+            let state = protocol_handler.connection_state().await;
+            if !matches!(state, ConnectionState::Connected | ConnectionState::Authenticated) {
+                break;
+            }
+            
+            // 切り替え先が無ければ、ターゲットがdownでも今のRouterに留まる
+            if target.can_fail_over && self.failover_requested.swap(false, Ordering::Relaxed) {
+                end = SessionEnd::TargetDown;
+                break;
+            }
+            
+            if let Some(ref preferred) = target.failback_to {
+                if !failback_interval.is_zero() && Instant::now() >= next_failback_check {
+                    next_failback_check = Instant::now() + failback_interval;
+                    if Self::probe_router(&preferred.addr, config.connection_timeout_seconds).await {
+                        info!("Preferred router {} is reachable again", preferred.addr);
+                        end = SessionEnd::Failback;
+                        break;
+                    }
                 }
             }
         }
         
-        // ハートビートループ終了
+        // Routerを切り替える場合は、こちらから今の接続を閉じる
+        if !matches!(end, SessionEnd::Closed) {
+            protocol_handler.disconnect().await
+                .map_err(|e| crate::common::error::Error::Network(e.to_string()))?;
+        }
+        
+        // ハートビート・送受信のタスクを終え、接続をクリア
         if let Some(handle) = heartbeat_handle {
             handle.abort();
         }
+        *self.outbound.write().await = None;
+        self.accepted_tunnels.write().await.clear();
+        writer_handle.abort();
+        reader_handle.abort();
         
-        // 接続をクリア
-        *connection.lock().await = None;
-        
-        if let Some(ref tx) = event_tx {
+        if let Some(ref tx) = self.event_tx {
             let _ = tx.send(ConnectionEvent::Disconnected);
         }
         
-        Ok(())
+        Ok(end)
     }
     
     /// ハートビートループ
-    async fn heartbeat_loop(
-        protocol_handler: &ProtocolHandler,
-        connection: Arc<Mutex<Option<ClientTlsStream<TcpStream>>>>,
-        client_id: Uuid,
-        event_tx: &Option<mpsc::UnboundedSender<ConnectionEvent>>,
-    ) -> Result<()> {
-        let mut interval = tokio::time::interval(Duration::from_secs(60));
+    ///
+    /// 応答に含まれるターゲットヘルスは`handle_heartbeat_response`で反映する
+    async fn heartbeat_loop(&self) -> Result<()> {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.heartbeat_interval_seconds.max(1)));
         
         loop {
             interval.tick().await;
             
-            let mut connection_guard = connection.lock().await;
-            if let Some(ref mut stream) = *connection_guard {
-                // ハートビートメッセージ作成
-                let heartbeat = Heartbeat {
-                    client_id,
-                    active_tunnels: 0, // TODO: 実際の値を取得
-                    active_connections: 0, // TODO: 実際の値を取得
-                    cpu_usage: 0.0, // TODO: 実際の値を取得
-                    memory_usage: 0, // TODO: 実際の値を取得
-                };
-                
-                let payload = MessagePayload::Heartbeat(heartbeat);
-                let message = Message::new(MessageType::Heartbeat, payload);
-                
-                // NOTE: この部分は実際の実装では異なる方法が必要
-                // 現在はコンパイルエラーを避けるため簡略化
-                
-                if let Some(ref tx) = event_tx {
-                    let _ = tx.send(ConnectionEvent::HeartbeatSent);
+            // ハートビートメッセージ作成
+            let heartbeat = Heartbeat {
+                client_id: self.config.client_id,
+                active_tunnels: 0, // TODO: 実際の値を取得
+                active_connections: 0, // TODO: 実際の値を取得
+                cpu_usage: 0.0, // TODO: 実際の値を取得
+                memory_usage: 0, // TODO: 実際の値を取得
+            };
+            let message = Message::new(MessageType::Heartbeat, MessagePayload::Heartbeat(heartbeat));
+            
+            if let Some(ref tx) = self.event_tx {
+                let _ = tx.send(ConnectionEvent::HeartbeatSent);
+            }
+            debug!("Heartbeat sent");
+            
+            match self.send_message(message).await {
+                Ok(Message { payload: MessagePayload::HeartbeatResponse(response), .. }) => {
+                    self.handle_heartbeat_response(&response).await;
+                }
+                Ok(response) => {
+                    warn!("Unexpected heartbeat response: {:?}", response.message_type);
+                }
+                Err(e) => {
+                    // 応答しないRouterとの接続は切れたものとして再接続させる
+                    warn!("Heartbeat failed: {}", e);
+                    self.protocol_handler.disconnect().await
+                        .map_err(|e| crate::common::error::Error::Network(e.to_string()))?;
+                    return Err(e);
                 }
-                
-                debug!("Heartbeat sent");
-            } else {
-                debug!("No active connection for heartbeat");
-                break;
             }
         }
+    }
+    
+    /// トンネルをRouterへ登録
+    ///
+    /// 接続中ならすぐに送り、Routerが受け入れた内容を返す。未接続ならNoneを返し、次の認証後に送る。
+    /// 登録したトンネルは再接続やRouterの切り替えのたびに送り直す
+    pub async fn register_tunnel(&self, create: TunnelCreate) -> Result<Option<TunnelCreate>> {
+        // 一覧に加えてから接続を確かめる。接続ループは送信キューを用意してから一覧を読むため、
+        // どちらかが必ずこのトンネルを送る
+        {
+            let mut tunnels = self.tunnels.write().await;
+            tunnels.retain(|t| t.tunnel_id != create.tunnel_id);
+            tunnels.push(create.clone());
+        }
+        if self.outbound.read().await.is_none() {
+            return Ok(None);
+        }
         
-        Ok(())
+        match self.send_tunnel_create(&create).await {
+            Ok(accepted) => Ok(Some(accepted)),
+            // 送れなかっただけなら次の接続で送り直す
+            Err(crate::common::error::Error::Network(e)) => {
+                warn!("Tunnel {} will be registered after reconnecting: {}", create.tunnel_name, e);
+                Ok(None)
+            }
+            Err(e) => {
+                self.tunnels.write().await.retain(|t| t.tunnel_id != create.tunnel_id);
+                Err(e)
+            }
+        }
     }
     
-    /// メッセージを送信
-    #[instrument(skip(self, message))]
-    pub async fn send_message(&self, message: Message) -> Result<Message> {
-        let mut connection_guard = self.connection.lock().await;
-        if let Some(ref mut stream) = *connection_guard {
-            // NOTE: この部分は実際の実装では適切な方法が必要
-            // 現在はコンパイルエラーを避けるため簡略化
-            
-            // 統計更新
-            {
-                let mut stats_guard = self.stats.write().await;
-                stats_guard.messages_sent += 1;
+    /// トンネルの登録を取り消す（以降の接続では送らない）
+    pub async fn unregister_tunnel(&self, tunnel_id: Uuid) {
+        self.tunnels.write().await.retain(|t| t.tunnel_id != tunnel_id);
+        self.accepted_tunnels.write().await.remove(&tunnel_id);
+    }
+    
+    /// 今の接続でRouterが受け入れたトンネルの内容
+    pub async fn accepted_tunnel(&self, tunnel_id: Uuid) -> Option<TunnelCreate> {
+        self.accepted_tunnels.read().await.get(&tunnel_id).cloned()
+    }
+    
+    // 認証した接続へ登録済みのトンネルを送り直す
+    async fn register_tunnels(&self) {
+        let tunnels = self.tunnels.read().await.clone();
+        for create in tunnels {
+            if let Err(e) = self.send_tunnel_create(&create).await {
+                error!("Failed to register tunnel {} with router: {}", create.tunnel_name, e);
+                if let Some(ref tx) = self.event_tx {
+                    let _ = tx.send(ConnectionEvent::Error(e.to_string()));
+                }
             }
-            
-            todo!("Implement actual message sending")
-        } else {
-            Err(crate::common::error::Error::Network("No active connection".to_string()))
         }
     }
     
+    // Routerと合意した機能に合わせてトンネル作成要求を送り、受け入れられた内容を返す
+    async fn send_tunnel_create(&self, create: &TunnelCreate) -> Result<TunnelCreate> {
+        let negotiated = self.negotiated().await
+            .ok_or_else(|| crate::common::error::Error::Network(
+                "Protocol has not been negotiated with the router".to_string()
+            ))?;
+        let create = negotiated.check_tunnel_create(create)?;
+        
+        let message = Message::new(MessageType::TunnelCreate, MessagePayload::TunnelCreate(create.clone()))
+            .with_version(negotiated.version);
+        match self.send_message(message).await?.payload {
+            MessagePayload::TunnelCreateResponse(resp) if resp.success => {
+                debug!("Tunnel registered with router: {}", resp.tunnel_id);
+                self.accepted_tunnels.write().await.insert(create.tunnel_id, create.clone());
+                Ok(create)
+            }
+            MessagePayload::TunnelCreateResponse(resp) => Err(crate::common::error::Error::Protocol(format!(
+                "Router rejected tunnel creation: {}",
+                resp.error.as_deref().unwrap_or("Unknown error")
+            ))),
+            _ => Err(crate::common::error::Error::Protocol(
+                "Unexpected response type for tunnel create".to_string()
+            )),
+        }
+    }
+    
+    /// ストリームの次の送信シーケンス番号
+    pub fn next_sequence(&self, tunnel_id: Uuid, connection_id: Uuid) -> u64 {
+        self.protocol_handler.next_sequence(tunnel_id, connection_id)
+    }
+    
+    /// 終わったストリームの再送用バッファを解放
+    pub fn close_stream(&self, tunnel_id: Uuid, connection_id: Uuid) {
+        self.protocol_handler.close_stream(tunnel_id, connection_id);
+    }
+    
+    /// トンネルデータを送信（Routerの確認応答まで再送用に保持する）
+    pub async fn send_tunnel_data(&self, data: TunnelData) -> Result<()> {
+        let outbound = self.outbound().await?;
+        self.stats.write().await.messages_sent += 1;
+        self.protocol_handler.queue_tunnel_data(&outbound, data).await
+            .map_err(|e| crate::common::error::Error::Network(e.to_string()))
+    }
+    
+    // 接続中のセッションの送信キュー
+    async fn outbound(&self) -> Result<mpsc::Sender<Message>> {
+        self.outbound.read().await.clone()
+            .ok_or_else(|| crate::common::error::Error::Network("No active connection".to_string()))
+    }
+    
+    /// メッセージを送信し、レスポンスを待機
+    #[instrument(skip(self, message))]
+    pub async fn send_message(&self, message: Message) -> Result<Message> {
+        let outbound = self.outbound().await?;
+        self.stats.write().await.messages_sent += 1;
+        self.protocol_handler.request(&outbound, message).await
+            .map_err(|e| crate::common::error::Error::Network(e.to_string()))
+    }
+    
     /// 非同期でメッセージを送信
     #[instrument(skip(self, message))]
     pub async fn send_message_async(&self, message: Message) -> Result<()> {
-        let mut connection_guard = self.connection.lock().await;
-        if let Some(ref mut stream) = *connection_guard {
-            // NOTE: この部分は実際の実装では適切な方法が必要
-            
-            // 統計更新
-            {
-                let mut stats_guard = self.stats.write().await;
-                stats_guard.messages_sent += 1;
-            }
-            
-            todo!("Implement actual async message sending")
-        } else {
-            Err(crate::common::error::Error::Network("No active connection".to_string()))
-        }
+        let outbound = self.outbound().await?;
+        self.stats.write().await.messages_sent += 1;
+        outbound.send(message).await
+            .map_err(|_| crate::common::error::Error::Network("Connection closed".to_string()))
     }
     
     /// 接続を停止
@@ -412,7 +839,7 @@ impl ConnectionManager {
             .map_err(|e| crate::common::error::Error::Network(e.to_string()))?;
         
         // 接続をクリア
-        *self.connection.lock().await = None;
+        *self.outbound.write().await = None;
         
         Ok(())
     }
@@ -493,4 +920,76 @@ mod tests {
         let manager = create_test_connection_manager();
         assert!(!manager.is_connected().await);
     }
+
+    #[tokio::test]
+    async fn test_register_tunnel_before_connecting() {
+        let manager = create_test_connection_manager();
+        let tunnel_id = Uuid::new_v4();
+        let create = TunnelCreate {
+            tunnel_id,
+            tunnel_name: "web".to_string(),
+            source_addr: "10.2.0.2:8080".parse().unwrap(),
+            bind_addr: "127.0.0.1:8080".parse().unwrap(),
+            protocol: "tcp".to_string(),
+            config: crate::protocol::messages::TunnelConfig::default(),
+            service: None,
+            source_addrs: Vec::new(),
+        };
+        
+        // 未接続なら送らずに残し、接続のたびに送る一覧に入れる
+        assert!(manager.register_tunnel(create.clone()).await.unwrap().is_none());
+        assert!(manager.register_tunnel(create).await.unwrap().is_none());
+        assert_eq!(manager.tunnels.read().await.len(), 1);
+        assert!(manager.accepted_tunnel(tunnel_id).await.is_none());
+        
+        manager.unregister_tunnel(tunnel_id).await;
+        assert!(manager.tunnels.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_target_health_change_events() {
+        use crate::protocol::TargetState;
+
+        let mut manager = create_test_connection_manager();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        manager.set_event_channel(tx);
+
+        let target: SocketAddr = "10.2.0.2:8080".parse().unwrap();
+        manager.register_tunnel(TunnelCreate {
+            tunnel_id: Uuid::new_v4(),
+            tunnel_name: "web".to_string(),
+            source_addr: target,
+            bind_addr: "127.0.0.1:8080".parse().unwrap(),
+            protocol: "tcp".to_string(),
+            config: crate::protocol::messages::TunnelConfig::default(),
+            service: None,
+            source_addrs: Vec::new(),
+        }).await.unwrap();
+        let mut health = TargetHealth::unknown(target);
+        health.state = TargetState::Down;
+        // 他のトンネルの転送先は無視する
+        let mut other = TargetHealth::unknown("10.9.0.9:80".parse().unwrap());
+        other.state = TargetState::Down;
+        let response = HeartbeatResponse {
+            server_time: chrono::Utc::now(),
+            connected_clients: 1,
+            total_tunnels: 1,
+            server_load: 0.0,
+            target_health: vec![health, other],
+        };
+
+        // 初回は変化として通知、同じ状態の再通知はしない
+        manager.handle_heartbeat_response(&response).await;
+        manager.handle_heartbeat_response(&response).await;
+
+        let mut changes = 0;
+        while let Ok(event) = rx.try_recv() {
+            if let ConnectionEvent::TargetHealthChanged(h) = event {
+                assert_eq!(h.state, TargetState::Down);
+                changes += 1;
+            }
+        }
+        assert_eq!(changes, 1);
+        assert_eq!(manager.target_health().await.len(), 1);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,217 @@
+// Routerのフェイルオーバー
+//
+// 同じグループの複数のRouterから優先度順に接続先を選び、到達できない・ターゲットがdownと
+// 報告されたRouterから次の候補へ切り替える。優先Routerが復旧したらフェイルバックする
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// フェイルオーバー設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FailoverSettings {
+    /// 優先Routerの復旧を確認する間隔（秒、0でフェイルバックしない）
+    pub failback_interval_seconds: u64,
+
+    /// Routerがターゲットのdownを報告したら別のRouterへ切り替える
+    pub on_target_down: bool,
+}
+
+impl Default for FailoverSettings {
+    fn default() -> Self {
+        Self {
+            failback_interval_seconds: 60,
+            on_target_down: true,
+        }
+    }
+}
+
+/// 接続先のRouter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouterEndpoint {
+    /// 接続アドレス（host:port）
+    pub addr: String,
+
+    /// 優先度（小さいほど優先）
+    pub priority: u32,
+}
+
+/// 接続先を切り替えた理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverReason {
+    /// 接続・認証できなかった
+    Unreachable,
+    /// Routerがターゲットのdownを報告した
+    TargetDown,
+    /// 優先Routerが復旧した
+    Failback,
+}
+
+impl FailoverReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailoverReason::Unreachable => "unreachable",
+            FailoverReason::TargetDown => "target_down",
+            FailoverReason::Failback => "failback",
+        }
+    }
+}
+
+impl fmt::Display for FailoverReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 接続先の切り替え
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailoverEvent {
+    pub group: String,
+    pub from: String,
+    pub to: String,
+    pub reason: FailoverReason,
+}
+
+/// Routerグループ内の接続先の選択
+#[derive(Debug, Clone)]
+pub struct RouterPool {
+    group: String,
+    // 優先度順（同じ優先度は設定順）
+    endpoints: Vec<RouterEndpoint>,
+    active: usize,
+    // 最優先から一巡する間に切り替え元になったRouter
+    tried: Vec<bool>,
+}
+
+impl RouterPool {
+    /// 空のグループは作れない（設定の検証で弾く）
+    pub fn new(group: impl Into<String>, mut endpoints: Vec<RouterEndpoint>) -> Self {
+        assert!(!endpoints.is_empty(), "router group must have at least one router");
+        endpoints.sort_by_key(|e| e.priority);
+        let tried = vec![false; endpoints.len()];
+        Self {
+            group: group.into(),
+            endpoints,
+            active: 0,
+            tried,
+        }
+    }
+
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn endpoints(&self) -> &[RouterEndpoint] {
+        &self.endpoints
+    }
+
+    /// 現在の接続先
+    pub fn active(&self) -> &RouterEndpoint {
+        &self.endpoints[self.active]
+    }
+
+    /// 最も優先度の高いRouter
+    pub fn preferred(&self) -> &RouterEndpoint {
+        &self.endpoints[0]
+    }
+
+    /// 現在の接続先が最優先の優先度か（同じ優先度のRouter間ではフェイルバックしない）
+    pub fn is_preferred(&self) -> bool {
+        self.active().priority == self.preferred().priority
+    }
+
+    /// この巡回でまだ切り替え先にできるRouterがあるか
+    pub fn has_alternative(&self) -> bool {
+        (0..self.endpoints.len()).any(|i| i != self.active && !self.tried[i])
+    }
+
+    /// 現在の接続先を外し、次の候補へ切り替える。一巡して候補が無ければNone
+    pub fn fail_over(&mut self, reason: FailoverReason) -> Option<FailoverEvent> {
+        self.tried[self.active] = true;
+        let len = self.endpoints.len();
+        let next = (1..len)
+            .map(|offset| (self.active + offset) % len)
+            .find(|&i| !self.tried[i])?;
+        Some(self.switch_to(next, reason))
+    }
+
+    /// 接続できたRouterで巡回を終える
+    pub fn connected(&mut self) {
+        self.tried.fill(false);
+    }
+
+    /// 全て失敗した後、最優先のRouterから巡回をやり直す
+    pub fn restart_round(&mut self) -> Option<FailoverEvent> {
+        self.tried.fill(false);
+        (self.active != 0).then(|| self.switch_to(0, FailoverReason::Unreachable))
+    }
+
+    /// 復旧した優先Routerへ戻る
+    pub fn fail_back(&mut self) -> Option<FailoverEvent> {
+        self.tried.fill(false);
+        (!self.is_preferred()).then(|| self.switch_to(0, FailoverReason::Failback))
+    }
+
+    fn switch_to(&mut self, index: usize, reason: FailoverReason) -> FailoverEvent {
+        let from = self.active().addr.clone();
+        self.active = index;
+        FailoverEvent {
+            group: self.group.clone(),
+            from,
+            to: self.active().addr.clone(),
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> RouterPool {
+        RouterPool::new(
+            "site-a",
+            vec![
+                RouterEndpoint { addr: "backup:9999".to_string(), priority: 20 },
+                RouterEndpoint { addr: "primary:9999".to_string(), priority: 10 },
+                RouterEndpoint { addr: "secondary:9999".to_string(), priority: 10 },
+            ],
+        )
+    }
+
+    #[test]
+    fn test_failover_in_priority_order() {
+        let mut pool = pool();
+        assert_eq!(pool.active().addr, "primary:9999");
+
+        let event = pool.fail_over(FailoverReason::Unreachable).unwrap();
+        assert_eq!((event.from.as_str(), event.to.as_str()), ("primary:9999", "secondary:9999"));
+        // 同じ優先度のRouterは優先Routerとして扱う
+        assert!(pool.is_preferred());
+
+        assert_eq!(pool.fail_over(FailoverReason::Unreachable).unwrap().to, "backup:9999");
+        assert!(!pool.has_alternative());
+        assert!(pool.fail_over(FailoverReason::Unreachable).is_none());
+
+        let event = pool.restart_round().unwrap();
+        assert_eq!(event.to, "primary:9999");
+        assert!(pool.has_alternative());
+    }
+
+    #[test]
+    fn test_failback() {
+        let mut pool = pool();
+        assert!(pool.fail_back().is_none());
+
+        pool.fail_over(FailoverReason::TargetDown);
+        pool.fail_over(FailoverReason::TargetDown);
+        pool.connected();
+        assert!(!pool.is_preferred());
+
+        let event = pool.fail_back().unwrap();
+        assert_eq!(event.reason, FailoverReason::Failback);
+        assert_eq!((event.from.as_str(), event.to.as_str()), ("backup:9999", "primary:9999"));
+        assert!(pool.is_preferred());
+    }
+}
@@ -0,0 +1,366 @@
+// Clientのデータプレーン
+//
+// トンネルのbindで受け付けた接続ごとにconnection_idを振り、読み込んだデータをTunnelDataでRouterへ送る。
+// Routerから届いたTunnelDataは同じconnection_idの接続へ書き込み、受信確認（TunnelDataResponse）を返す。
+// 空のTunnelDataはその向きの終端（half-close）を表す
+//
+// 接続の受け付け判定・一覧・転送量の制限はConnectionControlで行う（Tunnel ProcessではUDS gRPCの制御と共有する）
+
+use crate::client::config::TunnelSettings;
+use crate::client::connection::ConnectionManager;
+use crate::common::error::{Error, Result};
+use crate::ipc::control::{Admission, ConnectionControl};
+use crate::protocol::handler::MessageHandler;
+use crate::protocol::messages::TunnelDataResponse;
+use crate::protocol::{
+    CompressionStats, Message, MessagePayload, MessageType, PayloadCompressor, ProtocolResult,
+    TunnelData,
+};
+use crate::registry::models::ConnectionInfo;
+use dashmap::DashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+// (tunnel_id, connection_id)
+type StreamKey = (Uuid, Uuid);
+
+// ローカルの接続へ書き込み待ちのチャンク数
+const STREAM_QUEUE: usize = 64;
+
+// 1回の読み込みの上限（TunnelDataがメッセージサイズの上限に収まるように）
+const MAX_CHUNK_SIZE: usize = 256 * 1024;
+
+// Routerから届くペイロードの上限（ProtocolHandlerの既定のメッセージサイズに合わせる）
+const MAX_PAYLOAD_SIZE: usize = 1024 * 1024;
+
+// 受け付けに失敗した後、次のacceptまで待つ時間（ファイルディスクリプタ枯渇などで空回りしない）
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Routerから届いたトンネルデータをローカルの接続へ振り分ける
+#[derive(Default)]
+pub struct Forwarder {
+    streams: DashMap<StreamKey, mpsc::Sender<Vec<u8>>>,
+}
+
+impl Forwarder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Routerからのデータの書き込み先を登録する。終端が届くとReceiverが閉じる
+    fn open(&self, key: StreamKey) -> mpsc::Receiver<Vec<u8>> {
+        let (tx, rx) = mpsc::channel(STREAM_QUEUE);
+        self.streams.insert(key, tx);
+        rx
+    }
+
+    fn close(&self, key: &StreamKey) {
+        self.streams.remove(key);
+    }
+
+    async fn deliver(&self, data: &TunnelData) -> ProtocolResult<()> {
+        let key = (data.tunnel_id, data.connection_id);
+        let payload = data.payload(MAX_PAYLOAD_SIZE)?;
+        if payload.is_empty() {
+            // 書き込みキューを閉じてローカルの接続へ終端を伝える
+            self.close(&key);
+            return Ok(());
+        }
+
+        let sender = self.streams.get(&key).map(|s| s.clone());
+        match sender {
+            Some(sender) => {
+                // ローカルの接続が先に閉じていれば捨てる（終端は接続側からRouterへ送る）
+                let _ = sender.send(payload).await;
+            }
+            None => debug!("Dropping tunnel data for closed connection {}", data.connection_id),
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageHandler for Forwarder {
+    async fn handle_message(&self, message: Message) -> ProtocolResult<Option<Message>> {
+        let MessagePayload::TunnelData(data) = &message.payload else {
+            return Ok(None);
+        };
+
+        let mut response = TunnelDataResponse {
+            tunnel_id: data.tunnel_id,
+            connection_id: data.connection_id,
+            data: None,
+            ack_sequence: data.sequence,
+            error: None,
+        };
+        if let Err(e) = self.deliver(data).await {
+            warn!("Invalid tunnel data for connection {}: {}", data.connection_id, e);
+            response.error = Some(e.to_string());
+        }
+
+        // 受信確認は要求と同じIDとバージョンで返す
+        let mut reply = Message::new(MessageType::TunnelDataResponse, MessagePayload::TunnelDataResponse(response))
+            .with_version(message.version);
+        reply.id = message.id;
+        Ok(Some(reply))
+    }
+}
+
+/// トンネルの送信側（ローカルの接続からRouterへ）
+pub struct TunnelLink {
+    tunnel_id: Uuid,
+    // Router側の転送先（接続一覧に表示する）
+    target: SocketAddr,
+    connection: ConnectionManager,
+    control: Arc<ConnectionControl>,
+    // 設定で圧縮を有効にしたトンネルの圧縮器。Routerが今の接続で受け入れた時だけ使う
+    compressor: Option<Mutex<PayloadCompressor>>,
+    buffer_size: usize,
+    session_timeout: u32,
+}
+
+impl TunnelLink {
+    pub fn new(
+        tunnel_id: Uuid,
+        target: SocketAddr,
+        connection: ConnectionManager,
+        control: Arc<ConnectionControl>,
+        settings: &TunnelSettings,
+    ) -> Result<Self> {
+        let compressor = settings
+            .compression_enabled
+            .then(|| PayloadCompressor::new(settings.compression.clone()))
+            .transpose()?
+            .map(Mutex::new);
+        Ok(Self {
+            tunnel_id,
+            target,
+            connection,
+            control,
+            compressor,
+            buffer_size: settings.buffer_size,
+            session_timeout: u32::try_from(settings.connection_timeout_seconds).unwrap_or(u32::MAX),
+        })
+    }
+
+    /// 圧縮の効果（圧縮が無効なトンネルはNone）
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.compressor
+            .as_ref()
+            .map(|c| c.lock().unwrap_or_else(|e| e.into_inner()).stats())
+    }
+
+    // ペイロードをTunnelDataにする。compressがtrueで圧縮器があれば圧縮する
+    fn encode(&self, connection_id: Uuid, sequence: u64, payload: &[u8], compress: bool) -> TunnelData {
+        match self.compressor.as_ref().filter(|_| compress) {
+            Some(compressor) => {
+                let mut compressor = compressor.lock().unwrap_or_else(|e| e.into_inner());
+                TunnelData::new(self.tunnel_id, connection_id, sequence, payload, Some(&mut compressor))
+            }
+            None => TunnelData::new(self.tunnel_id, connection_id, sequence, payload, None),
+        }
+    }
+
+    async fn send(&self, connection_id: Uuid, payload: &[u8]) -> Result<()> {
+        // 切り替え先のRouterが圧縮に対応していなければ、そのまま送る
+        let compress = !payload.is_empty()
+            && self.compressor.is_some()
+            && self
+                .connection
+                .accepted_tunnel(self.tunnel_id)
+                .await
+                .is_some_and(|create| create.config.compression_enabled);
+        let sequence = self.connection.next_sequence(self.tunnel_id, connection_id);
+        let data = self.encode(connection_id, sequence, payload, compress);
+        self.connection.send_tunnel_data(data).await
+    }
+}
+
+/// bindで待ち受け、受け付けた接続をRouterへ中継する。返したタスクを止めると待ち受けも止まる
+pub async fn listen(bind: SocketAddr, link: Arc<TunnelLink>, forwarder: Arc<Forwarder>) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(bind)
+        .await
+        .map_err(|e| Error::Network(format!("Failed to bind {}: {}", bind, e)))?;
+    info!("Listening on {} for tunnel {}", bind, link.tunnel_id);
+
+    Ok(tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((socket, peer)) => {
+                    // 一時停止・ドレイン中や上限を超えた接続はすぐに閉じる
+                    let Some(admission) = link.control.admit() else {
+                        debug!("Rejected connection from {} for tunnel {}", peer, link.tunnel_id);
+                        continue;
+                    };
+                    debug!("Accepted connection from {} for tunnel {}", peer, link.tunnel_id);
+                    tokio::spawn(relay(Arc::clone(&link), Arc::clone(&forwarder), socket, peer, admission));
+                }
+                Err(e) => {
+                    warn!("Failed to accept connection on {}: {}", bind, e);
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                }
+            }
+        }
+    }))
+}
+
+async fn relay(
+    link: Arc<TunnelLink>,
+    forwarder: Arc<Forwarder>,
+    socket: TcpStream,
+    peer: SocketAddr,
+    admission: Admission,
+) {
+    let connection_id = Uuid::new_v4();
+    let id = connection_id.to_string();
+    let key = (link.tunnel_id, connection_id);
+    let mut from_router = forwarder.open(key);
+    let mut close_requested = admission.register(ConnectionInfo::open(
+        id.clone(),
+        link.tunnel_id.to_string(),
+        peer.to_string(),
+        link.target.to_string(),
+        link.session_timeout,
+    ));
+
+    let accepted_at = Instant::now();
+    let mut awaiting_response = true;
+
+    let (mut local_read, mut local_write) = socket.into_split();
+    let mut buffer = vec![0u8; link.buffer_size.clamp(1024, MAX_CHUNK_SIZE)];
+    let (mut local_open, mut router_open) = (true, true);
+
+    while local_open || router_open {
+        tokio::select! {
+            read = local_read.read(&mut buffer), if local_open => match read {
+                Ok(0) | Err(_) => {
+                    if let Err(e) = read {
+                        link.control.report_error(&id, format!("Failed to read from local connection: {}", e));
+                    }
+                    local_open = false;
+                    if link.send(connection_id, &[]).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(n) => {
+                    link.control.throttle(n).await;
+                    if let Err(e) = link.send(connection_id, &buffer[..n]).await {
+                        debug!("Failed to forward connection {} to router: {}", connection_id, e);
+                        link.control.report_error(&id, format!("Failed to forward to router: {}", e));
+                        break;
+                    }
+                    link.control.record_traffic(&id, n as u64, 0);
+                }
+            },
+            chunk = from_router.recv(), if router_open => match chunk {
+                Some(chunk) => {
+                    if awaiting_response {
+                        awaiting_response = false;
+                        link.control.record_latency(accepted_at.elapsed());
+                    }
+                    link.control.throttle(chunk.len()).await;
+                    if let Err(e) = local_write.write_all(&chunk).await {
+                        debug!("Failed to write to local connection {}: {}", connection_id, e);
+                        link.control.report_error(&id, format!("Failed to write to local connection: {}", e));
+                        break;
+                    }
+                    link.control.record_traffic(&id, 0, chunk.len() as u64);
+                }
+                None => {
+                    router_open = false;
+                    let _ = local_write.shutdown().await;
+                }
+            },
+            // CloseConnectionやドレインの期限による切断
+            _ = &mut close_requested => {
+                debug!("Closing connection {} on request", connection_id);
+                break;
+            }
+        }
+    }
+    // 途中で終わった場合もRouter側のストリームを閉じさせる
+    if local_open {
+        let _ = link.send(connection_id, &[]).await;
+    }
+
+    forwarder.close(&key);
+    link.connection.close_stream(link.tunnel_id, connection_id);
+    link.control.unregister(&id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::connection::ConnectionConfig;
+    use crate::security::{AuthManager, KeyManager, KeyRotationConfig, TlsClientConfig, TlsConfig};
+
+    fn connection_manager() -> ConnectionManager {
+        let tls_config = TlsClientConfig::new(&TlsConfig::default()).unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let key_manager = KeyManager::new(temp_dir.path(), KeyRotationConfig::default()).unwrap();
+        let auth_manager = Arc::new(AuthManager::new(
+            key_manager,
+            Duration::from_secs(3600),
+            Duration::from_secs(1800),
+        ));
+        ConnectionManager::new(ConnectionConfig::default(), tls_config, auth_manager)
+    }
+
+    fn tunnel_data(key: StreamKey, sequence: u64, payload: &[u8]) -> Message {
+        Message::new(
+            MessageType::TunnelData,
+            MessagePayload::TunnelData(TunnelData::new(key.0, key.1, sequence, payload, None)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_forwarder_delivers_and_acknowledges() {
+        let forwarder = Forwarder::new();
+        let key = (Uuid::new_v4(), Uuid::new_v4());
+        let mut local = forwarder.open(key);
+
+        let request = tunnel_data(key, 1, b"hello");
+        let reply = forwarder.handle_message(request.clone()).await.unwrap().unwrap();
+        assert_eq!(reply.id, request.id);
+        let MessagePayload::TunnelDataResponse(ack) = reply.payload else {
+            panic!("unexpected reply: {:?}", reply.message_type);
+        };
+        assert_eq!(ack.ack_sequence, 1);
+        assert!(ack.error.is_none());
+        assert_eq!(local.recv().await.unwrap(), b"hello");
+
+        // 終端が届いたらローカルの接続へ閉じたことを伝える
+        forwarder.handle_message(tunnel_data(key, 2, b"")).await.unwrap();
+        assert!(local.recv().await.is_none());
+
+        // 閉じた接続宛てのデータも受信確認は返す
+        let reply = forwarder.handle_message(tunnel_data(key, 3, b"late")).await.unwrap();
+        assert!(matches!(reply.unwrap().payload, MessagePayload::TunnelDataResponse(_)));
+    }
+
+    #[tokio::test]
+    async fn test_link_compresses_only_when_requested() {
+        let settings = TunnelSettings {
+            compression_enabled: true,
+            ..TunnelSettings::default()
+        };
+        let control = Arc::new(ConnectionControl::new(settings.max_connections));
+        let link = TunnelLink::new(Uuid::new_v4(), "127.0.0.1:80".parse().unwrap(), connection_manager(), control, &settings).unwrap();
+        let payload = vec![b'a'; 8192];
+
+        let compressed = link.encode(Uuid::new_v4(), 1, &payload, true);
+        assert!(compressed.compression.is_some());
+        assert_eq!(compressed.payload(MAX_PAYLOAD_SIZE).unwrap(), payload);
+
+        let plain = link.encode(Uuid::new_v4(), 1, &payload, false);
+        assert!(plain.compression.is_none());
+        assert_eq!(link.compression_stats().unwrap().compressed_frames, 1);
+    }
+}
@@ -5,21 +5,27 @@
 pub mod tunnel;
 pub mod connection;
 pub mod config;
+pub mod failover;
+pub mod forward;
 
 pub use config::{ClientConfig, ClientInfo, RouterConfig, ConnectionSettings, TunnelConfig};
-pub use connection::{ConnectionManager, ConnectionConfig, ConnectionEvent, ConnectionStats};
+pub use connection::{ConnectionManager, ConnectionConfig, ConnectionEvent, ConnectionStats, ReconnectStatus};
+pub use failover::{FailoverEvent, FailoverReason, FailoverSettings, RouterEndpoint, RouterPool};
 pub use tunnel::TunnelManager;
 
 use crate::common::error::Result;
+use crate::ipc::control::ConnectionControl;
+use crate::notifier::Notifier;
 use crate::security::{TlsClientConfig, AuthManager};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tracing::{info, error, instrument};
+use tracing::{info, warn, error, instrument};
 
 pub struct Client {
     config: ClientConfig,
     tunnel_manager: TunnelManager,
     connection_manager: Option<ConnectionManager>,
+    event_tx: Option<mpsc::UnboundedSender<ConnectionEvent>>,
 }
 
 impl Client {
@@ -42,36 +48,58 @@ impl Client {
             config,
             tunnel_manager,
             connection_manager: None,
+            event_tx: None,
         })
     }
     
+    /// トンネルの接続を管理する制御状態を設定（startより前に呼ぶ）
+    pub fn set_connection_control(&mut self, control: Arc<ConnectionControl>) {
+        self.tunnel_manager.set_connection_control(control);
+    }
+    
+    /// 接続イベントを受け取るチャンネルを設定（startより前に呼ぶ）
+    pub fn set_event_channel(&mut self, tx: mpsc::UnboundedSender<ConnectionEvent>) {
+        self.event_tx = Some(tx);
+    }
+    
     #[instrument(skip(self))]
     pub async fn start(&mut self) -> Result<()> {
         info!("Starting Conduit client: {}", self.config.client.name);
         
-        let connection_config = self.config.to_connection_config();
-        let tls_config = TlsClientConfig::new(&self.config.security.tls)
-            .map_err(|e| crate::common::error::Error::Security(format!("TLS config error: {}", e)))?;
-        let key_manager = crate::security::KeyManager::new(
-            "./keys",
-            crate::security::KeyRotationConfig::default()
-        ).expect("Failed to create KeyManager");
-        let session_timeout = std::time::Duration::from_secs(3600);
-        let token_duration = std::time::Duration::from_secs(1800);
-        let auth_manager = Arc::new(AuthManager::new(key_manager, session_timeout, token_duration));
-        
-        let mut connection_manager = ConnectionManager::new(
-            connection_config,
-            tls_config,
-            auth_manager,
-        );
-        
         let (event_tx, mut event_rx) = mpsc::unbounded_channel();
-        connection_manager.set_event_channel(event_tx);
+        let notifier = (!self.config.webhooks.is_empty())
+            .then(|| Arc::new(Notifier::from_config(&self.config.webhooks)));
         
-        connection_manager.start().await?;
-        
-        self.tunnel_manager.set_connection_manager(connection_manager).await;
+        // トンネルが使うRouterグループごとに接続し、グループ内でフェイルオーバーする
+        for group in self.config.router_groups() {
+            let connection_config = self.config.to_connection_config(&group);
+            let tls_config = TlsClientConfig::new(&self.config.security.tls)
+                .map_err(|e| crate::common::error::Error::Security(format!("TLS config error: {}", e)))?;
+            let key_manager = crate::security::KeyManager::new(
+                "./keys",
+                crate::security::KeyRotationConfig::default()
+            ).expect("Failed to create KeyManager");
+            let session_timeout = std::time::Duration::from_secs(3600);
+            let token_duration = std::time::Duration::from_secs(1800);
+            let auth_manager = Arc::new(AuthManager::new(key_manager, session_timeout, token_duration));
+            
+            let mut connection_manager = ConnectionManager::new(
+                connection_config,
+                tls_config,
+                auth_manager,
+            );
+            
+            connection_manager.set_event_channel(event_tx.clone());
+            connection_manager.set_message_handler(self.tunnel_manager.forwarder())?;
+            
+            if let Some(ref notifier) = notifier {
+                connection_manager.set_notifier(Arc::clone(notifier));
+            }
+            
+            connection_manager.start().await?;
+            
+            self.tunnel_manager.set_connection_manager(&group, connection_manager).await;
+        }
         
         for tunnel_config in &self.config.tunnels {
             if tunnel_config.enabled {
@@ -81,8 +109,12 @@ impl Client {
             }
         }
         
+        let forward = self.event_tx.clone();
         tokio::spawn(async move {
             while let Some(event) = event_rx.recv().await {
+                if let Some(ref tx) = forward {
+                    let _ = tx.send(event.clone());
+                }
                 match event {
                     ConnectionEvent::Connected => {
                         info!("Connected to router");
@@ -90,8 +122,13 @@ impl Client {
                     ConnectionEvent::Disconnected => {
                         info!("Disconnected from router");
                     }
-                    ConnectionEvent::Reconnecting => {
-                        info!("Reconnecting to router");
+                    ConnectionEvent::Reconnecting(status) => {
+                        info!(
+                            "Reconnecting to router in {}s (consecutive failures: {}, circuit: {})",
+                            status.next_attempt_in().as_secs(),
+                            status.failures,
+                            status.circuit
+                        );
                     }
                     ConnectionEvent::Authenticated => {
                         info!("Authenticated with router");
@@ -99,12 +136,38 @@ impl Client {
                     ConnectionEvent::Error(err) => {
                         error!("Connection error: {}", err);
                     }
+                    ConnectionEvent::ProtocolNegotiated(negotiated) => {
+                        info!(
+                            "Negotiated protocol {} with router (capabilities: {})",
+                            negotiated.version,
+                            negotiated.capabilities.to_names().join(", ")
+                        );
+                    }
+                    ConnectionEvent::Failover(event) => {
+                        warn!(
+                            "Router group {} switched from {} to {} ({})",
+                            event.group, event.from, event.to, event.reason
+                        );
+                    }
+                    ConnectionEvent::SessionResumed { replayed } => {
+                        info!("Resumed session with router ({} frames replayed)", replayed);
+                    }
                     ConnectionEvent::HeartbeatSent => {
                         info!("Heartbeat sent");
                     }
                     ConnectionEvent::HeartbeatReceived => {
                         info!("Heartbeat received");
                     }
+                    ConnectionEvent::TargetHealthChanged(health) => {
+                        match health.state {
+                            crate::protocol::TargetState::Down => warn!(
+                                "Target {} is down (router reachable): {}",
+                                health.target_addr,
+                                health.error.as_deref().unwrap_or("unknown error")
+                            ),
+                            _ => info!("Target {} is {}", health.target_addr, health.state.as_str()),
+                        }
+                    }
                 }
             }
         });
@@ -122,18 +185,29 @@ impl Client {
             )),
         };
         
-        let router_addr = format!("{}:{}", self.config.router.host, self.config.router.port)
+        // トンネル情報には最優先のRouterを記録する（接続先はグループ内で切り替わる）
+        let router = self.config.group_routers(tunnel_config.router_group())
+            .into_iter()
+            .min_by_key(|r| r.priority)
+            .ok_or_else(|| crate::common::error::Error::Config(
+                format!("No router in group '{}'", tunnel_config.router_group())
+            ))?;
+        let router_addr = router.addr()
             .parse()
             .map_err(|e| crate::common::error::Error::Config(
                 format!("Invalid router address: {}", e)
             ))?;
         
+        let sources = tunnel_config.source.parse()?;
+        
         self.tunnel_manager.create_tunnel(
             tunnel_config.name.clone(),
-            tunnel_config.source,
+            &sources,
             tunnel_config.bind,
             router_addr,
+            tunnel_config.router_group(),
             protocol,
+            &tunnel_config.settings,
         ).await?;
         
         info!("Tunnel started: {}", tunnel_config.name);
@@ -175,6 +249,16 @@ impl Client {
         self.tunnel_manager.list_tunnels()
     }
     
+    /// トンネルの圧縮の効果（圧縮が無効なトンネルはNone）
+    pub fn compression_stats(&self, tunnel_id: &crate::common::types::TunnelId) -> Option<crate::protocol::CompressionStats> {
+        self.tunnel_manager.compression_stats(tunnel_id)
+    }
+    
+    /// Routerグループごとの接続統計（再接続・認証失敗の回数など）
+    pub async fn connection_stats(&self) -> Vec<ConnectionStats> {
+        self.tunnel_manager.connection_stats().await
+    }
+    
     pub async fn connection_state(&self) -> Option<crate::protocol::ConnectionState> {
         if let Some(ref connection_manager) = self.connection_manager {
             Some(connection_manager.connection_state().await)
@@ -1,9 +1,14 @@
 // Clientのトンネル実装
 
 use crate::common::{error::Result, types::*};
-use crate::protocol::{ProtocolHandler, Message, MessageType, MessagePayload, TunnelCreate};
+use crate::protocol::{ProtocolHandler, TunnelCreate};
+use crate::protocol::CompressionStats;
+use crate::client::forward::{self, Forwarder, TunnelLink};
+use crate::client::config::TunnelSettings;
 use crate::security::{TlsClientConfig, AuthManager, TlsConfig, KeyManager, KeyRotationConfig};
-use crate::client::connection::ConnectionManager;
+use crate::client::connection::{ConnectionManager, ConnectionStats};
+use crate::ipc::control::ConnectionControl;
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -13,7 +18,15 @@ use tracing::{debug, info, warn, error, instrument};
 
 pub struct TunnelManager {
     tunnels: dashmap::DashMap<TunnelId, TunnelInfo>,
-    connection_manager: Arc<Mutex<Option<ConnectionManager>>>,
+    // トンネルごとのRouterへの送信側と、bindでの待ち受け
+    links: dashmap::DashMap<TunnelId, Arc<TunnelLink>>,
+    listeners: dashmap::DashMap<TunnelId, tokio::task::JoinHandle<()>>,
+    // Routerから届いたデータの振り分け先（全グループの接続で共有する）
+    forwarder: Arc<Forwarder>,
+    // 接続の受け付け判定と一覧（未設定ならトンネルごとに作る）
+    connection_control: Option<Arc<ConnectionControl>>,
+    // Routerグループごとの接続
+    connection_managers: Arc<Mutex<HashMap<String, ConnectionManager>>>,
     tls_config: TlsClientConfig,
     auth_manager: Arc<AuthManager>,
 }
@@ -25,29 +38,50 @@ impl TunnelManager {
     ) -> Self {
         Self {
             tunnels: dashmap::DashMap::new(),
-            connection_manager: Arc::new(Mutex::new(None)),
+            links: dashmap::DashMap::new(),
+            listeners: dashmap::DashMap::new(),
+            forwarder: Arc::new(Forwarder::new()),
+            connection_control: None,
+            connection_managers: Arc::new(Mutex::new(HashMap::new())),
             tls_config,
             auth_manager,
         }
     }
     
-    /// Set connection manager
-    pub async fn set_connection_manager(&self, connection_manager: ConnectionManager) {
-        *self.connection_manager.lock().await = Some(connection_manager);
+    /// Routerから届いたトンネルデータの処理先（ConnectionManagerに設定する）
+    pub fn forwarder(&self) -> Arc<Forwarder> {
+        Arc::clone(&self.forwarder)
+    }
+    
+    /// トンネルの接続を管理する制御状態を設定（以降に作るトンネルで共有する）
+    pub fn set_connection_control(&mut self, control: Arc<ConnectionControl>) {
+        self.connection_control = Some(control);
+    }
+    
+    /// Set connection manager for a router group
+    pub async fn set_connection_manager(&self, router_group: &str, connection_manager: ConnectionManager) {
+        self.connection_managers.lock().await.insert(router_group.to_string(), connection_manager);
     }
     
     /// Create a new tunnel with protocol support
+    #[allow(clippy::too_many_arguments)]
     #[instrument(skip(self))]
     pub async fn create_tunnel(
         &self,
         name: String,
-        source: SocketAddr,
+        sources: &[SocketAddr],
         bind: SocketAddr,
         router: SocketAddr,
+        router_group: &str,
         protocol: Protocol,
+        settings: &TunnelSettings,
     ) -> Result<TunnelId> {
         info!("Creating tunnel: {}", name);
         
+        let source = *sources.first()
+            .ok_or_else(|| crate::common::error::Error::Config(
+                format!("Tunnel {} has no source address", name)
+            ))?;
         let tunnel_id = TunnelId::new();
         let tunnel_info = TunnelInfo {
             id: tunnel_id.clone(),
@@ -64,10 +98,19 @@ impl TunnelManager {
         
         self.tunnels.insert(tunnel_id.clone(), tunnel_info);
         
-        // Router にトンネル作成要求を送信
-        if let Err(e) = self.send_tunnel_create_request(&tunnel_id, &name, source, bind, &protocol).await {
-            error!("Failed to send tunnel create request: {}", e);
-            // エラー時はトンネル情報を削除
+        // Router にトンネルを登録し、bindで待ち受けを始める
+        let connection_manager = match self.send_tunnel_create_request(&tunnel_id, &name, sources, bind, router_group, &protocol, settings).await {
+            Ok(connection_manager) => connection_manager,
+            Err(e) => {
+                error!("Failed to send tunnel create request: {}", e);
+                // エラー時はトンネル情報を削除
+                self.tunnels.remove(&tunnel_id);
+                return Err(e);
+            }
+        };
+        if let Err(e) = self.start_forwarding(&tunnel_id, bind, source, connection_manager.clone(), settings).await {
+            error!("Failed to start forwarding for tunnel {}: {}", name, e);
+            connection_manager.unregister_tunnel(tunnel_id.0).await;
             self.tunnels.remove(&tunnel_id);
             return Err(e);
         }
@@ -81,61 +124,75 @@ impl TunnelManager {
         Ok(tunnel_id)
     }
     
-    /// Router にトンネル作成要求を送信
+    /// Router にトンネルを登録
     async fn send_tunnel_create_request(
         &self,
         tunnel_id: &TunnelId,
         name: &str,
-        source: SocketAddr,
+        sources: &[SocketAddr],
         bind: SocketAddr,
+        router_group: &str,
         protocol: &Protocol,
-    ) -> Result<()> {
-        let connection_guard = self.connection_manager.lock().await;
+        settings: &TunnelSettings,
+    ) -> Result<ConnectionManager> {
+        let connection_guard = self.connection_managers.lock().await;
         
-        if let Some(ref connection_manager) = *connection_guard {
+        if let Some(connection_manager) = connection_guard.get(router_group) {
             let tunnel_create = TunnelCreate {
                 tunnel_id: Uuid::parse_str(&tunnel_id.to_string())
                     .map_err(|e| crate::common::error::Error::Protocol(format!("Invalid tunnel ID: {}", e)))?,
                 tunnel_name: name.to_string(),
-                source_addr: source,
+                source_addr: sources[0],
                 bind_addr: bind,
                 protocol: protocol.to_string(),
                 config: crate::protocol::messages::TunnelConfig {
-                    max_connections: 100,
-                    timeout_seconds: 300,
-                    buffer_size: 65536,
-                    compression_enabled: false,
+                    max_connections: settings.max_connections,
+                    timeout_seconds: settings.connection_timeout_seconds,
+                    buffer_size: settings.buffer_size,
+                    compression_enabled: settings.compression_enabled,
+                    compression: settings.compression.clone(),
                 },
+                service: settings.service.clone(),
+                // 単一の転送先はsource_addrだけで送り、レプリカ非対応の旧Routerとも互換にする
+                source_addrs: if sources.len() > 1 { sources.to_vec() } else { Vec::new() },
             };
             
-            let payload = MessagePayload::TunnelCreate(tunnel_create);
-            let message = Message::new(MessageType::TunnelCreate, payload);
-            
-            let response = connection_manager.send_message(message).await?;
-            
-            match response.payload {
-                MessagePayload::TunnelCreateResponse(ref resp) => {
-                    if resp.success {
-                        debug!("Tunnel create response received: {}", resp.tunnel_id);
-                        Ok(())
-                    } else {
-                        let error_msg = resp.error.as_deref().unwrap_or("Unknown error");
-                        Err(crate::common::error::Error::Network(
-                            format!("Router rejected tunnel creation: {}", error_msg)
-                        ))
-                    }
+            // 接続前でも登録しておけば、認証のたびにConnectionManagerが送る
+            match connection_manager.register_tunnel(tunnel_create).await? {
+                Some(accepted) if settings.compression_enabled && !accepted.config.compression_enabled => {
+                    warn!("Router does not support compression; tunnel {} will send uncompressed data", name);
                 }
-                _ => Err(crate::common::error::Error::Protocol(
-                    "Unexpected response type for tunnel create".to_string()
-                )),
+                Some(_) => {}
+                None => info!("Tunnel {} will be registered once connected to the router", name),
             }
+            Ok(connection_manager.clone())
         } else {
             Err(crate::common::error::Error::Network(
-                "No connection manager available".to_string()
+                format!("No connection manager available for router group '{}'", router_group)
             ))
         }
     }
     
+    // ローカルの接続をRouterへ中継する送信側を用意し、bindで待ち受ける
+    async fn start_forwarding(
+        &self,
+        tunnel_id: &TunnelId,
+        bind: SocketAddr,
+        source: SocketAddr,
+        connection_manager: ConnectionManager,
+        settings: &TunnelSettings,
+    ) -> Result<()> {
+        let control = self
+            .connection_control
+            .clone()
+            .unwrap_or_else(|| Arc::new(ConnectionControl::new(settings.max_connections)));
+        let link = Arc::new(TunnelLink::new(tunnel_id.0, source, connection_manager, control, settings)?);
+        let listener = forward::listen(bind, Arc::clone(&link), Arc::clone(&self.forwarder)).await?;
+        self.links.insert(tunnel_id.clone(), link);
+        self.listeners.insert(tunnel_id.clone(), listener);
+        Ok(())
+    }
+    
     /// Get tunnel information
     pub fn get_tunnel(&self, tunnel_id: &TunnelId) -> Option<TunnelInfo> {
         self.tunnels.get(tunnel_id).map(|entry| entry.clone())
@@ -153,6 +210,13 @@ impl TunnelManager {
         
         if let Some((_, mut tunnel_info)) = self.tunnels.remove(tunnel_id) {
             tunnel_info.status = TunnelStatus::Stopping;
+            self.links.remove(tunnel_id);
+            if let Some((_, listener)) = self.listeners.remove(tunnel_id) {
+                listener.abort();
+            }
+            for connection_manager in self.connection_managers.lock().await.values() {
+                connection_manager.unregister_tunnel(tunnel_id.0).await;
+            }
             
             // Router にトンネル削除要求を送信（実装時に追加）
             // TODO: Implement tunnel deletion request to router
@@ -162,6 +226,21 @@ impl TunnelManager {
         Ok(())
     }
     
+    /// 圧縮の効果（圧縮が無効なトンネルはNone）
+    pub fn compression_stats(&self, tunnel_id: &TunnelId) -> Option<CompressionStats> {
+        self.links.get(tunnel_id).and_then(|link| link.compression_stats())
+    }
+    
+    /// Routerグループごとの接続統計
+    pub async fn connection_stats(&self) -> Vec<ConnectionStats> {
+        let managers: Vec<ConnectionManager> = self.connection_managers.lock().await.values().cloned().collect();
+        let mut stats = Vec::with_capacity(managers.len());
+        for manager in managers {
+            stats.push(manager.stats().await);
+        }
+        stats
+    }
+    
     /// Update tunnel statistics
     pub fn update_tunnel_stats(&self, tunnel_id: &TunnelId, active_connections: u32, bytes_transferred: u64) {
         if let Some(mut tunnel_info) = self.tunnels.get_mut(tunnel_id) {
@@ -239,10 +318,12 @@ mod tests {
         
         let result = manager.create_tunnel(
             "test-tunnel".to_string(),
-            "127.0.0.1:8080".parse().unwrap(),
+            &["127.0.0.1:8080".parse().unwrap()],
             "0.0.0.0:80".parse().unwrap(),
             "127.0.0.1:9999".parse().unwrap(),
+            crate::client::config::DEFAULT_ROUTER_GROUP,
             Protocol::Tcp,
+            &TunnelSettings::default(),
         ).await;
         
         // 接続マネージャーがないので失敗するはず
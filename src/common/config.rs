@@ -4,15 +4,36 @@
 // CLI引数 > 環境変数 > 設定ファイル > デフォルト値
 
 use crate::common::error::{Error, Result};
+use crate::common::logging::LoggingConfig;
+use crate::common::retry::RetryPolicy;
+use crate::notifier::WebhookConfig;
+use crate::protocol::{CompressionAlgorithm, CompressionSettings};
+use crate::registry::models::{default_unhealthy_threshold, RestartPolicy};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub router: RouterConfig,
+    // 接続先のRouter。`[router]`1つ、またはフェイルオーバー先を優先順に並べた`[[router]]`
+    #[serde(rename = "router", deserialize_with = "deserialize_routers", serialize_with = "serialize_routers")]
+    pub routers: Vec<RouterConfig>,
     pub security: SecurityConfig,
     pub tunnels: Vec<TunnelConfig>,
+    // 状態遷移の通知先
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+    // Routerとの接続が切れたときの再接続のバックオフ
+    #[serde(default, skip_serializing_if = "is_default_retry")]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+}
+
+fn is_default_retry(policy: &RetryPolicy) -> bool {
+    *policy == RetryPolicy::default()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +42,35 @@ pub struct RouterConfig {
     pub port: u16,
 }
 
+// 単一Routerの旧形式（`[router]`）も受け付ける
+fn deserialize_routers<'de, D>(deserializer: D) -> std::result::Result<Vec<RouterConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(RouterConfig),
+        Many(Vec<RouterConfig>),
+    }
+    
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(router) => vec![router],
+        OneOrMany::Many(routers) => routers,
+    })
+}
+
+// Routerが1つなら従来どおり`[router]`として書き出す
+fn serialize_routers<S>(routers: &[RouterConfig], serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match routers {
+        [router] => router.serialize(serializer),
+        routers => routers.serialize(serializer),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
     pub private_key_path: PathBuf,
@@ -30,12 +80,113 @@ pub struct SecurityConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TunnelConfig {
     pub name: String,
-    // Router側のサービスアドレス
-    pub source: String,
+    // Router側のサービスアドレス。レプリカは配列で並べ、Routerが振り分ける
+    pub source: SourceAddrs,
     // Clientのローカルバインドアドレス
     pub bind: String,
     #[serde(default = "default_protocol")]
     pub protocol: String,
+    // Tunnel Processが終了した際の再起動ポリシー
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    // 応答しなくなったTunnel Processを強制終了して起動し直す
+    #[serde(default)]
+    pub restart_on_unhealthy: bool,
+    // 生存確認にこの回数連続で失敗したらunhealthyとする
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+    // データの圧縮方式（zstd, lz4）。未指定なら圧縮しない
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionAlgorithm>,
+    // zstdの圧縮レベル（1-22）。未指定ならアルゴリズムの既定値
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_level: Option<i32>,
+}
+
+// 転送先のアドレス。単一なら文字列、複数なら配列で書ける
+// Registryやプロセス引数にはカンマ区切りの1つの文字列として渡す
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "OneOrMany", into = "OneOrMany")]
+pub struct SourceAddrs(Vec<String>);
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for SourceAddrs {
+    fn from(value: OneOrMany) -> Self {
+        match value {
+            OneOrMany::One(addr) => SourceAddrs(vec![addr]),
+            OneOrMany::Many(addrs) => SourceAddrs(addrs),
+        }
+    }
+}
+
+impl From<SourceAddrs> for OneOrMany {
+    fn from(mut value: SourceAddrs) -> Self {
+        if value.0.len() == 1 {
+            OneOrMany::One(value.0.remove(0))
+        } else {
+            OneOrMany::Many(value.0)
+        }
+    }
+}
+
+impl From<&str> for SourceAddrs {
+    fn from(addr: &str) -> Self {
+        SourceAddrs(vec![addr.to_string()])
+    }
+}
+
+impl SourceAddrs {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // 全アドレスを検証して返す（先頭がTunnelCreateのsource_addrになる）
+    pub fn parse(&self) -> Result<Vec<SocketAddr>> {
+        if self.0.is_empty() {
+            return Err(Error::config("At least one source address is required"));
+        }
+        let mut addrs: Vec<SocketAddr> = Vec::with_capacity(self.0.len());
+        for addr in &self.0 {
+            let parsed = addr
+                .parse::<SocketAddr>()
+                .map_err(|_| Error::config(format!("Invalid source address: {}", addr)))?;
+            if addrs.contains(&parsed) {
+                return Err(Error::config(format!("Duplicate source address: {}", addr)));
+            }
+            addrs.push(parsed);
+        }
+        Ok(addrs)
+    }
+}
+
+impl fmt::Display for SourceAddrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.join(","))
+    }
+}
+
+impl FromStr for SourceAddrs {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let addrs = SourceAddrs(s.split(',').map(|a| a.trim().to_string()).collect());
+        addrs.parse()?;
+        Ok(addrs)
+    }
 }
 
 impl Config {
@@ -62,10 +213,10 @@ impl Config {
     
     pub fn default() -> Self {
         Config {
-            router: RouterConfig {
+            routers: vec![RouterConfig {
                 host: "localhost".to_string(),
                 port: 9999,
-            },
+            }],
             security: SecurityConfig {
                 private_key_path: PathBuf::from("./keys/client.key"),
                 public_key_path: Some(PathBuf::from("./keys/client.pub")),
@@ -73,21 +224,29 @@ impl Config {
             tunnels: vec![
                 TunnelConfig {
                     name: "example-tunnel".to_string(),
-                    source: "127.0.0.1:8080".to_string(),
+                    source: "127.0.0.1:8080".into(),
                     bind: "0.0.0.0:80".to_string(),
                     protocol: "tcp".to_string(),
+                    restart: RestartPolicy::default(),
+                    restart_on_unhealthy: false,
+                    unhealthy_threshold: default_unhealthy_threshold(),
+                    compression: None,
+                    compression_level: None,
                 }
             ],
+            webhooks: Vec::new(),
+            retry: RetryPolicy::default(),
+            logging: LoggingConfig::default(),
         }
     }
     
     // サンプル設定を生成（architecture.mdの仕様に準拠）
     pub fn sample() -> Self {
         Config {
-            router: RouterConfig {
+            routers: vec![RouterConfig {
                 host: "10.2.0.1".to_string(),
                 port: 9999,
-            },
+            }],
             security: SecurityConfig {
                 private_key_path: PathBuf::from("./keys/client.key"),
                 public_key_path: Some(PathBuf::from("./keys/client.pub")),
@@ -95,27 +254,51 @@ impl Config {
             tunnels: vec![
                 TunnelConfig {
                     name: "web-server-access".to_string(),
-                    source: "10.2.0.2:8080".to_string(),
+                    source: "10.2.0.2:8080".into(),
                     bind: "0.0.0.0:80".to_string(),
                     protocol: "tcp".to_string(),
+                    restart: RestartPolicy::OnFailure { max_retries: Some(5) },
+                    restart_on_unhealthy: false,
+                    unhealthy_threshold: default_unhealthy_threshold(),
+                    compression: None,
+                    compression_level: None,
                 },
                 TunnelConfig {
                     name: "api-server-access".to_string(),
-                    source: "10.2.0.3:3000".to_string(),
+                    source: "10.2.0.3:3000".into(),
                     bind: "0.0.0.0:8080".to_string(),
                     protocol: "tcp".to_string(),
+                    restart: RestartPolicy::default(),
+                    restart_on_unhealthy: false,
+                    unhealthy_threshold: default_unhealthy_threshold(),
+                    compression: None,
+                    compression_level: None,
                 },
             ],
+            webhooks: Vec::new(),
+            retry: RetryPolicy::default(),
+            logging: LoggingConfig::default(),
         }
     }
     
     pub fn validate(&self) -> Result<()> {
-        if self.router.host.is_empty() {
-            return Err(Error::config("Router host cannot be empty"));
+        if self.routers.is_empty() {
+            return Err(Error::config("At least one router must be configured"));
         }
         
-        if self.router.port == 0 {
-            return Err(Error::config("Router port cannot be 0"));
+        let mut router_addresses = std::collections::HashSet::new();
+        for router in &self.routers {
+            if router.host.is_empty() {
+                return Err(Error::config("Router host cannot be empty"));
+            }
+            
+            if router.port == 0 {
+                return Err(Error::config("Router port cannot be 0"));
+            }
+            
+            if !router_addresses.insert(router.addr()) {
+                return Err(Error::config(format!("Duplicate router: {}", router.addr())));
+            }
         }
         
         if self.tunnels.is_empty() {
@@ -134,8 +317,7 @@ impl Config {
                 return Err(Error::config(format!("Duplicate bind address: {}", tunnel.bind)));
             }
             
-            tunnel.source.parse::<SocketAddr>()
-                .map_err(|_| Error::config(format!("Invalid source address: {}", tunnel.source)))?;
+            tunnel.source.parse()?;
             
             tunnel.bind.parse::<SocketAddr>()
                 .map_err(|_| Error::config(format!("Invalid bind address: {}", tunnel.bind)))?;
@@ -144,20 +326,57 @@ impl Config {
                 "tcp" | "udp" => {},
                 _ => return Err(Error::config(format!("Invalid protocol: {}", tunnel.protocol))),
             }
+            
+            if tunnel.unhealthy_threshold == 0 {
+                return Err(Error::config(format!("unhealthy_threshold must be at least 1: {}", tunnel.name)));
+            }
+            
+            if tunnel.compression.is_none() && tunnel.compression_level.is_some() {
+                return Err(Error::config(format!("compression_level requires compression: {}", tunnel.name)));
+            }
+            if let Some(settings) = tunnel.compression_settings() {
+                settings.validate()
+                    .map_err(|e| Error::config(format!("{}: {}", tunnel.name, e)))?;
+            }
         }
         
+        for webhook in &self.webhooks {
+            webhook.validate().map_err(Error::config)?;
+        }
+        
+        self.retry.validate()
+            .map_err(|e| Error::config(format!("Invalid retry: {}", e)))?;
+        
+        self.logging.validate()?;
+        
         Ok(())
     }
     
-    // RouterのSocketAddrを取得
-    pub fn router_addr(&self) -> SocketAddr {
-        format!("{}:{}", self.router.host, self.router.port)
-            .parse()
-            .expect("Invalid router address")
+    // Registryやプロセス引数へ渡すRouterアドレス（優先順のカンマ区切り）
+    pub fn router_addrs(&self) -> String {
+        self.routers.iter()
+            .map(RouterConfig::addr)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl TunnelConfig {
+    // Registryへ記録する圧縮設定
+    pub fn compression_settings(&self) -> Option<CompressionSettings> {
+        self.compression.map(|algorithm| CompressionSettings {
+            algorithm,
+            level: self.compression_level,
+            ..CompressionSettings::default()
+        })
     }
 }
 
 impl RouterConfig {
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+    
     // 環境変数からRouter設定を作成
     pub fn from_env() -> Self {
         RouterConfig {
@@ -206,6 +425,214 @@ mod tests {
         let config = Config::sample();
         let toml_str = toml::to_string(&config).unwrap();
         let parsed: Config = toml::from_str(&toml_str).unwrap();
-        assert_eq!(config.router.host, parsed.router.host);
+        assert_eq!(config.routers[0].host, parsed.routers[0].host);
+        
+        // Routerが1つなら`[router]`のまま書き出す
+        assert!(toml_str.contains("[router]"));
+    }
+    
+    #[test]
+    fn test_webhook_config() {
+        let toml_str = r#"
+            [router]
+            host = "10.2.0.1"
+            port = 9999
+
+            [security]
+            private_key_path = "./keys/client.key"
+
+            [[tunnels]]
+            name = "web"
+            source = "10.2.0.2:8080"
+            bind = "0.0.0.0:80"
+
+            [[webhooks]]
+            name = "slack"
+            url = "https://hooks.slack.com/services/T000/B000/XXX"
+            events = ["tunnel_exited", "reconnect_failed"]
+            body_template = '{"text": "[{{severity}}] {{subject}}: {{message}}"}'
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.webhooks.len(), 1);
+        assert_eq!(config.webhooks[0].events.len(), 2);
+        assert_eq!(config.webhooks[0].rate_limit_per_minute, 10);
+    }
+    
+    #[test]
+    fn test_retry_config() {
+        let toml_str = r#"
+            [router]
+            host = "10.2.0.1"
+            port = 9999
+
+            [security]
+            private_key_path = "./keys/client.key"
+
+            [[tunnels]]
+            name = "web"
+            source = "10.2.0.2:8080"
+            bind = "0.0.0.0:80"
+
+            [retry]
+            initial_delay_ms = 1000
+            max_attempts = 20
+
+            [retry.circuit_breaker]
+            failure_threshold = 5
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.retry.initial_delay_ms, 1000);
+        assert_eq!(config.retry.max_attempts, 20);
+        assert_eq!(config.retry.circuit_breaker.failure_threshold, 5);
+        // 省略した項目は既定値
+        assert_eq!(config.retry.max_delay_ms, RetryPolicy::default().max_delay_ms);
+        
+        // 既定のままなら書き出さない
+        assert!(!toml::to_string(&Config::sample()).unwrap().contains("[retry]"));
+        
+        config.retry.initial_delay_ms = config.retry.max_delay_ms + 1;
+        assert!(config.validate().is_err());
+    }
+    
+    #[test]
+    fn test_multiple_sources() {
+        let toml_str = r#"
+            [router]
+            host = "10.2.0.1"
+            port = 9999
+
+            [security]
+            private_key_path = "./keys/client.key"
+
+            [[tunnels]]
+            name = "web"
+            source = ["10.2.0.2:8080", "10.2.0.3:8080"]
+            bind = "0.0.0.0:80"
+
+            [[tunnels]]
+            name = "ssh"
+            source = "10.2.0.4:22"
+            bind = "0.0.0.0:2222"
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.tunnels[0].source.parse().unwrap().len(), 2);
+        assert_eq!(config.tunnels[1].source.len(), 1);
+        
+        // Registryへはカンマ区切りで渡し、同じ内容に戻せる
+        let arg = config.tunnels[0].source.to_string();
+        assert_eq!(arg, "10.2.0.2:8080,10.2.0.3:8080");
+        assert_eq!(arg.parse::<SourceAddrs>().unwrap(), config.tunnels[0].source);
+        
+        // 単一のアドレスは文字列のまま書き出す
+        let written = toml::to_string(&config).unwrap();
+        assert!(written.contains(r#"source = "10.2.0.4:22""#));
+        
+        config.tunnels[0].source = SourceAddrs::from_str("10.2.0.2:8080").unwrap();
+        config.tunnels[1].source = SourceAddrs(vec!["10.2.0.4:22".to_string(), "10.2.0.4:22".to_string()]);
+        assert!(config.validate().is_err());
+    }    
+    #[test]
+    fn test_unhealthy_threshold() {
+        let toml_str = r#"
+            [router]
+            host = "10.2.0.1"
+            port = 9999
+
+            [security]
+            private_key_path = "./keys/client.key"
+
+            [[tunnels]]
+            name = "web"
+            source = "10.2.0.2:8080"
+            bind = "0.0.0.0:80"
+            restart_on_unhealthy = true
+            unhealthy_threshold = 5
+
+            [[tunnels]]
+            name = "ssh"
+            source = "10.2.0.4:22"
+            bind = "0.0.0.0:2222"
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.tunnels[0].unhealthy_threshold, 5);
+        assert_eq!(config.tunnels[1].unhealthy_threshold, 3);
+        
+        config.tunnels[0].unhealthy_threshold = 0;
+        assert!(config.validate().is_err());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_tunnel_compression() {
+        let toml_str = r#"
+            [router]
+            host = "10.2.0.1"
+            port = 9999
+
+            [security]
+            private_key_path = "./keys/client.key"
+
+            [[tunnels]]
+            name = "web"
+            source = "10.2.0.2:8080"
+            bind = "0.0.0.0:80"
+            compression = "zstd"
+            compression_level = 9
+
+            [[tunnels]]
+            name = "ssh"
+            source = "10.2.0.4:22"
+            bind = "0.0.0.0:2222"
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        let settings = config.tunnels[0].compression_settings().unwrap();
+        assert_eq!(settings.algorithm, CompressionAlgorithm::Zstd);
+        assert_eq!(settings.level, Some(9));
+        assert!(config.tunnels[1].compression_settings().is_none());
+        
+        // lz4はレベルを持たない
+        config.tunnels[0].compression = Some(CompressionAlgorithm::Lz4);
+        assert!(config.validate().is_err());
+        
+        // 圧縮方式なしのレベル指定は誤り
+        config.tunnels[0].compression = None;
+        assert!(config.validate().is_err());
+    }
+    
+    #[test]
+    fn test_multiple_routers() {
+        let toml_str = r#"
+            [[router]]
+            host = "10.2.0.1"
+            port = 9999
+
+            [[router]]
+            host = "10.3.0.1"
+            port = 9999
+
+            [security]
+            private_key_path = "./keys/client.key"
+
+            [[tunnels]]
+            name = "web"
+            source = "10.2.0.2:8080"
+            bind = "0.0.0.0:80"
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.routers.len(), 2);
+        assert_eq!(config.router_addrs(), "10.2.0.1:9999,10.3.0.1:9999");
+        
+        let written = toml::to_string(&config).unwrap();
+        assert!(written.contains("[[router]]"));
+        let parsed: Config = toml::from_str(&written).unwrap();
+        assert_eq!(parsed.router_addrs(), config.router_addrs());
+        
+        config.routers[1].host = "10.2.0.1".to_string();
+        assert!(config.validate().is_err());
+    }
+}
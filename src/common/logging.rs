@@ -0,0 +1,393 @@
+// ログ出力の設定と初期化
+//
+// 出力形式・出力先は CLI引数 > 環境変数 > 設定ファイル > デフォルト値 の順で決定する。
+// Tunnel Processの標準出力はProcessManagerがトンネルごとのログファイルへ向ける
+
+use crate::common::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("Invalid log format '{}' (expected text or json)", other)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+// ログファイルのローテーション方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    #[default]
+    Daily,
+    Hourly,
+    // max_size_mbを超えたら <file>.1, <file>.2 ... へ退避
+    Size,
+    Never,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "daily" => Ok(LogRotation::Daily),
+            "hourly" => Ok(LogRotation::Hourly),
+            "size" => Ok(LogRotation::Size),
+            "never" => Ok(LogRotation::Never),
+            other => Err(format!(
+                "Invalid log rotation '{}' (expected daily, hourly, size or never)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    // conduitクレートのログレベル（RUST_LOGの指定が優先される）
+    #[serde(default = "default_level")]
+    pub level: String,
+    #[serde(default)]
+    pub format: LogFormat,
+    // 未指定なら標準出力
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64,
+    // 保持するローテーション済みファイル数
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_level(),
+            format: LogFormat::default(),
+            file: None,
+            rotation: LogRotation::default(),
+            max_size_mb: default_max_size_mb(),
+            max_files: default_max_files(),
+        }
+    }
+}
+
+fn default_level() -> String {
+    "info".to_string()
+}
+
+fn default_max_size_mb() -> u64 {
+    10
+}
+
+fn default_max_files() -> usize {
+    7
+}
+
+// 設定ファイルのうち[logging]だけを読むための型
+#[derive(Deserialize)]
+struct LoggingSection {
+    #[serde(default)]
+    logging: LoggingConfig,
+}
+
+impl LoggingConfig {
+    // ログ初期化はコマンドより先に行うため、設定ファイルの他の部分が不正でも失敗させない
+    pub fn from_config_file(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str::<LoggingSection>(&content).ok())
+            .map(|section| section.logging)
+            .unwrap_or_default()
+    }
+
+    pub fn apply_env(&mut self) {
+        if let Ok(level) = std::env::var("CONDUIT_LOG_LEVEL") {
+            self.level = level;
+        }
+        if let Some(format) = std::env::var("CONDUIT_LOG_FORMAT").ok().and_then(|v| v.parse().ok()) {
+            self.format = format;
+        }
+        if let Ok(file) = std::env::var("CONDUIT_LOG_FILE") {
+            self.file = Some(PathBuf::from(file));
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.rotation == LogRotation::Size && self.max_size_mb == 0 {
+            return Err(Error::config("logging.max_size_mb must be greater than 0 for size rotation"));
+        }
+        Ok(())
+    }
+}
+
+// グローバルsubscriberの設定。ファイル出力時に返るguardはプロセス終了まで保持すること
+pub fn init(config: &LoggingConfig) -> Result<Option<WorkerGuard>> {
+    config.validate()?;
+
+    let directive = format!("conduit={}", config.level)
+        .parse()
+        .map_err(|e| Error::config(format!("Invalid log level '{}': {}", config.level, e)))?;
+    let filter = EnvFilter::from_default_env().add_directive(directive);
+
+    let (writer, guard, ansi) = match config.file {
+        Some(ref path) => {
+            let (writer, guard) = tracing_appender::non_blocking(file_writer(path, config)?);
+            (BoxMakeWriter::new(writer), Some(guard), false)
+        }
+        None => (BoxMakeWriter::new(io::stdout), None, io::stdout().is_terminal()),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(ansi);
+    let result = match config.format {
+        LogFormat::Json => builder.json().try_init(),
+        LogFormat::Text => builder.try_init(),
+    };
+    result.map_err(|e| Error::config(format!("Failed to initialize logging: {}", e)))?;
+
+    Ok(guard)
+}
+
+fn file_writer(path: &Path, config: &LoggingConfig) -> Result<Box<dyn Write + Send>> {
+    let directory = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::config(format!("Invalid log file path: {}", path.display())))?
+        .to_string_lossy()
+        .to_string();
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| Error::config(format!("Failed to create log directory {}: {}", directory.display(), e)))?;
+
+    let rotation = match config.rotation {
+        LogRotation::Size => {
+            let writer = SizeRotatingWriter::open(path, config.max_size_mb * 1024 * 1024, config.max_files)
+                .map_err(|e| Error::config(format!("Failed to open log file {}: {}", path.display(), e)))?;
+            return Ok(Box::new(writer));
+        }
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+
+    // 日時ローテーションでは <file>.YYYY-MM-DD のように日付が付く
+    let appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(file_name)
+        .max_log_files(config.max_files.max(1))
+        .build(&directory)
+        .map_err(|e| Error::config(format!("Failed to open log file {}: {}", path.display(), e)))?;
+    Ok(Box::new(appender))
+}
+
+// サイズ上限で <file>.1, <file>.2 ... へ退避するファイル
+//
+// non_blockingのワーカースレッドからのみ書き込まれるので内部でロックは取らない
+pub struct SizeRotatingWriter {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl SizeRotatingWriter {
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = open_append(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            written,
+            max_bytes,
+            max_files,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        rotate_files(&self.path, self.max_files)?;
+        self.file = open_append(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // 1行が上限を超える場合でも空ファイルには書き込む
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+// <file>.N を一つずつずらし、上限を超えた最古のファイルを削除する
+fn rotate_files(path: &Path, max_files: usize) -> io::Result<()> {
+    let numbered = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+
+    if max_files == 0 {
+        return std::fs::remove_file(path).or_else(ignore_not_found);
+    }
+    std::fs::remove_file(numbered(max_files)).or_else(ignore_not_found)?;
+    for n in (1..max_files).rev() {
+        std::fs::rename(numbered(n), numbered(n + 1)).or_else(ignore_not_found)?;
+    }
+    std::fs::rename(path, numbered(1)).or_else(ignore_not_found)
+}
+
+fn ignore_not_found(e: io::Error) -> io::Result<()> {
+    if e.kind() == io::ErrorKind::NotFound {
+        Ok(())
+    } else {
+        Err(e)
+    }
+}
+
+// Tunnel Processのログ出力先
+pub const TUNNEL_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+pub const TUNNEL_LOG_MAX_FILES: usize = 3;
+
+pub fn logs_dir() -> PathBuf {
+    crate::common::paths::logs_dir()
+}
+
+pub fn tunnel_log_path(tunnel_name: &str) -> PathBuf {
+    // トンネル名はユーザー指定なのでパス区切りなどを含めない
+    let file_name: String = tunnel_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    logs_dir().join("tunnels").join(format!("{}.log", file_name))
+}
+
+// Tunnel Process起動時に標準出力・標準エラーの出力先として開く。
+// 子プロセスが直接書き込むためローテーションは起動時にのみ行う
+pub fn open_tunnel_log(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if std::fs::metadata(path).map(|m| m.len() > TUNNEL_LOG_MAX_BYTES).unwrap_or(false) {
+        rotate_files(path, TUNNEL_LOG_MAX_FILES)?;
+    }
+    open_append(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_options() {
+        assert_eq!("JSON".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("xml".parse::<LogFormat>().is_err());
+        assert_eq!("size".parse::<LogRotation>().unwrap(), LogRotation::Size);
+        assert!("weekly".parse::<LogRotation>().is_err());
+    }
+
+    #[test]
+    fn test_from_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conduit.toml");
+        std::fs::write(
+            &path,
+            r#"
+                [router]
+                host = "10.2.0.1"
+
+                [logging]
+                format = "json"
+                file = "/var/log/conduit/conduit.log"
+                rotation = "size"
+                max_size_mb = 50
+            "#,
+        )
+        .unwrap();
+
+        // [router]が不完全でもloggingは読める
+        let config = LoggingConfig::from_config_file(&path);
+        assert_eq!(config.format, LogFormat::Json);
+        assert_eq!(config.rotation, LogRotation::Size);
+        assert_eq!(config.max_size_mb, 50);
+        assert_eq!(config.max_files, 7);
+
+        let missing = LoggingConfig::from_config_file(&dir.path().join("missing.toml"));
+        assert_eq!(missing.format, LogFormat::Text);
+        assert!(missing.file.is_none());
+    }
+
+    #[test]
+    fn test_size_rotating_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conduit.log");
+        let mut writer = SizeRotatingWriter::open(&path, 16, 2).unwrap();
+
+        for line in ["first line 0001\n", "second line 002\n", "third line 0003\n", "fourth line 004\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let read = |p: PathBuf| std::fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "fourth line 004\n");
+        assert_eq!(read(dir.path().join("conduit.log.1")), "third line 0003\n");
+        assert_eq!(read(dir.path().join("conduit.log.2")), "second line 002\n");
+        // max_filesを超えた最古のファイルは削除される
+        assert!(!dir.path().join("conduit.log.3").exists());
+    }
+
+    #[test]
+    fn test_tunnel_log_path() {
+        let path = tunnel_log_path("web/../server access");
+        assert_eq!(path.file_name().unwrap(), "web_.._server_access.log");
+        assert_eq!(path.parent().unwrap(), logs_dir().join("tunnels"));
+    }
+}
@@ -4,6 +4,9 @@
 
 pub mod config;
 pub mod error;
+pub mod logging;
+pub mod paths;
+pub mod retry;
 pub mod types;
 
 pub use error::{Error, Result};
\ No newline at end of file
@@ -0,0 +1,361 @@
+// Conduitが使うディレクトリの決定
+//
+// init・ipc・registry・loggingが同じ場所を参照するよう、パスの決定はこのモジュールに集約する。
+// 優先順位: CONDUIT_HOME > XDG Base Directory（XDG_STATE_HOME / XDG_RUNTIME_DIR） > ホームディレクトリ配下の既定値
+// システムスコープ（--system）では/var/lib/conduit・/run/conduitを使い、conduitグループのメンバーと共有する
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+pub const CONDUIT_HOME_ENV: &str = "CONDUIT_HOME";
+// Tunnel Processなどの子プロセスへスコープを引き継ぐための環境変数
+pub const SCOPE_ENV: &str = "CONDUIT_SCOPE";
+pub const REGISTRY_FILE_NAME: &str = "registry.db";
+// システムスコープのRegistry・ソケットを参照できるグループ
+pub const SYSTEM_GROUP: &str = "conduit";
+
+const SYSTEM_STATE_DIR: &str = "/var/lib/conduit";
+const SYSTEM_SOCKET_DIR: &str = "/run/conduit";
+const SYSTEM_LOGS_DIR: &str = "/var/log/conduit";
+
+// SQLiteのWALモードでは本体と一緒に移動しないとコミット済みの書き込みを失う
+const REGISTRY_SIDECAR_SUFFIXES: [&str; 2] = ["-wal", "-shm"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scope {
+    // 実行ユーザーのホームディレクトリ配下
+    #[default]
+    User,
+    // systemdユニット等からrootで起動するトンネル用の共有Registry
+    System,
+}
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::User => "user",
+            Scope::System => "system",
+        }
+    }
+
+    // システムスコープではsetgidを付け、配下に作られるファイルもconduitグループにする
+    pub fn dir_mode(self) -> u32 {
+        match self {
+            Scope::User => 0o700,
+            Scope::System => 0o2770,
+        }
+    }
+
+    pub fn socket_mode(self) -> u32 {
+        match self {
+            Scope::User => 0o600,
+            Scope::System => 0o660,
+        }
+    }
+}
+
+static SCOPE: OnceLock<Scope> = OnceLock::new();
+
+// CLIの--systemで指定する。ディレクトリを参照する前に呼ぶこと
+pub fn set_scope(scope: Scope) {
+    if SCOPE.set(scope).is_err() && self::scope() != scope {
+        warn!("Conduit scope was already resolved as {}", self::scope().as_str());
+    }
+}
+
+pub fn scope() -> Scope {
+    *SCOPE.get_or_init(|| match std::env::var(SCOPE_ENV) {
+        Ok(value) if value == Scope::System.as_str() => Scope::System,
+        _ => Scope::User,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConduitPaths {
+    pub scope: Scope,
+    // Registryなど再起動後も残す状態の置き場
+    pub state_dir: PathBuf,
+    // UDSの置き場（ログアウトや再起動で消えてよい）
+    pub socket_dir: PathBuf,
+    pub logs_dir: PathBuf,
+    // CONDUIT_HOMEで明示された場合は旧レイアウトからの移行を行わない
+    pub explicit_home: bool,
+}
+
+impl ConduitPaths {
+    pub fn from_env() -> Self {
+        Self::resolve(
+            scope(),
+            std::env::var_os(CONDUIT_HOME_ENV),
+            std::env::var_os("XDG_STATE_HOME"),
+            std::env::var_os("XDG_RUNTIME_DIR"),
+            dirs::home_dir(),
+        )
+    }
+
+    // 環境変数の値から各ディレクトリを決める（テストのため環境から切り離している）
+    pub fn resolve(
+        scope: Scope,
+        conduit_home: Option<OsString>,
+        xdg_state_home: Option<OsString>,
+        xdg_runtime_dir: Option<OsString>,
+        home: Option<PathBuf>,
+    ) -> Self {
+        if let Some(conduit_home) = conduit_home.filter(|v| !v.is_empty()) {
+            let conduit_home = PathBuf::from(conduit_home);
+            return Self {
+                scope,
+                socket_dir: conduit_home.join("sockets"),
+                logs_dir: conduit_home.join("logs"),
+                state_dir: conduit_home,
+                explicit_home: true,
+            };
+        }
+
+        if scope == Scope::System {
+            return Self {
+                scope,
+                state_dir: PathBuf::from(SYSTEM_STATE_DIR),
+                socket_dir: PathBuf::from(SYSTEM_SOCKET_DIR),
+                logs_dir: PathBuf::from(SYSTEM_LOGS_DIR),
+                explicit_home: false,
+            };
+        }
+
+        let home = home.unwrap_or_else(|| PathBuf::from("."));
+        let state_dir = xdg_dir(xdg_state_home)
+            .unwrap_or_else(|| home.join(".local").join("state"))
+            .join("conduit");
+        // XDG_RUNTIME_DIRが無い環境（macOSなど）では状態ディレクトリの下に置く
+        let socket_dir = match xdg_dir(xdg_runtime_dir) {
+            Some(runtime_dir) => runtime_dir.join("conduit").join("sockets"),
+            None => state_dir.join("sockets"),
+        };
+
+        Self {
+            scope,
+            logs_dir: state_dir.join("logs"),
+            state_dir,
+            socket_dir,
+            explicit_home: false,
+        }
+    }
+
+    pub fn registry_db(&self) -> PathBuf {
+        self.state_dir.join(REGISTRY_FILE_NAME)
+    }
+}
+
+// XDG Base Directory仕様では相対パスは無効として扱う
+fn xdg_dir(value: Option<OsString>) -> Option<PathBuf> {
+    value.map(PathBuf::from).filter(|path| path.is_absolute())
+}
+
+pub fn state_dir() -> PathBuf {
+    ConduitPaths::from_env().state_dir
+}
+
+pub fn socket_dir() -> PathBuf {
+    ConduitPaths::from_env().socket_dir
+}
+
+pub fn registry_db_path() -> PathBuf {
+    ConduitPaths::from_env().registry_db()
+}
+
+pub fn logs_dir() -> PathBuf {
+    ConduitPaths::from_env().logs_dir
+}
+
+// 以前のバージョンが使っていた置き場（~/.conduitが本来の配置、~/.config/conduitはinitが作っていたもの）
+fn legacy_dirs(home: &Path) -> [PathBuf; 2] {
+    [home.join(".conduit"), home.join(".config").join("conduit")]
+}
+
+// 旧レイアウトのRegistryとログを現在の置き場へ移す。移動したパスを(移動元, 移動先)で返す。
+// 移動先に既にRegistryがある場合は上書きせず、旧ファイルはそのまま残す
+pub fn migrate_legacy_layout() -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let paths = ConduitPaths::from_env();
+    match dirs::home_dir() {
+        Some(home) if paths.scope == Scope::User && !paths.explicit_home => migrate_from(&legacy_dirs(&home), &paths),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn migrate_from(legacy_dirs: &[PathBuf], paths: &ConduitPaths) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut moved = Vec::new();
+
+    for legacy_dir in legacy_dirs {
+        if legacy_dir == &paths.state_dir || !legacy_dir.is_dir() {
+            continue;
+        }
+
+        let legacy_db = legacy_dir.join(REGISTRY_FILE_NAME);
+        let new_db = paths.registry_db();
+        if legacy_db.is_file() {
+            if new_db.exists() {
+                warn!(
+                    "Ignoring legacy registry {} because {} already exists",
+                    legacy_db.display(),
+                    new_db.display()
+                );
+            } else {
+                create_conduit_dir(&paths.state_dir)?;
+                move_path(&legacy_db, &new_db)?;
+                moved.push((legacy_db.clone(), new_db.clone()));
+                for suffix in REGISTRY_SIDECAR_SUFFIXES {
+                    let from = sidecar(&legacy_db, suffix);
+                    if from.exists() {
+                        let to = sidecar(&new_db, suffix);
+                        move_path(&from, &to)?;
+                        moved.push((from, to));
+                    }
+                }
+            }
+        }
+
+        let legacy_logs = legacy_dir.join("logs");
+        let new_logs = paths.logs_dir.clone();
+        if legacy_logs.is_dir() && !new_logs.exists() {
+            create_conduit_dir(&paths.state_dir)?;
+            move_path(&legacy_logs, &new_logs)?;
+            moved.push((legacy_logs, new_logs));
+        }
+    }
+
+    for (from, to) in &moved {
+        info!("Migrated {} to {}", from.display(), to.display());
+    }
+    Ok(moved)
+}
+
+fn sidecar(db_path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", db_path.display(), suffix))
+}
+
+// XDG_STATE_HOMEが別のファイルシステムにある場合はrenameできないため、コピーしてから消す
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match std::fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(_) if from.is_file() => {
+            std::fs::copy(from, to)?;
+            std::fs::remove_file(from)
+        }
+        Err(_) => {
+            copy_dir(from, to)?;
+            std::fs::remove_dir_all(from)
+        }
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+// Registryやソケットを含むため、ユーザースコープでは所有者のみ、システムスコープではconduitグループまでに限定する
+pub fn create_conduit_dir(dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        // rootが用意したシステムスコープのディレクトリをグループメンバーが使う場合は権限を変更しない
+        if std::fs::metadata(dir)?.uid() != nix::unistd::geteuid().as_raw() {
+            return Ok(());
+        }
+        let scope = scope();
+        if scope == Scope::System {
+            match system_group_id() {
+                Some(gid) => nix::unistd::chown(dir, None, Some(nix::unistd::Gid::from_raw(gid)))?,
+                None => warn!("Group '{}' not found; {} is accessible only by its owner", SYSTEM_GROUP, dir.display()),
+            }
+        }
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(scope.dir_mode()))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn system_group_id() -> Option<u32> {
+    nix::unistd::Group::from_name(SYSTEM_GROUP)
+        .ok()
+        .flatten()
+        .map(|group| group.gid.as_raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn os(value: &str) -> Option<OsString> {
+        Some(OsString::from(value))
+    }
+
+    #[test]
+    fn test_resolve_paths() {
+        let home = Some(PathBuf::from("/home/user"));
+
+        let defaults = ConduitPaths::resolve(Scope::User, None, None, None, home.clone());
+        assert_eq!(defaults.state_dir, PathBuf::from("/home/user/.local/state/conduit"));
+        assert_eq!(defaults.socket_dir, PathBuf::from("/home/user/.local/state/conduit/sockets"));
+        assert_eq!(defaults.registry_db(), PathBuf::from("/home/user/.local/state/conduit/registry.db"));
+
+        let xdg = ConduitPaths::resolve(Scope::User, None, os("/var/state"), os("/run/user/1000"), home.clone());
+        assert_eq!(xdg.state_dir, PathBuf::from("/var/state/conduit"));
+        assert_eq!(xdg.socket_dir, PathBuf::from("/run/user/1000/conduit/sockets"));
+        assert_eq!(xdg.logs_dir, PathBuf::from("/var/state/conduit/logs"));
+
+        // 相対パスのXDG変数は無視する
+        let relative = ConduitPaths::resolve(Scope::User, None, os("state"), os("run"), home.clone());
+        assert_eq!(relative, defaults);
+
+        let explicit = ConduitPaths::resolve(Scope::User, os("/opt/conduit"), os("/var/state"), os("/run/user/1000"), home.clone());
+        assert_eq!(explicit.state_dir, PathBuf::from("/opt/conduit"));
+        assert_eq!(explicit.socket_dir, PathBuf::from("/opt/conduit/sockets"));
+        assert!(explicit.explicit_home);
+
+        // システムスコープはユーザーのXDG変数に影響されない
+        let system = ConduitPaths::resolve(Scope::System, None, os("/var/state"), os("/run/user/1000"), home);
+        assert_eq!(system.registry_db(), PathBuf::from("/var/lib/conduit/registry.db"));
+        assert_eq!(system.socket_dir, PathBuf::from("/run/conduit"));
+        assert_eq!(system.logs_dir, PathBuf::from("/var/log/conduit"));
+    }
+
+    #[test]
+    fn test_migrate_legacy_layout() {
+        let temp = tempdir().unwrap();
+        let legacy = temp.path().join(".conduit");
+        std::fs::create_dir_all(legacy.join("logs").join("tunnels")).unwrap();
+        std::fs::write(legacy.join("registry.db"), b"db").unwrap();
+        std::fs::write(legacy.join("registry.db-wal"), b"wal").unwrap();
+        std::fs::write(legacy.join("logs").join("tunnels").join("web.log"), b"log").unwrap();
+
+        let paths = ConduitPaths::resolve(Scope::User, None, None, None, Some(temp.path().to_path_buf()));
+        let moved = migrate_from(&legacy_dirs(temp.path()), &paths).unwrap();
+        assert_eq!(moved.len(), 3);
+        assert_eq!(std::fs::read(paths.registry_db()).unwrap(), b"db");
+        assert!(sidecar(&paths.registry_db(), "-wal").exists());
+        assert!(paths.logs_dir.join("tunnels").join("web.log").exists());
+        assert!(!legacy.join("registry.db").exists());
+
+        // 移動先に既にRegistryがあれば上書きしない
+        std::fs::write(legacy.join("registry.db"), b"old").unwrap();
+        let moved = migrate_from(&legacy_dirs(temp.path()), &paths).unwrap();
+        assert!(moved.is_empty());
+        assert_eq!(std::fs::read(paths.registry_db()).unwrap(), b"db");
+        assert!(legacy.join("registry.db").exists());
+    }
+}
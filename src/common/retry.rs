@@ -0,0 +1,311 @@
+// 再試行の待ち時間とサーキットブレーカー
+//
+// Routerへの再接続（ConnectionManager）と接続試行（ProtocolHandler）で同じポリシーを使う。
+// 待ち時間は指数的に伸ばし、多数のClientが同時に再接続しないようジッターを加える。
+// 連続失敗がしきい値に達したら一定時間は試行を止め（open）、その後の1回（half-open）で復旧を確かめる
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // 1回目の失敗後の待ち時間
+    pub initial_delay_ms: u64,
+    // 待ち時間の上限
+    pub max_delay_ms: u64,
+    pub multiplier: f64,
+    // 待ち時間を±この割合でばらつかせる（0で無効）
+    pub jitter: f64,
+    // 連続して失敗できる回数（0で無制限）
+    pub max_attempts: u32,
+    pub circuit_breaker: CircuitBreakerConfig,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 5_000,
+            max_delay_ms: 300_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: 0,
+            circuit_breaker: CircuitBreakerConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    // 回路を開く連続失敗回数（0で無効）
+    pub failure_threshold: u32,
+    // 回路を開いたまま試行を止める時間
+    pub open_seconds: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 10,
+            open_seconds: 600,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // 1つの接続試行の中での短い再試行用（TLSハンドシェイクの一時的な失敗など）
+    pub fn quick(max_attempts: u32) -> Self {
+        Self {
+            initial_delay_ms: 100,
+            max_delay_ms: 2_000,
+            max_attempts,
+            circuit_breaker: CircuitBreakerConfig {
+                failure_threshold: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // 設定の待ち時間のまま、1つの接続試行の中での再試行に使う
+    // 回数を区切り回路も開かない（開くと次のRouterへの切り替えまで止まってしまう）
+    pub fn within_attempt(&self, max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            circuit_breaker: CircuitBreakerConfig {
+                failure_threshold: 0,
+                ..self.circuit_breaker.clone()
+            },
+            ..self.clone()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.initial_delay_ms == 0 {
+            return Err("initial_delay_ms must be greater than 0".to_string());
+        }
+        if self.max_delay_ms < self.initial_delay_ms {
+            return Err("max_delay_ms must not be less than initial_delay_ms".to_string());
+        }
+        if !(self.multiplier >= 1.0 && self.multiplier.is_finite()) {
+            return Err("multiplier must be at least 1.0".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err("jitter must be between 0.0 and 1.0".to_string());
+        }
+        if self.circuit_breaker.failure_threshold > 0 && self.circuit_breaker.open_seconds == 0 {
+            return Err("circuit_breaker.open_seconds must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
+    // ジッターを加える前の待ち時間（failuresは連続失敗回数、1始まり）
+    pub fn base_delay(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(63) as i32;
+        let delay_ms = (self.initial_delay_ms as f64 * self.multiplier.powi(exponent)).min(self.max_delay_ms as f64);
+        Duration::from_millis(delay_ms as u64)
+    }
+
+    pub fn delay(&self, failures: u32) -> Duration {
+        let base = self.base_delay(failures);
+        if self.jitter <= 0.0 {
+            return base;
+        }
+        let factor = 1.0 + rand::thread_rng().gen_range(-self.jitter..=self.jitter);
+        base.mul_f64(factor).min(Duration::from_millis(self.max_delay_ms))
+    }
+
+    fn open_duration(&self) -> Duration {
+        Duration::from_secs(self.circuit_breaker.open_seconds)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    // 試行を止めている
+    Open,
+    // 止めた後の様子見の1回
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// 失敗後の次の試行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryDecision {
+    // 連続失敗回数
+    pub failures: u32,
+    pub delay: Duration,
+    pub circuit: CircuitState,
+}
+
+// 1つの相手に対する連続失敗の記録
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    policy: RetryPolicy,
+    failures: u32,
+    circuit: CircuitState,
+}
+
+impl Backoff {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            failures: 0,
+            circuit: CircuitState::Closed,
+        }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    pub fn circuit(&self) -> CircuitState {
+        self.circuit
+    }
+
+    // 試行の直前に呼ぶ。開いた回路は待ち時間を終えたので様子見に移る
+    pub fn begin_attempt(&mut self) {
+        if self.circuit == CircuitState::Open {
+            self.circuit = CircuitState::HalfOpen;
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.circuit = CircuitState::Closed;
+    }
+
+    // 失敗を記録し、次の試行までの待ち時間を返す。再試行の上限に達したらNone
+    pub fn record_failure(&mut self) -> Option<RetryDecision> {
+        self.failures = self.failures.saturating_add(1);
+        if self.policy.max_attempts > 0 && self.failures >= self.policy.max_attempts {
+            return None;
+        }
+
+        let threshold = self.policy.circuit_breaker.failure_threshold;
+        // 様子見の1回が失敗したら、しきい値を待たずに回路を開き直す
+        let open = threshold > 0 && (self.failures >= threshold || self.circuit == CircuitState::HalfOpen);
+        let delay = if open {
+            self.circuit = CircuitState::Open;
+            self.policy.open_duration()
+        } else {
+            self.policy.delay(self.failures)
+        };
+
+        Some(RetryDecision {
+            failures: self.failures,
+            delay,
+            circuit: self.circuit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_delay_ms: 1_000,
+            max_delay_ms: 10_000,
+            multiplier: 2.0,
+            jitter: 0.0,
+            max_attempts: 0,
+            circuit_breaker: CircuitBreakerConfig {
+                failure_threshold: 0,
+                open_seconds: 60,
+            },
+        }
+    }
+
+    #[test]
+    fn test_exponential_delay_with_jitter() {
+        let mut policy = policy();
+        let delays: Vec<u64> = (1..=6).map(|n| policy.delay(n).as_millis() as u64).collect();
+        assert_eq!(delays, vec![1_000, 2_000, 4_000, 8_000, 10_000, 10_000]);
+
+        policy.jitter = 0.5;
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_millis(1_000) && delay <= Duration::from_millis(3_000));
+        }
+        // ジッターを加えても上限は超えない
+        assert!(policy.delay(10) <= Duration::from_millis(10_000));
+
+        assert!(policy.validate().is_ok());
+        policy.jitter = 1.5;
+        assert!(policy.validate().is_err());
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let mut backoff = Backoff::new(RetryPolicy { max_attempts: 3, ..policy() });
+        assert!(backoff.record_failure().is_some());
+        assert!(backoff.record_failure().is_some());
+        assert!(backoff.record_failure().is_none());
+
+        backoff.record_success();
+        assert_eq!(backoff.failures(), 0);
+        assert_eq!(backoff.record_failure().unwrap().delay, Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn test_within_attempt() {
+        let mut configured = policy();
+        configured.circuit_breaker.failure_threshold = 1;
+        let mut backoff = Backoff::new(configured.within_attempt(2));
+
+        // 待ち時間は設定どおりで、回路は開かずに回数で打ち切る
+        let decision = backoff.record_failure().unwrap();
+        assert_eq!(decision.delay, Duration::from_millis(1_000));
+        assert_eq!(decision.circuit, CircuitState::Closed);
+        assert!(backoff.record_failure().is_none());
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let mut policy = policy();
+        policy.circuit_breaker.failure_threshold = 3;
+        let mut backoff = Backoff::new(policy);
+
+        assert_eq!(backoff.record_failure().unwrap().circuit, CircuitState::Closed);
+        assert_eq!(backoff.record_failure().unwrap().circuit, CircuitState::Closed);
+        let decision = backoff.record_failure().unwrap();
+        assert_eq!(decision.circuit, CircuitState::Open);
+        assert_eq!(decision.delay, Duration::from_secs(60));
+
+        // 様子見の1回が失敗したら再び開く
+        backoff.begin_attempt();
+        assert_eq!(backoff.circuit(), CircuitState::HalfOpen);
+        assert_eq!(backoff.record_failure().unwrap().circuit, CircuitState::Open);
+
+        backoff.begin_attempt();
+        backoff.record_success();
+        assert_eq!(backoff.circuit(), CircuitState::Closed);
+        assert_eq!(backoff.record_failure().unwrap().delay, Duration::from_millis(1_000));
+    }
+}
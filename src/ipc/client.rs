@@ -130,6 +130,98 @@ impl UdsGrpcClient {
         Ok(shutdown_response)
     }
 
+    // 個別接続の切断
+    pub async fn close_connection(&mut self, connection_id: &str) -> Result<ControlResponse> {
+        debug!("Requesting close of connection {} on: {}", connection_id, self.socket_path.display());
+
+        let request = Request::new(CloseConnectionRequest {
+            connection_id: connection_id.to_string(),
+        });
+
+        let response = self
+            .client
+            .close_connection(request)
+            .await
+            .map_err(|e| {
+                error!("Failed to close connection: {}", e);
+                anyhow::anyhow!("gRPC error: {}", e)
+            })?;
+
+        Ok(response.into_inner())
+    }
+
+    // 新規接続の受け付け停止
+    pub async fn pause(&mut self) -> Result<ControlResponse> {
+        debug!("Requesting pause of: {}", self.socket_path.display());
+
+        let response = self
+            .client
+            .pause(Request::new(PauseRequest {}))
+            .await
+            .map_err(|e| {
+                error!("Failed to pause tunnel: {}", e);
+                anyhow::anyhow!("gRPC error: {}", e)
+            })?;
+
+        Ok(response.into_inner())
+    }
+
+    // 新規接続の受け付け再開
+    pub async fn resume(&mut self) -> Result<ControlResponse> {
+        debug!("Requesting resume of: {}", self.socket_path.display());
+
+        let response = self
+            .client
+            .resume(Request::new(ResumeRequest {}))
+            .await
+            .map_err(|e| {
+                error!("Failed to resume tunnel: {}", e);
+                anyhow::anyhow!("gRPC error: {}", e)
+            })?;
+
+        Ok(response.into_inner())
+    }
+
+    // 接続制限の変更
+    pub async fn update_limits(&mut self, request: UpdateLimitsRequest) -> Result<UpdateLimitsResponse> {
+        debug!("Requesting limits update on: {}", self.socket_path.display());
+
+        let response = self
+            .client
+            .update_limits(Request::new(request))
+            .await
+            .map_err(|e| {
+                error!("Failed to update limits: {}", e);
+                anyhow::anyhow!("gRPC error: {}", e)
+            })?;
+
+        Ok(response.into_inner())
+    }
+
+    // ドレイン（サーバー側の待機が終わるまで応答しない）
+    pub async fn drain(&mut self, timeout_seconds: i32, shutdown: bool) -> Result<DrainResponse> {
+        debug!(
+            "Requesting tunnel drain: timeout={}s, shutdown={}",
+            timeout_seconds, shutdown
+        );
+
+        let request = Request::new(DrainRequest {
+            timeout_seconds,
+            shutdown,
+        });
+
+        let response = self
+            .client
+            .drain(request)
+            .await
+            .map_err(|e| {
+                error!("Failed to drain tunnel: {}", e);
+                anyhow::anyhow!("gRPC error: {}", e)
+            })?;
+
+        Ok(response.into_inner())
+    }
+
     // メトリクスストリームの取得
     pub async fn get_metrics_stream(&mut self) -> Result<impl StreamExt<Item = Result<MetricsResponse, tonic::Status>>> {
         debug!("Starting metrics stream from: {}", self.socket_path.display());
@@ -148,6 +240,27 @@ impl UdsGrpcClient {
         Ok(response.into_inner())
     }
 
+    // 接続イベントストリームの取得
+    pub async fn watch_connections(
+        &mut self,
+        include_existing: bool,
+    ) -> Result<impl StreamExt<Item = Result<ConnectionEvent, tonic::Status>>> {
+        debug!("Starting connection event stream from: {}", self.socket_path.display());
+
+        let request = Request::new(WatchRequest { include_existing });
+
+        let response = self
+            .client
+            .watch_connections(request)
+            .await
+            .map_err(|e| {
+                error!("Failed to start connection event stream: {}", e);
+                anyhow::anyhow!("gRPC error: {}", e)
+            })?;
+
+        Ok(response.into_inner())
+    }
+
     // 限定されたメトリクス取得（指定回数分）
     pub async fn get_metrics_limited(&mut self, count: usize) -> Result<Vec<MetricsResponse>> {
         let mut stream = self.get_metrics_stream().await?;
@@ -290,6 +403,76 @@ impl ParallelUdsClient {
         }
     }
 
+    // 接続一覧の取得（複数トンネル並列）
+    pub async fn list_multiple_connections(
+        socket_paths: Vec<std::path::PathBuf>,
+        timeout_ms: u64,
+    ) -> Vec<(std::path::PathBuf, Result<ListResponse>)> {
+        let timeout_duration = Duration::from_millis(timeout_ms);
+
+        let tasks: Vec<_> = socket_paths
+            .into_iter()
+            .map(|socket_path| {
+                tokio::spawn(async move {
+                    let result = async {
+                        let mut client = UdsGrpcClient::connect_with_timeout(&socket_path, timeout_duration).await?;
+                        timeout(timeout_duration, client.list_connections())
+                            .await
+                            .context("List connections timeout")?
+                    }.await;
+                    (socket_path, result)
+                })
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for task in tasks {
+            if let Ok(result) = task.await {
+                results.push(result);
+            }
+        }
+
+        results
+    }
+
+    // 接続イベントの購読（複数トンネルのストリームを1つに合流）
+    // 接続できなかった・ストリームが切れたトンネルはErrを1度流して終わる
+    pub fn watch_multiple_connections(
+        socket_paths: Vec<std::path::PathBuf>,
+        include_existing: bool,
+        timeout_ms: u64,
+    ) -> tokio::sync::mpsc::Receiver<(std::path::PathBuf, Result<ConnectionEvent>)> {
+        let timeout_duration = Duration::from_millis(timeout_ms);
+        let (tx, rx) = tokio::sync::mpsc::channel(256);
+
+        for socket_path in socket_paths {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut stream = match async {
+                    let mut client = UdsGrpcClient::connect_with_timeout(&socket_path, timeout_duration).await?;
+                    client.watch_connections(include_existing).await
+                }.await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        let _ = tx.send((socket_path, Err(e))).await;
+                        return;
+                    }
+                };
+
+                while let Some(result) = stream.next().await {
+                    let result = result.map_err(|e| anyhow::anyhow!("gRPC error: {}", e));
+                    let failed = result.is_err();
+                    if tx.send((socket_path.clone(), result)).await.is_err() || failed {
+                        return;
+                    }
+                }
+                let _ = tx.send((socket_path, Err(anyhow::anyhow!("Connection event stream closed")))).await;
+            });
+        }
+
+        rx
+    }
+
     // 一括停止
     pub async fn shutdown_multiple(
         socket_paths: Vec<std::path::PathBuf>,
@@ -0,0 +1,550 @@
+// Tunnel Processの接続制御
+// gRPCの制御系RPC（CloseConnection/Pause/Resume/UpdateLimits/Drain）とデータプレーンの間で共有する状態
+
+use crate::ipc::protocol::ConnectionEventKind;
+use crate::metrics::LatencyHistogram;
+use crate::registry::models::ConnectionInfo;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot, watch};
+use tracing::{debug, info};
+
+// 接続イベントのバッファ。これを超えて遅れた購読者はイベントを取りこぼす
+const CONNECTION_EVENT_CAPACITY: usize = 1024;
+
+// 接続制限（0は無制限。max_connectionsのみ常に有効）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionLimits {
+    pub max_connections: u32,
+    pub rate_limit_bytes_per_sec: u64,
+    pub max_new_connections_per_sec: u32,
+}
+
+impl ConnectionLimits {
+    pub fn new(max_connections: u32) -> Self {
+        Self {
+            max_connections,
+            rate_limit_bytes_per_sec: 0,
+            max_new_connections_per_sec: 0,
+        }
+    }
+}
+
+// 制限の部分更新（Noneの項目は変更しない）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LimitsUpdate {
+    pub max_connections: Option<u32>,
+    pub rate_limit_bytes_per_sec: Option<u64>,
+    pub max_new_connections_per_sec: Option<u32>,
+}
+
+// ドレイン結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainOutcome {
+    // 期限内に自然に終了した接続数
+    pub drained: usize,
+    // 期限切れで切断した接続数
+    pub closed: usize,
+}
+
+// 接続の開始・終了の通知（WatchConnectionsで配信する）
+#[derive(Debug, Clone)]
+pub struct ConnectionNotice {
+    pub kind: ConnectionEventKind,
+    pub connection: ConnectionInfo,
+    pub error: Option<String>,
+}
+
+struct TrackedConnection {
+    info: ConnectionInfo,
+    close: oneshot::Sender<()>,
+}
+
+// 終了した接続も含めた累計（Tunnel Processのメトリクスとして公開する）
+#[derive(Debug, Clone, Default)]
+pub struct TrafficTotals {
+    pub connections: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub latency: LatencyHistogram,
+}
+
+// admitで確保した接続枠。registerで接続に引き継ぎ、登録せずに破棄すると枠を返す
+pub struct Admission {
+    control: Arc<ConnectionControl>,
+}
+
+impl Admission {
+    // 接続の登録。返されたReceiverが完了したら接続を閉じること
+    pub fn register(self, info: ConnectionInfo) -> oneshot::Receiver<()> {
+        let control = Arc::clone(&self.control);
+        let (tx, rx) = oneshot::channel();
+        let mut connections = control.connections.lock().unwrap();
+        connections.insert(info.id.clone(), TrackedConnection { info: info.clone(), close: tx });
+        // 登録と枠の解放を同じロックの中で行い、admitから見た接続数が途切れないようにする
+        drop(self);
+        control.active.send_replace(connections.len());
+        drop(connections);
+        control.totals.lock().unwrap().connections += 1;
+        control.notify(ConnectionEventKind::Open, info, None);
+        rx
+    }
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        self.control.reserved.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// 転送量の制限（1秒分まで貯められるトークンバケット）
+struct Bandwidth {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+// 新規接続の受け付けレート計測（1秒単位の固定ウィンドウ）
+struct AdmitWindow {
+    started_at: Instant,
+    count: u32,
+}
+
+// データプレーンは接続の受け付け時にadmitで枠を確保してregisterし、終了時にunregisterを呼ぶ
+// 転送したバイト数はthrottleで制限を待ってからrecord_trafficで記録する
+pub struct ConnectionControl {
+    paused: AtomicBool,
+    draining: AtomicBool,
+    limits: Mutex<ConnectionLimits>,
+    admit_window: Mutex<AdmitWindow>,
+    bandwidth: Mutex<Bandwidth>,
+    // 接続IDごとの接続情報と切断要求チャンネル（ListConnectionsもここを読む）
+    connections: Mutex<HashMap<String, TrackedConnection>>,
+    // admitで確保したまま、まだ登録されていない接続枠の数
+    reserved: AtomicUsize,
+    // ドレインで接続数が0になるのを待つため、登録数の変化を配信する
+    active: watch::Sender<usize>,
+    totals: Mutex<TrafficTotals>,
+    notices: broadcast::Sender<ConnectionNotice>,
+}
+
+impl ConnectionControl {
+    pub fn new(max_connections: u32) -> Self {
+        let (active, _) = watch::channel(0);
+        Self {
+            paused: AtomicBool::new(false),
+            draining: AtomicBool::new(false),
+            limits: Mutex::new(ConnectionLimits::new(max_connections)),
+            admit_window: Mutex::new(AdmitWindow { started_at: Instant::now(), count: 0 }),
+            bandwidth: Mutex::new(Bandwidth { tokens: 0.0, updated_at: Instant::now() }),
+            connections: Mutex::new(HashMap::new()),
+            reserved: AtomicUsize::new(0),
+            active,
+            totals: Mutex::new(TrafficTotals::default()),
+            notices: broadcast::channel(CONNECTION_EVENT_CAPACITY).0,
+        }
+    }
+
+    // 接続の開始・終了の購読
+    pub fn subscribe(&self) -> broadcast::Receiver<ConnectionNotice> {
+        self.notices.subscribe()
+    }
+
+    fn notify(&self, kind: ConnectionEventKind, connection: ConnectionInfo, error: Option<String>) {
+        // 購読者がいなければ破棄される
+        let _ = self.notices.send(ConnectionNotice { kind, connection, error });
+    }
+
+    // 新規接続を受け付ける状態か（Pause・Drain中はfalse）
+    pub fn is_accepting(&self) -> bool {
+        !self.paused.load(Ordering::SeqCst) && !self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    // 状態が変わった場合のみtrue
+    pub fn pause(&self) -> bool {
+        !self.paused.swap(true, Ordering::SeqCst)
+    }
+
+    // 停止せずに終えたドレインもここで受け付けを再開する
+    pub fn resume(&self) -> bool {
+        let was_paused = self.paused.swap(false, Ordering::SeqCst);
+        let was_draining = self.draining.swap(false, Ordering::SeqCst);
+        was_paused || was_draining
+    }
+
+    pub fn limits(&self) -> ConnectionLimits {
+        *self.limits.lock().unwrap()
+    }
+
+    pub fn update_limits(&self, update: LimitsUpdate) -> ConnectionLimits {
+        let mut limits = self.limits.lock().unwrap();
+        if let Some(max_connections) = update.max_connections {
+            limits.max_connections = max_connections;
+        }
+        if let Some(rate) = update.rate_limit_bytes_per_sec {
+            limits.rate_limit_bytes_per_sec = rate;
+        }
+        if let Some(rate) = update.max_new_connections_per_sec {
+            limits.max_new_connections_per_sec = rate;
+        }
+        info!("Connection limits updated: {:?}", *limits);
+        *limits
+    }
+
+    pub fn active_connections(&self) -> usize {
+        self.connections.lock().unwrap().len()
+    }
+
+    // 接続中の接続の一覧（接続した順）
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        let mut connections: Vec<ConnectionInfo> = self
+            .connections
+            .lock()
+            .unwrap()
+            .values()
+            .map(|c| c.info.clone())
+            .collect();
+        connections.sort_by(|a, b| a.connected_at.cmp(&b.connected_at).then_with(|| a.id.cmp(&b.id)));
+        connections
+    }
+
+    // 転送したバイト数の記録（sentはRouterへ、receivedはRouterから）
+    pub fn record_traffic(&self, connection_id: &str, sent: u64, received: u64) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(connection_id) {
+            connection.info.bytes_sent += sent;
+            connection.info.bytes_received += received;
+            connection.info.last_activity = chrono::Utc::now().timestamp();
+        }
+        let mut totals = self.totals.lock().unwrap();
+        totals.bytes_sent += sent;
+        totals.bytes_received += received;
+    }
+
+    // 受け付けてからRouter側の最初の応答が届くまでの時間の記録
+    pub fn record_latency(&self, latency: Duration) {
+        self.totals.lock().unwrap().latency.observe(latency.as_secs_f64() * 1000.0);
+    }
+
+    pub fn totals(&self) -> TrafficTotals {
+        self.totals.lock().unwrap().clone()
+    }
+
+    // 転送の失敗の通知。接続の後始末はデータプレーンがunregisterで行う
+    pub fn report_error(&self, connection_id: &str, error: String) {
+        let info = {
+            let mut connections = self.connections.lock().unwrap();
+            let Some(connection) = connections.get_mut(connection_id) else {
+                return;
+            };
+            connection.info.status = "error".to_string();
+            connection.info.clone()
+        };
+        self.notify(ConnectionEventKind::Error, info, Some(error));
+    }
+
+    // rate_limit_bytes_per_secを超えないよう、転送する前に必要なだけ待つ（0は無制限）
+    // 制限はトンネル全体で共有し、超過した分は後から転送する接続も含めて待たせる
+    pub async fn throttle(&self, bytes: usize) {
+        let rate = self.limits().rate_limit_bytes_per_sec;
+        if rate == 0 {
+            return;
+        }
+
+        let wait = {
+            let mut bandwidth = self.bandwidth.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bandwidth.updated_at).as_secs_f64() * rate as f64;
+            bandwidth.tokens = (bandwidth.tokens + refill).min(rate as f64) - bytes as f64;
+            bandwidth.updated_at = now;
+            if bandwidth.tokens < 0.0 {
+                Duration::from_secs_f64(-bandwidth.tokens / rate as f64)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    // 新規接続を受け付けてよいか（受け付け状態・同時接続数・受け付けレートを確認）
+    // 受け付けた場合は接続枠を確保して返す。登録前の枠も数えるため、同時に受け付けても上限を超えない
+    pub fn admit(self: &Arc<Self>) -> Option<Admission> {
+        if !self.is_accepting() {
+            return None;
+        }
+
+        let limits = self.limits();
+        let connections = self.connections.lock().unwrap();
+        if connections.len() + self.reserved.load(Ordering::SeqCst) >= limits.max_connections as usize {
+            return None;
+        }
+
+        if limits.max_new_connections_per_sec > 0 {
+            let mut window = self.admit_window.lock().unwrap();
+            if window.started_at.elapsed() >= Duration::from_secs(1) {
+                window.started_at = Instant::now();
+                window.count = 0;
+            }
+            if window.count >= limits.max_new_connections_per_sec {
+                return None;
+            }
+            window.count += 1;
+        }
+
+        self.reserved.fetch_add(1, Ordering::SeqCst);
+        drop(connections);
+        Some(Admission { control: Arc::clone(self) })
+    }
+
+    pub fn unregister(&self, connection_id: &str) {
+        let mut connections = self.connections.lock().unwrap();
+        let removed = connections.remove(connection_id);
+        if removed.is_some() {
+            self.active.send_replace(connections.len());
+        }
+        drop(connections);
+        if let Some(connection) = removed {
+            self.notify_closed(connection.info);
+        }
+    }
+
+    // 個別接続の切断要求。該当する接続がなければfalse
+    pub fn close(&self, connection_id: &str) -> bool {
+        let removed = self.connections.lock().unwrap().remove(connection_id);
+        let Some(connection) = removed else {
+            return false;
+        };
+        // データプレーン側が既に終了していても切断済みとして扱う
+        let _ = connection.close.send(());
+        self.active.send_replace(self.active_connections());
+        debug!("Requested close of connection {}", connection_id);
+        self.notify_closed(connection.info);
+        true
+    }
+
+    pub fn close_all(&self) -> usize {
+        let connections: Vec<_> = self.connections.lock().unwrap().drain().collect();
+        self.active.send_replace(0);
+        let count = connections.len();
+        for (_, connection) in connections {
+            let _ = connection.close.send(());
+            self.notify_closed(connection.info);
+        }
+        count
+    }
+
+    fn notify_closed(&self, mut info: ConnectionInfo) {
+        info.disconnected_at = Some(chrono::Utc::now().timestamp());
+        info.status = "closed".to_string();
+        self.notify(ConnectionEventKind::Close, info, None);
+    }
+
+    // 新規接続を止め、既存接続を待たずに全て切断する（強制停止用）
+    pub fn abort(&self) -> DrainOutcome {
+        self.draining.store(true, Ordering::SeqCst);
+        DrainOutcome {
+            drained: 0,
+            closed: self.close_all(),
+        }
+    }
+
+    // 新規接続を止めて期限まで既存接続の終了を待ち、残りは切断する
+    pub async fn drain(&self, timeout: Duration) -> DrainOutcome {
+        self.draining.store(true, Ordering::SeqCst);
+
+        let initial = self.active_connections();
+        info!("Draining {} connection(s) (timeout: {:?})", initial, timeout);
+
+        let mut active = self.active.subscribe();
+        let _ = tokio::time::timeout(timeout, active.wait_for(|&n| n == 0)).await;
+
+        let closed = self.close_all();
+        DrainOutcome {
+            drained: initial.saturating_sub(closed),
+            closed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(id: &str) -> ConnectionInfo {
+        ConnectionInfo::open(id.to_string(), "t1".to_string(), "127.0.0.1:50000".to_string(), "10.0.0.1:80".to_string(), 0)
+    }
+
+    fn register(control: &Arc<ConnectionControl>, id: &str) -> oneshot::Receiver<()> {
+        control.admit().unwrap().register(connection(id))
+    }
+
+    #[test]
+    fn test_admit_respects_pause_and_limits() {
+        let control = Arc::new(ConnectionControl::new(1));
+        assert!(control.admit().is_some());
+
+        let _rx = register(&control, "c1");
+        assert!(control.admit().is_none());
+        control.unregister("c1");
+        assert!(control.admit().is_some());
+
+        assert!(control.pause());
+        assert!(!control.pause());
+        assert!(control.admit().is_none());
+        assert!(control.resume());
+        assert!(control.admit().is_some());
+
+        control.update_limits(LimitsUpdate {
+            max_connections: Some(10),
+            max_new_connections_per_sec: Some(2),
+            ..Default::default()
+        });
+        assert!(control.admit().is_some());
+        assert!(control.admit().is_some());
+        assert!(control.admit().is_none());
+    }
+
+    #[test]
+    fn test_totals_include_closed_connections() {
+        let control = Arc::new(ConnectionControl::new(10));
+        let _rx = register(&control, "c1");
+        control.record_traffic("c1", 100, 250);
+        control.record_latency(Duration::from_millis(12));
+        control.unregister("c1");
+        let _rx = register(&control, "c2");
+        control.record_traffic("c2", 10, 0);
+
+        let totals = control.totals();
+        assert_eq!(totals.connections, 2);
+        assert_eq!(totals.bytes_sent, 110);
+        assert_eq!(totals.bytes_received, 250);
+        assert_eq!(totals.latency.count, 1);
+        assert_eq!(control.active_connections(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_admits_reserve_slots() {
+        let control = Arc::new(ConnectionControl::new(5));
+        let barrier = Arc::new(std::sync::Barrier::new(20));
+        let handles: Vec<_> = (0..20)
+            .map(|_| {
+                let control = Arc::clone(&control);
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    control.admit()
+                })
+            })
+            .collect();
+        let admitted: Vec<Admission> = handles.into_iter().filter_map(|h| h.join().unwrap()).collect();
+        // 登録前の枠も上限に数える
+        assert_eq!(admitted.len(), 5);
+        assert!(control.admit().is_none());
+
+        let mut admitted = admitted.into_iter();
+        let _rx = admitted.next().unwrap().register(connection("c1"));
+        assert!(control.admit().is_none());
+        // 登録しなかった枠は破棄すると返る
+        drop(admitted);
+        assert_eq!(control.active_connections(), 1);
+        assert!(control.admit().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_close_connection() {
+        let control = Arc::new(ConnectionControl::new(10));
+        let rx = register(&control, "c1");
+
+        assert!(control.close("c1"));
+        assert!(rx.await.is_ok());
+        assert!(!control.close("c1"));
+        assert_eq!(control.active_connections(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connections_record_traffic_and_notify() {
+        let control = Arc::new(ConnectionControl::new(10));
+        let mut notices = control.subscribe();
+        let _rx = register(&control, "c1");
+        control.record_traffic("c1", 10, 20);
+
+        let connections = control.connections();
+        assert_eq!(connections.len(), 1);
+        assert_eq!((connections[0].bytes_sent, connections[0].bytes_received), (10, 20));
+
+        control.unregister("c1");
+        assert!(control.connections().is_empty());
+        let opened = notices.recv().await.unwrap();
+        assert_eq!((opened.kind, opened.connection.id.as_str()), (ConnectionEventKind::Open, "c1"));
+        let closed = notices.recv().await.unwrap();
+        assert_eq!(closed.kind, ConnectionEventKind::Close);
+        assert_eq!(closed.connection.bytes_received, 20);
+        assert!(closed.connection.disconnected_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_report_error_notifies_before_close() {
+        let control = Arc::new(ConnectionControl::new(10));
+        let _rx = register(&control, "c1");
+        let mut notices = control.subscribe();
+
+        control.report_error("c1", "connection reset".to_string());
+        control.unregister("c1");
+        // 登録されていない接続の失敗は通知しない
+        control.report_error("c1", "late".to_string());
+
+        let failed = notices.recv().await.unwrap();
+        assert_eq!(failed.kind, ConnectionEventKind::Error);
+        assert_eq!(failed.error.as_deref(), Some("connection reset"));
+        assert_eq!(failed.connection.status, "error");
+        assert_eq!(notices.recv().await.unwrap().kind, ConnectionEventKind::Close);
+        assert!(notices.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_throttle_enforces_rate_limit() {
+        let control = Arc::new(ConnectionControl::new(10));
+        // 無制限なら待たない
+        let started = Instant::now();
+        control.throttle(1_000_000).await;
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        control.update_limits(LimitsUpdate {
+            rate_limit_bytes_per_sec: Some(10_000),
+            ..Default::default()
+        });
+        let started = Instant::now();
+        control.throttle(1_000).await;
+        control.throttle(1_000).await;
+        // 2,000バイトを10,000バイト/秒で送るには0.2秒かかる
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn test_drain_counts_drained_and_closed() {
+        let control = Arc::new(ConnectionControl::new(10));
+        let _finishing = register(&control, "c1");
+        let stuck = register(&control, "c2");
+
+        let finisher = Arc::clone(&control);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            finisher.unregister("c1");
+        });
+
+        let outcome = control.drain(Duration::from_millis(200)).await;
+        assert_eq!(outcome, DrainOutcome { drained: 1, closed: 1 });
+        assert!(stuck.await.is_ok());
+        assert!(!control.is_accepting());
+    }
+}
@@ -3,6 +3,8 @@
 
 pub mod server;
 pub mod client;
+pub mod control;
+pub mod peer;
 pub mod protocol;
 
 pub use server::UdsGrpcServer;
@@ -19,15 +21,7 @@ pub async fn init_uds_system() -> Result<()> {
     
     // ソケットディレクトリの作成
     let socket_dir = get_socket_directory()?;
-    tokio::fs::create_dir_all(&socket_dir).await?;
-    
-    // Unix系でのディレクトリ権限設定
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let perms = std::fs::Permissions::from_mode(0o700);
-        std::fs::set_permissions(&socket_dir, perms)?;
-    }
+    crate::common::paths::create_conduit_dir(&socket_dir)?;
     
     debug!("UDS socket directory prepared: {}", socket_dir.display());
     Ok(())
@@ -35,23 +29,42 @@ pub async fn init_uds_system() -> Result<()> {
 
 // ソケットディレクトリのパス取得
 pub fn get_socket_directory() -> Result<std::path::PathBuf> {
-    Ok(dirs::home_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join(".conduit")
-        .join("sockets"))
+    Ok(crate::common::paths::socket_dir())
 }
 
+// トンネルごとのディレクトリに置くソケットのファイル名
+pub const SOCKET_FILE_NAME: &str = "control.sock";
+
 // トンネル用ソケットパスの生成
 pub fn get_tunnel_socket_path(tunnel_id: &str) -> Result<std::path::PathBuf> {
-    Ok(get_socket_directory()?.join(format!("{}.sock", tunnel_id)))
+    tunnel_socket_path(&get_socket_directory()?, tunnel_id)
 }
 
-// ソケットファイルのクリーンアップ
+// ソケットは<ソケットディレクトリ>/<トンネルID>/control.sockに置き、パスをIDだけから決められるようにする
+// （Registryにはパスのハッシュのみを保存するため、ハッシュから逆算せずに済む）
+pub fn tunnel_socket_path(socket_dir: &Path, tunnel_id: &str) -> Result<std::path::PathBuf> {
+    // IDをそのままディレクトリ名に使うため、ソケットディレクトリの外を指すIDは拒否する
+    let valid = !tunnel_id.is_empty()
+        && tunnel_id != "."
+        && tunnel_id != ".."
+        && tunnel_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!("Invalid tunnel ID for socket path: {:?}", tunnel_id);
+    }
+    Ok(socket_dir.join(tunnel_id).join(SOCKET_FILE_NAME))
+}
+
+// ソケットファイルのクリーンアップ（トンネル用ディレクトリも空なら削除する）
 pub async fn cleanup_socket_file(socket_path: &Path) -> Result<()> {
     if socket_path.exists() {
         tokio::fs::remove_file(socket_path).await?;
         debug!("Cleaned up socket file: {}", socket_path.display());
     }
+    if socket_path.file_name().is_some_and(|name| name == SOCKET_FILE_NAME) {
+        if let Some(tunnel_dir) = socket_path.parent() {
+            let _ = tokio::fs::remove_dir(tunnel_dir).await;
+        }
+    }
     Ok(())
 }
 
@@ -80,12 +93,27 @@ mod tests {
         assert!(result.is_ok());
         
         let socket_dir = result.unwrap();
-        assert!(socket_dir.to_string_lossy().contains(".conduit"));
+        assert_eq!(socket_dir, crate::common::paths::socket_dir());
     }
     
     #[test]
     fn test_tunnel_socket_path_generation() {
         let socket_path = get_tunnel_socket_path("test-tunnel").unwrap();
-        assert!(socket_path.to_string_lossy().contains("test-tunnel.sock"));
+        assert!(socket_path.ends_with("test-tunnel/control.sock"));
+
+        assert!(tunnel_socket_path(Path::new("/tmp"), "../etc").is_err());
+        assert!(tunnel_socket_path(Path::new("/tmp"), "..").is_err());
+        assert!(tunnel_socket_path(Path::new("/tmp"), "").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_socket_file_removes_tunnel_directory() {
+        let temp_dir = tempdir().unwrap();
+        let socket_path = tunnel_socket_path(temp_dir.path(), "t1").unwrap();
+        std::fs::create_dir_all(socket_path.parent().unwrap()).unwrap();
+        std::fs::write(&socket_path, b"").unwrap();
+
+        cleanup_socket_file(&socket_path).await.unwrap();
+        assert!(!temp_dir.path().join("t1").exists());
     }
 }
\ No newline at end of file
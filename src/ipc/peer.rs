@@ -0,0 +1,133 @@
+// UDS接続元の認可
+// 受け付けたUnix streamのSO_PEERCRED（UID/GID/PID）をRPCごとのポリシーと照合する
+
+use crate::common::paths::{self, Scope};
+use std::fmt;
+
+// 接続元プロセスの資格情報
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCred {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<i32>,
+}
+
+impl From<tokio::net::unix::UCred> for PeerCred {
+    fn from(cred: tokio::net::unix::UCred) -> Self {
+        Self {
+            uid: cred.uid(),
+            gid: cred.gid(),
+            pid: cred.pid(),
+        }
+    }
+}
+
+impl fmt::Display for PeerCred {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "uid={} gid={}", self.uid, self.gid)?;
+        if let Some(pid) = self.pid {
+            write!(f, " pid={}", pid)?;
+        }
+        Ok(())
+    }
+}
+
+// RPCが必要とする権限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    // 状態・接続一覧・メトリクスの参照
+    Read,
+    // Shutdown・Drain・接続切断・制限変更などトンネルを変更する操作
+    Control,
+}
+
+impl Access {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Control => "control",
+        }
+    }
+}
+
+// rootとトンネルの所有者は全RPC、共有グループのメンバーは参照のみ許可する
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerPolicy {
+    pub owner_uid: u32,
+    // 参照を許可するグループ（ユーザースコープでは共有しない）
+    pub read_gid: Option<u32>,
+}
+
+impl PeerPolicy {
+    // 実行中のプロセスをトンネルの所有者とし、システムスコープではconduitグループに参照を許可する
+    pub fn for_current_process() -> Self {
+        let read_gid = match paths::scope() {
+            Scope::System => paths::system_group_id(),
+            Scope::User => None,
+        };
+        Self {
+            owner_uid: nix::unistd::geteuid().as_raw(),
+            read_gid,
+        }
+    }
+
+    pub fn check(&self, peer: Option<PeerCred>, access: Access) -> Result<(), String> {
+        // SO_PEERCREDが取れない接続は相手を特定できないため拒否する
+        let Some(peer) = peer else {
+            return Err("peer credentials are unavailable".to_string());
+        };
+        if peer.uid == 0 || peer.uid == self.owner_uid {
+            return Ok(());
+        }
+
+        match (access, self.read_gid) {
+            (Access::Read, Some(gid)) if peer.gid == gid || is_group_member(peer.uid, gid) => Ok(()),
+            (Access::Read, _) => Err(format!("uid {} is not the tunnel owner or a member of the shared group", peer.uid)),
+            (Access::Control, _) => Err(format!("uid {} is not root or the tunnel owner (uid {})", peer.uid, self.owner_uid)),
+        }
+    }
+}
+
+// SO_PEERCREDは主グループしか返さないため、補助グループはグループDBで確認する
+fn is_group_member(uid: u32, gid: u32) -> bool {
+    use nix::unistd::{Gid, Group, Uid, User};
+
+    let Ok(Some(user)) = User::from_uid(Uid::from_raw(uid)) else {
+        return false;
+    };
+    Group::from_gid(Gid::from_raw(gid))
+        .ok()
+        .flatten()
+        .is_some_and(|group| group.mem.iter().any(|member| member == &user.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(uid: u32, gid: u32) -> Option<PeerCred> {
+        Some(PeerCred { uid, gid, pid: Some(4242) })
+    }
+
+    #[test]
+    fn test_peer_policy() {
+        let shared = PeerPolicy { owner_uid: 1000, read_gid: Some(990) };
+        assert!(shared.check(peer(1000, 1000), Access::Control).is_ok());
+        assert!(shared.check(peer(0, 0), Access::Control).is_ok());
+        assert!(shared.check(peer(1001, 990), Access::Read).is_ok());
+        assert!(shared.check(peer(1001, 990), Access::Control).is_err());
+        assert!(shared.check(peer(1002, 1002), Access::Read).is_err());
+        assert!(shared.check(None, Access::Read).is_err());
+
+        // グループを共有しない場合は所有者とrootのみ
+        let private = PeerPolicy { owner_uid: 1000, read_gid: None };
+        assert!(private.check(peer(1001, 990), Access::Read).is_err());
+        assert!(private.check(peer(1000, 1000), Access::Read).is_ok());
+    }
+
+    #[test]
+    fn test_peer_cred_display() {
+        let cred = PeerCred { uid: 1001, gid: 990, pid: Some(4242) };
+        assert_eq!(cred.to_string(), "uid=1001 gid=990 pid=4242");
+    }
+}
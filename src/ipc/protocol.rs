@@ -35,6 +35,24 @@ pub async fn create_uds_channel(socket_path: &Path) -> Result<Channel> {
     Ok(channel)
 }
 
+// 接続イベント種別（ConnectionEvent.kindの値）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEventKind {
+    Open,
+    Close,
+    Error,
+}
+
+impl ConnectionEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Close => "close",
+            Self::Error => "error",
+        }
+    }
+}
+
 // TunnelInfoの変換ヘルパー
 impl From<crate::registry::models::TunnelInfo> for TunnelInfo {
     fn from(info: crate::registry::models::TunnelInfo) -> Self {
@@ -65,6 +83,44 @@ impl From<crate::registry::models::TunnelMetrics> for TunnelMetrics {
             memory_usage: metrics.memory_usage as i64,
             uptime_seconds: metrics.uptime_seconds as i64,
             avg_latency_ms: metrics.avg_latency_ms,
+            reconnects: metrics.reconnects as i64,
+            auth_failures: metrics.auth_failures as i64,
+            latency_buckets: metrics.latency.buckets.iter().map(|&n| n as i64).collect(),
+            latency_count: metrics.latency.count as i64,
+            latency_sum_ms: metrics.latency.sum_ms,
+            compression_bytes_in: metrics.compression.bytes_in as i64,
+            compression_bytes_out: metrics.compression.bytes_out as i64,
+            compressed_frames: metrics.compression.compressed_frames as i64,
+        }
+    }
+}
+
+// Prometheusエクスポート用の形式への変換
+impl TunnelMetrics {
+    pub fn to_sample(&self, tunnel: String) -> crate::metrics::TunnelSample {
+        let non_negative = |v: i64| v.max(0) as u64;
+        let mut latency = crate::metrics::LatencyHistogram {
+            count: non_negative(self.latency_count),
+            sum_ms: self.latency_sum_ms,
+            ..Default::default()
+        };
+        // 古いTunnel Processはバケットを返さないため、その場合は件数と合計のみ出す
+        for (bucket, count) in latency.buckets.iter_mut().zip(&self.latency_buckets) {
+            *bucket = non_negative(*count);
+        }
+
+        crate::metrics::TunnelSample {
+            tunnel,
+            up: true,
+            active_connections: non_negative(self.active_connections as i64),
+            total_connections: non_negative(self.total_connections),
+            bytes_sent: non_negative(self.total_bytes_sent),
+            bytes_received: non_negative(self.total_bytes_received),
+            reconnects: non_negative(self.reconnects),
+            auth_failures: non_negative(self.auth_failures),
+            compression_bytes_in: non_negative(self.compression_bytes_in),
+            compression_bytes_out: non_negative(self.compression_bytes_out),
+            latency,
         }
     }
 }
@@ -84,6 +140,43 @@ impl From<crate::registry::models::ConnectionInfo> for ConnectionInfo {
     }
 }
 
+// ConnectionLimitsの変換ヘルパー
+impl From<crate::ipc::control::ConnectionLimits> for ConnectionLimits {
+    fn from(limits: crate::ipc::control::ConnectionLimits) -> Self {
+        Self {
+            max_connections: limits.max_connections,
+            rate_limit_bytes_per_sec: limits.rate_limit_bytes_per_sec,
+            max_new_connections_per_sec: limits.max_new_connections_per_sec,
+        }
+    }
+}
+
+// TargetHealthの変換ヘルパー
+impl From<crate::protocol::messages::TargetHealth> for TargetHealth {
+    fn from(health: crate::protocol::messages::TargetHealth) -> Self {
+        Self {
+            target_addr: health.target_addr.to_string(),
+            state: health.state.as_str().to_string(),
+            checked_at: health.checked_at.timestamp(),
+            latency_ms: health.latency_ms.unwrap_or(0.0),
+            consecutive_failures: health.consecutive_failures as i32,
+            error: health.error.unwrap_or_default(),
+        }
+    }
+}
+
+// 再接続待ち状態の変換ヘルパー
+impl From<crate::client::ReconnectStatus> for RouterReconnect {
+    fn from(status: crate::client::ReconnectStatus) -> Self {
+        Self {
+            consecutive_failures: status.failures,
+            next_attempt_at: status.next_attempt_at.timestamp(),
+            circuit: status.circuit.as_str().to_string(),
+            last_error: String::new(),
+        }
+    }
+}
+
 // レスポンス構築ヘルパー
 pub mod response_builders {
     use super::*;
@@ -92,6 +185,7 @@ pub mod response_builders {
     pub fn build_status_response(
         tunnel_info: models::TunnelInfo,
         connections: Vec<models::ConnectionInfo>,
+        target_health: Option<crate::protocol::messages::TargetHealth>,
     ) -> StatusResponse {
         let tunnel_info_proto = TunnelInfo::from(tunnel_info.clone());
         let connections_proto: Vec<ConnectionInfo> = connections
@@ -104,6 +198,11 @@ pub mod response_builders {
             tunnel_info: Some(tunnel_info_proto),
             connections: connections_proto,
             metrics: Some(metrics_proto),
+            target_health: target_health.map(TargetHealth::from),
+            // 接続制御・再接続の状態はサービス側で設定する
+            accepting: true,
+            limits: None,
+            router_reconnect: None,
         }
     }
 
@@ -119,7 +218,31 @@ pub mod response_builders {
     }
 
     pub fn build_shutdown_response(success: bool, message: String) -> ShutdownResponse {
-        ShutdownResponse { success, message }
+        ShutdownResponse {
+            success,
+            message,
+            drained_connections: 0,
+            closed_connections: 0,
+        }
+    }
+
+    pub fn build_control_response(success: bool, message: String) -> ControlResponse {
+        ControlResponse { success, message }
+    }
+
+    pub fn build_connection_event(
+        tunnel_id: &str,
+        kind: ConnectionEventKind,
+        connection: models::ConnectionInfo,
+        error: Option<String>,
+    ) -> ConnectionEvent {
+        ConnectionEvent {
+            tunnel_id: tunnel_id.to_string(),
+            kind: kind.as_str().to_string(),
+            connection: Some(ConnectionInfo::from(connection)),
+            error: error.unwrap_or_default(),
+            timestamp: chrono::Utc::now().timestamp(),
+        }
     }
 
     pub fn build_metrics_response(metrics: models::TunnelMetrics, timestamp: i64) -> MetricsResponse {
@@ -160,6 +283,10 @@ pub mod error_handling {
         Status::internal(message)
     }
 
+    pub fn permission_denied_error(message: &str) -> Status {
+        Status::permission_denied(message)
+    }
+
     pub fn invalid_argument_error(message: &str) -> Status {
         Status::invalid_argument(message)
     }
@@ -180,6 +307,24 @@ pub mod validation {
         Ok(())
     }
 
+    pub fn validate_drain_request(request: &DrainRequest) -> Result<(), Status> {
+        if request.timeout_seconds < 0 {
+            return Err(Status::invalid_argument("Timeout must be non-negative"));
+        }
+        if request.timeout_seconds > 300 {
+            return Err(Status::invalid_argument("Timeout cannot exceed 300 seconds"));
+        }
+        Ok(())
+    }
+
+    pub fn validate_update_limits_request(request: &UpdateLimitsRequest) -> Result<(), Status> {
+        // 0にすると一切受け付けなくなるため、受け付け停止はPauseを使う
+        if request.max_connections == Some(0) {
+            return Err(Status::invalid_argument("Max connections must be greater than 0 (use Pause to stop accepting)"));
+        }
+        Ok(())
+    }
+
     pub fn validate_tunnel_id(tunnel_id: &str) -> Result<(), Status> {
         if tunnel_id.is_empty() {
             return Err(Status::invalid_argument("Tunnel ID cannot be empty"));
@@ -209,6 +354,12 @@ mod tests {
             protocol: "tcp".to_string(),
             timeout_seconds: 30,
             max_connections: 100,
+            restart_policy: Default::default(),
+            restart_on_unhealthy: false,
+            unhealthy_threshold: models::DEFAULT_UNHEALTHY_THRESHOLD,
+            compression: None,
+            webhooks: Vec::new(),
+            retry: Default::default(),
         };
 
         let tunnel_info = models::TunnelInfo {
@@ -222,6 +373,9 @@ mod tests {
             updated_at: 1000000001,
             last_activity: 1000000002,
             exit_code: None,
+            restart_count: 0,
+            last_exit_code: None,
+            health: Default::default(),
             metrics: models::TunnelMetrics::default(),
         };
 
@@ -256,5 +410,34 @@ mod tests {
             timeout_seconds: -1,
         };
         assert!(validate_shutdown_request(&invalid_request).is_err());
+
+        // ドレインと制限変更
+        assert!(validate_drain_request(&DrainRequest { timeout_seconds: 30, shutdown: true }).is_ok());
+        assert!(validate_drain_request(&DrainRequest { timeout_seconds: 301, shutdown: false }).is_err());
+        assert!(validate_update_limits_request(&UpdateLimitsRequest::default()).is_ok());
+        assert!(validate_update_limits_request(&UpdateLimitsRequest {
+            max_connections: Some(0),
+            ..Default::default()
+        }).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_metrics_to_sample() {
+        let mut registry_metrics = models::TunnelMetrics {
+            active_connections: 3,
+            total_connections: 42,
+            reconnects: 2,
+            ..Default::default()
+        };
+        registry_metrics.latency.observe(7.0);
+        registry_metrics.latency.observe(8.0);
+
+        let sample = TunnelMetrics::from(registry_metrics).to_sample("web".to_string());
+        assert!(sample.up);
+        assert_eq!(sample.tunnel, "web");
+        assert_eq!(sample.active_connections, 3);
+        assert_eq!(sample.reconnects, 2);
+        assert_eq!(sample.latency.buckets[2], 2);
+        assert_eq!(sample.latency.count, 2);
+    }
+}
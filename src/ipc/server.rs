@@ -1,13 +1,19 @@
 // Unix Domain Socket gRPCサーバー
 // Tunnel ProcessとCLI Commands間の通信サーバー
 
-use crate::ipc::protocol::{self, tunnel::*, TunnelControl, TunnelControlServer};
-use crate::registry::models::{TunnelInfo as RegistryTunnelInfo, TunnelMetrics as RegistryTunnelMetrics, ConnectionInfo as RegistryConnectionInfo, TunnelStatus, TunnelConfig};
+use crate::client::ReconnectStatus;
+use crate::ipc::control::{ConnectionControl, LimitsUpdate};
+use crate::ipc::peer::{Access, PeerCred, PeerPolicy};
+use crate::ipc::protocol::{self, tunnel::*, ConnectionEventKind, TunnelControl, TunnelControlServer};
+use crate::protocol::messages::TargetHealth as RouterTargetHealth;
+use crate::registry::sqlite::SqliteRegistry;
+use crate::registry::models::{TunnelInfo as RegistryTunnelInfo, TunnelMetrics as RegistryTunnelMetrics, TunnelStatus, TunnelConfig, DEFAULT_UNHEALTHY_THRESHOLD};
 use anyhow::Result;
 use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::UdsConnectInfo;
 use tonic::{transport::Server, Request, Response, Status};
 use tracing::{debug, error, info, warn};
 
@@ -40,11 +46,11 @@ impl UdsGrpcServer {
         // Unix Domain Socketリスナーの作成
         let listener = tokio::net::UnixListener::bind(&self.socket_path)?;
 
-        // ソケットファイルの権限設定（所有者のみアクセス可能）
+        // ソケットファイルの権限設定（所有者のみ、システムスコープではconduitグループも接続可能）
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            let perms = std::fs::Permissions::from_mode(0o600);
+            let perms = std::fs::Permissions::from_mode(crate::common::paths::scope().socket_mode());
             std::fs::set_permissions(&self.socket_path, perms)?;
         }
 
@@ -78,9 +84,18 @@ impl UdsGrpcServer {
 pub struct TunnelControlService {
     tunnel_id: String,
     tunnel_info: Arc<RwLock<RegistryTunnelInfo>>,
-    connections: Arc<RwLock<Vec<RegistryConnectionInfo>>>,
     metrics: Arc<RwLock<RegistryTunnelMetrics>>,
+    target_health: Arc<RwLock<Option<RouterTargetHealth>>>,
+    reconnect_status: Arc<RwLock<Option<ReconnectStatus>>>,
+    // Routerとの接続で直近に発生したエラー（再接続待ちの理由として返す）
+    router_error: Arc<RwLock<Option<String>>>,
     shutdown_signal: Arc<RwLock<Option<tokio::sync::oneshot::Sender<()>>>>,
+    // 接続の受け付け判定と接続一覧（データプレーンと共有する）
+    control: Arc<ConnectionControl>,
+    // 接続元の資格情報に対するRPCごとの認可ポリシー
+    policy: PeerPolicy,
+    // 拒否したRPCの記録先（Registryのaudit_log）
+    audit_registry: Arc<RwLock<Option<Arc<SqliteRegistry>>>>,
 }
 
 impl TunnelControlService {
@@ -99,40 +114,106 @@ impl TunnelControlService {
                 protocol: "tcp".to_string(),
                 timeout_seconds: 30,
                 max_connections: 100,
+                restart_policy: Default::default(),
+                restart_on_unhealthy: false,
+                unhealthy_threshold: DEFAULT_UNHEALTHY_THRESHOLD,
+                compression: None,
+                webhooks: Vec::new(),
+                retry: Default::default(),
             },
             created_at: chrono::Utc::now().timestamp(),
             updated_at: chrono::Utc::now().timestamp(),
             last_activity: chrono::Utc::now().timestamp(),
             exit_code: None,
+            restart_count: 0,
+            last_exit_code: None,
+            health: Default::default(),
             metrics: RegistryTunnelMetrics::default(),
         };
 
+        let control = Arc::new(ConnectionControl::new(tunnel_info.config.max_connections));
+
         Ok(Self {
             tunnel_id,
             tunnel_info: Arc::new(RwLock::new(tunnel_info)),
-            connections: Arc::new(RwLock::new(Vec::new())),
             metrics: Arc::new(RwLock::new(RegistryTunnelMetrics::default())),
+            target_health: Arc::new(RwLock::new(None)),
+            reconnect_status: Arc::new(RwLock::new(None)),
+            router_error: Arc::new(RwLock::new(None)),
             shutdown_signal: Arc::new(RwLock::new(None)),
+            control,
+            policy: PeerPolicy::for_current_process(),
+            audit_registry: Arc::new(RwLock::new(None)),
         })
     }
 
+    // データプレーンが受け付け判定・接続登録に使う制御状態
+    pub fn connection_control(&self) -> Arc<ConnectionControl> {
+        Arc::clone(&self.control)
+    }
+
+    // RPCの認可。接続元の資格情報はtonicが受け付け時にSO_PEERCREDで取得したものを使う
+    // 接続情報が無い（UDS以外から届いた）呼び出しは相手を特定できないため拒否する
+    async fn authorize<T>(&self, request: &Request<T>, rpc: &str, access: Access) -> Result<(), Status> {
+        let peer = request
+            .extensions()
+            .get::<UdsConnectInfo>()
+            .and_then(|info| info.peer_cred)
+            .map(PeerCred::from);
+        self.check_peer(peer, rpc, access).await
+    }
+
+    async fn check_peer(&self, peer: Option<PeerCred>, rpc: &str, access: Access) -> Result<(), Status> {
+        let Err(reason) = self.policy.check(peer, access) else {
+            return Ok(());
+        };
+
+        let peer = peer.map_or_else(|| "unknown".to_string(), |peer| peer.to_string());
+        warn!("Denied {} on tunnel {} for {}: {}", rpc, self.tunnel_id, peer, reason);
+        // 記録に失敗しても拒否の結果は変えない
+        if let Some(registry) = self.audit_registry.read().await.as_ref() {
+            if let Err(e) = registry.record_access_denied(&self.tunnel_id, rpc, access, &peer, &reason).await {
+                error!("Failed to record denied {} in audit log: {}", rpc, e);
+            }
+        }
+        Err(protocol::error_handling::permission_denied_error(&format!(
+            "{} requires {} access: {}",
+            rpc, access.as_str(), reason
+        )))
+    }
+
     // トンネル情報の更新
     pub async fn update_tunnel_info(&self, info: RegistryTunnelInfo) {
         let mut tunnel_info = self.tunnel_info.write().await;
         *tunnel_info = info;
     }
 
-    // 接続情報の更新
-    pub async fn update_connections(&self, connections: Vec<RegistryConnectionInfo>) {
-        let mut conn_guard = self.connections.write().await;
-        *conn_guard = connections;
-    }
-
     // メトリクスの更新
     pub async fn update_metrics(&self, metrics: RegistryTunnelMetrics) {
         let mut metrics_guard = self.metrics.write().await;
         *metrics_guard = metrics;
     }
+
+    // Routerから通知されたターゲットヘルス情報の更新
+    pub async fn update_target_health(&self, health: RouterTargetHealth) {
+        let mut health_guard = self.target_health.write().await;
+        *health_guard = Some(health);
+    }
+
+    // Routerとの接続が切れて古くなったターゲットヘルス情報を破棄する
+    pub async fn clear_target_health(&self) {
+        *self.target_health.write().await = None;
+    }
+
+    // Routerへの再接続の待機状態の更新（接続できたらNone）
+    pub async fn update_reconnect_status(&self, status: Option<ReconnectStatus>) {
+        *self.reconnect_status.write().await = status;
+    }
+
+    // Routerとの接続エラーの更新（接続できたらNone）
+    pub async fn update_router_error(&self, error: Option<String>) {
+        *self.router_error.write().await = error;
+    }
 }
 
 #[tonic::async_trait]
@@ -140,14 +221,24 @@ impl TunnelControl for TunnelControlService {
     // トンネル状態取得
     async fn get_status(
         &self,
-        _request: Request<StatusRequest>,
+        request: Request<StatusRequest>,
     ) -> Result<Response<StatusResponse>, Status> {
+        self.authorize(&request, "GetStatus", Access::Read).await?;
         debug!("Received GetStatus request for tunnel: {}", self.tunnel_id);
 
-        let tunnel_info = self.tunnel_info.read().await.clone();
-        let connections = self.connections.read().await.clone();
-
-        let response = protocol::response_builders::build_status_response(tunnel_info, connections);
+        let mut tunnel_info = self.tunnel_info.read().await.clone();
+        // update_metricsで更新された最新値を返す
+        tunnel_info.metrics = self.metrics.read().await.clone();
+        let connections = self.control.connections();
+        let target_health = self.target_health.read().await.clone();
+
+        let mut response = protocol::response_builders::build_status_response(tunnel_info, connections, target_health);
+        response.accepting = self.control.is_accepting();
+        response.limits = Some(self.control.limits().into());
+        response.router_reconnect = self.reconnect_status.read().await.clone().map(Into::into);
+        if let Some(reconnect) = response.router_reconnect.as_mut() {
+            reconnect.last_error = self.router_error.read().await.clone().unwrap_or_default();
+        }
 
         Ok(Response::new(response))
     }
@@ -155,11 +246,12 @@ impl TunnelControl for TunnelControlService {
     // アクティブ接続一覧取得
     async fn list_connections(
         &self,
-        _request: Request<ListRequest>,
+        request: Request<ListRequest>,
     ) -> Result<Response<ListResponse>, Status> {
+        self.authorize(&request, "ListConnections", Access::Read).await?;
         debug!("Received ListConnections request for tunnel: {}", self.tunnel_id);
 
-        let connections = self.connections.read().await.clone();
+        let connections = self.control.connections();
         let response = protocol::response_builders::build_list_response(connections);
 
         Ok(Response::new(response))
@@ -170,6 +262,7 @@ impl TunnelControl for TunnelControlService {
         &self,
         request: Request<ShutdownRequest>,
     ) -> Result<Response<ShutdownResponse>, Status> {
+        self.authorize(&request, "Shutdown", Access::Control).await?;
         let req = request.into_inner();
         info!("Received Shutdown request for tunnel: {} (force: {})", self.tunnel_id, req.force);
 
@@ -180,35 +273,37 @@ impl TunnelControl for TunnelControlService {
                 e
             })?;
 
+        // 新規接続を止め、既存接続の終了を待つ（強制停止時は待たずに切断する）
+        let outcome = if req.force {
+            self.control.abort()
+        } else {
+            self.control
+                .drain(std::time::Duration::from_secs(req.timeout_seconds as u64))
+                .await
+        };
+        info!("Tunnel {} drained before shutdown: {} finished, {} closed",
+            self.tunnel_id, outcome.drained, outcome.closed);
+
         // シャットダウンシグナルの送信
-        let mut shutdown_guard = self.shutdown_signal.write().await;
-        if let Some(sender) = shutdown_guard.take() {
-            match sender.send(()) {
-                Ok(_) => {
-                    let response = protocol::response_builders::build_shutdown_response(
-                        true,
-                        "Shutdown initiated successfully".to_string(),
-                    );
-                    info!("Tunnel {} shutdown initiated", self.tunnel_id);
-                    Ok(Response::new(response))
-                }
-                Err(_) => {
-                    let response = protocol::response_builders::build_shutdown_response(
-                        false,
-                        "Failed to send shutdown signal".to_string(),
-                    );
-                    error!("Failed to send shutdown signal for tunnel: {}", self.tunnel_id);
-                    Ok(Response::new(response))
-                }
-            }
+        let sent = self.shutdown_signal.write().await
+            .take()
+            .is_some_and(|sender| sender.send(()).is_ok());
+        let mut response = if sent {
+            info!("Tunnel {} shutdown initiated", self.tunnel_id);
+            protocol::response_builders::build_shutdown_response(
+                true,
+                "Shutdown initiated successfully".to_string(),
+            )
         } else {
-            let response = protocol::response_builders::build_shutdown_response(
+            warn!("Shutdown signal not available for tunnel: {}", self.tunnel_id);
+            protocol::response_builders::build_shutdown_response(
                 false,
                 "Shutdown signal not available".to_string(),
-            );
-            warn!("Shutdown signal not available for tunnel: {}", self.tunnel_id);
-            Ok(Response::new(response))
-        }
+            )
+        };
+        response.drained_connections = outcome.drained as i32;
+        response.closed_connections = outcome.closed as i32;
+        Ok(Response::new(response))
     }
 
     // メトリクスストリーミング
@@ -216,8 +311,9 @@ impl TunnelControl for TunnelControlService {
 
     async fn get_metrics_stream(
         &self,
-        _request: Request<MetricsRequest>,
+        request: Request<MetricsRequest>,
     ) -> Result<Response<ReceiverStream<Result<MetricsResponse, Status>>>, Status> {
+        self.authorize(&request, "GetMetricsStream", Access::Read).await?;
         debug!("Received GetMetricsStream request for tunnel: {}", self.tunnel_id);
 
         let (tx, rx) = tokio::sync::mpsc::channel(100);
@@ -247,6 +343,190 @@ impl TunnelControl for TunnelControlService {
 
         Ok(Response::new(ReceiverStream::new(rx)))
     }
+
+    // 接続イベントストリーミング
+    type WatchConnectionsStream = ReceiverStream<Result<ConnectionEvent, Status>>;
+
+    async fn watch_connections(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<ReceiverStream<Result<ConnectionEvent, Status>>>, Status> {
+        self.authorize(&request, "WatchConnections", Access::Read).await?;
+        let req = request.into_inner();
+        debug!("Received WatchConnections request for tunnel: {}", self.tunnel_id);
+
+        // スナップショットとの間のイベントを取りこぼさないよう先に購読する
+        let mut notices = self.control.subscribe();
+        let existing = if req.include_existing {
+            self.control.connections()
+        } else {
+            Vec::new()
+        };
+
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        let tunnel_id = self.tunnel_id.clone();
+
+        tokio::spawn(async move {
+            for connection in existing {
+                let event = protocol::response_builders::build_connection_event(
+                    &tunnel_id,
+                    ConnectionEventKind::Open,
+                    connection,
+                    None,
+                );
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+
+            loop {
+                tokio::select! {
+                    // イベントがなくてもクライアント切断を検出してタスクを終える
+                    _ = tx.closed() => break,
+                    received = notices.recv() => match received {
+                        Ok(notice) => {
+                            let event = protocol::response_builders::build_connection_event(
+                                &tunnel_id,
+                                notice.kind,
+                                notice.connection,
+                                notice.error,
+                            );
+                            if tx.send(Ok(event)).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Connection event watcher lagged, {} event(s) dropped for tunnel: {}", skipped, tunnel_id);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                }
+            }
+            debug!("Connection event watcher disconnected for tunnel: {}", tunnel_id);
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    // 個別接続の切断
+    async fn close_connection(
+        &self,
+        request: Request<CloseConnectionRequest>,
+    ) -> Result<Response<ControlResponse>, Status> {
+        self.authorize(&request, "CloseConnection", Access::Control).await?;
+        let req = request.into_inner();
+        info!("Received CloseConnection request for tunnel: {} (connection: {})", self.tunnel_id, req.connection_id);
+
+        if req.connection_id.is_empty() {
+            return Err(protocol::error_handling::invalid_argument_error("Connection ID cannot be empty"));
+        }
+        if !self.control.close(&req.connection_id) {
+            return Err(protocol::error_handling::not_found_error(&format!("Connection {}", req.connection_id)));
+        }
+
+        Ok(Response::new(protocol::response_builders::build_control_response(
+            true,
+            format!("Connection {} closed", req.connection_id),
+        )))
+    }
+
+    // 新規接続の受け付け停止
+    async fn pause(
+        &self,
+        request: Request<PauseRequest>,
+    ) -> Result<Response<ControlResponse>, Status> {
+        self.authorize(&request, "Pause", Access::Control).await?;
+        info!("Received Pause request for tunnel: {}", self.tunnel_id);
+
+        let message = if self.control.pause() {
+            "Tunnel paused; existing connections are kept"
+        } else {
+            "Tunnel is already paused"
+        };
+        Ok(Response::new(protocol::response_builders::build_control_response(true, message.to_string())))
+    }
+
+    // 新規接続の受け付け再開
+    async fn resume(
+        &self,
+        request: Request<ResumeRequest>,
+    ) -> Result<Response<ControlResponse>, Status> {
+        self.authorize(&request, "Resume", Access::Control).await?;
+        info!("Received Resume request for tunnel: {}", self.tunnel_id);
+
+        let message = if self.control.resume() {
+            "Tunnel resumed"
+        } else {
+            "Tunnel is not paused"
+        };
+        Ok(Response::new(protocol::response_builders::build_control_response(true, message.to_string())))
+    }
+
+    // 接続制限の変更
+    async fn update_limits(
+        &self,
+        request: Request<UpdateLimitsRequest>,
+    ) -> Result<Response<UpdateLimitsResponse>, Status> {
+        self.authorize(&request, "UpdateLimits", Access::Control).await?;
+        let req = request.into_inner();
+        info!("Received UpdateLimits request for tunnel: {}", self.tunnel_id);
+
+        protocol::validation::validate_update_limits_request(&req)?;
+
+        let limits = self.control.update_limits(LimitsUpdate {
+            max_connections: req.max_connections,
+            rate_limit_bytes_per_sec: req.rate_limit_bytes_per_sec,
+            max_new_connections_per_sec: req.max_new_connections_per_sec,
+        });
+
+        // 既存接続は切断せず、新しい上限は以降の受け付けから適用する
+        Ok(Response::new(UpdateLimitsResponse {
+            success: true,
+            message: "Limits updated".to_string(),
+            limits: Some(limits.into()),
+        }))
+    }
+
+    // ドレイン（待機完了まで応答しない）
+    async fn drain(
+        &self,
+        request: Request<DrainRequest>,
+    ) -> Result<Response<DrainResponse>, Status> {
+        self.authorize(&request, "Drain", Access::Control).await?;
+        let req = request.into_inner();
+        info!("Received Drain request for tunnel: {} (timeout: {}s, shutdown: {})",
+            self.tunnel_id, req.timeout_seconds, req.shutdown);
+
+        protocol::validation::validate_drain_request(&req)?;
+
+        let outcome = self.control
+            .drain(std::time::Duration::from_secs(req.timeout_seconds as u64))
+            .await;
+        info!("Tunnel {} drained: {} finished, {} closed", self.tunnel_id, outcome.drained, outcome.closed);
+
+        let mut message = format!(
+            "{} connection(s) drained, {} closed at deadline",
+            outcome.drained, outcome.closed
+        );
+        if req.shutdown {
+            let sent = self.shutdown_signal.write().await
+                .take()
+                .is_some_and(|sender| sender.send(()).is_ok());
+            if sent {
+                message.push_str("; shutdown initiated");
+            } else {
+                warn!("Shutdown signal not available for tunnel: {}", self.tunnel_id);
+                message.push_str("; shutdown signal not available");
+            }
+        }
+
+        Ok(Response::new(DrainResponse {
+            success: true,
+            message,
+            drained_connections: outcome.drained as i32,
+            closed_connections: outcome.closed as i32,
+        }))
+    }
 }
 
 // シャットダウンシグナル設定用のヘルパー
@@ -256,6 +536,11 @@ impl TunnelControlService {
         *shutdown_guard = Some(sender);
     }
 
+    // 拒否したRPCをRegistryのaudit_logへ記録する
+    pub async fn set_audit_registry(&self, registry: Arc<SqliteRegistry>) {
+        *self.audit_registry.write().await = Some(registry);
+    }
+
     // ping機能（ヘルスチェック用）
     pub async fn ping(&self) -> Result<(), Status> {
         debug!("Ping request for tunnel: {}", self.tunnel_id);
@@ -271,9 +556,11 @@ pub struct TunnelProcessServer {
 }
 
 impl TunnelProcessServer {
-    pub async fn new(socket_path: &Path, tunnel_id: String) -> Result<Self> {
+    // 拒否したRPCはaudit_registryのaudit_logへ記録する
+    pub async fn new(socket_path: &Path, tunnel_id: String, audit_registry: Arc<SqliteRegistry>) -> Result<Self> {
         let server = UdsGrpcServer::new(socket_path, tunnel_id.clone())?;
         let service = Arc::clone(&server.tunnel_service);
+        service.set_audit_registry(audit_registry).await;
         
         // シャットダウンシグナルの設定
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
@@ -313,8 +600,35 @@ impl TunnelProcessServer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::registry::models::ConnectionInfo;
     use tempfile::tempdir;
 
+    fn connection(id: &str) -> ConnectionInfo {
+        ConnectionInfo::open(
+            id.to_string(),
+            "test-tunnel".to_string(),
+            "127.0.0.1:50000".to_string(),
+            "10.2.0.2:8080".to_string(),
+            3600,
+        )
+    }
+
+    // 接続元の資格情報が要るため、RPCは実際のUDS越しに呼ぶ
+    async fn serve(dir: &Path) -> (Arc<TunnelControlService>, protocol::TunnelControlClient<tonic::transport::Channel>) {
+        let socket_path = dir.join("test.sock");
+        let server = UdsGrpcServer::new(&socket_path, "test-tunnel".to_string()).unwrap();
+        let service = Arc::clone(&server.tunnel_service);
+        tokio::spawn(async move { server.serve().await });
+        for _ in 0..100 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let channel = protocol::create_uds_channel(&socket_path).await.unwrap();
+        (service, protocol::TunnelControlClient::new(channel))
+    }
+
     #[tokio::test]
     async fn test_tunnel_control_service_creation() {
         let service = TunnelControlService::new("test-tunnel".to_string()).unwrap();
@@ -323,6 +637,23 @@ mod tests {
         assert!(service.ping().await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_denied_rpc_is_audited() {
+        let temp_dir = tempdir().unwrap();
+        let registry = Arc::new(SqliteRegistry::new(Some(temp_dir.path().join("test.db"))).await.unwrap());
+        let mut service = TunnelControlService::new("test-tunnel".to_string()).unwrap();
+        service.policy = PeerPolicy { owner_uid: 1000, read_gid: Some(990) };
+        service.set_audit_registry(Arc::clone(&registry)).await;
+
+        let member = Some(PeerCred { uid: 1001, gid: 990, pid: Some(4242) });
+        assert!(service.check_peer(member, "GetStatus", Access::Read).await.is_ok());
+
+        let status = service.check_peer(member, "Shutdown", Access::Control).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let status = service.check_peer(None, "GetStatus", Access::Read).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
     #[tokio::test] 
     async fn test_uds_grpc_server_creation() {
         let temp_dir = tempdir().unwrap();
@@ -354,6 +685,7 @@ mod tests {
             uptime_seconds: u64::try_from(new_metrics.uptime_seconds.max(0)).unwrap_or(0),
             avg_latency_ms: new_metrics.avg_latency_ms,
             error_rate: 0.0, // gRPCプロトコルにはerror_rateがないのでデフォルト値
+            ..Default::default()
         };
         service.update_metrics(registry_metrics).await;
         
@@ -361,4 +693,122 @@ mod tests {
         assert_eq!(stored_metrics.active_connections, 5);
         assert_eq!(stored_metrics.total_connections, 100);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_calls_without_peer_credentials_are_denied() {
+        let service = TunnelControlService::new("test-tunnel".to_string()).unwrap();
+        let status = service.get_status(Request::new(StatusRequest {})).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let status = service.shutdown(Request::new(ShutdownRequest { force: true, timeout_seconds: 0 })).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_control_rpcs() {
+        let temp_dir = tempdir().unwrap();
+        let (service, mut client) = serve(temp_dir.path()).await;
+        let control = service.connection_control();
+        let closed = control.admit().unwrap().register(connection("conn-1"));
+
+        client.pause(Request::new(PauseRequest {})).await.unwrap();
+        let status = client.get_status(Request::new(StatusRequest {})).await.unwrap().into_inner();
+        assert!(!status.accepting);
+        client.resume(Request::new(ResumeRequest {})).await.unwrap();
+        assert!(control.is_accepting());
+
+        let response = client.update_limits(Request::new(UpdateLimitsRequest {
+            max_connections: Some(5),
+            ..Default::default()
+        })).await.unwrap().into_inner();
+        assert_eq!(response.limits.unwrap().max_connections, 5);
+
+        client.close_connection(Request::new(CloseConnectionRequest {
+            connection_id: "conn-1".to_string(),
+        })).await.unwrap();
+        assert!(closed.await.is_ok());
+
+        let missing = client.close_connection(Request::new(CloseConnectionRequest {
+            connection_id: "conn-1".to_string(),
+        })).await;
+        assert_eq!(missing.unwrap_err().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_router_error() {
+        let temp_dir = tempdir().unwrap();
+        let (service, mut client) = serve(temp_dir.path()).await;
+
+        service.update_router_error(Some("Connection refused".to_string())).await;
+        service.update_reconnect_status(Some(ReconnectStatus {
+            failures: 2,
+            next_attempt_at: chrono::Utc::now() + chrono::Duration::seconds(5),
+            circuit: crate::common::retry::CircuitState::Closed,
+        })).await;
+        let status = client.get_status(Request::new(StatusRequest {})).await.unwrap().into_inner();
+        let reconnect = status.router_reconnect.unwrap();
+        assert_eq!(reconnect.consecutive_failures, 2);
+        assert_eq!(reconnect.last_error, "Connection refused");
+
+        // 接続できたら再接続待ちもエラーも返さない
+        service.update_reconnect_status(None).await;
+        service.update_router_error(None).await;
+        let status = client.get_status(Request::new(StatusRequest {})).await.unwrap().into_inner();
+        assert!(status.router_reconnect.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_connections() {
+        let temp_dir = tempdir().unwrap();
+        let (service, mut client) = serve(temp_dir.path()).await;
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+        service.set_shutdown_signal(shutdown_tx).await;
+
+        let control = service.connection_control();
+        let _finishing = control.admit().unwrap().register(connection("conn-1"));
+        let stuck = control.admit().unwrap().register(connection("conn-2"));
+        let finisher = Arc::clone(&control);
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            finisher.unregister("conn-1");
+        });
+
+        let response = client.shutdown(Request::new(ShutdownRequest {
+            force: false,
+            timeout_seconds: 1,
+        })).await.unwrap().into_inner();
+        assert!(response.success);
+        assert_eq!((response.drained_connections, response.closed_connections), (1, 1));
+        assert!(stuck.await.is_ok());
+        assert!(shutdown_rx.await.is_ok());
+        assert!(!control.is_accepting());
+    }
+
+    #[tokio::test]
+    async fn test_watch_connections_emits_open_and_close() {
+        use tokio_stream::StreamExt;
+
+        let temp_dir = tempdir().unwrap();
+        let (service, mut client) = serve(temp_dir.path()).await;
+        let control = service.connection_control();
+        let _closed = control.admit().unwrap().register(connection("conn-1"));
+        control.record_traffic("conn-1", 10, 20);
+
+        let mut stream = client
+            .watch_connections(Request::new(WatchRequest { include_existing: true }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let existing = stream.next().await.unwrap().unwrap();
+        assert_eq!(existing.kind, "open");
+        assert_eq!(existing.connection.unwrap().id, "conn-1");
+
+        let listed = client.list_connections(Request::new(ListRequest {})).await.unwrap().into_inner();
+        assert_eq!(listed.connections.len(), 1);
+
+        control.unregister("conn-1");
+        let closed = stream.next().await.unwrap().unwrap();
+        assert_eq!(closed.kind, "close");
+        assert_eq!(closed.connection.unwrap().bytes_received, 20);
+    }
+}
@@ -10,6 +10,9 @@ pub mod protocol;
 pub mod security;
 pub mod registry;
 pub mod ipc;
+pub mod notifier;
+pub mod api;
+pub mod metrics;
 
 pub use common::{
     config::Config,
@@ -3,16 +3,36 @@
 // ConduitのCLIアプリケーションのメインエントリポイント
 
 use clap::Parser;
+use std::path::PathBuf;
 use std::process;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+use tracing_appender::non_blocking::WorkerGuard;
 use conduit::cli::{CliArgs, Commands};
+use conduit::common::logging::{self, LoggingConfig};
 
 #[tokio::main]
 async fn main() {
-    init_logging();
-    
     let args = CliArgs::parse();
     
+    // Registry・ソケットの場所はスコープで決まるため、他の処理より先に確定させる
+    if args.system {
+        conduit::common::paths::set_scope(conduit::common::paths::Scope::System);
+    }
+    
+    // ファイル出力時のguardはmainの終了まで保持し、未書き込みのログを失わないようにする
+    let _log_guard = match init_logging(&args) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    
+    // 旧バージョンの~/.conduit等に残っているRegistryとログを現在の置き場へ移す
+    if let Err(e) = conduit::common::paths::migrate_legacy_layout() {
+        warn!("Failed to migrate legacy Conduit directories: {}", e);
+    }
+    
     let result = match args.command {
         Commands::Init(cmd) => conduit::cli::commands::init::execute(cmd).await,
         Commands::Start(cmd) => conduit::cli::commands::start::execute(cmd).await,
@@ -20,10 +40,17 @@ async fn main() {
         Commands::Down(cmd) => conduit::cli::commands::down::execute(cmd).await,
         Commands::Router(cmd) => conduit::cli::commands::router::execute(cmd).await,
         Commands::List(cmd) => conduit::cli::commands::list::execute(cmd).await,
+        Commands::Events(cmd) => conduit::cli::commands::events::execute(cmd).await,
         Commands::Kill(cmd) => conduit::cli::commands::kill::execute(cmd).await,
         Commands::Status(cmd) => conduit::cli::commands::status::execute(cmd).await,
+        Commands::Logs(cmd) => conduit::cli::commands::logs::execute(cmd).await,
         Commands::Config(cmd) => conduit::cli::commands::config::execute(cmd).await,
+        Commands::Metrics(cmd) => conduit::cli::commands::metrics::execute(cmd).await,
+        Commands::Top(cmd) => conduit::cli::commands::top::execute(cmd).await,
+        Commands::Audit(cmd) => conduit::cli::commands::audit::execute(cmd).await,
+        Commands::Registry(cmd) => conduit::cli::commands::registry::execute(cmd).await,
         Commands::Version => conduit::cli::commands::version::execute().await,
+        Commands::InternalTunnelProcess(cmd) => conduit::cli::commands::tunnel_process::execute(cmd).await,
     };
 
     if let Err(e) = result {
@@ -33,13 +60,33 @@ async fn main() {
     }
 }
 
-fn init_logging() {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("conduit=info".parse().unwrap())
-        )
-        .init();
+// CLI引数 > 環境変数 > 設定ファイル > デフォルト値
+fn init_logging(args: &CliArgs) -> conduit::Result<Option<WorkerGuard>> {
+    let config_path = match args.command {
+        Commands::Up(ref cmd) => cmd.file.clone(),
+        Commands::Down(ref cmd) => cmd.file.clone(),
+        _ => PathBuf::from("conduit.toml"),
+    };
+    let mut config = LoggingConfig::from_config_file(&config_path);
+    config.apply_env();
     
+    if let Some(format) = args.log_format {
+        config.format = format;
+    }
+    if let Some(ref file) = args.log_file {
+        config.file = Some(file.clone());
+    }
+    if let Some(rotation) = args.log_rotation {
+        config.rotation = rotation;
+    }
+    
+    // Tunnel Processの出力はProcessManagerがトンネル別のログファイルへ向けているため、
+    // 親と同じファイルへ書き込んでローテーションが競合しないようにする
+    if std::env::var_os("CONDUIT_TUNNEL_ID").is_some() {
+        config.file = None;
+    }
+    
+    let guard = logging::init(&config)?;
     info!("Conduit starting up");
-}
\ No newline at end of file
+    Ok(guard)
+}
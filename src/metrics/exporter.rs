@@ -0,0 +1,367 @@
+// Prometheusテキスト形式へのエンコードとスクレイプ用HTTPエンドポイント
+//
+// 値はスクレイプごとにスナップショットから組み立てるため、カウンタもその時点の累計値をそのまま出す。
+// HTTPはGETに応答するだけなので、api機能（axum）に依存せず最小限の実装で済ませる
+
+use super::{MetricsConfig, MetricsSnapshot, LATENCY_BUCKETS_MS};
+use crate::common::error::{Error, Result};
+use async_trait::async_trait;
+use prometheus::proto::{Bucket, Counter, Gauge, Histogram, LabelPair, Metric, MetricFamily, MetricType};
+use prometheus::{Encoder, TextEncoder};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+const MAX_REQUEST_HEAD: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// スクレイプのたびに呼ばれるスナップショットの取得元
+#[async_trait]
+pub trait MetricsSource: Send + Sync {
+    async fn snapshot(&self) -> MetricsSnapshot;
+}
+
+pub fn encode(snapshot: &MetricsSnapshot) -> Result<String> {
+    let mut families = Vec::new();
+
+    let tunnel_gauge = |name: &str, help: &str, value: &dyn Fn(&super::TunnelSample) -> f64| {
+        family(
+            name,
+            help,
+            MetricType::GAUGE,
+            snapshot
+                .tunnels
+                .iter()
+                .map(|t| with_tunnel_label(gauge(value(t)), &t.tunnel))
+                .collect(),
+        )
+    };
+    families.push(tunnel_gauge("conduit_tunnel_up", "Whether the tunnel process responded to the scrape", &|t| {
+        if t.up { 1.0 } else { 0.0 }
+    }));
+    families.push(tunnel_gauge(
+        "conduit_tunnel_connections_active",
+        "Currently open connections through the tunnel",
+        &|t| t.active_connections as f64,
+    ));
+
+    let tunnel_counter = |name: &str, help: &str, value: &dyn Fn(&super::TunnelSample) -> u64| {
+        family(
+            name,
+            help,
+            MetricType::COUNTER,
+            snapshot
+                .tunnels
+                .iter()
+                .map(|t| with_tunnel_label(counter(value(t) as f64), &t.tunnel))
+                .collect(),
+        )
+    };
+    families.push(tunnel_counter(
+        "conduit_tunnel_connections_total",
+        "Connections accepted by the tunnel",
+        &|t| t.total_connections,
+    ));
+    families.push(tunnel_counter(
+        "conduit_tunnel_bytes_sent_total",
+        "Bytes sent through the tunnel",
+        &|t| t.bytes_sent,
+    ));
+    families.push(tunnel_counter(
+        "conduit_tunnel_bytes_received_total",
+        "Bytes received through the tunnel",
+        &|t| t.bytes_received,
+    ));
+    families.push(tunnel_counter(
+        "conduit_tunnel_reconnects_total",
+        "Reconnect attempts to the router",
+        &|t| t.reconnects,
+    ));
+    families.push(tunnel_counter(
+        "conduit_tunnel_auth_failures_total",
+        "Failed authentications against the router",
+        &|t| t.auth_failures,
+    ));
+    families.push(tunnel_counter(
+        "conduit_tunnel_compression_input_bytes_total",
+        "Payload bytes handed to the compressor before compression",
+        &|t| t.compression_bytes_in,
+    ));
+    families.push(tunnel_counter(
+        "conduit_tunnel_compression_output_bytes_total",
+        "Payload bytes sent after compression",
+        &|t| t.compression_bytes_out,
+    ));
+    families.push(family(
+        "conduit_tunnel_connection_latency_seconds",
+        "Time to establish a connection through the tunnel",
+        MetricType::HISTOGRAM,
+        snapshot
+            .tunnels
+            .iter()
+            .map(|t| with_tunnel_label(histogram(&t.latency), &t.tunnel))
+            .collect(),
+    ));
+
+    if let Some(ref router) = snapshot.router {
+        families.push(family(
+            "conduit_router_connected_clients",
+            "Clients currently connected to the router",
+            MetricType::GAUGE,
+            vec![gauge(router.connected_clients as f64)],
+        ));
+        families.push(family(
+            "conduit_router_auth_failures_total",
+            "Client handshakes rejected by the router",
+            MetricType::COUNTER,
+            vec![counter(router.auth_failures as f64)],
+        ));
+    }
+
+    // サンプルのないファミリはエンコーダーがエラーにするため除外する
+    families.retain(|f| !f.get_metric().is_empty());
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&families, &mut buffer)
+        .map_err(|e| Error::generic(format!("Failed to encode metrics: {}", e)))?;
+    String::from_utf8(buffer).map_err(|e| Error::generic(format!("Failed to encode metrics: {}", e)))
+}
+
+fn family(name: &str, help: &str, metric_type: MetricType, metrics: Vec<Metric>) -> MetricFamily {
+    let mut family = MetricFamily::default();
+    family.set_name(name.to_string());
+    family.set_help(help.to_string());
+    family.set_field_type(metric_type);
+    family.set_metric(metrics.into());
+    family
+}
+
+fn with_tunnel_label(mut metric: Metric, tunnel: &str) -> Metric {
+    let mut label = LabelPair::default();
+    label.set_name("tunnel".to_string());
+    label.set_value(tunnel.to_string());
+    metric.set_label(vec![label].into());
+    metric
+}
+
+fn gauge(value: f64) -> Metric {
+    let mut gauge = Gauge::default();
+    gauge.set_value(value);
+    let mut metric = Metric::default();
+    metric.set_gauge(gauge);
+    metric
+}
+
+fn counter(value: f64) -> Metric {
+    let mut counter = Counter::default();
+    counter.set_value(value);
+    let mut metric = Metric::default();
+    metric.set_counter(counter);
+    metric
+}
+
+fn histogram(latency: &super::LatencyHistogram) -> Metric {
+    let buckets: Vec<Bucket> = LATENCY_BUCKETS_MS
+        .iter()
+        .zip(latency.cumulative())
+        .map(|(bound_ms, cumulative)| {
+            let mut bucket = Bucket::default();
+            bucket.set_upper_bound(bound_ms / 1000.0);
+            bucket.set_cumulative_count(cumulative);
+            bucket
+        })
+        .collect();
+
+    let mut histogram = Histogram::default();
+    histogram.set_sample_count(latency.count);
+    histogram.set_sample_sum(latency.sum_ms / 1000.0);
+    histogram.set_bucket(buckets.into());
+    let mut metric = Metric::default();
+    metric.set_histogram(histogram);
+    metric
+}
+
+// メトリクスエンドポイントの待ち受け（shutdownが通知されるまで）
+pub async fn serve(config: &MetricsConfig, source: Arc<dyn MetricsSource>, shutdown: Arc<Notify>) -> Result<()> {
+    config.validate()?;
+    let listener = TcpListener::bind(config.bind)
+        .await
+        .map_err(|e| Error::network(format!("Failed to bind metrics endpoint on {}: {}", config.bind, e)))?;
+    info!("Metrics endpoint listening on http://{}{}", config.bind, config.path);
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = shutdown.notified() => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Metrics endpoint accept error: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let source = Arc::clone(&source);
+        let path = config.path.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_scrape(stream, &path, source).await {
+                debug!("Metrics scrape from {} failed: {}", peer, e);
+            }
+        });
+    }
+
+    info!("Metrics endpoint stopped");
+    Ok(())
+}
+
+async fn handle_scrape(mut stream: TcpStream, path: &str, source: Arc<dyn MetricsSource>) -> std::io::Result<()> {
+    let request_line = match tokio::time::timeout(REQUEST_TIMEOUT, read_request_line(&mut stream)).await {
+        Ok(result) => result?,
+        Err(_) => return Ok(()),
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    // クエリ文字列は無視する
+    let target = parts.next().unwrap_or_default().split('?').next().unwrap_or_default();
+
+    let (status, content_type, body) = if target != path {
+        ("404 Not Found", "text/plain", "Not Found\n".to_string())
+    } else if method != "GET" && method != "HEAD" {
+        ("405 Method Not Allowed", "text/plain", "Method Not Allowed\n".to_string())
+    } else {
+        match encode(&source.snapshot().await) {
+            Ok(body) => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", body),
+            Err(e) => ("500 Internal Server Error", "text/plain", format!("{}\n", e)),
+        }
+    };
+
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes()).await?;
+    }
+    stream.shutdown().await
+}
+
+// ヘッダー終端まで読み、リクエスト行だけを返す
+async fn read_request_line(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 || buffer.len() + n > MAX_REQUEST_HEAD {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&buffer);
+    Ok(head.lines().next().unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{RouterSample, TunnelSample};
+
+    struct FixedSource(MetricsSnapshot);
+
+    #[async_trait]
+    impl MetricsSource for FixedSource {
+        async fn snapshot(&self) -> MetricsSnapshot {
+            self.0.clone()
+        }
+    }
+
+    fn sample_snapshot() -> MetricsSnapshot {
+        let mut web = TunnelSample {
+            tunnel: "web".to_string(),
+            up: true,
+            active_connections: 2,
+            total_connections: 10,
+            bytes_sent: 4096,
+            reconnects: 1,
+            compression_bytes_in: 8192,
+            compression_bytes_out: 2048,
+            ..Default::default()
+        };
+        web.latency.observe(3.0);
+        web.latency.observe(40.0);
+
+        MetricsSnapshot {
+            tunnels: vec![web],
+            router: Some(RouterSample {
+                connected_clients: 1,
+                auth_failures: 3,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let text = encode(&sample_snapshot()).unwrap();
+
+        assert!(text.contains("conduit_tunnel_up{tunnel=\"web\"} 1"));
+        assert!(text.contains("conduit_tunnel_connections_active{tunnel=\"web\"} 2"));
+        assert!(text.contains("conduit_tunnel_bytes_sent_total{tunnel=\"web\"} 4096"));
+        assert!(text.contains("conduit_tunnel_reconnects_total{tunnel=\"web\"} 1"));
+        assert!(text.contains("conduit_tunnel_compression_output_bytes_total{tunnel=\"web\"} 2048"));
+        assert!(text.contains("conduit_tunnel_connection_latency_seconds_bucket{tunnel=\"web\",le=\"0.005\"} 1"));
+        assert!(text.contains("conduit_tunnel_connection_latency_seconds_count{tunnel=\"web\"} 2"));
+        assert!(text.contains("conduit_router_auth_failures_total 3"));
+    }
+
+    #[test]
+    fn test_encode_empty() {
+        // トンネルがなくてもエラーにしない
+        assert!(encode(&MetricsSnapshot::default()).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_serve_scrape() {
+        let addr = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let config = MetricsConfig {
+            enabled: true,
+            bind: addr,
+            ..Default::default()
+        };
+        let shutdown = Arc::new(Notify::new());
+        let server_shutdown = Arc::clone(&shutdown);
+        let source: Arc<dyn MetricsSource> = Arc::new(FixedSource(sample_snapshot()));
+        tokio::spawn(async move { serve(&config, source, server_shutdown).await.unwrap() });
+
+        let mut response = String::new();
+        for _ in 0..50 {
+            if let Ok(mut stream) = TcpStream::connect(addr).await {
+                stream
+                    .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                    .await
+                    .unwrap();
+                stream.read_to_string(&mut response).await.unwrap();
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("conduit_tunnel_connections_total{tunnel=\"web\"} 10"));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /other HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        shutdown.notify_waiters();
+    }
+}
@@ -0,0 +1,170 @@
+// Prometheus形式のメトリクス
+//
+// Router・Tunnel Processの状態をスナップショットとして集め、スクレイプ時にテキスト形式へ変換する。
+// 値の保持は各コンポーネント側（RouterState、Tunnel ProcessのTunnelMetrics）が行い、
+// このモジュールはエクスポートのみを担当する
+
+#[cfg(feature = "metrics")]
+mod exporter;
+
+#[cfg(feature = "metrics")]
+pub use exporter::{encode, serve, MetricsSource};
+
+use crate::common::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+// レイテンシヒストグラムのバケット上限（ms）
+pub const LATENCY_BUCKETS_MS: [f64; 10] = [1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 5000.0];
+
+// メトリクスエンドポイントの設定（デフォルトは無効）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_bind")]
+    pub bind: SocketAddr,
+    #[serde(default = "default_path")]
+    pub path: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_bind(),
+            path: default_path(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.path.starts_with('/') {
+            return Err(Error::config(format!("Metrics path must start with '/': {}", self.path)));
+        }
+        Ok(())
+    }
+}
+
+fn default_bind() -> SocketAddr {
+    "127.0.0.1:9090".parse().unwrap()
+}
+
+fn default_path() -> String {
+    "/metrics".to_string()
+}
+
+// 接続確立までのレイテンシ分布
+//
+// bucketsは各上限以下かつ一つ前の上限超の件数（累積ではない）。上限超過分はcountとの差で表す
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub sum_ms: f64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS_MS.len()],
+            count: 0,
+            sum_ms: 0.0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, latency_ms: f64) {
+        if let Some(index) = LATENCY_BUCKETS_MS.iter().position(|bound| latency_ms <= *bound) {
+            if let Some(bucket) = self.buckets.get_mut(index) {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum_ms += latency_ms;
+    }
+
+    // Prometheusのhistogramは累積件数で表現する
+    pub fn cumulative(&self) -> Vec<u64> {
+        self.buckets
+            .iter()
+            .scan(0u64, |acc, count| {
+                *acc += count;
+                Some(*acc)
+            })
+            .collect()
+    }
+
+    pub fn average_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum_ms / self.count as f64
+        }
+    }
+}
+
+// トンネル単位のメトリクス（tunnelラベルの値ごとに1件）
+#[derive(Debug, Clone, Default)]
+pub struct TunnelSample {
+    pub tunnel: String,
+    pub up: bool,
+    pub active_connections: u64,
+    pub total_connections: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub reconnects: u64,
+    pub auth_failures: u64,
+    // 圧縮前後の送信バイト数（差が圧縮による削減量）
+    pub compression_bytes_in: u64,
+    pub compression_bytes_out: u64,
+    pub latency: LatencyHistogram,
+}
+
+// スクレイプ1回分のスナップショット
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    pub tunnels: Vec<TunnelSample>,
+    // Router固有の値（Client・集約エクスポーターではNone）
+    pub router: Option<RouterSample>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RouterSample {
+    pub connected_clients: u64,
+    // トンネル確立前に弾いた認証なのでtunnelラベルを付けられない
+    pub auth_failures: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.observe(0.5);
+        histogram.observe(7.0);
+        histogram.observe(7.5);
+        histogram.observe(10_000.0);
+
+        assert_eq!(histogram.count, 4);
+        assert_eq!(histogram.buckets[0], 1);
+        assert_eq!(histogram.buckets[2], 2);
+        // 上限超過分は最後の累積値に含まれない
+        assert_eq!(*histogram.cumulative().last().unwrap(), 3);
+        assert!((histogram.average_ms() - 2503.75).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_metrics_config_validation() {
+        let mut config = MetricsConfig::default();
+        assert!(!config.enabled);
+        assert!(config.validate().is_ok());
+
+        config.path = "metrics".to_string();
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,205 @@
+// 状態遷移の外部通知（アラート）
+//
+// Router切断・トンネル異常終了などを、設定されたWebhookへJSONでPOSTする。
+// 通知の失敗がトンネル本体の処理を止めないよう、送信はバックグラウンドタスクで行う
+
+pub mod webhook;
+
+pub use webhook::WebhookConfig;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+// アラート種別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    // Routerとの接続が切れた
+    RouterDisconnected,
+    // Router接続・認証でエラーが発生した
+    ConnectionError,
+    // 再接続が連続して失敗している
+    ReconnectFailed,
+    // Tunnel Processが予期せず終了した
+    TunnelExited,
+    // Tunnel Processが生存確認に応答しなくなった
+    TunnelUnhealthy,
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::RouterDisconnected => "router_disconnected",
+            AlertKind::ConnectionError => "connection_error",
+            AlertKind::ReconnectFailed => "reconnect_failed",
+            AlertKind::TunnelExited => "tunnel_exited",
+            AlertKind::TunnelUnhealthy => "tunnel_unhealthy",
+        }
+    }
+
+    pub fn severity(&self) -> &'static str {
+        match self {
+            AlertKind::RouterDisconnected | AlertKind::ConnectionError => "warning",
+            AlertKind::ReconnectFailed | AlertKind::TunnelExited | AlertKind::TunnelUnhealthy => "critical",
+        }
+    }
+}
+
+// 通知内容
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub severity: &'static str,
+    // 対象（トンネルIDやRouterアドレス）
+    pub subject: String,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Alert {
+    pub fn new(kind: AlertKind, subject: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            severity: kind.severity(),
+            subject: subject.into(),
+            message: message.into(),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+// 通知の送信窓口
+//
+// 各Webhookは独立したワーカーを持ち、重複抑止とレート制限も個別に判定する
+#[derive(Clone, Default)]
+pub struct Notifier {
+    workers: Vec<mpsc::UnboundedSender<Alert>>,
+}
+
+impl Notifier {
+    // 設定からNotifierを作成（tokioランタイム内で呼ぶこと）
+    pub fn from_config(webhooks: &[WebhookConfig]) -> Self {
+        let workers = webhooks
+            .iter()
+            .map(|config| {
+                let (tx, rx) = mpsc::unbounded_channel();
+                tokio::spawn(webhook::run_worker(config.clone(), rx));
+                tx
+            })
+            .collect();
+
+        Self { workers }
+    }
+
+    // 通知先が1つもない
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    // アラートを送信キューに積む（呼び出し側はブロックしない）
+    pub fn notify(&self, alert: Alert) {
+        debug!("Queueing alert: {} ({})", alert.kind.as_str(), alert.subject);
+        for worker in &self.workers {
+            if worker.send(alert.clone()).is_err() {
+                warn!("Webhook worker is not running, alert dropped: {}", alert.kind.as_str());
+            }
+        }
+    }
+}
+
+// 同一アラートの連投防止とWebhook単位の送信レート制限
+pub(crate) struct AlertThrottle {
+    dedup_window: Duration,
+    rate_limit_per_minute: u32,
+    last_sent: HashMap<(AlertKind, String), Instant>,
+    recent: VecDeque<Instant>,
+}
+
+impl AlertThrottle {
+    pub(crate) fn new(dedup_window: Duration, rate_limit_per_minute: u32) -> Self {
+        Self {
+            dedup_window,
+            rate_limit_per_minute,
+            last_sent: HashMap::new(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    // 送信してよければtrueを返す。レート制限は送信の試行回数で数える
+    // 重複抑止は配送に成功したものだけを対象にするため、record_deliveredで別に記録する
+    pub(crate) fn allow(&mut self, alert: &Alert, now: Instant) -> bool {
+        let key = (alert.kind, alert.subject.clone());
+        if let Some(last) = self.last_sent.get(&key) {
+            if now.duration_since(*last) < self.dedup_window {
+                debug!("Suppressing duplicate alert: {} ({})", alert.kind.as_str(), alert.subject);
+                return false;
+            }
+        }
+
+        while let Some(front) = self.recent.front() {
+            if now.duration_since(*front) >= Duration::from_secs(60) {
+                self.recent.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.rate_limit_per_minute > 0 && self.recent.len() >= self.rate_limit_per_minute as usize {
+            warn!("Webhook rate limit reached, alert dropped: {} ({})", alert.kind.as_str(), alert.subject);
+            return false;
+        }
+
+        self.recent.push_back(now);
+        true
+    }
+
+    // 配送に失敗したアラートは次回そのまま再送できるよう記録しない
+    pub(crate) fn record_delivered(&mut self, alert: &Alert, now: Instant) {
+        self.last_sent.insert((alert.kind, alert.subject.clone()), now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_dedup() {
+        let mut throttle = AlertThrottle::new(Duration::from_secs(300), 0);
+        let now = Instant::now();
+        let alert = Alert::new(AlertKind::TunnelExited, "tunnel-a", "exited");
+
+        assert!(throttle.allow(&alert, now));
+        // 配送に失敗した（記録されていない）アラートは抑止しない
+        assert!(throttle.allow(&alert, now + Duration::from_secs(5)));
+        throttle.record_delivered(&alert, now + Duration::from_secs(5));
+        assert!(!throttle.allow(&alert, now + Duration::from_secs(10)));
+        // 別の対象は重複扱いしない
+        let other = Alert::new(AlertKind::TunnelExited, "tunnel-b", "exited");
+        assert!(throttle.allow(&other, now + Duration::from_secs(10)));
+        // 抑止期間を過ぎれば再送する
+        assert!(throttle.allow(&alert, now + Duration::from_secs(306)));
+    }
+
+    #[test]
+    fn test_throttle_rate_limit() {
+        let mut throttle = AlertThrottle::new(Duration::ZERO, 2);
+        let now = Instant::now();
+
+        assert!(throttle.allow(&Alert::new(AlertKind::ConnectionError, "a", "x"), now));
+        assert!(throttle.allow(&Alert::new(AlertKind::ConnectionError, "b", "x"), now));
+        assert!(!throttle.allow(&Alert::new(AlertKind::ConnectionError, "c", "x"), now));
+        assert!(throttle.allow(&Alert::new(AlertKind::ConnectionError, "c", "x"), now + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_empty_notifier() {
+        let notifier = Notifier::default();
+        assert!(notifier.is_empty());
+        // 通知先がなくてもパニックしない
+        notifier.notify(Alert::new(AlertKind::RouterDisconnected, "10.2.0.1:9999", "closed"));
+    }
+}
@@ -0,0 +1,315 @@
+// Webhook通知
+//
+// conduit.tomlの[[webhooks]]ごとにワーカーを起動し、テンプレートから組み立てたJSONをPOSTする
+
+use super::{Alert, AlertKind, AlertThrottle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+// Webhook設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default = "default_name")]
+    pub name: String,
+    pub url: String,
+    // 通知対象のイベント（空なら全て）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<AlertKind>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    // {{kind}} {{severity}} {{subject}} {{message}} {{timestamp}} を置換したJSON本文
+    // 未指定ならAlertをそのままJSON化して送る
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_template: Option<String>,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    // 同じ対象・同じ種別のアラートを再送しない期間
+    #[serde(default = "default_dedup_window_seconds")]
+    pub dedup_window_seconds: u64,
+    // 0で無制限
+    #[serde(default = "default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: u32,
+}
+
+fn default_name() -> String {
+    "webhook".to_string()
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    500
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_dedup_window_seconds() -> u64 {
+    300
+}
+
+fn default_rate_limit_per_minute() -> u32 {
+    10
+}
+
+impl WebhookConfig {
+    // 設定値の検証（URL形式とテンプレートのJSON妥当性）
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(format!("Webhook '{}' URL must start with http:// or https://", self.name));
+        }
+
+        let sample = Alert::new(AlertKind::TunnelExited, "sample", "sample \"message\"");
+        render_body(self.body_template.as_deref(), &sample)
+            .map_err(|e| format!("Webhook '{}' body_template is invalid: {}", self.name, e))?;
+
+        Ok(())
+    }
+
+    fn accepts(&self, kind: AlertKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+// テンプレートからPOST本文を生成
+//
+// 置換値はJSON文字列としてエスケープするため、テンプレート側では "..." の内側に書く
+pub fn render_body(template: Option<&str>, alert: &Alert) -> Result<serde_json::Value, String> {
+    let Some(template) = template else {
+        return serde_json::to_value(alert).map_err(|e| e.to_string());
+    };
+
+    let timestamp = alert.timestamp.to_rfc3339();
+    let values = [
+        ("kind", alert.kind.as_str()),
+        ("severity", alert.severity),
+        ("subject", alert.subject.as_str()),
+        ("message", alert.message.as_str()),
+        ("timestamp", timestamp.as_str()),
+    ];
+
+    let mut body = template.to_string();
+    for (key, value) in values {
+        body = body.replace(&format!("{{{{{}}}}}", key), &escape_json(value));
+    }
+
+    serde_json::from_str(&body).map_err(|e| e.to_string())
+}
+
+fn escape_json(value: &str) -> String {
+    let quoted = serde_json::Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+// Webhookごとの送信ワーカー
+pub(crate) async fn run_worker(config: WebhookConfig, mut rx: mpsc::UnboundedReceiver<Alert>) {
+    let mut throttle = AlertThrottle::new(
+        Duration::from_secs(config.dedup_window_seconds),
+        config.rate_limit_per_minute,
+    );
+
+    #[cfg(feature = "webhooks")]
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to create HTTP client for webhook '{}': {}", config.name, e);
+            return;
+        }
+    };
+
+    #[cfg(not(feature = "webhooks"))]
+    warn!(
+        "Webhook '{}' is configured but this build does not include the 'webhooks' feature; alerts will be dropped",
+        config.name
+    );
+
+    while let Some(alert) = rx.recv().await {
+        if !config.accepts(alert.kind) || !throttle.allow(&alert, Instant::now()) {
+            continue;
+        }
+
+        let body = match render_body(config.body_template.as_deref(), &alert) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to render webhook '{}' body: {}", config.name, e);
+                continue;
+            }
+        };
+
+        #[cfg(feature = "webhooks")]
+        {
+            match send_with_retry(&client, &config, &body).await {
+                Ok(()) => throttle.record_delivered(&alert, Instant::now()),
+                Err(e) => warn!("Webhook '{}' delivery failed: {}", config.name, e),
+            }
+        }
+
+        #[cfg(not(feature = "webhooks"))]
+        debug!("Dropping webhook '{}' alert: {}", config.name, body);
+    }
+
+    debug!("Webhook worker '{}' stopped", config.name);
+}
+
+// 5xx/429/通信エラーは指数バックオフで再試行、それ以外の4xxは設定ミスとみなし即失敗
+#[cfg(feature = "webhooks")]
+pub(crate) async fn send_with_retry(
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    body: &serde_json::Value,
+) -> Result<(), String> {
+    let mut attempt = 0;
+    loop {
+        let mut request = client.post(&config.url).json(body);
+        for (name, value) in &config.headers {
+            request = request.header(name, value);
+        }
+
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                debug!("Webhook '{}' delivered (status {})", config.name, response.status());
+                return Ok(());
+            }
+            Ok(response) => {
+                let status = response.status();
+                if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    return Err(format!("HTTP status {}", status));
+                }
+                format!("HTTP status {}", status)
+            }
+            Err(e) => e.to_string(),
+        };
+
+        if attempt >= config.max_retries {
+            return Err(format!("{} (after {} retries)", error, attempt));
+        }
+
+        let delay = config.retry_backoff_ms.saturating_mul(1u64 << attempt.min(16));
+        debug!("Webhook '{}' failed: {}, retrying in {}ms", config.name, error, delay);
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config(url: String) -> WebhookConfig {
+        toml::from_str(&format!("url = \"{}\"", url)).unwrap()
+    }
+
+    #[test]
+    fn test_render_body_template() {
+        let alert = Alert::new(AlertKind::TunnelExited, "web", "exit \"code\" -1");
+        let body = render_body(
+            Some(r#"{"text": "[{{severity}}] {{kind}} {{subject}}: {{message}}"}"#),
+            &alert,
+        )
+        .unwrap();
+        assert_eq!(body["text"], "[critical] tunnel_exited web: exit \"code\" -1");
+
+        // テンプレート未指定時はAlertそのまま
+        let body = render_body(None, &alert).unwrap();
+        assert_eq!(body["kind"], "tunnel_exited");
+        assert_eq!(body["subject"], "web");
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let mut config = test_config("https://hooks.example.com/alert".to_string());
+        assert!(config.validate().is_ok());
+        assert_eq!(config.max_retries, 3);
+
+        config.body_template = Some("{\"text\": {{message}}".to_string());
+        assert!(config.validate().is_err());
+
+        config.url = "ftp://example.com".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let mut config = test_config("http://127.0.0.1:1".to_string());
+        assert!(config.accepts(AlertKind::ConnectionError));
+        config.events = vec![AlertKind::TunnelExited];
+        assert!(!config.accepts(AlertKind::ConnectionError));
+        assert!(config.accepts(AlertKind::TunnelExited));
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[tokio::test]
+    async fn test_send_with_retry_against_local_server() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // 1回目は503、2回目で200を返すスタンドイン
+        let server = tokio::spawn(async move {
+            let mut bodies = Vec::new();
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                bodies.push(String::from_utf8_lossy(&buf[..n]).to_string());
+                let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            bodies
+        });
+
+        let mut config = test_config(format!("http://{}/hook", addr));
+        config.retry_backoff_ms = 10;
+        config.headers.insert("x-conduit-token".to_string(), "secret".to_string());
+        let client = reqwest::Client::new();
+        let body = render_body(None, &Alert::new(AlertKind::ReconnectFailed, "router", "5 attempts")).unwrap();
+
+        send_with_retry(&client, &config, &body).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("x-conduit-token: secret"));
+        assert!(requests[1].contains("reconnect_failed"));
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[tokio::test]
+    async fn test_send_gives_up_on_client_error() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            socket
+                .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let config = test_config(format!("http://{}/missing", addr));
+        let client = reqwest::Client::new();
+        let body = render_body(None, &Alert::new(AlertKind::TunnelExited, "web", "exited")).unwrap();
+
+        let err = send_with_retry(&client, &config, &body).await.unwrap_err();
+        assert!(err.contains("404"));
+    }
+}
@@ -0,0 +1,352 @@
+// トンネルペイロードの圧縮
+//
+// TunnelDataのペイロードをトンネルごとの設定で圧縮します：
+// - zstd（圧縮率重視、レベル指定可）とlz4（速度重視）
+// - 適応モードでは圧縮率を計測し、既に圧縮済みのデータ（TLS、画像など）では一時的に圧縮をやめる
+// - 圧縮前後のバイト数を集計し、TunnelMetricsで削減量を確認できるようにする
+//
+// 圧縮はネゴシエーションでcompressionを合意した相手にのみ使います。
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{ProtocolModuleError, ProtocolResult};
+
+/// これより小さいペイロードは圧縮しない（ヘッダ分でかえって大きくなる）
+pub const MIN_COMPRESS_SIZE: usize = 128;
+
+/// 適応モードで「効果なし」とみなす圧縮率（圧縮後 / 圧縮前）
+pub const ADAPTIVE_RATIO_THRESHOLD: f64 = 0.9;
+
+/// 効果がなかった後、圧縮を試さずに送るフレーム数
+pub const ADAPTIVE_BACKOFF_FRAMES: u32 = 64;
+
+/// 圧縮アルゴリズム
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    /// テキスト主体のプロトコル向け。低速なWANではこちらが有利
+    #[default]
+    Zstd,
+    /// CPU負荷を抑えたい高速な回線向け
+    Lz4,
+}
+
+impl CompressionAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Zstd => "zstd",
+            CompressionAlgorithm::Lz4 => "lz4",
+        }
+    }
+
+    /// レベル省略時の値（lz4はレベルを持たない）
+    pub fn default_level(&self) -> Option<i32> {
+        match self {
+            CompressionAlgorithm::Zstd => Some(zstd::DEFAULT_COMPRESSION_LEVEL),
+            CompressionAlgorithm::Lz4 => None,
+        }
+    }
+}
+
+impl std::fmt::Display for CompressionAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for CompressionAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zstd" => Ok(CompressionAlgorithm::Zstd),
+            "lz4" => Ok(CompressionAlgorithm::Lz4),
+            _ => Err(format!("Unknown compression algorithm '{}' (expected zstd or lz4)", s)),
+        }
+    }
+}
+
+/// トンネルごとの圧縮設定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionSettings {
+    /// 圧縮アルゴリズム
+    #[serde(default)]
+    pub algorithm: CompressionAlgorithm,
+
+    /// 圧縮レベル（zstdのみ、1-22。省略時はアルゴリズムの既定値）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+
+    /// 圧縮率を計測し、効果のないデータでは圧縮しない
+    #[serde(default = "default_adaptive")]
+    pub adaptive: bool,
+}
+
+fn default_adaptive() -> bool {
+    true
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: CompressionAlgorithm::default(),
+            level: None,
+            adaptive: default_adaptive(),
+        }
+    }
+}
+
+impl CompressionSettings {
+    /// 設定を検証
+    pub fn validate(&self) -> ProtocolResult<()> {
+        match (self.algorithm, self.level) {
+            (CompressionAlgorithm::Lz4, Some(_)) => Err(ProtocolModuleError::Compression {
+                message: "lz4 does not take a compression level".to_string(),
+            }),
+            // 負のレベル（高速モード）は圧縮率が低くWAN向けの用途に合わないため受け付けない
+            (CompressionAlgorithm::Zstd, Some(level)) if !(1..=max_zstd_level()).contains(&level) => {
+                Err(ProtocolModuleError::Compression {
+                    message: format!("Invalid zstd compression level {} (expected 1-{})", level, max_zstd_level()),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 実際に使うレベル
+    pub fn effective_level(&self) -> Option<i32> {
+        self.level.or_else(|| self.algorithm.default_level())
+    }
+}
+
+fn max_zstd_level() -> i32 {
+    *zstd::compression_level_range().end()
+}
+
+/// 圧縮の効果の集計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionStats {
+    /// 送ったペイロードの元のバイト数
+    pub bytes_in: u64,
+
+    /// 実際に送ったバイト数（圧縮しなかったフレームは元のサイズ）
+    pub bytes_out: u64,
+
+    /// 圧縮して送ったフレーム数
+    pub compressed_frames: u64,
+
+    /// 圧縮せずに送ったフレーム数（小さすぎる、効果がない、適応モードで休止中）
+    pub uncompressed_frames: u64,
+}
+
+impl CompressionStats {
+    /// 圧縮で削減できたバイト数
+    pub fn saved_bytes(&self) -> u64 {
+        self.bytes_in.saturating_sub(self.bytes_out)
+    }
+
+    /// 送信量の比率（圧縮後 / 圧縮前、未送信なら1.0）
+    pub fn ratio(&self) -> f64 {
+        if self.bytes_in == 0 {
+            1.0
+        } else {
+            self.bytes_out as f64 / self.bytes_in as f64
+        }
+    }
+
+    fn record(&mut self, original: usize, sent: usize, compressed: bool) {
+        self.bytes_in += original as u64;
+        self.bytes_out += sent as u64;
+        if compressed {
+            self.compressed_frames += 1;
+        } else {
+            self.uncompressed_frames += 1;
+        }
+    }
+}
+
+/// 1本のトンネルの送信側の圧縮器
+#[derive(Debug)]
+pub struct PayloadCompressor {
+    settings: CompressionSettings,
+    // 適応モードで圧縮を休止する残りフレーム数
+    backoff: u32,
+    stats: CompressionStats,
+}
+
+impl PayloadCompressor {
+    pub fn new(settings: CompressionSettings) -> ProtocolResult<Self> {
+        settings.validate()?;
+        Ok(Self {
+            settings,
+            backoff: 0,
+            stats: CompressionStats::default(),
+        })
+    }
+
+    pub fn settings(&self) -> &CompressionSettings {
+        &self.settings
+    }
+
+    pub fn stats(&self) -> CompressionStats {
+        self.stats
+    }
+
+    /// ペイロードを圧縮する
+    ///
+    /// 圧縮した場合は使ったアルゴリズムを、そのまま送る場合はNoneを返します。
+    /// 圧縮しても小さくならなければ元のデータを送ります。
+    pub fn compress(&mut self, payload: &[u8]) -> (Option<CompressionAlgorithm>, Vec<u8>) {
+        if payload.len() < MIN_COMPRESS_SIZE {
+            return self.send_uncompressed(payload);
+        }
+        if self.backoff > 0 {
+            self.backoff -= 1;
+            return self.send_uncompressed(payload);
+        }
+
+        let compressed = match self.settings.algorithm {
+            CompressionAlgorithm::Zstd => {
+                let level = self.settings.effective_level().unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+                match zstd::bulk::compress(payload, level) {
+                    Ok(compressed) => compressed,
+                    Err(e) => {
+                        tracing::debug!("zstd compression failed, sending uncompressed: {}", e);
+                        return self.send_uncompressed(payload);
+                    }
+                }
+            }
+            CompressionAlgorithm::Lz4 => lz4_flex::block::compress_prepend_size(payload),
+        };
+
+        let ratio = compressed.len() as f64 / payload.len() as f64;
+        if self.settings.adaptive && ratio > ADAPTIVE_RATIO_THRESHOLD {
+            // 既に圧縮済みのデータが続く間はCPUを使わない
+            self.backoff = ADAPTIVE_BACKOFF_FRAMES;
+        }
+        if compressed.len() >= payload.len() {
+            return self.send_uncompressed(payload);
+        }
+
+        self.stats.record(payload.len(), compressed.len(), true);
+        (Some(self.settings.algorithm), compressed)
+    }
+
+    fn send_uncompressed(&mut self, payload: &[u8]) -> (Option<CompressionAlgorithm>, Vec<u8>) {
+        self.stats.record(payload.len(), payload.len(), false);
+        (None, payload.to_vec())
+    }
+}
+
+/// 受信したペイロードを展開する
+///
+/// 展開後のサイズがmax_sizeを超えるものは展開前に拒否します（圧縮爆弾対策）。
+pub fn decompress(algorithm: CompressionAlgorithm, data: &[u8], max_size: usize) -> ProtocolResult<Vec<u8>> {
+    let compression_error = |message: String| ProtocolModuleError::Compression { message };
+    match algorithm {
+        CompressionAlgorithm::Zstd => zstd::bulk::decompress(data, max_size)
+            .map_err(|e| compression_error(format!("Failed to decompress zstd payload: {}", e))),
+        CompressionAlgorithm::Lz4 => {
+            let (size, block) = lz4_flex::block::uncompressed_size(data)
+                .map_err(|e| compression_error(format!("Invalid lz4 payload: {}", e)))?;
+            if size > max_size {
+                return Err(compression_error(format!(
+                    "Decompressed payload too large: {} > {}",
+                    size, max_size
+                )));
+            }
+            lz4_flex::block::decompress(block, size)
+                .map_err(|e| compression_error(format!("Failed to decompress lz4 payload: {}", e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_payload() -> Vec<u8> {
+        b"GET /api/v1/tunnels HTTP/1.1\r\nHost: 10.2.0.2\r\nAccept: application/json\r\n\r\n".repeat(32)
+    }
+
+    // 圧縮済みデータの代わりの高エントロピーなバイト列
+    fn random_payload(len: usize) -> Vec<u8> {
+        use rand::RngCore;
+        let mut data = vec![0u8; len];
+        rand::thread_rng().fill_bytes(&mut data);
+        data
+    }
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let payload = text_payload();
+        for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::Lz4] {
+            let mut compressor = PayloadCompressor::new(CompressionSettings {
+                algorithm,
+                ..Default::default()
+            })
+            .unwrap();
+
+            let (used, data) = compressor.compress(&payload);
+            assert_eq!(used, Some(algorithm));
+            assert!(data.len() < payload.len());
+            assert_eq!(decompress(algorithm, &data, payload.len()).unwrap(), payload);
+
+            let stats = compressor.stats();
+            assert_eq!(stats.compressed_frames, 1);
+            assert_eq!(stats.saved_bytes(), (payload.len() - data.len()) as u64);
+
+            // 上限を超える展開は拒否する
+            assert!(decompress(algorithm, &data, payload.len() - 1).is_err());
+        }
+
+        // 小さいペイロードはそのまま送る
+        let mut compressor = PayloadCompressor::new(CompressionSettings::default()).unwrap();
+        assert_eq!(compressor.compress(b"ping"), (None, b"ping".to_vec()));
+    }
+
+    #[test]
+    fn test_adaptive_backoff() {
+        let mut compressor = PayloadCompressor::new(CompressionSettings::default()).unwrap();
+        let incompressible = random_payload(4096);
+
+        let (used, data) = compressor.compress(&incompressible);
+        assert_eq!(used, None);
+        assert_eq!(data, incompressible);
+
+        // 休止中は圧縮できるデータでも試さない
+        let (used, _) = compressor.compress(&text_payload());
+        assert_eq!(used, None);
+        for _ in 1..ADAPTIVE_BACKOFF_FRAMES {
+            compressor.compress(&text_payload());
+        }
+        let (used, _) = compressor.compress(&text_payload());
+        assert_eq!(used, Some(CompressionAlgorithm::Zstd));
+
+        let stats = compressor.stats();
+        assert_eq!(stats.uncompressed_frames, 1 + u64::from(ADAPTIVE_BACKOFF_FRAMES));
+        assert_eq!(stats.compressed_frames, 1);
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        assert!(CompressionSettings::default().validate().is_ok());
+        assert_eq!(CompressionSettings::default().effective_level(), Some(zstd::DEFAULT_COMPRESSION_LEVEL));
+
+        let lz4_with_level = CompressionSettings {
+            algorithm: CompressionAlgorithm::Lz4,
+            level: Some(3),
+            ..Default::default()
+        };
+        assert!(lz4_with_level.validate().is_err());
+
+        for level in [0, 23] {
+            let settings = CompressionSettings { level: Some(level), ..Default::default() };
+            assert!(settings.validate().is_err(), "level {}", level);
+        }
+
+        let settings: CompressionSettings = toml::from_str("algorithm = \"lz4\"").unwrap();
+        assert_eq!(settings.algorithm, CompressionAlgorithm::Lz4);
+        assert!(settings.adaptive);
+    }
+}
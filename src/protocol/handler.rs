@@ -8,6 +8,7 @@
 
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio::time::{timeout, sleep};
@@ -19,9 +20,12 @@ use tracing::{debug, info, warn, error, instrument};
 use crate::protocol::{
     Message, MessageType, MessagePayload, ProtocolError,
     MessageCodec, CodecError, ProtocolResult, ProtocolModuleError,
+    Negotiated, ProtocolSupport, Capability,
+    ResumableSession, ResumptionConfig, SessionResume, SessionTicket, StreamAck, TunnelData,
 };
 use crate::security::{TlsClientConfig, AuthManager, SecurityResult, TlsConfig, KeyManager, KeyRotationConfig};
 use crate::common::error::Result;
+use crate::common::retry::{Backoff, RetryPolicy};
 
 #[derive(Debug, Clone)]
 pub struct ProtocolHandlerConfig {
@@ -33,17 +37,20 @@ pub struct ProtocolHandlerConfig {
     /// ハートビート間隔（秒）
     pub heartbeat_interval_seconds: u64,
     
-    /// 最大再試行回数
-    pub max_retries: usize,
-    
-    /// 再試行間隔（ミリ秒）
-    pub retry_delay_ms: u64,
+    /// 接続試行の再試行（回数と待ち時間）
+    pub retry: RetryPolicy,
     
     /// 最大メッセージサイズ（バイト）
     pub max_message_size: usize,
     
     /// 接続キープアライブ有効
     pub keepalive_enabled: bool,
+    
+    /// 登録時に提示する対応バージョンと機能
+    pub protocol_support: ProtocolSupport,
+    
+    /// 切断後のセッション再開
+    pub resumption: ResumptionConfig,
 }
 
 impl Default for ProtocolHandlerConfig {
@@ -52,14 +59,25 @@ impl Default for ProtocolHandlerConfig {
             connect_timeout_seconds: 30,
             message_timeout_seconds: 30,
             heartbeat_interval_seconds: 60,
-            max_retries: 3,
-            retry_delay_ms: 1000,
+            retry: RetryPolicy::quick(3),
             max_message_size: 1024 * 1024, // 1MB
             keepalive_enabled: true,
+            protocol_support: ProtocolSupport::default(),
+            resumption: ResumptionConfig::default(),
         }
     }
 }
 
+/// 登録（認証）の結果
+#[derive(Debug, Clone, Default)]
+pub struct Registration {
+    /// 切断前のセッションを引き継いだ
+    pub resumed: bool,
+    
+    /// Routerが受信していなかったため再送が必要なデータ
+    pub replay: Vec<TunnelData>,
+}
+
 /// 接続状態
 #[derive(Debug, Clone, PartialEq)]
 pub enum ConnectionState {
@@ -85,6 +103,10 @@ pub struct ProtocolHandler {
     connection_state: Arc<RwLock<ConnectionState>>,
     pending_requests: Arc<DashMap<Uuid, mpsc::Sender<Message>>>,
     message_handler: Option<Arc<dyn MessageHandler>>,
+    negotiated: Arc<RwLock<Option<Negotiated>>>,
+    // 再開に備えた送受信の記録。切断しても次の登録まで保持する
+    session: Arc<std::sync::Mutex<ResumableSession>>,
+    resume_ticket: Arc<RwLock<Option<(Uuid, SessionTicket)>>>,
 }
 
 impl ProtocolHandler {
@@ -95,6 +117,7 @@ impl ProtocolHandler {
         auth_manager: Arc<AuthManager>,
     ) -> Self {
         let codec = MessageCodec::new(config.max_message_size as u32);
+        let session = ResumableSession::new(config.resumption.max_buffered_bytes);
         
         Self {
             config,
@@ -104,6 +127,9 @@ impl ProtocolHandler {
             connection_state: Arc::new(RwLock::new(ConnectionState::Disconnected)),
             pending_requests: Arc::new(DashMap::new()),
             message_handler: None,
+            negotiated: Arc::new(RwLock::new(None)),
+            session: Arc::new(std::sync::Mutex::new(session)),
+            resume_ticket: Arc::new(RwLock::new(None)),
         }
     }
     
@@ -117,6 +143,59 @@ impl ProtocolHandler {
         self.connection_state.read().await.clone()
     }
     
+    /// 登録時にRouterと合意したバージョンと機能（未登録ならNone）
+    pub async fn negotiated(&self) -> Option<Negotiated> {
+        self.negotiated.read().await.clone()
+    }
+    
+    /// 合意したバージョンでメッセージを作成（登録前は既定のバージョン）
+    pub async fn new_message(&self, message_type: MessageType, payload: MessagePayload) -> Message {
+        let message = Message::new(message_type, payload);
+        match self.negotiated.read().await.as_ref() {
+            Some(negotiated) => message.with_version(negotiated.version),
+            None => message,
+        }
+    }
+    
+    fn session(&self) -> std::sync::MutexGuard<'_, ResumableSession> {
+        self.session.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// ストリームの次の送信シーケンス番号
+    pub fn next_sequence(&self, tunnel_id: Uuid, connection_id: Uuid) -> u64 {
+        self.session().next_sequence(tunnel_id, connection_id)
+    }
+    
+    /// 接続が終わったストリームの再送用バッファを解放
+    pub fn close_stream(&self, tunnel_id: Uuid, connection_id: Uuid) {
+        self.session().close_stream(tunnel_id, connection_id);
+    }
+    
+    /// トンネルデータを送信（再開時の再送に備えて確認応答まで保持する）
+    pub async fn send_tunnel_data(
+        &self,
+        stream: &mut ClientTlsStream<TcpStream>,
+        data: TunnelData,
+    ) -> ProtocolResult<()> {
+        self.session().record_sent(&data);
+        let message = self.new_message(MessageType::TunnelData, MessagePayload::TunnelData(data)).await;
+        self.send_message_async(stream, message).await
+    }
+    
+    /// 書き込みタスク経由でトンネルデータを送信（再開時の再送に備えて確認応答まで保持する）
+    pub async fn queue_tunnel_data(
+        &self,
+        outbound: &mpsc::Sender<Message>,
+        data: TunnelData,
+    ) -> ProtocolResult<()> {
+        self.session().record_sent(&data);
+        let message = self.new_message(MessageType::TunnelData, MessagePayload::TunnelData(data)).await;
+        outbound.send(message).await
+            .map_err(|_| ProtocolModuleError::Handler {
+                message: "Connection closed".to_string(),
+            })
+    }
+    
     /// Routerに接続
     #[instrument(skip(self))]
     pub async fn connect(&self, router_addr: &str) -> ProtocolResult<ClientTlsStream<TcpStream>> {
@@ -125,10 +204,10 @@ impl ProtocolHandler {
         // 接続状態を更新
         *self.connection_state.write().await = ConnectionState::Connecting;
         
-        let mut last_error = None;
+        let mut backoff = Backoff::new(self.config.retry.clone());
         
-        for attempt in 1..=self.config.max_retries {
-            debug!("Connection attempt {} of {}", attempt, self.config.max_retries);
+        let last_error = loop {
+            debug!("Connection attempt {}", backoff.failures() + 1);
             
             match self.try_connect(router_addr).await {
                 Ok(stream) => {
@@ -137,27 +216,23 @@ impl ProtocolHandler {
                     return Ok(stream);
                 }
                 Err(e) => {
-                    warn!("Connection attempt {} failed: {}", attempt, e);
-                    last_error = Some(e);
-                    
-                    if attempt < self.config.max_retries {
-                        let delay = Duration::from_millis(self.config.retry_delay_ms * attempt as u64);
-                        debug!("Waiting {:?} before retry", delay);
-                        sleep(delay).await;
+                    warn!("Connection attempt {} failed: {}", backoff.failures() + 1, e);
+                    match backoff.record_failure() {
+                        Some(decision) => {
+                            debug!("Waiting {:?} before retry", decision.delay);
+                            sleep(decision.delay).await;
+                        }
+                        None => break e,
                     }
                 }
             }
-        }
+        };
         
-        let error_msg = format!("Failed to connect after {} attempts", self.config.max_retries);
+        let error_msg = format!("Failed to connect after {} attempts", backoff.failures());
         error!("{}", error_msg);
-        *self.connection_state.write().await = ConnectionState::Error(error_msg.clone());
+        *self.connection_state.write().await = ConnectionState::Error(error_msg);
         
-        Err(last_error.unwrap_or_else(|| {
-            ProtocolModuleError::Handler {
-                message: error_msg,
-            }
-        }))
+        Err(last_error)
     }
     
     /// 単一の接続試行
@@ -243,6 +318,63 @@ impl ProtocolHandler {
         Ok(response)
     }
     
+    /// 書き込みタスクへメッセージを渡し、レスポンスを待機
+    ///
+    /// レスポンスは`start_message_loop`が受信してIDで振り分ける
+    pub async fn request(
+        &self,
+        outbound: &mpsc::Sender<Message>,
+        message: Message,
+    ) -> ProtocolResult<Message> {
+        let message_id = message.id;
+        debug!("Queueing request: {} (type: {:?})", message_id, message.message_type);
+        
+        let (tx, mut rx) = mpsc::channel(1);
+        self.pending_requests.insert(message_id, tx);
+        
+        let result = match outbound.send(message).await {
+            Ok(()) => timeout(
+                Duration::from_secs(self.config.message_timeout_seconds),
+                rx.recv(),
+            ).await
+            .map_err(|_| ProtocolModuleError::Handler {
+                message: "Message timeout".to_string(),
+            })
+            .and_then(|response| response.ok_or_else(|| ProtocolModuleError::Handler {
+                message: "Response channel closed".to_string(),
+            })),
+            Err(_) => Err(ProtocolModuleError::Handler {
+                message: "Connection closed".to_string(),
+            }),
+        };
+        
+        // 応答が来なかった要求を残さない
+        self.pending_requests.remove(&message_id);
+        result
+    }
+    
+    /// 書き込みループ。送信キューが閉じるか書き込みに失敗するまで送り続ける
+    #[instrument(skip(self, writer, outbound))]
+    pub async fn start_writer_loop<W>(
+        &self,
+        mut writer: W,
+        mut outbound: mpsc::Receiver<Message>,
+    ) -> ProtocolResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        while let Some(message) = outbound.recv().await {
+            if let Err(e) = self.codec.write_message(&mut writer, &message).await {
+                error!("Failed to write message: {}", e);
+                *self.connection_state.write().await = ConnectionState::Error(e.to_string());
+                return Err(ProtocolModuleError::Handler {
+                    message: format!("Writer loop error: {}", e),
+                });
+            }
+        }
+        Ok(())
+    }
+    
     /// 非同期でメッセージを送信（レスポンス待機なし）
     #[instrument(skip(self, stream, message))]
     pub async fn send_message_async(
@@ -261,20 +393,35 @@ impl ProtocolHandler {
     }
     
     /// メッセージ受信ループを開始
-    #[instrument(skip(self, stream))]
-    pub async fn start_message_loop(
-        &self,
-        mut stream: ClientTlsStream<TcpStream>,
-    ) -> ProtocolResult<()> {
+    ///
+    /// メッセージハンドラーが返した応答は`outbound`へ送る
+    #[instrument(skip(self, reader, outbound))]
+    pub async fn start_message_loop<R>(&self, mut reader: R, outbound: mpsc::Sender<Message>) -> ProtocolResult<()>
+    where
+        R: AsyncRead + Unpin,
+    {
         info!("Starting message receive loop");
         
         loop {
-            match self.codec.read_message(&mut stream).await {
+            match self.codec.read_message(&mut reader).await {
                 Ok(message) => {
                     debug!("Received message: {} (type: {:?})", message.id, message.message_type);
                     
-                    if let Err(e) = self.handle_received_message(message).await {
-                        warn!("Failed to handle received message: {}", e);
+                    // Routerからの切断要求。接続ループが状態を見て再接続する
+                    if let MessagePayload::Disconnect(disconnect) = &message.payload {
+                        info!("Router requested disconnect: {}", disconnect.reason);
+                        *self.connection_state.write().await = ConnectionState::Disconnected;
+                        break;
+                    }
+                    
+                    match self.handle_received_message(message).await {
+                        Ok(Some(response)) => {
+                            if outbound.send(response).await.is_err() {
+                                debug!("Writer closed; dropping handler response");
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!("Failed to handle received message: {}", e),
                     }
                 }
                 Err(CodecError::ConnectionClosed) => {
@@ -295,15 +442,31 @@ impl ProtocolHandler {
         Ok(())
     }
     
-    /// 受信したメッセージを処理
-    async fn handle_received_message(&self, message: Message) -> ProtocolResult<()> {
+    /// 受信したメッセージを処理し、Routerへ返す応答があれば返す
+    async fn handle_received_message(&self, message: Message) -> ProtocolResult<Option<Message>> {
+        match &message.payload {
+            // 再開時にRouterが再送したデータは一度だけ渡す
+            MessagePayload::TunnelData(data) if !self.session().record_received(data) => {
+                debug!("Dropping duplicate tunnel data (sequence {})", data.sequence);
+                return Ok(None);
+            }
+            MessagePayload::TunnelDataResponse(resp) => {
+                self.session().acknowledge(&[StreamAck {
+                    tunnel_id: resp.tunnel_id,
+                    connection_id: resp.connection_id,
+                    sequence: resp.ack_sequence,
+                }]);
+            }
+            _ => {}
+        }
+        
         // レスポンスメッセージの場合、対応するリクエストに送信
         if self.is_response_message(&message.message_type) {
             if let Some((_, tx)) = self.pending_requests.remove(&message.id) {
                 if tx.send(message).await.is_err() {
                     warn!("Failed to send response to waiting request");
                 }
-                return Ok(());
+                return Ok(None);
             }
         }
         
@@ -311,8 +474,8 @@ impl ProtocolHandler {
         if let Some(handler) = &self.message_handler {
             match handler.handle_message(message).await {
                 Ok(Some(response)) => {
-                    // レスポンスがある場合は送信（実際の実装では送信手段が必要）
                     debug!("Handler returned response: {}", response.id);
+                    return Ok(Some(response));
                 }
                 Ok(None) => {
                     debug!("Handler processed message without response");
@@ -323,7 +486,7 @@ impl ProtocolHandler {
             }
         }
         
-        Ok(())
+        Ok(None)
     }
     
     /// レスポンスメッセージかどうかを判定
@@ -368,7 +531,7 @@ impl ProtocolHandler {
                 memory_usage: 0, // TODO: 実際の値を取得
             });
             
-            let message = Message::new(MessageType::Heartbeat, heartbeat);
+            let message = self.new_message(MessageType::Heartbeat, heartbeat).await;
             
             // ハートビート送信
             let mut stream_guard = stream.lock().await;
@@ -390,9 +553,20 @@ impl ProtocolHandler {
         stream: &mut ClientTlsStream<TcpStream>,
         client_id: Uuid,
         client_name: String,
-    ) -> ProtocolResult<()> {
+    ) -> ProtocolResult<Registration> {
         info!("Authenticating client: {} ({})", client_name, client_id);
         
+        // 前回のセッションのチケットがあれば、受信済みの位置を添えて再開を要求する
+        let resume = match self.resume_ticket.read().await.as_ref() {
+            Some((session_id, ticket)) if self.config.resumption.enabled => Some(SessionResume {
+                session_id: *session_id,
+                ticket: ticket.clone(),
+                acks: self.session().acks(),
+            }),
+            _ => None,
+        };
+        let resume_requested = resume.is_some();
+        
         // 認証メッセージを作成
         // 認証リクエストを作成（簡略化）
         // 実際の実装では、より適切な認証フローが必要
@@ -404,7 +578,9 @@ impl ProtocolHandler {
             public_key: "TODO".to_string(), // TODO: 実際の公開鍵を設定
             signature: "TODO".to_string(), // TODO: 実際の署名を設定
             client_version: env!("CARGO_PKG_VERSION").to_string(),
-            capabilities: vec!["tcp".to_string(), "heartbeat".to_string()],
+            capabilities: self.config.protocol_support.capabilities.to_names(),
+            supported_versions: self.config.protocol_support.versions.clone(),
+            resume,
         });
         
         let message = Message::new(MessageType::ClientRegister, register);
@@ -416,9 +592,37 @@ impl ProtocolHandler {
         match response.payload {
             MessagePayload::ClientRegisterResponse(ref resp) => {
                 if resp.success {
-                    info!("Authentication successful");
+                    // ネゴシエーション非対応のRouterは合意バージョンを返さないため1.0として扱う
+                    let remote_versions: Vec<_> = resp.negotiated_version.into_iter().collect();
+                    let negotiated = self.config.protocol_support
+                        .negotiate(&remote_versions, &resp.server_capabilities)?;
+                    info!(
+                        "Authentication successful (protocol {}, capabilities: {})",
+                        negotiated.version,
+                        negotiated.capabilities.to_names().join(", ")
+                    );
+                    let resumable = negotiated.capabilities.contains(Capability::Resumption);
+                    *self.negotiated.write().await = Some(negotiated);
                     *self.connection_state.write().await = ConnectionState::Authenticated;
-                    Ok(())
+                    
+                    let registration = if resp.resumed && resume_requested {
+                        let replay = self.session().replay(&resp.acks);
+                        info!("Session resumed; replaying {} frames", replay.len());
+                        Registration { resumed: true, replay }
+                    } else {
+                        if resume_requested {
+                            warn!("Router did not resume the previous session; open connections are lost");
+                        }
+                        // 新しいセッションでは以前のシーケンス番号を引き継がない
+                        self.session().reset();
+                        Registration::default()
+                    };
+                    
+                    *self.resume_ticket.write().await = match (resp.session_id, resp.session_ticket.clone()) {
+                        (Some(session_id), Some(ticket)) if resumable => Some((session_id, ticket)),
+                        _ => None,
+                    };
+                    Ok(registration)
                 } else {
                     let error_msg = resp.error.as_deref().unwrap_or("Unknown auth error");
                     error!("Authentication failed: {}", error_msg);
@@ -447,6 +651,9 @@ impl ProtocolHandler {
         // 待機中のリクエストをクリア
         self.pending_requests.clear();
         
+        // 再接続先のRouterとは改めてネゴシエーションする
+        *self.negotiated.write().await = None;
+        
         Ok(())
     }
 }
@@ -489,6 +696,67 @@ mod tests {
         assert_eq!(handler.extract_domain("example.com").unwrap(), "example.com");
     }
 
+    #[tokio::test]
+    async fn test_request_over_split_stream() {
+        let handler = Arc::new(create_test_handler());
+        let (local, mut remote) = tokio::io::duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(local);
+        let (outbound, outbound_rx) = mpsc::channel(8);
+        
+        let writer_task = tokio::spawn({
+            let handler = Arc::clone(&handler);
+            async move { handler.start_writer_loop(writer, outbound_rx).await }
+        });
+        let reader_task = tokio::spawn({
+            let handler = Arc::clone(&handler);
+            let outbound = outbound.clone();
+            async move { handler.start_message_loop(reader, outbound).await }
+        });
+        
+        // Router役：受け取った要求と同じIDで応答を返す
+        let codec = MessageCodec::new(1024 * 1024);
+        let router = tokio::spawn(async move {
+            let request = codec.read_message(&mut remote).await.unwrap();
+            assert_eq!(request.message_type, MessageType::Heartbeat);
+            let mut response = Message::new(
+                MessageType::HeartbeatResponse,
+                MessagePayload::HeartbeatResponse(crate::protocol::messages::HeartbeatResponse {
+                    server_time: chrono::Utc::now(),
+                    connected_clients: 1,
+                    total_tunnels: 0,
+                    server_load: 0.0,
+                    target_health: Vec::new(),
+                }),
+            );
+            response.id = request.id;
+            codec.write_message(&mut remote, &response).await.unwrap();
+            remote
+        });
+        
+        let heartbeat = Message::new(
+            MessageType::Heartbeat,
+            MessagePayload::Heartbeat(crate::protocol::messages::Heartbeat {
+                client_id: Uuid::new_v4(),
+                active_tunnels: 0,
+                active_connections: 0,
+                cpu_usage: 0.0,
+                memory_usage: 0,
+            }),
+        );
+        let request_id = heartbeat.id;
+        let response = handler.request(&outbound, heartbeat).await.unwrap();
+        assert_eq!(response.id, request_id);
+        assert!(matches!(response.payload, MessagePayload::HeartbeatResponse(_)));
+        assert!(handler.pending_requests.is_empty());
+        
+        // Routerが閉じれば受信ループは終わり、送信キューを閉じれば書き込みループも終わる
+        drop(router.await.unwrap());
+        reader_task.await.unwrap().unwrap();
+        drop(outbound);
+        writer_task.await.unwrap().unwrap();
+        assert_eq!(handler.connection_state().await, ConnectionState::Disconnected);
+    }
+
     #[test]
     fn test_response_message_detection() {
         let handler = create_test_handler();
@@ -9,13 +9,25 @@ use std::net::SocketAddr;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use base64::Engine;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+use crate::protocol::compression::{self, CompressionAlgorithm, CompressionSettings, PayloadCompressor};
+use crate::protocol::resumption::{SessionResume, SessionTicket, StreamAck};
+use crate::protocol::{ProtocolModuleError, ProtocolResult};
+
+/// プロトコルバージョン（majorが同じでもminorごとに使える機能が異なる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct MessageVersion {
     pub major: u32,
     pub minor: u32,
 }
 
+impl MessageVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+}
+
 impl Default for MessageVersion {
     fn default() -> Self {
         Self { major: 1, minor: 0 }
@@ -105,6 +117,14 @@ pub struct ClientRegister {
     
     /// サポートする機能
     pub capabilities: Vec<String>,
+    
+    /// 対応するプロトコルバージョン（ネゴシエーション非対応のClientは送らない）
+    #[serde(default)]
+    pub supported_versions: Vec<MessageVersion>,
+    
+    /// 切断前のセッションの再開要求
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume: Option<SessionResume>,
 }
 
 /// トンネル作成要求
@@ -127,6 +147,30 @@ pub struct TunnelCreate {
     
     /// トンネル設定
     pub config: TunnelConfig,
+    
+    /// 負荷分散のサービス名（省略時はトンネル名）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    
+    /// 転送先の全エンドポイント（レプリカ、先頭はsource_addrと同じ）。空ならsource_addrのみ
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_addrs: Vec<SocketAddr>,
+}
+
+impl TunnelCreate {
+    /// 同じサービス名のトンネルはRouterで1つのサービスとして負荷分散される
+    pub fn service_name(&self) -> &str {
+        self.service.as_deref().unwrap_or(&self.tunnel_name)
+    }
+    
+    /// Routerが接続を振り分ける転送先
+    pub fn source_endpoints(&self) -> Vec<SocketAddr> {
+        if self.source_addrs.is_empty() {
+            vec![self.source_addr]
+        } else {
+            self.source_addrs.clone()
+        }
+    }
 }
 
 /// トンネル設定
@@ -143,6 +187,10 @@ pub struct TunnelConfig {
     
     /// 圧縮有効
     pub compression_enabled: bool,
+    
+    /// 圧縮アルゴリズムとレベル（compression_enabledの場合のみ使う）
+    #[serde(default)]
+    pub compression: CompressionSettings,
 }
 
 impl Default for TunnelConfig {
@@ -152,6 +200,7 @@ impl Default for TunnelConfig {
             timeout_seconds: 300,
             buffer_size: 65536,
             compression_enabled: false,
+            compression: CompressionSettings::default(),
         }
     }
 }
@@ -168,11 +217,60 @@ pub struct TunnelData {
     /// データ（Base64エンコード）
     pub data: String,
     
-    /// データサイズ（バイト）
+    /// データサイズ（バイト、展開後）
     pub data_size: usize,
     
     /// シーケンス番号
     pub sequence: u64,
+    
+    /// データの圧縮方式（圧縮していなければNone）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionAlgorithm>,
+}
+
+impl TunnelData {
+    /// ペイロードからTunnelDataを作成（圧縮器があればその設定で圧縮）
+    pub fn new(
+        tunnel_id: Uuid,
+        connection_id: Uuid,
+        sequence: u64,
+        payload: &[u8],
+        compressor: Option<&mut PayloadCompressor>,
+    ) -> Self {
+        let (compression, data) = match compressor {
+            Some(compressor) => compressor.compress(payload),
+            None => (None, payload.to_vec()),
+        };
+        Self {
+            tunnel_id,
+            connection_id,
+            data: base64::engine::general_purpose::STANDARD.encode(data),
+            data_size: payload.len(),
+            sequence,
+            compression,
+        }
+    }
+    
+    /// 元のペイロードを取り出す（必要なら展開する）
+    pub fn payload(&self, max_size: usize) -> ProtocolResult<Vec<u8>> {
+        if self.data_size > max_size {
+            return Err(ProtocolError::MessageTooLarge { size: self.data_size, max_size }.into());
+        }
+        let data = base64::engine::general_purpose::STANDARD.decode(&self.data)
+            .map_err(|e| ProtocolModuleError::InvalidFormat {
+                message: format!("Invalid tunnel data encoding: {}", e),
+            })?;
+        let payload = match self.compression {
+            Some(algorithm) => compression::decompress(algorithm, &data, self.data_size)?,
+            None => data,
+        };
+        if payload.len() != self.data_size {
+            return Err(ProtocolModuleError::InvalidFormat {
+                message: format!("Tunnel data size mismatch: expected {}, got {}", self.data_size, payload.len()),
+            });
+        }
+        Ok(payload)
+    }
 }
 
 /// ハートビート
@@ -213,6 +311,26 @@ pub struct ClientRegisterResponse {
     
     /// サーバー機能
     pub server_capabilities: Vec<String>,
+    
+    /// 合意したプロトコルバージョン（ネゴシエーション非対応のRouterは送らない）
+    #[serde(default)]
+    pub negotiated_version: Option<MessageVersion>,
+    
+    /// 次回の再開に使うセッションチケット（再開を合意した場合のみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_ticket: Option<SessionTicket>,
+    
+    /// 切断後にセッションを保持する時間（秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_grace_seconds: Option<u64>,
+    
+    /// 再開要求に応じて前回のセッションを引き継いだ
+    #[serde(default)]
+    pub resumed: bool,
+    
+    /// Routerが各ストリームで受信済みのsequence（Clientはこれより後ろを再送する）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub acks: Vec<StreamAck>,
 }
 
 /// トンネル作成レスポンス
@@ -264,6 +382,66 @@ pub struct HeartbeatResponse {
     
     /// サーバー負荷（0.0-1.0）
     pub server_load: f32,
+
+    /// Router側サービスのヘルスチェック結果
+    #[serde(default)]
+    pub target_health: Vec<TargetHealth>,
+}
+
+/// Router側サービスの疎通状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetState {
+    /// まだ判定できるだけのプローブ結果がない
+    Unknown,
+    Up,
+    Down,
+}
+
+impl TargetState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TargetState::Unknown => "unknown",
+            TargetState::Up => "up",
+            TargetState::Down => "down",
+        }
+    }
+}
+
+/// Router側サービスのヘルスチェック結果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetHealth {
+    /// ターゲットアドレス（--source）
+    pub target_addr: SocketAddr,
+
+    /// 疎通状態
+    pub state: TargetState,
+
+    /// 最終チェック時刻
+    pub checked_at: DateTime<Utc>,
+
+    /// 直近のプローブ所要時間（ms）
+    pub latency_ms: Option<f64>,
+
+    /// 連続失敗回数
+    pub consecutive_failures: u32,
+
+    /// 直近のエラー内容
+    pub error: Option<String>,
+}
+
+impl TargetHealth {
+    /// 未判定状態のヘルス情報を作成
+    pub fn unknown(target_addr: SocketAddr) -> Self {
+        Self {
+            target_addr,
+            state: TargetState::Unknown,
+            checked_at: Utc::now(),
+            latency_ms: None,
+            consecutive_failures: 0,
+            error: None,
+        }
+    }
 }
 
 // === 共通メッセージ ===
@@ -331,6 +509,12 @@ impl Message {
         }
     }
     
+    /// ネゴシエーションで合意したバージョンを設定
+    pub fn with_version(mut self, version: MessageVersion) -> Self {
+        self.version = version;
+        self
+    }
+    
     /// JSONにシリアライズ
     pub fn to_json(&self) -> Result<String, ProtocolError> {
         serde_json::to_string(self).map_err(ProtocolError::from)
@@ -420,5 +604,23 @@ mod tests {
         assert_eq!(config.timeout_seconds, 300);
         assert_eq!(config.buffer_size, 65536);
         assert!(!config.compression_enabled);
+        assert_eq!(config.compression, CompressionSettings::default());
+    }
+
+    #[test]
+    fn test_tunnel_data_compression() {
+        let payload = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n".repeat(16);
+        let mut compressor = PayloadCompressor::new(CompressionSettings::default()).unwrap();
+        let data = TunnelData::new(Uuid::new_v4(), Uuid::new_v4(), 1, &payload, Some(&mut compressor));
+        assert_eq!(data.compression, Some(CompressionAlgorithm::Zstd));
+        assert_eq!(data.data_size, payload.len());
+        assert_eq!(data.payload(payload.len()).unwrap(), payload);
+        assert!(data.payload(payload.len() - 1).is_err());
+
+        // 圧縮を合意していない相手向けは平文のまま（旧Routerは圧縮フィールドを知らない）
+        let plain = TunnelData::new(Uuid::new_v4(), Uuid::new_v4(), 2, &payload, None);
+        let json = serde_json::to_value(&plain).unwrap();
+        assert!(json.get("compression").is_none());
+        assert_eq!(plain.payload(payload.len()).unwrap(), payload);
     }
 }
\ No newline at end of file
@@ -9,14 +9,21 @@
 pub mod messages;
 pub mod handler;
 pub mod codec;
+pub mod negotiation;
+pub mod compression;
+pub mod resumption;
 
 pub use messages::{
     Message, MessageType, MessageVersion, MessagePayload, ProtocolError,
     ClientRegister, TunnelCreate, TunnelData, Heartbeat,
     ClientRegisterResponse, TunnelCreateResponse, TunnelDataResponse, HeartbeatResponse,
+    TargetHealth, TargetState,
 };
-pub use handler::{ProtocolHandler, ProtocolHandlerConfig, ConnectionState};
+pub use handler::{ProtocolHandler, ProtocolHandlerConfig, ConnectionState, Registration};
 pub use codec::{MessageCodec, CodecError};
+pub use negotiation::{Capability, CapabilitySet, Negotiated, ProtocolSupport, SUPPORTED_VERSIONS};
+pub use compression::{CompressionAlgorithm, CompressionSettings, CompressionStats, PayloadCompressor};
+pub use resumption::{ResumableSession, ResumeError, ResumptionConfig, SessionResume, SessionTicket, StreamAck};
 
 use crate::common::error::Error;
 
@@ -37,6 +44,9 @@ pub enum ProtocolModuleError {
     
     #[error("Invalid message format: {message}")]
     InvalidFormat { message: String },
+    
+    #[error("Compression error: {message}")]
+    Compression { message: String },
 }
 
 impl From<ProtocolModuleError> for Error {
@@ -0,0 +1,338 @@
+// プロトコルバージョンと機能のネゴシエーション
+//
+// ClientRegisterで送る対応バージョン・機能と、Routerの対応範囲から
+// 双方が使えるバージョンと機能を決めます：
+// - 共通するバージョンのうち最も新しいものを選択
+// - 機能は両者の共通部分のうち、選択したバージョンで使えるものに限定
+// - 知らない機能名は無視し、ネゴシエーション非対応の相手は1.0として扱う
+//
+// これにより新旧のClient・Routerが混在したままでも段階的に更新できます。
+
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::messages::{MessageVersion, TunnelCreate};
+use crate::protocol::{ProtocolModuleError, ProtocolResult};
+
+/// ネゴシエーション導入前のバージョン
+pub const PROTOCOL_V1_0: MessageVersion = MessageVersion::new(1, 0);
+
+/// 対応バージョン・機能のネゴシエーションを導入したバージョン
+pub const PROTOCOL_V1_1: MessageVersion = MessageVersion::new(1, 1);
+
+/// このビルドが話せるバージョン（昇順）
+pub const SUPPORTED_VERSIONS: [MessageVersion; 2] = [PROTOCOL_V1_0, PROTOCOL_V1_1];
+
+/// プロトコル機能
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Tcp,
+    Heartbeat,
+    Udp,
+    /// JSONではなくバイナリのフレームでTunnelDataを送る
+    BinaryFrames,
+    /// トンネルペイロードの圧縮
+    Compression,
+    /// 1本の接続上での複数ストリームの多重化
+    Multiplexing,
+    /// 切断後のセッション再開
+    Resumption,
+    /// 1つのトンネルに複数の転送先エンドポイント（レプリカ）
+    MultiSource,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 8] = [
+        Capability::Tcp,
+        Capability::Heartbeat,
+        Capability::Udp,
+        Capability::BinaryFrames,
+        Capability::Compression,
+        Capability::Multiplexing,
+        Capability::Resumption,
+        Capability::MultiSource,
+    ];
+
+    /// ClientRegister::capabilitiesでの名前
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Tcp => "tcp",
+            Capability::Heartbeat => "heartbeat",
+            Capability::Udp => "udp",
+            Capability::BinaryFrames => "binary_frames",
+            Capability::Compression => "compression",
+            Capability::Multiplexing => "multiplexing",
+            Capability::Resumption => "resumption",
+            Capability::MultiSource => "multi_source",
+        }
+    }
+
+    /// 名前から機能を取得（新しい相手が送る未知の機能はNone）
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == name)
+    }
+
+    /// この機能を使うために必要な最小バージョン
+    pub fn min_version(&self) -> MessageVersion {
+        match self {
+            Capability::Tcp | Capability::Heartbeat | Capability::Udp => PROTOCOL_V1_0,
+            _ => PROTOCOL_V1_1,
+        }
+    }
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 機能の集合
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CapabilitySet(BTreeSet<Capability>);
+
+impl CapabilitySet {
+    /// このビルドが実装している機能
+    pub fn supported() -> Self {
+        [
+            Capability::Tcp,
+            Capability::Heartbeat,
+            Capability::Udp,
+            Capability::Compression,
+            Capability::Resumption,
+            Capability::MultiSource,
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    /// 機能名のリストから作成（未知の名前は無視する）
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Self {
+        names.iter().filter_map(|name| Capability::parse(name.as_ref())).collect()
+    }
+
+    pub fn to_names(&self) -> Vec<String> {
+        self.0.iter().map(|c| c.as_str().to_string()).collect()
+    }
+
+    pub fn contains(&self, capability: Capability) -> bool {
+        self.0.contains(&capability)
+    }
+
+    pub fn insert(&mut self, capability: Capability) -> bool {
+        self.0.insert(capability)
+    }
+
+    pub fn remove(&mut self, capability: Capability) -> bool {
+        self.0.remove(&capability)
+    }
+
+    pub fn intersection(&self, other: &CapabilitySet) -> CapabilitySet {
+        self.0.intersection(&other.0).copied().collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Capability> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl FromIterator<Capability> for CapabilitySet {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// 自分側の対応範囲
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolSupport {
+    /// 対応バージョン
+    pub versions: Vec<MessageVersion>,
+
+    /// 提供する機能
+    pub capabilities: CapabilitySet,
+}
+
+impl Default for ProtocolSupport {
+    fn default() -> Self {
+        Self {
+            versions: SUPPORTED_VERSIONS.to_vec(),
+            capabilities: CapabilitySet::supported(),
+        }
+    }
+}
+
+impl ProtocolSupport {
+    /// 相手の対応バージョン・機能との共通部分を決める
+    ///
+    /// 相手がバージョンを送らない（ネゴシエーション非対応の）場合は1.0として扱います。
+    pub fn negotiate<S: AsRef<str>>(
+        &self,
+        remote_versions: &[MessageVersion],
+        remote_capabilities: &[S],
+    ) -> ProtocolResult<Negotiated> {
+        let remote_versions = if remote_versions.is_empty() {
+            std::slice::from_ref(&PROTOCOL_V1_0)
+        } else {
+            remote_versions
+        };
+
+        let version = self
+            .versions
+            .iter()
+            .filter(|v| remote_versions.contains(v))
+            .max()
+            .cloned()
+            .ok_or_else(|| ProtocolModuleError::VersionMismatch {
+                expected: format_versions(&self.versions),
+                actual: format_versions(remote_versions),
+            })?;
+
+        let capabilities = self
+            .capabilities
+            .intersection(&CapabilitySet::from_names(remote_capabilities))
+            .iter()
+            .filter(|c| c.min_version() <= version)
+            .collect();
+
+        Ok(Negotiated { version, capabilities })
+    }
+}
+
+/// ネゴシエーションの結果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Negotiated {
+    /// 以降のメッセージで使うバージョン
+    pub version: MessageVersion,
+
+    /// 双方が使える機能
+    pub capabilities: CapabilitySet,
+}
+
+impl Negotiated {
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(capability)
+    }
+
+    /// トンネル作成要求を合意した機能に合わせる
+    ///
+    /// 相手が扱えないプロトコルはエラーとし、圧縮と複数の転送先は合意していなければ
+    /// 警告を出したうえで外して送ります（旧Routerとも接続を保つため）。
+    pub fn check_tunnel_create(&self, create: &TunnelCreate) -> ProtocolResult<TunnelCreate> {
+        let required = match create.protocol.as_str() {
+            "udp" => Capability::Udp,
+            _ => Capability::Tcp,
+        };
+        if !self.supports(required) {
+            return Err(ProtocolModuleError::Handler {
+                message: format!(
+                    "Tunnel {} requires '{}', which was not negotiated with the peer",
+                    create.tunnel_name, required
+                ),
+            });
+        }
+
+        let mut create = create.clone();
+        if create.config.compression_enabled && !self.supports(Capability::Compression) {
+            tracing::warn!(
+                "Compression for tunnel {} was not negotiated with the peer; sending uncompressed",
+                create.tunnel_name
+            );
+            create.config.compression_enabled = false;
+        }
+        // 旧Routerは先頭のエンドポイント（source_addr）だけを使う
+        if !create.source_addrs.is_empty() && !self.supports(Capability::MultiSource) {
+            if create.source_addrs.len() > 1 {
+                tracing::warn!(
+                    "Peer does not support multiple sources; tunnel {} will only use {}",
+                    create.tunnel_name, create.source_addr
+                );
+            }
+            create.source_addrs.clear();
+        }
+        Ok(create)
+    }
+}
+
+fn format_versions(versions: &[MessageVersion]) -> String {
+    versions.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::messages::TunnelConfig;
+    use uuid::Uuid;
+
+    fn support(versions: &[MessageVersion], capabilities: &[Capability]) -> ProtocolSupport {
+        ProtocolSupport {
+            versions: versions.to_vec(),
+            capabilities: capabilities.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn test_negotiate_picks_highest_common_version() {
+        let router = support(&SUPPORTED_VERSIONS, &Capability::ALL);
+        let client = support(&SUPPORTED_VERSIONS, &[Capability::Tcp, Capability::Compression]);
+
+        let negotiated = router
+            .negotiate(&client.versions, &client.capabilities.to_names())
+            .unwrap();
+        assert_eq!(negotiated.version, PROTOCOL_V1_1);
+        assert_eq!(negotiated.capabilities.to_names(), vec!["tcp", "compression"]);
+
+        // 新しい相手が送る未知の機能・バージョンは無視する
+        let newer = [PROTOCOL_V1_1, MessageVersion::new(1, 2)];
+        let negotiated = router.negotiate(&newer, &["tcp", "quic"]).unwrap();
+        assert_eq!(negotiated.version, PROTOCOL_V1_1);
+        assert_eq!(negotiated.capabilities.to_names(), vec!["tcp"]);
+    }
+
+    #[test]
+    fn test_negotiate_with_legacy_peer() {
+        let router = support(&SUPPORTED_VERSIONS, &Capability::ALL);
+
+        // バージョンを送らない旧Clientは1.0として扱い、1.1以降の機能は使わない
+        let negotiated = router
+            .negotiate::<&str>(&[], &["tcp", "heartbeat", "compression"])
+            .unwrap();
+        assert_eq!(negotiated.version, PROTOCOL_V1_0);
+        assert!(negotiated.supports(Capability::Heartbeat));
+        assert!(!negotiated.supports(Capability::Compression));
+
+        let future_only = [MessageVersion::new(2, 0)];
+        assert!(matches!(
+            router.negotiate(&future_only, &["tcp"]),
+            Err(ProtocolModuleError::VersionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_tunnel_create() {
+        let negotiated = Negotiated {
+            version: PROTOCOL_V1_1,
+            capabilities: [Capability::Tcp].into_iter().collect(),
+        };
+        let mut create = TunnelCreate {
+            tunnel_id: Uuid::new_v4(),
+            tunnel_name: "web".to_string(),
+            source_addr: "10.2.0.2:8080".parse().unwrap(),
+            bind_addr: "0.0.0.0:80".parse().unwrap(),
+            protocol: "tcp".to_string(),
+            config: TunnelConfig { compression_enabled: true, ..Default::default() },
+            service: None,
+            source_addrs: vec!["10.2.0.2:8080".parse().unwrap(), "10.2.0.3:8080".parse().unwrap()],
+        };
+
+        // 合意していない圧縮は無効にして送る
+        let checked = negotiated.check_tunnel_create(&create).unwrap();
+        assert!(!checked.config.compression_enabled);
+        assert!(checked.source_addrs.is_empty());
+        assert_eq!(checked.source_endpoints(), vec![create.source_addr]);
+
+        create.protocol = "udp".to_string();
+        assert!(negotiated.check_tunnel_create(&create).is_err());
+    }
+}
@@ -0,0 +1,367 @@
+// 切断後のセッション再開
+//
+// 一時的な回線断（Wi-Fiの瞬断など）でトンネル内の接続が切れないようにします：
+// - Routerは登録時にセッションチケットを発行し、切断後も猶予期間だけセッション（トンネル・未確認データ）を保持
+// - Clientは再接続時にチケットと各ストリームで受信済みのsequenceを送ってセッションを再開
+// - 双方は相手が受信していないTunnelDataをsequence順に再送し、重複して届いたものは捨てる
+//
+// 未確認データはバッファの上限までしか保持せず、超えたセッションは再開できないものとして扱います。
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::protocol::messages::TunnelData;
+
+/// セッション再開の設定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumptionConfig {
+    /// セッション再開を有効にする
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// 切断後にセッションを保持する時間（秒）
+    #[serde(default = "default_grace_seconds")]
+    pub grace_seconds: u64,
+
+    /// 再送のために保持する未確認データの上限（バイト）
+    #[serde(default = "default_max_buffered_bytes")]
+    pub max_buffered_bytes: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_grace_seconds() -> u64 {
+    60
+}
+
+fn default_max_buffered_bytes() -> usize {
+    4 * 1024 * 1024
+}
+
+impl Default for ResumptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            grace_seconds: default_grace_seconds(),
+            max_buffered_bytes: default_max_buffered_bytes(),
+        }
+    }
+}
+
+impl ResumptionConfig {
+    pub fn grace_period(&self) -> Duration {
+        Duration::from_secs(self.grace_seconds)
+    }
+}
+
+/// セッションチケット
+///
+/// Routerが登録時に発行する推測できない値です。Routerはハッシュのみを保持します。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionTicket(String);
+
+impl SessionTicket {
+    /// 新しいチケットを発行
+    pub fn generate() -> Self {
+        use rand::RngCore;
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 保存・照合用のハッシュ
+    ///
+    /// 比較するのはハッシュ同士なので、一致までの時間からチケットは推測できません。
+    pub fn digest(&self) -> [u8; 32] {
+        let digest = ring::digest::digest(&ring::digest::SHA256, self.0.as_bytes());
+        let mut out = [0u8; 32];
+        out.copy_from_slice(digest.as_ref());
+        out
+    }
+}
+
+impl From<String> for SessionTicket {
+    fn from(ticket: String) -> Self {
+        Self(ticket)
+    }
+}
+
+/// ストリーム（トンネル内の1接続）の受信済み位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamAck {
+    pub tunnel_id: Uuid,
+    pub connection_id: Uuid,
+    /// 受信済みの最大sequence
+    pub sequence: u64,
+}
+
+/// ClientRegisterに載せる再開要求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResume {
+    /// 前回の登録で受け取ったセッションID
+    pub session_id: Uuid,
+
+    /// 前回の登録で受け取ったチケット
+    pub ticket: SessionTicket,
+
+    /// Clientが各ストリームで受信済みのsequence
+    #[serde(default)]
+    pub acks: Vec<StreamAck>,
+}
+
+/// 再開を受け付けなかった理由
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ResumeError {
+    #[error("No resumable session for this client")]
+    UnknownSession,
+
+    #[error("Session ticket does not match")]
+    InvalidTicket,
+
+    #[error("Session expired before the client reconnected")]
+    Expired,
+
+    #[error("Session buffered more unacknowledged data than allowed and cannot be resumed")]
+    BufferOverflow,
+}
+
+type StreamKey = (Uuid, Uuid);
+
+#[derive(Debug, Default)]
+struct StreamState {
+    next_sequence: u64,
+    // 相手から受信済みの最大sequence
+    last_received: Option<u64>,
+    // 相手の受信確認を待っている送信済みデータ（sequence順）
+    unacked: VecDeque<TunnelData>,
+}
+
+/// 再開に必要なストリームごとの送受信状態
+///
+/// 送信したTunnelDataは相手の受信確認（StreamAck）まで保持し、
+/// 受信したTunnelDataは重複を捨てるためにsequenceを記録します。
+#[derive(Debug)]
+pub struct ResumableSession {
+    streams: HashMap<StreamKey, StreamState>,
+    buffered_bytes: usize,
+    max_buffered_bytes: usize,
+    overflowed: bool,
+}
+
+impl ResumableSession {
+    pub fn new(max_buffered_bytes: usize) -> Self {
+        Self {
+            streams: HashMap::new(),
+            buffered_bytes: 0,
+            max_buffered_bytes,
+            overflowed: false,
+        }
+    }
+
+    /// 送信に使う次のsequence（ストリームごとに1から始まる）
+    pub fn next_sequence(&mut self, tunnel_id: Uuid, connection_id: Uuid) -> u64 {
+        let stream = self.streams.entry((tunnel_id, connection_id)).or_default();
+        stream.next_sequence += 1;
+        stream.next_sequence
+    }
+
+    /// 送信したデータを再送用に保持
+    ///
+    /// 上限を超えた場合は以降のデータを保持せず、このセッションは再開できなくなります。
+    pub fn record_sent(&mut self, data: &TunnelData) {
+        if self.overflowed {
+            return;
+        }
+        let size = data.data.len();
+        if self.buffered_bytes + size > self.max_buffered_bytes {
+            tracing::warn!(
+                "Resumption buffer exceeded {} bytes; the session will not survive a disconnect",
+                self.max_buffered_bytes
+            );
+            self.overflowed = true;
+            self.clear_buffers();
+            return;
+        }
+        self.buffered_bytes += size;
+        self.streams
+            .entry((data.tunnel_id, data.connection_id))
+            .or_default()
+            .unacked
+            .push_back(data.clone());
+    }
+
+    /// 受信したデータを記録（再送による重複ならfalse）
+    pub fn record_received(&mut self, data: &TunnelData) -> bool {
+        let stream = self.streams.entry((data.tunnel_id, data.connection_id)).or_default();
+        if stream.last_received.is_some_and(|last| data.sequence <= last) {
+            return false;
+        }
+        stream.last_received = Some(data.sequence);
+        true
+    }
+
+    /// 相手に伝える受信済みの位置
+    pub fn acks(&self) -> Vec<StreamAck> {
+        let mut acks: Vec<StreamAck> = self
+            .streams
+            .iter()
+            .filter_map(|(&(tunnel_id, connection_id), stream)| {
+                stream.last_received.map(|sequence| StreamAck { tunnel_id, connection_id, sequence })
+            })
+            .collect();
+        acks.sort_by_key(|ack| (ack.tunnel_id, ack.connection_id));
+        acks
+    }
+
+    /// 相手が受信済みのデータを再送用バッファから外す
+    pub fn acknowledge(&mut self, acks: &[StreamAck]) {
+        for ack in acks {
+            if let Some(stream) = self.streams.get_mut(&(ack.tunnel_id, ack.connection_id)) {
+                while stream.unacked.front().is_some_and(|d| d.sequence <= ack.sequence) {
+                    if let Some(data) = stream.unacked.pop_front() {
+                        self.buffered_bytes -= data.data.len();
+                    }
+                }
+            }
+        }
+    }
+
+    /// 再開時に再送するデータ（相手の受信済み位置より後ろをストリームごとにsequence順で）
+    pub fn replay(&mut self, acks: &[StreamAck]) -> Vec<TunnelData> {
+        self.acknowledge(acks);
+        let ordered: BTreeMap<&StreamKey, &StreamState> = self.streams.iter().collect();
+        ordered
+            .into_values()
+            .flat_map(|stream| stream.unacked.iter().cloned())
+            .collect()
+    }
+
+    /// 閉じたストリームの状態を破棄
+    pub fn close_stream(&mut self, tunnel_id: Uuid, connection_id: Uuid) {
+        if let Some(stream) = self.streams.remove(&(tunnel_id, connection_id)) {
+            self.buffered_bytes -= stream.unacked.iter().map(|d| d.data.len()).sum::<usize>();
+        }
+    }
+
+    /// 閉じたトンネルの全ストリームを破棄
+    pub fn close_tunnel(&mut self, tunnel_id: Uuid) {
+        let closed: Vec<StreamKey> = self.streams.keys().filter(|(t, _)| *t == tunnel_id).copied().collect();
+        for (tunnel_id, connection_id) in closed {
+            self.close_stream(tunnel_id, connection_id);
+        }
+    }
+
+    /// 再開できる状態か（バッファが溢れていない）
+    pub fn is_resumable(&self) -> bool {
+        !self.overflowed
+    }
+
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes
+    }
+
+    pub fn stream_count(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// 再開できなかった場合に新しいセッションとしてやり直す
+    pub fn reset(&mut self) {
+        self.streams.clear();
+        self.buffered_bytes = 0;
+        self.overflowed = false;
+    }
+
+    fn clear_buffers(&mut self) {
+        for stream in self.streams.values_mut() {
+            stream.unacked.clear();
+        }
+        self.buffered_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(tunnel_id: Uuid, connection_id: Uuid, sequence: u64, payload: &[u8]) -> TunnelData {
+        TunnelData::new(tunnel_id, connection_id, sequence, payload, None)
+    }
+
+    #[test]
+    fn test_replay_unacknowledged() {
+        let (tunnel, ssh, db) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut sender = ResumableSession::new(1024 * 1024);
+        let mut receiver = ResumableSession::new(1024 * 1024);
+
+        for (connection, count) in [(ssh, 3), (db, 2)] {
+            for _ in 0..count {
+                let sequence = sender.next_sequence(tunnel, connection);
+                let frame = data(tunnel, connection, sequence, b"payload");
+                sender.record_sent(&frame);
+                // 切断前に届いたのは各ストリームの1件目だけ
+                if sequence == 1 {
+                    assert!(receiver.record_received(&frame));
+                }
+            }
+        }
+
+        // 再開時は受信側の位置より後ろだけをsequence順に再送する
+        let replayed = sender.replay(&receiver.acks());
+        assert_eq!(replayed.len(), 3);
+        for stream in [ssh, db] {
+            let sequences: Vec<u64> = replayed.iter().filter(|d| d.connection_id == stream).map(|d| d.sequence).collect();
+            assert!(sequences.windows(2).all(|w| w[0] < w[1]));
+            assert_eq!(sequences[0], 2);
+        }
+        for frame in &replayed {
+            assert!(receiver.record_received(frame));
+            // 同じデータが二重に届いても捨てる
+            assert!(!receiver.record_received(frame));
+        }
+
+        sender.acknowledge(&receiver.acks());
+        assert_eq!(sender.buffered_bytes(), 0);
+        assert!(sender.replay(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_buffer_overflow_disables_resumption() {
+        let (tunnel, connection) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut session = ResumableSession::new(16);
+
+        let sequence = session.next_sequence(tunnel, connection);
+        session.record_sent(&data(tunnel, connection, sequence, b"12345678"));
+        assert!(session.is_resumable());
+
+        let sequence = session.next_sequence(tunnel, connection);
+        session.record_sent(&data(tunnel, connection, sequence, b"this frame does not fit"));
+        assert!(!session.is_resumable());
+        assert_eq!(session.buffered_bytes(), 0);
+
+        session.reset();
+        assert!(session.is_resumable());
+        assert_eq!(session.stream_count(), 0);
+    }
+
+    #[test]
+    fn test_session_ticket() {
+        let ticket = SessionTicket::generate();
+        assert_ne!(ticket, SessionTicket::generate());
+        assert_eq!(ticket.digest(), SessionTicket::from(ticket.as_str().to_string()).digest());
+
+        let config: ResumptionConfig = toml::from_str("grace_seconds = 120").unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.grace_period(), Duration::from_secs(120));
+    }
+}
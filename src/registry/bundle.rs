@@ -0,0 +1,242 @@
+// Registryのエクスポート・インポート用バンドル
+// トンネル定義・セッション統計・監査ログを他のホストへ移したり、ディスク障害後に復元したりするための可搬形式（JSON/TOML）
+
+use crate::registry::models::{AuditLogEntry, SessionEntry, TunnelConfig};
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// バンドル形式のバージョン（互換性のない変更をしたら上げる）
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryBundle {
+    pub format_version: u32,
+    // エクスポート元のスキーマバージョン（これより古いConduitではインポートしない）
+    pub schema_version: i64,
+    pub exported_at: i64,
+    // 設定を暗号化した宛先の公開鍵（Ed25519、base64）。無ければ設定は平文
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    #[serde(default)]
+    pub tunnels: Vec<BundleTunnel>,
+    #[serde(default)]
+    pub sessions: Vec<SessionEntry>,
+    #[serde(default)]
+    pub audit_log: Vec<AuditLogEntry>,
+    // checksum自身を空にしたバンドル全体（JSON）のSHA-256（base64）
+    // セッション・監査ログを含むどの部分の書き換えや破損もインポート時に検出する
+    #[serde(default)]
+    pub checksum: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleTunnel {
+    pub id: String,
+    pub name: String,
+    pub status: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub restart_count: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_exit_code: Option<i32>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub last_activity: i64,
+    // 復号した設定のチェックサム（TunnelEntry::checksum_config）
+    pub config_checksum: String,
+    // recipientが無い場合の平文の設定
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<TunnelConfig>,
+    // recipient宛てに暗号化した設定（base64）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_sealed: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleFormat {
+    Json,
+    Toml,
+}
+
+impl BundleFormat {
+    // 拡張子から形式を決める（.toml以外はJSON）
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => BundleFormat::Toml,
+            _ => BundleFormat::Json,
+        }
+    }
+}
+
+impl std::str::FromStr for BundleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(BundleFormat::Json),
+            "toml" => Ok(BundleFormat::Toml),
+            _ => Err(format!("Unknown bundle format '{}' (expected json or toml)", s)),
+        }
+    }
+}
+
+// インポート結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub imported_tunnels: Vec<String>,
+    // 同じIDのトンネルが既にあるため取り込まなかったもの
+    pub skipped_tunnels: Vec<String>,
+    pub imported_sessions: usize,
+    pub restored_audit_entries: usize,
+    // インポート先に監査ログがあり、チェーンを混ぜないため復元しなかった件数
+    pub skipped_audit_entries: usize,
+}
+
+impl RegistryBundle {
+    pub fn compute_checksum(&self) -> Result<String> {
+        use ring::digest::{digest, SHA256};
+        let mut unsealed = self.clone();
+        unsealed.checksum.clear();
+        let json = serde_json::to_vec(&unsealed).context("Failed to serialize bundle")?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(digest(&SHA256, &json).as_ref()))
+    }
+
+    // エクスポートの最後に呼び、以降は内容を変更しない
+    pub fn update_checksum(&mut self) -> Result<()> {
+        self.checksum = self.compute_checksum()?;
+        Ok(())
+    }
+
+    pub fn verify_checksum(&self) -> Result<()> {
+        if self.checksum.is_empty() {
+            bail!("Bundle has no checksum");
+        }
+        if self.compute_checksum()? != self.checksum {
+            bail!("Bundle checksum mismatch (the bundle was modified or corrupted)");
+        }
+        Ok(())
+    }
+
+    pub fn serialize_as(&self, format: BundleFormat) -> Result<String> {
+        match format {
+            BundleFormat::Json => serde_json::to_string_pretty(self)
+                .context("Failed to serialize bundle as JSON"),
+            BundleFormat::Toml => toml::to_string_pretty(self)
+                .context("Failed to serialize bundle as TOML"),
+        }
+    }
+
+    pub fn parse(text: &str, format: BundleFormat) -> Result<Self> {
+        let bundle: Self = match format {
+            BundleFormat::Json => serde_json::from_str(text).context("Failed to parse JSON bundle")?,
+            BundleFormat::Toml => toml::from_str(text).context("Failed to parse TOML bundle")?,
+        };
+        if bundle.format_version != BUNDLE_FORMAT_VERSION {
+            bail!(
+                "Unsupported bundle format version {} (this build supports {})",
+                bundle.format_version, BUNDLE_FORMAT_VERSION
+            );
+        }
+        Ok(bundle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::models::{RestartPolicy, DEFAULT_UNHEALTHY_THRESHOLD};
+
+    fn sample_bundle() -> RegistryBundle {
+        RegistryBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            schema_version: 4,
+            exported_at: 1_700_000_000,
+            recipient: None,
+            tunnels: vec![BundleTunnel {
+                id: "tunnel-1".to_string(),
+                name: "web".to_string(),
+                status: 5,
+                exit_code: Some(0),
+                restart_count: 1,
+                last_exit_code: None,
+                created_at: 1_700_000_000,
+                updated_at: 1_700_000_100,
+                last_activity: 1_700_000_100,
+                config_checksum: "checksum".to_string(),
+                config: Some(TunnelConfig {
+                    router_addr: "10.2.0.1:9999".to_string(),
+                    source_addr: "10.2.0.2:8080".to_string(),
+                    bind_addr: "0.0.0.0:80".to_string(),
+                    protocol: "tcp".to_string(),
+                    timeout_seconds: 30,
+                    max_connections: 100,
+                    restart_policy: RestartPolicy::OnFailure { max_retries: Some(3) },
+                    restart_on_unhealthy: false,
+                    unhealthy_threshold: DEFAULT_UNHEALTHY_THRESHOLD,
+                    compression: None,
+                    webhooks: Vec::new(),
+                    retry: Default::default(),
+                }),
+                config_sealed: None,
+            }],
+            sessions: vec![SessionEntry {
+                id: "session-1".to_string(),
+                tunnel_id: "tunnel-1".to_string(),
+                started_at: 1_700_000_000,
+                ended_at: None,
+                total_connections: 3,
+                total_bytes_sent: 1024,
+                total_bytes_received: 2048,
+                avg_latency_ms: 1.5,
+                error_count: 0,
+            }],
+            audit_log: Vec::new(),
+            checksum: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let bundle = sample_bundle();
+        for format in [BundleFormat::Json, BundleFormat::Toml] {
+            let text = bundle.serialize_as(format).unwrap();
+            let parsed = RegistryBundle::parse(&text, format).unwrap();
+            assert_eq!(parsed.tunnels.len(), 1);
+            assert_eq!(parsed.tunnels[0].config.as_ref().unwrap().restart_policy, RestartPolicy::OnFailure { max_retries: Some(3) });
+            assert_eq!(parsed.sessions[0].ended_at, None);
+        }
+
+        assert_eq!(BundleFormat::from_path(Path::new("backup.TOML")), BundleFormat::Toml);
+        assert_eq!(BundleFormat::from_path(Path::new("backup.json")), BundleFormat::Json);
+
+        let mut future = bundle;
+        future.format_version = BUNDLE_FORMAT_VERSION + 1;
+        let text = future.serialize_as(BundleFormat::Json).unwrap();
+        assert!(RegistryBundle::parse(&text, BundleFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_bundle_checksum() {
+        let mut bundle = sample_bundle();
+        assert!(bundle.verify_checksum().is_err());
+        bundle.update_checksum().unwrap();
+
+        // 形式を変えて書き出しても同じ内容として検証できる
+        for format in [BundleFormat::Json, BundleFormat::Toml] {
+            let text = bundle.serialize_as(format).unwrap();
+            RegistryBundle::parse(&text, format).unwrap().verify_checksum().unwrap();
+        }
+
+        let mut tampered = bundle.clone();
+        tampered.sessions[0].total_bytes_sent = 0;
+        assert!(tampered.verify_checksum().is_err());
+
+        let mut tampered = bundle;
+        tampered.audit_log.clear();
+        tampered.sessions.clear();
+        assert!(tampered.verify_checksum().is_err());
+    }
+}
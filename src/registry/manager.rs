@@ -1,21 +1,44 @@
 // プロセス管理・監視機能
 // 軽量Tunnel Processの起動・監視・クリーンアップ
 
+use crate::common::{logging, paths};
+use crate::ipc::client::UdsGrpcClient;
+use crate::notifier::{Alert, AlertKind, Notifier};
 use crate::registry::{models::*, sqlite::SqliteRegistry};
 use anyhow::{Context, Result};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
+// 再起動バックオフ（1s, 2s, 4s ... 最大60s）
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+// この時間以上動作していればクラッシュループではないとみなし、バックオフを初期値に戻す
+const RESTART_STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+// 生存確認1回あたりの期限（接続とStatus応答の合計）
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Shutdown応答後・各シグナル送信後にプロセスの終了を待つ時間
+const STOP_EXIT_GRACE: Duration = Duration::from_secs(5);
+// Shutdown RPCは接続の終了待ちの分だけ応答が遅れるため、期限にこの余裕を加えて待つ
+const SHUTDOWN_RPC_MARGIN: Duration = Duration::from_secs(5);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Tunnel Processへ渡すWebhook設定（JSON）。URLやヘッダーに認証情報を含むため引数には載せない
+pub const WEBHOOKS_ENV: &str = "CONDUIT_WEBHOOKS";
+
 // プロセス管理構造体
 pub struct ProcessManager {
     registry: Arc<SqliteRegistry>,
     running_processes: Arc<RwLock<HashMap<String, ProcessInfo>>>,
+    // 監視タスク起動後に設定されるため共有ロック越しに参照する
+    notifier: Arc<RwLock<Option<Arc<Notifier>>>>,
     cleanup_interval: Duration,
     health_check_interval: Duration,
 }
@@ -24,11 +47,26 @@ pub struct ProcessManager {
 #[derive(Debug, Clone)]
 struct ProcessInfo {
     tunnel_id: String,
+    name: String,
+    // 再起動時に同じ設定で起動し直すため保持する
+    config: TunnelConfig,
     pid: u32,
+    // 自身が起動したプロセスのみ保持（終了コードの取得とゾンビの回収に使う）
+    child: Option<Arc<Mutex<Child>>>,
     socket_path: PathBuf,
     started_at: Instant,
     last_health_check: Instant,
     restart_count: u32,
+    // 短時間で終了し続けている回数（バックオフ計算用）
+    consecutive_failures: u32,
+    last_exit_code: Option<i32>,
+    // 終了を検出し、バックオフ待機中
+    restart_pending: bool,
+    health: HealthStatus,
+    // 生存確認の連続失敗回数
+    health_failures: u32,
+    // unhealthyのため強制終了した（再起動ポリシーに関わらず起動し直す）
+    force_restart: bool,
 }
 
 impl ProcessManager {
@@ -37,11 +75,17 @@ impl ProcessManager {
         Self {
             registry,
             running_processes: Arc::new(RwLock::new(HashMap::new())),
+            notifier: Arc::new(RwLock::new(None)),
             cleanup_interval: Duration::from_secs(30),
             health_check_interval: Duration::from_secs(10),
         }
     }
 
+    // 予期しない終了を通知するNotifierの設定
+    pub async fn set_notifier(&self, notifier: Arc<Notifier>) {
+        *self.notifier.write().await = Some(notifier);
+    }
+
     // 軽量Tunnel Processの起動
     pub async fn start_tunnel_process(
         &self,
@@ -54,59 +98,37 @@ impl ProcessManager {
         // ソケットパスの準備
         let socket_path = self.prepare_socket_path(&tunnel_id).await?;
         
-        // コマンドライン引数の構築
-        let mut cmd = Command::new(std::env::current_exe()?);
-        cmd.args(&[
-            "internal-tunnel-process",
-            "--id", &tunnel_id,
-            "--name", &name,
-            "--router", &config.router_addr,
-            "--source", &config.source_addr,
-            "--bind", &config.bind_addr,
-            "--socket", &socket_path.to_string_lossy(),
-            "--protocol", &config.protocol,
-            "--timeout", &config.timeout_seconds.to_string(),
-            "--max-connections", &config.max_connections.to_string(),
-        ]);
-
-        // プロセス起動設定（conmonパターン）
-        cmd.stdin(Stdio::null())
-           .stdout(Stdio::piped())
-           .stderr(Stdio::piped());
-
-        // 環境変数設定
-        cmd.env("CONDUIT_TUNNEL_ID", &tunnel_id);
-        cmd.env("CONDUIT_SOCKET_PATH", &socket_path);
-
-        // プロセス起動
-        let child = cmd.spawn()
-            .context("Failed to spawn tunnel process")?;
-
+        let child = Self::spawn_process(&tunnel_id, &name, config, &socket_path)?;
         let pid = child.id();
         debug!("Spawned tunnel process with PID: {}", pid);
 
         // レジストリにトンネル情報を登録
         self.registry.create_tunnel(
             tunnel_id.clone(),
-            name,
+            name.clone(),
             pid as i32,
             &socket_path.to_string_lossy(),
             config,
         ).await?;
 
         // プロセス情報を管理対象に追加
-        let pid = child.id();
-        
-        // プロセスをデタッチして独立実行させる
-        std::mem::forget(child);
-        
+        // Childをdropしてもプロセスは終了しないため、デタッチしたまま終了コードの取得用に保持する
         let process_info = ProcessInfo {
             tunnel_id: tunnel_id.clone(),
+            name,
+            config: config.clone(),
             pid,
+            child: Some(Arc::new(Mutex::new(child))),
             socket_path,
             started_at: Instant::now(),
             last_health_check: Instant::now(),
             restart_count: 0,
+            consecutive_failures: 0,
+            last_exit_code: None,
+            restart_pending: false,
+            health: HealthStatus::Unknown,
+            health_failures: 0,
+            force_restart: false,
         };
 
         self.running_processes.write().await.insert(tunnel_id, process_info);
@@ -114,46 +136,176 @@ impl ProcessManager {
         Ok(pid)
     }
 
-    // プロセス停止
-    pub async fn stop_tunnel_process(&self, tunnel_id: &str, force: bool) -> Result<bool> {
-        info!("Stopping tunnel process: {} (force: {})", tunnel_id, force);
+    // Tunnel Processの起動（初回起動と再起動で共通）
+    fn spawn_process(
+        tunnel_id: &str,
+        name: &str,
+        config: &TunnelConfig,
+        socket_path: &Path,
+    ) -> Result<Child> {
+        // コマンドライン引数の構築
+        let mut cmd = Command::new(std::env::current_exe()?);
+        cmd.args([
+            "internal-tunnel-process",
+            "--id", tunnel_id,
+            "--name", name,
+            "--router", &config.router_addr,
+            "--source", &config.source_addr,
+            "--bind", &config.bind_addr,
+            "--socket", &socket_path.to_string_lossy(),
+            "--protocol", &config.protocol,
+            "--timeout", &config.timeout_seconds.to_string(),
+            "--max-connections", &config.max_connections.to_string(),
+            "--retry", &serde_json::to_string(&config.retry)?,
+        ]);
+        if let Some(compression) = &config.compression {
+            cmd.args(["--compression", compression.algorithm.as_str()]);
+            if let Some(level) = compression.level {
+                cmd.args(["--compression-level", &level.to_string()]);
+            }
+        }
 
-        let process_info = {
-            let processes = self.running_processes.read().await;
-            processes.get(tunnel_id).cloned()
+        // プロセス起動設定（conmonパターン）
+        // 読み手のいないパイプだとバッファが埋まって書き込みが詰まるため、トンネルごとのログファイルへ向ける
+        let log_path = logging::tunnel_log_path(name);
+        let log_file = logging::open_tunnel_log(&log_path)
+            .with_context(|| format!("Failed to open tunnel log file: {}", log_path.display()))?;
+        cmd.stdin(Stdio::null())
+           .stdout(Stdio::from(log_file.try_clone()?))
+           .stderr(Stdio::from(log_file));
+
+        // 環境変数設定
+        cmd.env("CONDUIT_TUNNEL_ID", tunnel_id);
+        cmd.env("CONDUIT_SOCKET_PATH", socket_path);
+        cmd.env(paths::SCOPE_ENV, paths::scope().as_str());
+        if !config.webhooks.is_empty() {
+            cmd.env(WEBHOOKS_ENV, serde_json::to_string(&config.webhooks)?);
+        }
+
+        // プロセス起動
+        cmd.spawn().context("Failed to spawn tunnel process")
+    }
+
+    // プロセス停止
+    // 受け付けを止めて既存接続の終了を待つようUDSで依頼し、終了しなければSIGTERM、SIGKILLの順に強める
+    pub async fn stop_tunnel_process(&self, tunnel_id: &str, force: bool, timeout: Duration) -> Result<Option<StopOutcome>> {
+        info!("Stopping tunnel process: {} (force: {}, timeout: {:?})", tunnel_id, force, timeout);
+
+        // 監視タスクが終了を検出して再起動しないよう、先に管理対象から外す
+        let process_info = self.running_processes.write().await.remove(tunnel_id);
+
+        let (pid, child, socket_path) = match process_info {
+            Some(info) if info.restart_pending => {
+                // 既に終了して再起動待ちなので、待機中の再起動を取り消すだけでよい
+                self.registry.set_restart_pending(tunnel_id, false).await?;
+                self.registry.update_tunnel_status(
+                    tunnel_id,
+                    TunnelStatus::Exited,
+                    Some(info.last_exit_code.unwrap_or(0)),
+                ).await?;
+                let _ = crate::ipc::cleanup_socket_file(&info.socket_path).await;
+                info!("Cancelled pending restart of tunnel process {}", tunnel_id);
+                return Ok(Some(StopOutcome::default()));
+            }
+            Some(info) => (info.pid, info.child, info.socket_path),
+            // 別のCLIプロセスが起動したトンネルはRegistryに記録されたPIDとソケットで停止する
+            // 監視しているCLIプロセスが再起動を待っていれば、それも取り消す
+            None => match (self.registry.set_restart_pending(tunnel_id, false).await?, self.registry.get_tunnel(tunnel_id).await?) {
+                (_, Some(TunnelInfo {
+                    pid: Some(pid),
+                    socket_path,
+                    status: TunnelStatus::Created | TunnelStatus::Running | TunnelStatus::Stopping,
+                    ..
+                })) => (pid, None, socket_path),
+                (true, Some(_)) => {
+                    info!("Cancelled pending restart of tunnel process {}", tunnel_id);
+                    return Ok(Some(StopOutcome::default()));
+                }
+                _ => {
+                    warn!("Tunnel process {} not found in running processes", tunnel_id);
+                    return Ok(None);
+                }
+            },
         };
 
-        if let Some(info) = process_info {
-            // レジストリ状態を停止中に更新
-            self.registry.update_tunnel_status(
-                tunnel_id,
-                TunnelStatus::Stopping,
-                None,
-            ).await?;
+        // レジストリ状態を停止中に更新
+        self.registry.update_tunnel_status(
+            tunnel_id,
+            TunnelStatus::Stopping,
+            None,
+        ).await?;
 
-            // プロセス終了シグナル送信
-            let success = Self::kill_process(info.pid, force).await;
-            
-            let exit_code = if success { 0 } else { -1 };
-            
-            // レジストリ状態を終了に更新
-            self.registry.update_tunnel_status(
-                tunnel_id,
-                if success { TunnelStatus::Exited } else { TunnelStatus::Error },
-                Some(exit_code),
-            ).await?;
-
-            // プロセス情報を削除
-            self.running_processes.write().await.remove(tunnel_id);
-            
-            // ソケットファイルのクリーンアップ
-            let _ = tokio::fs::remove_file(&info.socket_path).await;
-
-            info!("Tunnel process {} stopped with exit code: {}", tunnel_id, exit_code);
-            Ok(true)
-        } else {
-            warn!("Tunnel process {} not found in running processes", tunnel_id);
-            Ok(false)
+        let mut outcome = StopOutcome::default();
+        let mut exit_code = None;
+
+        if !force {
+            match Self::request_shutdown(&socket_path, timeout).await {
+                Ok((drained, closed)) => {
+                    outcome.drained = Some(drained);
+                    outcome.closed = Some(closed);
+                    exit_code = Self::wait_for_exit(child.as_ref(), pid, STOP_EXIT_GRACE).await;
+                }
+                // UDSが応答しない場合はシグナルでの停止に切り替える
+                Err(e) => warn!("Graceful shutdown of tunnel {} failed: {}", tunnel_id, e),
+            }
+        }
+
+        // 強制停止でなければSIGTERMで終了処理の機会を与えてからSIGKILLする
+        let signals: &[StopSignal] = if force { &[StopSignal::Kill] } else { &[StopSignal::Term, StopSignal::Kill] };
+        for &signal in signals {
+            if exit_code.is_some() {
+                break;
+            }
+            warn!("Tunnel process {} still running, sending {}", tunnel_id, signal);
+            Self::kill_process(pid, signal == StopSignal::Kill).await;
+            outcome.escalated = Some(signal);
+            exit_code = Self::wait_for_exit(child.as_ref(), pid, STOP_EXIT_GRACE).await;
+        }
+
+        // レジストリ状態を終了に更新
+        let stopped = exit_code.is_some();
+        let exit_code = exit_code.unwrap_or(-1);
+        self.registry.update_tunnel_status(
+            tunnel_id,
+            if stopped { TunnelStatus::Exited } else { TunnelStatus::Error },
+            Some(exit_code),
+        ).await?;
+
+        // ソケットファイルのクリーンアップ
+        let _ = crate::ipc::cleanup_socket_file(&socket_path).await;
+
+        if !stopped {
+            anyhow::bail!("Tunnel process {} (PID: {}) did not exit after SIGKILL", tunnel_id, pid);
+        }
+        info!("Tunnel process {} stopped with exit code: {}", tunnel_id, exit_code);
+        Ok(Some(outcome))
+    }
+
+    // UDS経由の停止要求。戻り値は（期限内に終了した接続数, 切断した接続数）
+    async fn request_shutdown(socket_path: &Path, timeout: Duration) -> Result<(u32, u32)> {
+        let request = async {
+            let mut client = UdsGrpcClient::connect_with_timeout(socket_path, HEALTH_CHECK_TIMEOUT).await?;
+            client.shutdown(false, timeout.as_secs() as i32).await
+        };
+        let response = tokio::time::timeout(timeout + SHUTDOWN_RPC_MARGIN, request).await
+            .context("Shutdown request timed out")??;
+        if !response.success {
+            anyhow::bail!("{}", response.message);
+        }
+        Ok((response.drained_connections.max(0) as u32, response.closed_connections.max(0) as u32))
+    }
+
+    // 期限までプロセスの終了を待つ。終了していれば終了コード（自身の子でなければ0）
+    async fn wait_for_exit(child: Option<&Arc<Mutex<Child>>>, pid: u32, timeout: Duration) -> Option<i32> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(exit_code) = Self::exit_status_of(child, pid) {
+                return Some(if child.is_some() { exit_code } else { 0 });
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            tokio::time::sleep(STOP_POLL_INTERVAL).await;
         }
     }
 
@@ -183,17 +335,28 @@ impl ProcessManager {
     }
 
     // 全プロセス停止
-    pub async fn stop_all_processes(&self, force: bool) -> Result<Vec<String>> {
-        let tunnel_ids: Vec<String> = {
+    // 接続の終了待ちがトンネル数だけ積み重ならないよう並列に停止する
+    pub async fn stop_all_processes(&self, force: bool, timeout: Duration) -> Result<Vec<(String, StopOutcome)>> {
+        let mut tunnel_ids: Vec<String> = {
             let processes = self.running_processes.read().await;
             processes.keys().cloned().collect()
         };
+        // 別のCLIプロセスが起動したトンネルも対象にする
+        for tunnel in self.registry.list_active_tunnels().await? {
+            if !tunnel_ids.contains(&tunnel.id) {
+                tunnel_ids.push(tunnel.id);
+            }
+        }
+
+        let results = futures::future::join_all(
+            tunnel_ids.iter().map(|tunnel_id| self.stop_tunnel_process(tunnel_id, force, timeout))
+        ).await;
 
         let mut stopped = Vec::new();
-        for tunnel_id in tunnel_ids {
-            match self.stop_tunnel_process(&tunnel_id, force).await {
-                Ok(true) => stopped.push(tunnel_id),
-                Ok(false) => {
+        for (tunnel_id, result) in tunnel_ids.into_iter().zip(results) {
+            match result {
+                Ok(Some(outcome)) => stopped.push((tunnel_id, outcome)),
+                Ok(None) => {
                     warn!("Failed to stop process: {}", tunnel_id);
                 }
                 Err(e) => {
@@ -209,6 +372,7 @@ impl ProcessManager {
     pub async fn start_monitoring(&self) -> Result<()> {
         let registry = Arc::clone(&self.registry);
         let processes = Arc::clone(&self.running_processes);
+        let notifier = Arc::clone(&self.notifier);
         let cleanup_interval = self.cleanup_interval;
         let health_check_interval = self.health_check_interval;
 
@@ -218,7 +382,7 @@ impl ProcessManager {
             loop {
                 cleanup_timer.tick().await;
                 
-                if let Err(e) = Self::cleanup_dead_processes(&registry, &processes).await {
+                if let Err(e) = Self::cleanup_dead_processes(&registry, &processes, &notifier).await {
                     error!("Error during process cleanup: {}", e);
                 }
             }
@@ -227,12 +391,13 @@ impl ProcessManager {
         // ヘルスチェックタスク
         let registry_health = Arc::clone(&self.registry);
         let processes_health = Arc::clone(&self.running_processes);
+        let notifier_health = Arc::clone(&self.notifier);
         tokio::spawn(async move {
             let mut health_timer = interval(health_check_interval);
             loop {
                 health_timer.tick().await;
                 
-                if let Err(e) = Self::health_check_processes(&registry_health, &processes_health).await {
+                if let Err(e) = Self::health_check_processes(&registry_health, &processes_health, &notifier_health).await {
                     error!("Error during health check: {}", e);
                 }
             }
@@ -244,107 +409,308 @@ impl ProcessManager {
 
     // デッドプロセスのクリーンアップ
     async fn cleanup_dead_processes(
-        registry: &SqliteRegistry,
-        processes: &RwLock<HashMap<String, ProcessInfo>>,
+        registry: &Arc<SqliteRegistry>,
+        processes: &Arc<RwLock<HashMap<String, ProcessInfo>>>,
+        notifier: &RwLock<Option<Arc<Notifier>>>,
     ) -> Result<()> {
         // データベースから外部終了したプロセスをクリーンアップ
-        let cleaned_ids = registry.cleanup_dead_processes().await?;
+        // 自身の子プロセスは下の再起動判定で終了コードとともに扱うため対象から外す
+        let tracked: HashSet<String> = processes.read().await.keys().cloned().collect();
+        let cleaned_ids = registry.cleanup_dead_processes(&tracked).await?;
         
-        // メモリ内のプロセス情報もクリーンアップ
-        if !cleaned_ids.is_empty() {
-            let mut processes_guard = processes.write().await;
+        // 明示的な停止（down/kill）は利用者の操作なので通知せず、ここで検出した終了のみ通知する
+        let notifier = notifier.read().await.clone();
+        if let Some(ref notifier) = notifier {
             for id in &cleaned_ids {
-                if let Some(info) = processes_guard.remove(id) {
-                    // ソケットファイルのクリーンアップ
-                    let _ = tokio::fs::remove_file(&info.socket_path).await;
-                    debug!("Cleaned up process info for: {}", id);
-                }
+                notifier.notify(Alert::new(AlertKind::TunnelExited, id, "Tunnel process exited unexpectedly"));
             }
         }
-
-        // 実行中プロセスの生存確認
+        
+        // 実行中プロセスの生存確認（再起動待ちのものは既に検出済み）
         let mut dead_processes = Vec::new();
         {
             let processes_guard = processes.read().await;
             
             for (tunnel_id, info) in processes_guard.iter() {
-                if !Self::process_exists(info.pid) {
-                    dead_processes.push(tunnel_id.clone());
+                if info.restart_pending {
+                    continue;
+                }
+                if let Some(exit_code) = Self::exit_status(info) {
+                    dead_processes.push((tunnel_id.clone(), exit_code));
                 }
             }
         }
 
-        // デッドプロセスのレジストリ状態更新とクリーンアップ
-        if !dead_processes.is_empty() {
+        // デッドプロセスのレジストリ状態更新と再起動ポリシーの適用
+        for (tunnel_id, exit_code) in dead_processes {
+            // 別のCLIプロセスからdown/killで停止されたものは、停止側が状態を記録するので再起動も通知もしない
+            if let Ok(Some(tunnel)) = registry.get_tunnel(&tunnel_id).await {
+                if matches!(tunnel.status, TunnelStatus::Stopping | TunnelStatus::Exited) {
+                    if processes.write().await.remove(&tunnel_id).is_some() {
+                        info!("Tunnel process {} was stopped by another command", tunnel_id);
+                    }
+                    continue;
+                }
+            }
+
             let mut processes_guard = processes.write().await;
-            for tunnel_id in dead_processes {
-                let status = TunnelStatus::Exited;
-                if let Err(e) = registry.update_tunnel_status(&tunnel_id, status, Some(-1)).await {
-                    error!("Failed to update status for dead process {}: {}", tunnel_id, e);
-                } else {
-                    info!("Cleaned up dead process: {}", tunnel_id);
+            let Some(info) = processes_guard.get_mut(&tunnel_id) else {
+                // 待機中にdown/killで停止された
+                continue;
+            };
+            info.last_exit_code = Some(exit_code);
+
+            let force_restart = std::mem::take(&mut info.force_restart);
+            let restart = force_restart || info.config.restart_policy.should_restart(exit_code, info.restart_count);
+            // 終了を記録する前に再起動待ちを記録し、別のCLIプロセスのdownがいつ来ても取り消せるようにする
+            if restart {
+                if let Err(e) = registry.set_restart_pending(&tunnel_id, true).await {
+                    error!("Failed to record pending restart of {}: {}", tunnel_id, e);
                 }
-                processes_guard.remove(&tunnel_id);
             }
+            if let Err(e) = registry.update_tunnel_status(&tunnel_id, TunnelStatus::Exited, Some(exit_code)).await {
+                error!("Failed to update status for dead process {}: {}", tunnel_id, e);
+            }
+
+            if !restart {
+                info!("Tunnel process {} exited with code {} (restart policy: {})",
+                    tunnel_id, exit_code, info.config.restart_policy);
+                if let Some(ref notifier) = notifier {
+                    notifier.notify(Alert::new(
+                        AlertKind::TunnelExited,
+                        &tunnel_id,
+                        format!("Tunnel process exited unexpectedly with code {}", exit_code),
+                    ));
+                }
+                if let Some(info) = processes_guard.remove(&tunnel_id) {
+                    let _ = crate::ipc::cleanup_socket_file(&info.socket_path).await;
+                }
+                continue;
+            }
+
+            // 起動直後に落ち続ける場合のみ待ち時間を延ばす
+            info.consecutive_failures = if info.started_at.elapsed() >= RESTART_STABLE_UPTIME {
+                0
+            } else {
+                info.consecutive_failures + 1
+            };
+            info.restart_pending = true;
+            let delay = restart_backoff(info.consecutive_failures);
+
+            warn!("Tunnel process {} exited with code {}; restarting in {:?} (restart #{})",
+                tunnel_id, exit_code, delay, info.restart_count + 1);
+            if let Some(ref notifier) = notifier {
+                notifier.notify(Alert::new(
+                    AlertKind::TunnelExited,
+                    &tunnel_id,
+                    format!("Tunnel process exited with code {}; restarting in {}s", exit_code, delay.as_secs()),
+                ));
+            }
+
+            let registry = Arc::clone(registry);
+            let processes = Arc::clone(processes);
+            let notifier = notifier.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                Self::restart_process(&registry, &processes, notifier.as_deref(), &tunnel_id).await;
+            });
         }
 
         Ok(())
     }
 
+    // バックオフ後の再起動。待機中に停止された場合は何もしない
+    async fn restart_process(
+        registry: &SqliteRegistry,
+        processes: &RwLock<HashMap<String, ProcessInfo>>,
+        notifier: Option<&Notifier>,
+        tunnel_id: &str,
+    ) {
+        // 停止操作との競合を避けるため、起動から管理情報の更新までロックを保持する
+        let (pid, restart_count, last_exit_code) = {
+            let mut processes_guard = processes.write().await;
+            let Some(info) = processes_guard.get_mut(tunnel_id).filter(|info| info.restart_pending) else {
+                debug!("Restart of {} cancelled", tunnel_id);
+                return;
+            };
+            // 別のCLIプロセスからのdownはRegistryの再起動待ちを取り消す
+            match registry.set_restart_pending(tunnel_id, false).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    info!("Pending restart of tunnel process {} was cancelled by another command", tunnel_id);
+                    if let Some(info) = processes_guard.remove(tunnel_id) {
+                        let _ = crate::ipc::cleanup_socket_file(&info.socket_path).await;
+                    }
+                    return;
+                }
+                Err(e) => error!("Failed to read pending restart of {}: {}", tunnel_id, e),
+            }
+            let last_exit_code = info.last_exit_code.unwrap_or(-1);
+
+            let _ = std::fs::remove_file(&info.socket_path);
+            match Self::spawn_process(&info.tunnel_id, &info.name, &info.config, &info.socket_path) {
+                Ok(child) => {
+                    info.pid = child.id();
+                    info.child = Some(Arc::new(Mutex::new(child)));
+                    info.started_at = Instant::now();
+                    info.restart_count += 1;
+                    info.restart_pending = false;
+                    info.health = HealthStatus::Unknown;
+                    info.health_failures = 0;
+                    (info.pid, info.restart_count, last_exit_code)
+                }
+                Err(e) => {
+                    error!("Failed to restart tunnel process {}: {}", tunnel_id, e);
+                    processes_guard.remove(tunnel_id);
+                    drop(processes_guard);
+
+                    if let Err(e) = registry.update_tunnel_status(tunnel_id, TunnelStatus::Error, Some(last_exit_code)).await {
+                        error!("Failed to update status for {}: {}", tunnel_id, e);
+                    }
+                    if let Some(notifier) = notifier {
+                        notifier.notify(Alert::new(
+                            AlertKind::TunnelExited,
+                            tunnel_id,
+                            format!("Failed to restart tunnel process: {}", e),
+                        ));
+                    }
+                    return;
+                }
+            }
+        };
+
+        if let Err(e) = registry.record_restart(tunnel_id, pid as i32, restart_count, last_exit_code).await {
+            error!("Failed to record restart of {}: {}", tunnel_id, e);
+        }
+        info!("Restarted tunnel process {} (PID: {}, restart #{})", tunnel_id, pid, restart_count);
+    }
+
+    // 終了していれば終了コードを返す
+    fn exit_status(info: &ProcessInfo) -> Option<i32> {
+        Self::exit_status_of(info.child.as_ref(), info.pid)
+    }
+
+    fn exit_status_of(child: Option<&Arc<Mutex<Child>>>, pid: u32) -> Option<i32> {
+        if let Some(child) = child {
+            if let Ok(mut child) = child.lock() {
+                match child.try_wait() {
+                    Ok(Some(status)) => return Some(exit_code_of(status)),
+                    Ok(None) => return None,
+                    Err(e) => debug!("Failed to wait for PID {}: {}", pid, e),
+                }
+            }
+        }
+
+        // 別のCLIプロセスが起動したものは終了コードを取得できない
+        (!Self::process_exists(pid)).then_some(-1)
+    }
+
     // プロセスのヘルスチェック
     async fn health_check_processes(
         registry: &SqliteRegistry,
         processes: &RwLock<HashMap<String, ProcessInfo>>,
+        notifier: &RwLock<Option<Arc<Notifier>>>,
     ) -> Result<()> {
-        let process_ids: Vec<String> = {
+        // 再起動待ちのプロセスはソケットが存在しないため対象外
+        let targets: Vec<(String, PathBuf)> = {
             let processes_guard = processes.read().await;
-            processes_guard.keys().cloned().collect()
+            processes_guard.values()
+                .filter(|info| !info.restart_pending)
+                .map(|info| (info.tunnel_id.clone(), info.socket_path.clone()))
+                .collect()
         };
 
-        for tunnel_id in process_ids {
-            // UDS接続の確認を通じたヘルスチェック
-            // 実際の実装では、各プロセスのgRPCエンドポイントに接続してpingを送信
-            if let Err(e) = Self::check_process_health(&tunnel_id).await {
-                warn!("Health check failed for process {}: {}", tunnel_id, e);
-                
-                // ヘルスチェック失敗時の処理（必要に応じてプロセス再起動）
-                // ここでは警告ログのみ
+        // 1つのハングしたプロセスが他の確認を遅らせないよう並列に行う
+        let results = futures::future::join_all(targets.into_iter().map(|(tunnel_id, socket_path)| async move {
+            let result = Self::check_process_health(&socket_path).await;
+            (tunnel_id, result)
+        })).await;
+
+        for (tunnel_id, result) in results {
+            let mut processes_guard = processes.write().await;
+            // 確認中に停止・終了したものは無視する
+            let Some(info) = processes_guard.get_mut(&tunnel_id).filter(|info| !info.restart_pending) else {
+                continue;
+            };
+            info.last_health_check = Instant::now();
+
+            let previous = info.health;
+            match result {
+                Ok(()) => {
+                    info.health_failures = 0;
+                    info.health = HealthStatus::Healthy;
+                }
+                Err(e) => {
+                    info.health_failures += 1;
+                    warn!("Health check failed for process {} ({}/{}): {}",
+                        tunnel_id, info.health_failures, info.config.unhealthy_threshold, e);
+                    if info.health_failures >= info.config.unhealthy_threshold {
+                        info.health = HealthStatus::Unhealthy;
+                    }
+                }
+            }
+            if info.health == previous {
+                continue;
+            }
+
+            let health = info.health;
+            let threshold = info.config.unhealthy_threshold;
+            let restart = health == HealthStatus::Unhealthy && info.config.restart_on_unhealthy;
+            if restart {
+                // 終了の検出と再起動はクリーンアップタスクに任せる
+                info.force_restart = true;
+                if !Self::kill_process(info.pid, true).await {
+                    warn!("Failed to kill unhealthy process {}", tunnel_id);
+                }
+            }
+            drop(processes_guard);
+
+            if let Err(e) = registry.update_tunnel_health(&tunnel_id, health).await {
+                error!("Failed to update health for {}: {}", tunnel_id, e);
+            }
+
+            if health == HealthStatus::Unhealthy {
+                let message = if restart {
+                    format!("Tunnel process did not respond to {} consecutive health checks; restarting", threshold)
+                } else {
+                    format!("Tunnel process did not respond to {} consecutive health checks", threshold)
+                };
+                error!("{}: {}", tunnel_id, message);
+                if let Some(notifier) = notifier.read().await.as_ref() {
+                    notifier.notify(Alert::new(AlertKind::TunnelUnhealthy, &tunnel_id, message));
+                }
+            } else {
+                info!("Tunnel process {} is {}", tunnel_id, health.as_str());
             }
         }
 
         Ok(())
     }
 
-    // 個別プロセスのヘルスチェック
-    async fn check_process_health(tunnel_id: &str) -> Result<()> {
-        // TODO: UDS gRPCクライアントを使用したヘルスチェック実装
-        // 現在は簡略化
-        debug!("Health check for process: {}", tunnel_id);
+    // 個別プロセスのヘルスチェック（TunnelControlのStatusに期限内に応答できるか）
+    async fn check_process_health(socket_path: &Path) -> Result<()> {
+        let check = async {
+            let mut client = UdsGrpcClient::connect(socket_path).await?;
+            client.get_status().await
+        };
+        tokio::time::timeout(HEALTH_CHECK_TIMEOUT, check)
+            .await
+            .context("Health check timed out")??;
         Ok(())
     }
 
     // ソケットパスの準備
+    // トンネルごとのディレクトリを所有者（システムスコープではconduitグループ）のみアクセス可能にして作成する
     async fn prepare_socket_path(&self, tunnel_id: &str) -> Result<PathBuf> {
-        let socket_dir = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".conduit")
-            .join("sockets");
+        let socket_path = self.registry.socket_path(tunnel_id)?;
+        let socket_dir = self.registry.sockets_dir();
+        let tunnel_dir = socket_path.parent().unwrap_or(socket_dir);
 
-        // ディレクトリ作成
-        tokio::fs::create_dir_all(&socket_dir).await
-            .context("Failed to create socket directory")?;
-
-        // Unix系でのディレクトリ権限設定
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let perms = std::fs::Permissions::from_mode(0o700);
-            std::fs::set_permissions(&socket_dir, perms)
-                .context("Failed to set socket directory permissions")?;
+        for dir in [socket_dir, tunnel_dir] {
+            paths::create_conduit_dir(dir)
+                .with_context(|| format!("Failed to create socket directory: {}", dir.display()))?;
         }
 
-        let socket_path = socket_dir.join(format!("{}.sock", tunnel_id));
-        
         // 既存のソケットファイルを削除
         if socket_path.exists() {
             tokio::fs::remove_file(&socket_path).await
@@ -372,6 +738,9 @@ impl ProcessManager {
                 pid: Some(info.pid),
                 uptime_seconds: uptime.as_secs(),
                 restart_count: info.restart_count,
+                restart_policy: info.config.restart_policy,
+                last_exit_code: info.last_exit_code,
+                health: info.health,
                 last_health_check: info.last_health_check.elapsed().as_secs(),
                 socket_path: info.socket_path.clone(),
             };
@@ -385,7 +754,14 @@ impl ProcessManager {
     pub fn process_exists(pid: u32) -> bool {
         #[cfg(unix)]
         {
-            std::path::Path::new(&format!("/proc/{}", pid)).exists()
+            // 監視中の親が回収するまで子はゾンビとして残るため、終了済みとして扱う
+            match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+                Ok(stat) => stat
+                    .rsplit_once(')')
+                    .and_then(|(_, rest)| rest.split_whitespace().next())
+                    .map_or(true, |state| state != "Z"),
+                Err(_) => false,
+            }
         }
 
         #[cfg(windows)]
@@ -403,6 +779,53 @@ impl ProcessManager {
     }
 }
 
+// 連続失敗回数に応じた再起動までの待ち時間
+fn restart_backoff(consecutive_failures: u32) -> Duration {
+    let exponent = consecutive_failures.saturating_sub(1).min(16);
+    RESTART_BACKOFF_BASE
+        .saturating_mul(1 << exponent)
+        .min(RESTART_BACKOFF_MAX)
+}
+
+// シグナルによる終了はシェルと同じく128+シグナル番号で表す
+fn exit_code_of(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(-1)
+}
+
+// 停止時に送ったシグナル
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopSignal {
+    Term,
+    Kill,
+}
+
+impl std::fmt::Display for StopSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Term => write!(f, "SIGTERM"),
+            Self::Kill => write!(f, "SIGKILL"),
+        }
+    }
+}
+
+// 停止結果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StopOutcome {
+    // 期限内に終了した接続数（UDSで停止できなかった場合はNone）
+    pub drained: Option<u32>,
+    // 期限切れで切断した接続数（UDSで停止できなかった場合はNone）
+    pub closed: Option<u32>,
+    // Shutdownで終了せずシグナルを送った場合、最後に送ったシグナル
+    pub escalated: Option<StopSignal>,
+}
+
 // プロセス統計情報
 #[derive(Debug, Clone)]
 pub struct ProcessStats {
@@ -410,6 +833,9 @@ pub struct ProcessStats {
     pub pid: Option<u32>,
     pub uptime_seconds: u64,
     pub restart_count: u32,
+    pub restart_policy: RestartPolicy,
+    pub last_exit_code: Option<i32>,
+    pub health: HealthStatus,
     pub last_health_check: u64,
     pub socket_path: PathBuf,
 }
@@ -437,6 +863,146 @@ mod tests {
         let manager = ProcessManager::new(registry);
 
         let socket_path = manager.prepare_socket_path("test-tunnel").await.unwrap();
-        assert!(socket_path.to_string_lossy().contains("test-tunnel.sock"));
+        assert_eq!(socket_path, temp_dir.path().join("sockets").join("test-tunnel").join("control.sock"));
+        assert!(socket_path.parent().unwrap().is_dir());
+        assert!(manager.prepare_socket_path("../escape").await.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_restart_backoff() {
+        assert_eq!(restart_backoff(0), Duration::from_secs(1));
+        assert_eq!(restart_backoff(1), Duration::from_secs(1));
+        assert_eq!(restart_backoff(3), Duration::from_secs(4));
+        assert_eq!(restart_backoff(10), RESTART_BACKOFF_MAX);
+        assert_eq!(restart_backoff(u32::MAX), RESTART_BACKOFF_MAX);
+    }
+
+    fn test_config() -> TunnelConfig {
+        TunnelConfig {
+            router_addr: "10.2.0.1:9999".to_string(),
+            source_addr: "10.2.0.2:8080".to_string(),
+            bind_addr: "0.0.0.0:80".to_string(),
+            protocol: "tcp".to_string(),
+            timeout_seconds: 30,
+            max_connections: 100,
+            restart_policy: RestartPolicy::Always,
+            restart_on_unhealthy: false,
+            unhealthy_threshold: DEFAULT_UNHEALTHY_THRESHOLD,
+            compression: None,
+            webhooks: Vec::new(),
+            retry: Default::default(),
+        }
+    }
+
+    fn test_process_info(child: Child, socket_path: PathBuf) -> ProcessInfo {
+        ProcessInfo {
+            tunnel_id: "t1".to_string(),
+            name: "web".to_string(),
+            config: test_config(),
+            pid: child.id(),
+            child: Some(Arc::new(Mutex::new(child))),
+            socket_path,
+            started_at: Instant::now(),
+            last_health_check: Instant::now(),
+            restart_count: 0,
+            consecutive_failures: 0,
+            last_exit_code: None,
+            restart_pending: false,
+            health: HealthStatus::Unknown,
+            health_failures: 0,
+            force_restart: false,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_status_of_child() {
+        let mut child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+        child.wait().unwrap();
+
+        let info = test_process_info(child, PathBuf::from("/tmp/t1.sock"));
+        assert_eq!(ProcessManager::exit_status(&info), Some(3));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_zombie_is_not_running() {
+        let mut child = Command::new("sh").args(["-c", "exit 0"]).spawn().unwrap();
+        let pid = child.id();
+        assert!(ProcessManager::process_exists(std::process::id()));
+
+        // 回収前のゾンビは別プロセスからの停止待ちで終了済みと判定されなければならない
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while ProcessManager::process_exists(pid) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!ProcessManager::process_exists(pid));
+        child.wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_escalates_to_sigterm_without_socket() {
+        let temp_dir = tempdir().unwrap();
+        let registry = Arc::new(SqliteRegistry::new(Some(temp_dir.path().join("test.db"))).await.unwrap());
+        let manager = ProcessManager::new(Arc::clone(&registry));
+
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        let socket_path = temp_dir.path().join("t1.sock");
+        registry.create_tunnel(
+            "t1".to_string(),
+            "web".to_string(),
+            child.id() as i32,
+            &socket_path.to_string_lossy(),
+            &test_config(),
+        ).await.unwrap();
+        manager.running_processes.write().await
+            .insert("t1".to_string(), test_process_info(child, socket_path));
+
+        // UDSが応答しないためSIGTERMで停止する
+        let outcome = manager.stop_tunnel_process("t1", false, Duration::ZERO).await.unwrap().unwrap();
+        assert_eq!(outcome, StopOutcome { drained: None, closed: None, escalated: Some(StopSignal::Term) });
+        assert!(manager.stop_tunnel_process("t1", false, Duration::ZERO).await.unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_down_from_another_command_cancels_pending_restart() {
+        let temp_dir = tempdir().unwrap();
+        let registry = Arc::new(SqliteRegistry::new(Some(temp_dir.path().join("test.db"))).await.unwrap());
+        let supervisor = ProcessManager::new(Arc::clone(&registry));
+
+        let mut child = Command::new("sh").args(["-c", "exit 1"]).spawn().unwrap();
+        let socket_path = temp_dir.path().join("t1.sock");
+        registry.create_tunnel(
+            "t1".to_string(),
+            "web".to_string(),
+            child.id() as i32,
+            &socket_path.to_string_lossy(),
+            &test_config(),
+        ).await.unwrap();
+        child.wait().unwrap();
+        supervisor.running_processes.write().await
+            .insert("t1".to_string(), test_process_info(child, socket_path));
+
+        // 終了を検出して再起動を待つ間、Registry上は終了として見える
+        ProcessManager::cleanup_dead_processes(&registry, &supervisor.running_processes, &supervisor.notifier).await.unwrap();
+        assert!(supervisor.running_processes.read().await["t1"].restart_pending);
+        assert_eq!(registry.get_tunnel("t1").await.unwrap().unwrap().status, TunnelStatus::Exited);
+
+        // 別のCLIプロセスからのdownは待機中の再起動を取り消す
+        let other = ProcessManager::new(Arc::clone(&registry));
+        assert!(other.stop_tunnel_process("t1", false, Duration::ZERO).await.unwrap().is_some());
+        assert!(other.stop_tunnel_process("t1", false, Duration::ZERO).await.unwrap().is_none());
+
+        ProcessManager::restart_process(&registry, &supervisor.running_processes, None, "t1").await;
+        assert!(!supervisor.running_processes.read().await.contains_key("t1"));
+        assert_eq!(registry.get_tunnel("t1").await.unwrap().unwrap().status, TunnelStatus::Exited);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_fails_without_socket() {
+        let result = ProcessManager::check_process_health(Path::new("/nonexistent/conduit-test.sock")).await;
+        assert!(result.is_err());
+    }
+}
@@ -1,19 +1,23 @@
 // Process Registry統合管理
 // SQLite Registry + プロセス管理の統合インターフェース
 
+pub mod bundle;
 pub mod models;
+pub mod schema;
 pub mod sqlite;
 pub mod manager;
 
+use crate::notifier::Notifier;
 use crate::registry::{
     models::*,
     sqlite::SqliteRegistry,
-    manager::{ProcessManager, ProcessStats},
+    manager::{ProcessManager, ProcessStats, StopOutcome},
 };
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, info};
 
 // Process Registry統合管理構造体
@@ -30,15 +34,23 @@ impl ProcessRegistry {
         let sqlite_registry = Arc::new(SqliteRegistry::new(db_path).await?);
         let process_manager = ProcessManager::new(Arc::clone(&sqlite_registry));
 
-        // プロセス監視の開始
-        process_manager.start_monitoring().await?;
-
         Ok(Self {
             sqlite_registry,
             process_manager,
         })
     }
 
+    // 終了検出・再起動・ヘルスチェックの開始
+    // 監視は起動したプロセスが生きている間だけ働くため、upなど常駐するプロセスからのみ呼ぶ
+    pub async fn supervise(&self) -> Result<()> {
+        self.process_manager.start_monitoring().await
+    }
+
+    // Tunnel Processの予期しない終了を通知するNotifierの設定
+    pub async fn set_notifier(&self, notifier: Arc<Notifier>) {
+        self.process_manager.set_notifier(notifier).await;
+    }
+
     // トンネルの作成と起動
     pub async fn create_and_start_tunnel(
         &self,
@@ -59,24 +71,24 @@ impl ProcessRegistry {
         Ok(pid)
     }
 
-    // トンネルの停止
-    pub async fn stop_tunnel(&self, tunnel_id: &str, force: bool) -> Result<bool> {
-        debug!("Stopping tunnel: {} (force: {})", tunnel_id, force);
+    // トンネルの停止（停止対象がなければNone）
+    pub async fn stop_tunnel(&self, tunnel_id: &str, force: bool, timeout: Duration) -> Result<Option<StopOutcome>> {
+        debug!("Stopping tunnel: {} (force: {}, timeout: {:?})", tunnel_id, force, timeout);
         
-        let stopped = self.process_manager.stop_tunnel_process(tunnel_id, force).await?;
+        let outcome = self.process_manager.stop_tunnel_process(tunnel_id, force, timeout).await?;
         
-        if stopped {
+        if outcome.is_some() {
             info!("Successfully stopped tunnel: {}", tunnel_id);
         }
         
-        Ok(stopped)
+        Ok(outcome)
     }
 
     // 全トンネル停止
-    pub async fn stop_all_tunnels(&self, force: bool) -> Result<Vec<String>> {
-        info!("Stopping all tunnels (force: {})", force);
+    pub async fn stop_all_tunnels(&self, force: bool, timeout: Duration) -> Result<Vec<(String, StopOutcome)>> {
+        info!("Stopping all tunnels (force: {}, timeout: {:?})", force, timeout);
         
-        let stopped = self.process_manager.stop_all_processes(force).await?;
+        let stopped = self.process_manager.stop_all_processes(force, timeout).await?;
         
         info!("Stopped {} tunnels", stopped.len());
         Ok(stopped)
@@ -116,7 +128,7 @@ impl ProcessRegistry {
         debug!("Deleting tunnel: {}", tunnel_id);
         
         // プロセス停止とレジストリ削除
-        let _ = self.stop_tunnel(tunnel_id, true).await;
+        let _ = self.stop_tunnel(tunnel_id, true, Duration::ZERO).await;
         let deleted = self.sqlite_registry.delete_tunnel(tunnel_id).await?;
         
         if deleted {
@@ -129,7 +141,29 @@ impl ProcessRegistry {
     // デッドプロセスのクリーンアップ
     pub async fn cleanup_dead_processes(&self) -> Result<Vec<String>> {
         debug!("Running dead process cleanup");
-        self.sqlite_registry.cleanup_dead_processes().await
+        self.sqlite_registry.cleanup_dead_processes(&HashSet::new()).await
+    }
+
+    // ソケットディレクトリとRegistryの整合性チェック
+    pub async fn check_socket_consistency(&self) -> Result<SocketConsistencyReport> {
+        debug!("Checking socket directory consistency");
+        self.sqlite_registry.check_socket_consistency().await
+    }
+
+    // 孤立ソケットの削除
+    pub async fn remove_orphaned_sockets(&self, report: &SocketConsistencyReport) -> Result<usize> {
+        self.sqlite_registry.remove_orphaned_sockets(report).await
+    }
+
+    // 監査ログの検索
+    pub async fn list_audit_entries(&self, query: &AuditQuery) -> Result<Vec<AuditLogEntry>> {
+        self.sqlite_registry.list_audit_entries(query).await
+    }
+
+    // 監査ログのハッシュチェーン検証
+    pub async fn verify_audit_chain(&self, anchor: Option<&str>) -> Result<AuditVerifyReport> {
+        debug!("Verifying audit log hash chain");
+        self.sqlite_registry.verify_audit_chain(anchor).await
     }
 
     // プロセス統計情報の取得
@@ -342,6 +376,12 @@ mod tests {
             protocol: "tcp".to_string(),
             timeout_seconds: 30,
             max_connections: 100,
+            restart_policy: Default::default(),
+            restart_on_unhealthy: false,
+            unhealthy_threshold: DEFAULT_UNHEALTHY_THRESHOLD,
+            compression: None,
+            webhooks: Vec::new(),
+            retry: Default::default(),
         };
 
         // NOTE: 実際のプロセス起動はテスト環境では困難なため、
@@ -1,9 +1,13 @@
 // Process Registry データモデル
 // Podmanライクな数値状態管理システム
 
+use crate::common::retry::RetryPolicy;
+use crate::metrics::LatencyHistogram;
+use crate::notifier::WebhookConfig;
+use crate::protocol::{CompressionSettings, CompressionStats};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use base64::Engine;
 
 // Podmanライクなトンネル状態（数値管理）
@@ -44,6 +48,35 @@ impl TunnelStatus {
     }
 }
 
+// 監視によるgRPC生存確認の結果（TEXTで保存）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    // まだ確認していない、または確認できない（別のCLIが起動したプロセスなど）
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+impl HealthStatus {
+    pub fn from_str_lossy(value: &str) -> Self {
+        match value {
+            "healthy" => Self::Healthy,
+            "unhealthy" => Self::Unhealthy,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Healthy => "healthy",
+            Self::Unhealthy => "unhealthy",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TunnelConfig {
     pub router_addr: String,     
@@ -52,6 +85,99 @@ pub struct TunnelConfig {
     pub protocol: String,        
     pub timeout_seconds: u32,    
     pub max_connections: u32,    
+    // 旧バージョンで登録された設定にはないためデフォルトで補う
+    #[serde(default)]
+    pub restart_policy: RestartPolicy,
+    // 生存確認に連続で失敗したら強制終了して起動し直す
+    #[serde(default)]
+    pub restart_on_unhealthy: bool,
+    // この回数連続で生存確認に失敗したらunhealthyとする
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+    // 未指定なら圧縮しない（Routerとのネゴシエーションで合意した場合のみ使われる）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionSettings>,
+    // Tunnel ProcessがRouter接続の異常を通知する先
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+    // Routerへの再接続のバックオフ
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+pub const DEFAULT_UNHEALTHY_THRESHOLD: u32 = 3;
+
+pub fn default_unhealthy_threshold() -> u32 {
+    DEFAULT_UNHEALTHY_THRESHOLD
+}
+
+// Podmanライクな再起動ポリシー（no, on-failure[:max], always）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RestartPolicy {
+    #[default]
+    No,
+    // max_retries未指定なら無制限
+    OnFailure { max_retries: Option<u32> },
+    Always,
+}
+
+impl RestartPolicy {
+    // 終了コードとこれまでの再起動回数から再起動するかを判定
+    pub fn should_restart(&self, exit_code: i32, restart_count: u32) -> bool {
+        match self {
+            Self::No => false,
+            Self::OnFailure { max_retries } => {
+                exit_code != 0 && !matches!(max_retries, Some(max) if restart_count >= *max)
+            }
+            Self::Always => true,
+        }
+    }
+}
+
+impl std::str::FromStr for RestartPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "no" => Ok(Self::No),
+            None if s == "always" => Ok(Self::Always),
+            None if s == "on-failure" => Ok(Self::OnFailure { max_retries: None }),
+            Some(("on-failure", max)) => max
+                .parse()
+                .map(|max| Self::OnFailure { max_retries: Some(max) })
+                .map_err(|_| format!("Invalid max retries in restart policy: {}", s)),
+            _ => Err(format!(
+                "Invalid restart policy '{}' (expected no, on-failure[:max] or always)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::No => write!(f, "no"),
+            Self::OnFailure { max_retries: None } => write!(f, "on-failure"),
+            Self::OnFailure { max_retries: Some(max) } => write!(f, "on-failure:{}", max),
+            Self::Always => write!(f, "always"),
+        }
+    }
+}
+
+impl TryFrom<String> for RestartPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RestartPolicy> for String {
+    fn from(policy: RestartPolicy) -> Self {
+        policy.to_string()
+    }
 }
 
 // SQLiteデータベースレコード構造体
@@ -68,6 +194,10 @@ pub struct TunnelEntry {
     pub updated_at: i64,               
     pub last_activity: i64,            
     pub exit_code: Option<i32>,        
+    pub restart_count: i64,
+    // 再起動後も直前の終了コードを残す（実行中はexit_codeをNULLにする制約があるため別カラム）
+    pub last_exit_code: Option<i32>,
+    pub health: String,
 }
 
 impl TunnelEntry {
@@ -81,9 +211,7 @@ impl TunnelEntry {
     ) -> anyhow::Result<Self> {
         let now = chrono::Utc::now().timestamp();
         let socket_path_hash = Self::hash_path(socket_path)?;
-        let config_json = serde_json::to_string(config)?;
-        let config_encrypted = Self::encrypt_config(&config_json, encryption_key)?;
-        let config_checksum = Self::compute_checksum(&config_json)?;
+        let (config_encrypted, config_checksum) = Self::seal_config(config, encryption_key)?;
 
         Ok(Self {
             id,
@@ -97,6 +225,9 @@ impl TunnelEntry {
             updated_at: now,
             last_activity: now,
             exit_code: None,
+            restart_count: 0,
+            last_exit_code: None,
+            health: HealthStatus::Unknown.as_str().to_string(),
         })
     }
 
@@ -104,6 +235,10 @@ impl TunnelEntry {
         TunnelStatus::from_i32(self.status).unwrap_or(TunnelStatus::Error)
     }
 
+    pub fn get_health(&self) -> HealthStatus {
+        HealthStatus::from_str_lossy(&self.health)
+    }
+
     // 設定データの完全性チェック付き復号化
     pub fn decrypt_config(&self, encryption_key: &[u8]) -> anyhow::Result<TunnelConfig> {
         let encrypted_data = self.config_encrypted.as_ref()
@@ -121,8 +256,27 @@ impl TunnelEntry {
         Ok(config)
     }
 
+    // 設定の暗号化とチェックサムの計算（インポート時はインポート先の鍵で封印し直す）
+    pub fn seal_config(config: &TunnelConfig, encryption_key: &[u8]) -> anyhow::Result<(Vec<u8>, String)> {
+        let config_json = serde_json::to_string(config)?;
+        let config_encrypted = Self::encrypt_config(&config_json, encryption_key)?;
+        let config_checksum = Self::compute_checksum(&config_json)?;
+        Ok((config_encrypted, config_checksum))
+    }
+
+    // 復号済みの設定のチェックサム（seal_configと同じ正規化したJSONから求める）
+    pub fn checksum_config(config: &TunnelConfig) -> anyhow::Result<String> {
+        Self::compute_checksum(&serde_json::to_string(config)?)
+    }
+
+    // 配置規則から求めたソケットパスが登録時のものと一致するか（ハッシュで照合する）
+    pub fn matches_socket_path(&self, socket_path: &Path) -> bool {
+        Self::hash_path(&socket_path.to_string_lossy())
+            .is_ok_and(|hash| hash == self.socket_path_hash)
+    }
+
     // セキュリティのためパス情報をハッシュ化
-    fn hash_path(path: &str) -> anyhow::Result<String> {
+    pub(crate) fn hash_path(path: &str) -> anyhow::Result<String> {
         use ring::digest::{Context, SHA256};
         let mut context = Context::new(&SHA256);
         context.update(path.as_bytes());
@@ -204,7 +358,7 @@ pub struct ClientEntry {
     pub session_timeout: i32,          
 }
 
-#[derive(Debug, Clone, FromRow)]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct SessionEntry {
     pub id: String,                    
     pub tunnel_id: String,             
@@ -230,6 +384,12 @@ pub struct TunnelInfo {
     pub updated_at: i64,
     pub last_activity: i64,
     pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub restart_count: u32,
+    #[serde(default)]
+    pub last_exit_code: Option<i32>,
+    #[serde(default)]
+    pub health: HealthStatus,
     pub metrics: TunnelMetrics,
 }
 
@@ -244,6 +404,15 @@ pub struct TunnelMetrics {
     pub uptime_seconds: u64,           
     pub avg_latency_ms: f64,           
     pub error_rate: f64,               
+    #[serde(default)]
+    pub reconnects: u64,
+    #[serde(default)]
+    pub auth_failures: u64,
+    #[serde(default)]
+    pub latency: LatencyHistogram,
+    // ペイロード圧縮の効果（圧縮が無効なトンネルは0のまま）
+    #[serde(default)]
+    pub compression: CompressionStats,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -261,7 +430,46 @@ pub struct ConnectionInfo {
     pub session_timeout: u32,
 }
 
-#[derive(Debug, Clone, FromRow)]
+impl ConnectionInfo {
+    // 受け付けたばかりの接続
+    pub fn open(id: String, tunnel_id: String, client_addr: String, target_addr: String, session_timeout: u32) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            id,
+            tunnel_id,
+            client_addr,
+            target_addr,
+            connected_at: now,
+            disconnected_at: None,
+            last_activity: now,
+            bytes_sent: 0,
+            bytes_received: 0,
+            status: "active".to_string(),
+            session_timeout,
+        }
+    }
+}
+
+// ソケットディレクトリとRegistryの整合性チェック結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SocketConsistencyReport {
+    // ソケットディレクトリで見つかったソケット数
+    pub sockets: usize,
+    // 稼働中のトンネルに対応しないソケット（停止済み・削除済みトンネルの残骸）
+    pub orphaned_sockets: Vec<PathBuf>,
+    // 稼働中なのにソケットが存在しないトンネル（ID, 名前）
+    pub missing_sockets: Vec<(String, String)>,
+    // 登録時のソケットパスのハッシュと一致しないトンネルID（ソケットディレクトリの移動・改ざん）
+    pub hash_mismatches: Vec<String>,
+}
+
+impl SocketConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.orphaned_sockets.is_empty() && self.missing_sockets.is_empty() && self.hash_mismatches.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct AuditLogEntry {
     pub id: i64,
     pub action: String,                
@@ -271,12 +479,178 @@ pub struct AuditLogEntry {
     pub timestamp: i64,                
     pub success: bool,                 
     pub error_message: Option<String>, 
+    // ハッシュチェーン（直前のエントリのハッシュと、それを含めたこのエントリのハッシュ）
+    pub prev_hash: Option<String>,
+    pub entry_hash: Option<String>,
+}
+
+// チェーン先頭のエントリが参照する直前のハッシュ
+pub const AUDIT_GENESIS_HASH: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+
+impl AuditLogEntry {
+    // 直前のハッシュと行の内容からエントリのハッシュを求める
+    // JSON配列で直列化し、フィールドの区切りが曖昧にならないようにする
+    pub fn compute_hash(&self, prev_hash: &str) -> String {
+        use ring::digest::{digest, SHA256};
+        let content = serde_json::json!([
+            prev_hash,
+            self.id,
+            self.action,
+            self.target_table,
+            self.target_id,
+            self.user_context,
+            self.timestamp,
+            self.success,
+            self.error_message,
+        ]);
+        let digest = digest(&SHA256, content.to_string().as_bytes());
+        base64::engine::general_purpose::STANDARD.encode(digest.as_ref())
+    }
+}
+
+// 監査ログの検索条件
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub since: Option<i64>,
+    pub action: Option<String>,
+    pub target_id: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditViolation {
+    pub id: i64,
+    pub reason: String,
+}
+
+// ハッシュチェーンの検証結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditVerifyReport {
+    pub total_entries: usize,
+    pub verified_entries: usize,
+    // ハッシュチェーン導入前から残っているエントリ（検証の対象外）
+    pub unsealed_legacy_entries: usize,
+    // 保存期間を過ぎたエントリの削除により、チェーンが途中から始まっている
+    pub starts_after_retention: bool,
+    pub head_hash: Option<String>,
+    // 外部に控えておいたハッシュがチェーン上に見つかったか（指定時のみ）
+    pub anchor_found: Option<bool>,
+    pub violations: Vec<AuditViolation>,
+}
+
+impl AuditVerifyReport {
+    pub fn is_intact(&self) -> bool {
+        self.violations.is_empty() && self.anchor_found != Some(false)
+    }
+}
+
+// id順に並んだ監査ログのハッシュチェーンを検証する
+pub fn verify_audit_chain(entries: &[AuditLogEntry], anchor: Option<&str>) -> AuditVerifyReport {
+    let mut report = AuditVerifyReport {
+        total_entries: entries.len(),
+        anchor_found: anchor.map(|_| false),
+        ..Default::default()
+    };
+    let mut previous: Option<&str> = None;
+
+    for entry in entries {
+        let (Some(prev_hash), Some(entry_hash)) = (entry.prev_hash.as_deref(), entry.entry_hash.as_deref()) else {
+            // 封印済みのエントリより後にハッシュの無い行があれば、チェーンを経由せずに挿入されている
+            if previous.is_none() {
+                report.unsealed_legacy_entries += 1;
+            } else {
+                report.violations.push(AuditViolation { id: entry.id, reason: "entry is not sealed".to_string() });
+            }
+            continue;
+        };
+
+        match previous {
+            None => report.starts_after_retention = prev_hash != AUDIT_GENESIS_HASH,
+            Some(previous) if previous != prev_hash => report.violations.push(AuditViolation {
+                id: entry.id,
+                reason: "chain broken (a preceding entry was modified or removed)".to_string(),
+            }),
+            Some(_) => {}
+        }
+
+        if entry.compute_hash(prev_hash) == entry_hash {
+            report.verified_entries += 1;
+        } else {
+            report.violations.push(AuditViolation { id: entry.id, reason: "entry contents were modified".to_string() });
+        }
+        if anchor == Some(entry_hash) {
+            report.anchor_found = Some(true);
+        }
+        previous = Some(entry_hash);
+    }
+
+    report.head_hash = previous.map(str::to_string);
+    report
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn sealed_chain(count: i64) -> Vec<AuditLogEntry> {
+        let mut prev_hash = AUDIT_GENESIS_HASH.to_string();
+        (1..=count).map(|id| {
+            let mut entry = AuditLogEntry {
+                id,
+                action: "UPDATE".to_string(),
+                target_table: "tunnels".to_string(),
+                target_id: Some(format!("tunnel-{}", id)),
+                user_context: Some("alice".to_string()),
+                timestamp: 1_700_000_000 + id,
+                success: true,
+                error_message: None,
+                prev_hash: None,
+                entry_hash: None,
+            };
+            let hash = entry.compute_hash(&prev_hash);
+            entry.prev_hash = Some(std::mem::replace(&mut prev_hash, hash.clone()));
+            entry.entry_hash = Some(hash);
+            entry
+        }).collect()
+    }
+
+    #[test]
+    fn test_verify_audit_chain() {
+        let entries = sealed_chain(4);
+        let report = verify_audit_chain(&entries, entries[1].entry_hash.as_deref());
+        assert!(report.is_intact());
+        assert_eq!(report.verified_entries, 4);
+        assert_eq!(report.head_hash, entries[3].entry_hash);
+
+        // 内容の書き換え
+        let mut modified = entries.clone();
+        modified[1].success = false;
+        let report = verify_audit_chain(&modified, None);
+        assert_eq!(report.violations, vec![AuditViolation { id: 2, reason: "entry contents were modified".to_string() }]);
+
+        // 途中の削除
+        let mut removed = entries.clone();
+        removed.remove(2);
+        assert_eq!(verify_audit_chain(&removed, None).violations[0].id, 4);
+
+        // 保存期間による先頭の削除は改ざんとしないが、控えたハッシュが消えていれば検出する
+        let report = verify_audit_chain(&entries[2..], entries[0].entry_hash.as_deref());
+        assert!(report.starts_after_retention);
+        assert!(report.violations.is_empty());
+        assert!(!report.is_intact());
+
+        // 導入前のエントリは対象外、封印済みの後のハッシュ無しは違反
+        let mut legacy = sealed_chain(2);
+        let mut unsealed = legacy[0].clone();
+        unsealed.prev_hash = None;
+        unsealed.entry_hash = None;
+        legacy.insert(0, AuditLogEntry { id: 0, ..unsealed.clone() });
+        legacy.push(AuditLogEntry { id: 3, ..unsealed });
+        let report = verify_audit_chain(&legacy, None);
+        assert_eq!(report.unsealed_legacy_entries, 1);
+        assert_eq!(report.violations, vec![AuditViolation { id: 3, reason: "entry is not sealed".to_string() }]);
+    }
+
     #[test]
     fn test_tunnel_status_conversion() {
         assert_eq!(TunnelStatus::from_i32(1), Some(TunnelStatus::Created));
@@ -301,6 +675,12 @@ mod tests {
             protocol: "tcp".to_string(),
             timeout_seconds: 30,
             max_connections: 100,
+            restart_policy: RestartPolicy::default(),
+            restart_on_unhealthy: false,
+            unhealthy_threshold: DEFAULT_UNHEALTHY_THRESHOLD,
+            compression: None,
+            webhooks: Vec::new(),
+            retry: Default::default(),
         };
 
         let key = b"0123456789abcdef0123456789abcdef"; // 32 bytes
@@ -317,4 +697,58 @@ mod tests {
         assert_eq!(decrypted_config.router_addr, config.router_addr);
         assert_eq!(decrypted_config.source_addr, config.source_addr);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_tunnel_config_webhooks() {
+        // 旧バージョンで登録された設定にはWebhookがない
+        let json = r#"{"router_addr":"10.2.0.1:9999","source_addr":"10.2.0.2:8080","bind_addr":"0.0.0.0:80","protocol":"tcp","timeout_seconds":30,"max_connections":100}"#;
+        let mut config: TunnelConfig = serde_json::from_str(json).unwrap();
+        assert!(config.webhooks.is_empty());
+
+        config.webhooks = serde_json::from_str(r#"[{"name":"slack","url":"https://hooks.example.com/T000"}]"#).unwrap();
+        let key = b"0123456789abcdef0123456789abcdef";
+        let entry = TunnelEntry::new(
+            "test-id".to_string(),
+            "test-tunnel".to_string(),
+            12345,
+            "/tmp/test.sock",
+            &config,
+            key,
+        ).unwrap();
+
+        // 再起動時もRegistryの設定から同じ通知先を渡せる
+        let decrypted_config = entry.decrypt_config(key).unwrap();
+        assert_eq!(decrypted_config.webhooks.len(), 1);
+        assert_eq!(decrypted_config.webhooks[0].url, "https://hooks.example.com/T000");
+    }
+
+    #[test]
+    fn test_restart_policy_parse() {
+        assert_eq!("no".parse::<RestartPolicy>().unwrap(), RestartPolicy::No);
+        assert_eq!("always".parse::<RestartPolicy>().unwrap(), RestartPolicy::Always);
+        assert_eq!(
+            "on-failure:3".parse::<RestartPolicy>().unwrap(),
+            RestartPolicy::OnFailure { max_retries: Some(3) }
+        );
+        assert!("on-failure:x".parse::<RestartPolicy>().is_err());
+        assert!("sometimes".parse::<RestartPolicy>().is_err());
+
+        // 文字列表現で往復できる
+        let policy = RestartPolicy::OnFailure { max_retries: Some(5) };
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(json, "\"on-failure:5\"");
+        assert_eq!(serde_json::from_str::<RestartPolicy>(&json).unwrap(), policy);
+    }
+
+    #[test]
+    fn test_restart_policy_decision() {
+        assert!(!RestartPolicy::No.should_restart(1, 0));
+        assert!(RestartPolicy::Always.should_restart(0, 100));
+
+        let on_failure = RestartPolicy::OnFailure { max_retries: Some(2) };
+        assert!(!on_failure.should_restart(0, 0));
+        assert!(on_failure.should_restart(1, 1));
+        assert!(!on_failure.should_restart(1, 2));
+        assert!(RestartPolicy::OnFailure { max_retries: None }.should_restart(137, 1000));
+    }
+}
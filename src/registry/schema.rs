@@ -0,0 +1,237 @@
+// Registryスキーマのバージョン管理
+// migrations/のSQLをバイナリに埋め込み、適用済みのバージョンはsqlxの_sqlx_migrationsに記録する。
+// 既存のRegistryを更新する前にはバックアップを取り、新しいConduitが書いたRegistryは開かない
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use sqlx::migrate::Migrator;
+use sqlx::{Pool, Sqlite};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaMigration {
+    pub version: i64,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaStatus {
+    // 未初期化のRegistryはNone
+    pub current_version: Option<i64>,
+    pub latest_version: i64,
+    pub pending: Vec<SchemaMigration>,
+}
+
+impl SchemaStatus {
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationOutcome {
+    pub from_version: Option<i64>,
+    pub to_version: i64,
+    pub applied: Vec<SchemaMigration>,
+    pub backup: Option<PathBuf>,
+}
+
+// このビルドが扱えるスキーマの最新バージョン
+pub fn latest_version() -> i64 {
+    latest_version_of(&MIGRATOR)
+}
+
+fn latest_version_of(migrator: &Migrator) -> i64 {
+    migrator.iter().map(|m| m.version).max().unwrap_or(0)
+}
+
+// 適用済みのバージョンを確認し、このビルドで開けるRegistryかを判定する
+pub async fn inspect(pool: &Pool<Sqlite>) -> Result<SchemaStatus> {
+    inspect_with(&MIGRATOR, pool).await
+}
+
+async fn inspect_with(migrator: &Migrator, pool: &Pool<Sqlite>) -> Result<SchemaStatus> {
+    let latest_version = latest_version_of(migrator);
+    let has_table: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')"
+    )
+    .fetch_one(pool)
+    .await?;
+    let applied: Vec<(i64, bool, Vec<u8>)> = if has_table {
+        sqlx::query_as("SELECT version, success, checksum FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    for (version, success, checksum) in &applied {
+        let Some(migration) = migrator.iter().find(|m| m.version == *version) else {
+            if *version > latest_version {
+                bail!(
+                    "Registry schema version {} was written by a newer Conduit (this build supports up to {}); \
+                     upgrade Conduit or restore a backup of the registry",
+                    version, latest_version
+                );
+            }
+            bail!("Registry schema version {} is unknown to this build", version);
+        };
+        if !success {
+            bail!("Registry migration {} did not complete; restore the registry from its backup", version);
+        }
+        if migration.checksum.as_ref() != checksum.as_slice() {
+            bail!("Registry migration {} differs from the one in this build (checksum mismatch)", version);
+        }
+    }
+
+    let pending = migrator.iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .filter(|m| !applied.iter().any(|(version, _, _)| *version == m.version))
+        .map(|m| SchemaMigration { version: m.version, description: m.description.to_string() })
+        .collect();
+
+    Ok(SchemaStatus {
+        current_version: applied.iter().map(|(version, _, _)| *version).max(),
+        latest_version,
+        pending,
+    })
+}
+
+// 未適用のマイグレーションを適用する（既存のRegistryは事前にバックアップする）
+pub async fn upgrade(pool: &Pool<Sqlite>, db_path: &Path) -> Result<MigrationOutcome> {
+    upgrade_with(&MIGRATOR, pool, db_path).await
+}
+
+async fn upgrade_with(migrator: &Migrator, pool: &Pool<Sqlite>, db_path: &Path) -> Result<MigrationOutcome> {
+    let status = inspect_with(migrator, pool).await?;
+    let mut outcome = MigrationOutcome {
+        from_version: status.current_version,
+        to_version: status.current_version.unwrap_or(0),
+        applied: Vec::new(),
+        backup: None,
+    };
+    if status.is_up_to_date() {
+        return Ok(outcome);
+    }
+
+    // 新規作成時は失うデータが無いためバックアップしない
+    if let Some(version) = status.current_version {
+        outcome.backup = Some(backup(pool, db_path, version).await?);
+    }
+
+    migrator.run(pool).await
+        .context("Failed to run database migrations")?;
+
+    info!(
+        "Registry schema migrated from {} to {}",
+        status.current_version.map_or("empty".to_string(), |v| v.to_string()),
+        status.latest_version
+    );
+    outcome.to_version = status.latest_version;
+    outcome.applied = status.pending;
+    Ok(outcome)
+}
+
+// 更新前のバックアップ先（<registry.db>.v<バージョン>-<時刻>.bak）
+pub fn backup_path(db_path: &Path, version: i64) -> PathBuf {
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S");
+    PathBuf::from(format!("{}.v{}-{}.bak", db_path.display(), version, timestamp))
+}
+
+// WALの内容も含めた一貫性のある複製を作る
+async fn backup(pool: &Pool<Sqlite>, db_path: &Path, version: i64) -> Result<PathBuf> {
+    let path = backup_path(db_path, version);
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to back up registry to {}", path.display()))?;
+
+    // 暗号化された設定を含むため所有者のみ読めるようにする
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    info!("Backed up registry schema version {} to {}", version, path.display());
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::borrow::Cow;
+    use tempfile::tempdir;
+
+    async fn open(db_path: &Path) -> Pool<Sqlite> {
+        SqlitePoolOptions::new()
+            .connect(&format!("sqlite:{}?mode=rwc", db_path.display()))
+            .await
+            .unwrap()
+    }
+
+    // 1つ前のリリースに相当するマイグレーション
+    fn previous_release() -> Migrator {
+        Migrator {
+            migrations: Cow::Owned(MIGRATOR.migrations[..MIGRATOR.migrations.len() - 1].to_vec()),
+            ignore_missing: false,
+            locking: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_backs_up_existing_registry() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("registry.db");
+        let pool = open(&db_path).await;
+
+        // 新規作成はバックアップしない
+        let previous = previous_release();
+        let outcome = upgrade_with(&previous, &pool, &db_path).await.unwrap();
+        assert_eq!(outcome.from_version, None);
+        assert!(outcome.backup.is_none());
+
+        let status = inspect(&pool).await.unwrap();
+        assert_eq!(status.current_version, Some(latest_version_of(&previous)));
+        assert_eq!(status.pending.len(), 1);
+
+        let outcome = upgrade(&pool, &db_path).await.unwrap();
+        assert_eq!(outcome.to_version, latest_version());
+        assert_eq!(outcome.applied, status.pending);
+        let backup = outcome.backup.unwrap();
+        assert!(backup.exists());
+
+        // バックアップは更新前のバージョンのまま
+        let backup_pool = open(&backup).await;
+        let backup_status = inspect(&backup_pool).await.unwrap();
+        assert_eq!(backup_status.current_version, status.current_version);
+
+        assert!(inspect(&pool).await.unwrap().is_up_to_date());
+        assert!(upgrade(&pool, &db_path).await.unwrap().backup.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refuses_registry_from_newer_release() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("registry.db");
+        let pool = open(&db_path).await;
+        upgrade(&pool, &db_path).await.unwrap();
+
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (?, 'future', TRUE, x'00', 0)"
+        )
+        .bind(latest_version() + 1)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let error = inspect(&pool).await.unwrap_err().to_string();
+        assert!(error.contains("newer Conduit"), "{}", error);
+        assert!(upgrade(&pool, &db_path).await.is_err());
+    }
+}
@@ -1,10 +1,15 @@
 // SQLite Process Registry実装
 // WAL modeによる高性能並行アクセス対応
 
-use crate::registry::models::*;
-use anyhow::{Context, Result};
+use crate::ipc::peer::Access;
+use crate::registry::bundle::{BundleTunnel, ImportReport, RegistryBundle, BUNDLE_FORMAT_VERSION};
+use crate::registry::{models::*, schema};
+use crate::security::crypto::{seal_for_public_key, Ed25519KeyPair};
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use std::collections::HashSet;
 use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 
@@ -13,17 +18,39 @@ pub struct SqliteRegistry {
     pool: Pool<Sqlite>,
     encryption_key: Arc<[u8; 32]>,
     db_path: PathBuf,
+    // Tunnel Processのソケット置き場（既定ではcommon::pathsのソケットディレクトリ）
+    sockets_dir: PathBuf,
 }
 
 impl SqliteRegistry {
     // 新しいレジストリインスタンスの作成
     pub async fn new(db_path: Option<PathBuf>) -> Result<Self> {
-        let db_path = db_path.unwrap_or_else(|| {
-            dirs::home_dir()
-                .unwrap_or_else(|| PathBuf::from("."))
-                .join(".conduit")
-                .join("registry.db")
-        });
+        let shared = db_path.is_none() && crate::common::paths::scope() == crate::common::paths::Scope::System;
+        let (pool, db_path, sockets_dir) = Self::open(db_path).await?;
+
+        // データベーススキーマの初期化（既存のRegistryは更新前にバックアップし、新しいConduitのRegistryは開かない）
+        schema::upgrade(&pool, &db_path).await?;
+
+        // 暗号化キーの生成または取得
+        let encryption_key = Self::get_or_create_encryption_key(&pool, &db_path.with_extension("key"), shared).await?;
+
+        Ok(Self {
+            pool,
+            encryption_key: Arc::new(encryption_key),
+            db_path,
+            sockets_dir,
+        })
+    }
+
+    // 接続プールの作成（スキーマは変更しないため、registry migrateからも使う）
+    // 戻り値は接続プール・Registryのパス・ソケットディレクトリ
+    pub(crate) async fn open(db_path: Option<PathBuf>) -> Result<(Pool<Sqlite>, PathBuf, PathBuf)> {
+        let shared = db_path.is_none() && crate::common::paths::scope() == crate::common::paths::Scope::System;
+        if db_path.is_none() {
+            crate::common::paths::create_conduit_dir(&crate::common::paths::state_dir())
+                .context("Failed to create registry directory")?;
+        }
+        let (db_path, sockets_dir) = Self::resolve_paths(db_path);
 
         // ディレクトリ作成
         if let Some(parent) = db_path.parent() {
@@ -41,6 +68,13 @@ impl SqliteRegistry {
             .await
             .context("Failed to create SQLite connection pool")?;
 
+        // システムスコープではconduitグループのメンバーもlistできるよう、グループで読み書き可能にする
+        // （SQLiteはWAL・SHMを本体と同じ権限で作成する）
+        #[cfg(unix)]
+        if shared {
+            share_with_group(&db_path);
+        }
+
         // WAL mode有効化とパフォーマンス設定
         sqlx::query("PRAGMA journal_mode=WAL").execute(&pool).await?;
         sqlx::query("PRAGMA synchronous=FULL").execute(&pool).await?;
@@ -51,18 +85,45 @@ impl SqliteRegistry {
         sqlx::query("PRAGMA secure_delete=ON").execute(&pool).await?;
         sqlx::query("PRAGMA foreign_keys=ON").execute(&pool).await?;
 
-        // データベーススキーマの初期化
-        sqlx::migrate!("./migrations").run(&pool).await
-            .context("Failed to run database migrations")?;
+        Ok((pool, db_path, sockets_dir))
+    }
 
-        // 暗号化キーの生成または取得
-        let encryption_key = Self::get_or_create_encryption_key(&pool).await?;
+    // 読み取り専用の接続プール（registry migrate --dry-run用）
+    // WALへの切り替えなどのPRAGMAはファイルを書き換えるため実行しない
+    pub(crate) async fn open_read_only(db_path: Option<PathBuf>) -> Result<(Pool<Sqlite>, PathBuf)> {
+        let (db_path, _) = Self::resolve_paths(db_path);
+        let database_url = format!("sqlite:{}?mode=ro", db_path.display());
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .context("Failed to open registry read-only")?;
+        Ok((pool, db_path))
+    }
 
-        Ok(Self {
-            pool,
-            encryption_key: Arc::new(encryption_key),
-            db_path,
-        })
+    // 既定の場所ではソケットをXDG_RUNTIME_DIR側に置き、明示されたRegistryは同じディレクトリにソケットもまとめる
+    fn resolve_paths(db_path: Option<PathBuf>) -> (PathBuf, PathBuf) {
+        match db_path {
+            Some(db_path) => {
+                let sockets_dir = db_path.parent()
+                    .map(|parent| parent.join("sockets"))
+                    .unwrap_or_else(|| PathBuf::from("sockets"));
+                (db_path, sockets_dir)
+            }
+            None => {
+                let paths = crate::common::paths::ConduitPaths::from_env();
+                (paths.registry_db(), paths.socket_dir)
+            }
+        }
+    }
+
+    pub fn sockets_dir(&self) -> &Path {
+        &self.sockets_dir
+    }
+
+    // トンネルのソケットパス（IDから一意に決まる）
+    pub fn socket_path(&self, tunnel_id: &str) -> Result<PathBuf> {
+        crate::ipc::tunnel_socket_path(&self.sockets_dir, tunnel_id)
     }
 
     // トンネルエントリの作成
@@ -120,27 +181,23 @@ impl SqliteRegistry {
 
         let mut tx = self.pool.begin().await?;
 
-        // プロセス終了時はPIDをクリア
-        let pid = if matches!(status, TunnelStatus::Exited | TunnelStatus::Error) {
-            None
-        } else {
-            // 簡略化: 現在のPID値を保持
-            let current_pid: Option<i32> = None;
-            current_pid
-        };
+        // プロセス終了時はPIDをクリアし、それ以外（停止中など）は停止処理で参照するため保持する
+        let clear_pid = matches!(status, TunnelStatus::Exited | TunnelStatus::Error);
 
         let result = sqlx::query(
             r#"
             UPDATE tunnels
-            SET status = ?, exit_code = ?, updated_at = ?, last_activity = ?, pid = ?
+            SET status = ?, exit_code = ?, last_exit_code = COALESCE(?, last_exit_code),
+                updated_at = ?, last_activity = ?, pid = CASE WHEN ? THEN NULL ELSE pid END
             WHERE id = ?
             "#
         )
         .bind(status_value)
         .bind(exit_code)
+        .bind(exit_code)
         .bind(now)
         .bind(now)
-        .bind(pid)
+        .bind(clear_pid)
         .bind(id)
         .execute(&mut *tx)
         .await?;
@@ -158,22 +215,141 @@ impl SqliteRegistry {
         Ok(updated)
     }
 
+    // 監視による再起動の記録（新しいPIDで実行中に戻し、直前の終了コードを残す）
+    pub async fn record_restart(
+        &self,
+        id: &str,
+        pid: i32,
+        restart_count: u32,
+        last_exit_code: i32,
+    ) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE tunnels
+            SET status = ?, pid = ?, exit_code = NULL, restart_count = ?, last_exit_code = ?,
+                health = 'unknown', updated_at = ?, last_activity = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(TunnelStatus::Running as i32)
+        .bind(pid)
+        .bind(restart_count as i64)
+        .bind(last_exit_code)
+        .bind(now)
+        .bind(now)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        let updated = result.rows_affected() > 0;
+
+        if updated {
+            self.log_audit_action(&mut tx, "UPDATE", "tunnels", Some(id), true, None).await?;
+            debug!("Recorded restart #{} of tunnel {} (PID: {})", restart_count, id, pid);
+        } else {
+            warn!("Tunnel {} not found for restart record", id);
+        }
+
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    // 生存確認結果の更新（状態が変わった時のみ呼ばれるため監査ログにも残す）
+    pub async fn update_tunnel_health(&self, id: &str, health: HealthStatus) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query("UPDATE tunnels SET health = ?, updated_at = ? WHERE id = ?")
+            .bind(health.as_str())
+            .bind(now)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let updated = result.rows_affected() > 0;
+
+        if updated {
+            self.log_audit_action(&mut tx, "UPDATE", "tunnels", Some(id), true, None).await?;
+            debug!("Updated tunnel {} health to: {}", id, health.as_str());
+        } else {
+            warn!("Tunnel {} not found for health update", id);
+        }
+
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    // 監視による再起動待ちの記録・取り消し。値が変わった時のみtrue
+    // 再起動する側も取り消しでfalseに戻すため、trueなら自分が待機中の再起動を引き受けたことになる
+    pub async fn set_restart_pending(&self, id: &str, pending: bool) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+
+        let result = sqlx::query(
+            "UPDATE tunnels SET restart_pending = ?, updated_at = ? WHERE id = ? AND restart_pending != ?"
+        )
+        .bind(pending)
+        .bind(now)
+        .bind(id)
+        .bind(pending)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     // アクティブトンネル一覧の取得（100並列対応）
+    // 起動直後（Created）から停止処理中（Stopping）までを終了していないトンネルとして扱う
     pub async fn list_active_tunnels(&self) -> Result<Vec<TunnelInfo>> {
-        // 一時的に空のリストを返す
-        Ok(Vec::new())
+        let entries: Vec<TunnelEntry> = sqlx::query_as(
+            "SELECT * FROM tunnels WHERE status IN (?, ?, ?) ORDER BY created_at"
+        )
+        .bind(TunnelStatus::Created as i32)
+        .bind(TunnelStatus::Running as i32)
+        .bind(TunnelStatus::Stopping as i32)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.entries_to_tunnel_infos(entries).await
     }
 
     // 全トンネル一覧の取得
     pub async fn list_all_tunnels(&self) -> Result<Vec<TunnelInfo>> {
-        // 一時的に空のリストを返す
-        Ok(Vec::new())
+        let entries: Vec<TunnelEntry> = sqlx::query_as("SELECT * FROM tunnels ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        self.entries_to_tunnel_infos(entries).await
     }
 
     // 特定トンネルの取得
-    pub async fn get_tunnel(&self, _id: &str) -> Result<Option<TunnelInfo>> {
-        // 一時的にNoneを返す
-        Ok(None)
+    pub async fn get_tunnel(&self, id: &str) -> Result<Option<TunnelInfo>> {
+        let entry: Option<TunnelEntry> = sqlx::query_as("SELECT * FROM tunnels WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match entry {
+            Some(entry) => Ok(Some(self.entry_to_tunnel_info(entry).await?)),
+            None => Ok(None),
+        }
+    }
+
+    // 復号できない行があっても他のトンネルは表示できるよう、その行だけ除外する
+    async fn entries_to_tunnel_infos(&self, entries: Vec<TunnelEntry>) -> Result<Vec<TunnelInfo>> {
+        let mut tunnels = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let id = entry.id.clone();
+            match self.entry_to_tunnel_info(entry).await {
+                Ok(info) => tunnels.push(info),
+                Err(e) => error!("Skipping unreadable tunnel entry {}: {}", id, e),
+            }
+        }
+        Ok(tunnels)
     }
 
     // トンネルの削除
@@ -196,10 +372,389 @@ impl SqliteRegistry {
         Ok(deleted)
     }
 
+    // UDS経由のRPCを拒否した記録。接続元の資格情報をuser_contextに残す
+    // （audit_logのactionは変更系をUPDATE、参照系をSELECT_SENSITIVEとして記録する）
+    pub async fn record_access_denied(
+        &self,
+        tunnel_id: &str,
+        rpc: &str,
+        access: Access,
+        peer: &str,
+        reason: &str,
+    ) -> Result<()> {
+        let action = match access {
+            Access::Control => "UPDATE",
+            Access::Read => "SELECT_SENSITIVE",
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let message = format!("{} denied: {}", rpc, reason);
+        Self::append_audit_entry(&mut tx, action, "tunnels", Some(tunnel_id), Some(peer), false, Some(&message)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // 監査ログの検索（条件に合う新しい順にlimit件を、古い順に並べて返す）
+    pub async fn list_audit_entries(&self, query: &AuditQuery) -> Result<Vec<AuditLogEntry>> {
+        let entries = sqlx::query_as::<_, AuditLogEntry>(
+            r#"
+            SELECT * FROM (
+                SELECT * FROM audit_log
+                WHERE (? IS NULL OR timestamp >= ?)
+                AND (? IS NULL OR action = ?)
+                AND (? IS NULL OR target_id = ?)
+                ORDER BY id DESC
+                LIMIT ?
+            ) ORDER BY id
+            "#
+        )
+        .bind(query.since)
+        .bind(query.since)
+        .bind(&query.action)
+        .bind(&query.action)
+        .bind(&query.target_id)
+        .bind(&query.target_id)
+        .bind(query.limit.map_or(-1, i64::from))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    // 監査ログ全体のハッシュチェーン検証
+    pub async fn verify_audit_chain(&self, anchor: Option<&str>) -> Result<AuditVerifyReport> {
+        let entries = sqlx::query_as::<_, AuditLogEntry>("SELECT * FROM audit_log ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(verify_audit_chain(&entries, anchor))
+    }
+
+    // Registryのエクスポート
+    // 設定はこのRegistryの鍵で復号し、recipientがあればその公開鍵宛てに暗号化し直す
+    // 戻り値の2つ目は復号できずに除外したトンネルのID
+    pub async fn export_bundle(&self, recipient: Option<&[u8]>) -> Result<(RegistryBundle, Vec<String>)> {
+        // 設定を平文で持ち出せる操作のため、エクスポート自体を監査ログに残す
+        let mut tx = self.pool.begin().await?;
+        self.log_audit_action(&mut tx, "SELECT_SENSITIVE", "tunnels", None, true, None).await?;
+        tx.commit().await?;
+
+        let entries: Vec<TunnelEntry> = sqlx::query_as("SELECT * FROM tunnels ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+        let mut tunnels = Vec::with_capacity(entries.len());
+        let mut skipped = Vec::new();
+        for entry in entries {
+            let config = match entry.decrypt_config(&self.encryption_key[..]) {
+                Ok(config) => config,
+                Err(e) => {
+                    error!("Skipping unreadable tunnel entry {}: {}", entry.id, e);
+                    skipped.push(entry.id);
+                    continue;
+                }
+            };
+            let config_checksum = TunnelEntry::checksum_config(&config)?;
+            let (config, config_sealed) = match recipient {
+                Some(public_key) => {
+                    let sealed = seal_for_public_key(public_key, serde_json::to_string(&config)?.as_bytes())?;
+                    (None, Some(base64::engine::general_purpose::STANDARD.encode(sealed)))
+                }
+                None => (Some(config), None),
+            };
+            tunnels.push(BundleTunnel {
+                id: entry.id,
+                name: entry.name,
+                status: entry.status,
+                exit_code: entry.exit_code,
+                restart_count: entry.restart_count,
+                last_exit_code: entry.last_exit_code,
+                created_at: entry.created_at,
+                updated_at: entry.updated_at,
+                last_activity: entry.last_activity,
+                config_checksum,
+                config,
+                config_sealed,
+            });
+        }
+
+        let sessions = sqlx::query_as::<_, SessionEntry>("SELECT * FROM sessions ORDER BY started_at")
+            .fetch_all(&self.pool)
+            .await?;
+        let audit_log = sqlx::query_as::<_, AuditLogEntry>("SELECT * FROM audit_log ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut bundle = RegistryBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            schema_version: schema::latest_version(),
+            exported_at: chrono::Utc::now().timestamp(),
+            recipient: recipient.map(|key| base64::engine::general_purpose::STANDARD.encode(key)),
+            tunnels,
+            sessions,
+            audit_log,
+            checksum: String::new(),
+        };
+        bundle.update_checksum()?;
+        info!("Exported {} tunnels, {} sessions and {} audit entries",
+              bundle.tunnels.len(), bundle.sessions.len(), bundle.audit_log.len());
+        Ok((bundle, skipped))
+    }
+
+    // Registryのインポート
+    // 全トンネルのチェックサムと監査ログのハッシュチェーンを検証してから、設定をこのRegistryの鍵で封印し直して登録する
+    pub async fn import_bundle(&self, bundle: &RegistryBundle, identity: Option<&Ed25519KeyPair>) -> Result<ImportReport> {
+        if bundle.schema_version > schema::latest_version() {
+            bail!(
+                "Bundle was exported from schema version {} by a newer Conduit (this build supports up to {})",
+                bundle.schema_version, schema::latest_version()
+            );
+        }
+        bundle.verify_checksum()?;
+        let configs = bundle.tunnels.iter()
+            .map(|tunnel| Self::bundle_config(bundle, tunnel, identity))
+            .collect::<Result<Vec<_>>>()?;
+        let audit_report = verify_audit_chain(&bundle.audit_log, None);
+        if !audit_report.is_intact() {
+            bail!("Audit log in the bundle failed hash chain verification ({} violations)", audit_report.violations.len());
+        }
+
+        let mut report = ImportReport::default();
+        let mut tx = self.pool.begin().await?;
+
+        // 監査ログは空のRegistryにだけ元のIDとハッシュのまま復元する（既存のチェーンに混ぜると検証できなくなる）
+        let existing_audit: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_log")
+            .fetch_one(&mut *tx)
+            .await?;
+        if existing_audit == 0 {
+            for entry in &bundle.audit_log {
+                sqlx::query(
+                    r#"
+                    INSERT INTO audit_log (
+                        id, action, target_table, target_id, user_context, timestamp,
+                        success, error_message, prev_hash, entry_hash
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#
+                )
+                .bind(entry.id)
+                .bind(&entry.action)
+                .bind(&entry.target_table)
+                .bind(&entry.target_id)
+                .bind(&entry.user_context)
+                .bind(entry.timestamp)
+                .bind(entry.success)
+                .bind(&entry.error_message)
+                .bind(&entry.prev_hash)
+                .bind(&entry.entry_hash)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to restore audit log entry")?;
+            }
+            report.restored_audit_entries = bundle.audit_log.len();
+        } else {
+            report.skipped_audit_entries = bundle.audit_log.len();
+        }
+
+        let mut imported_ids = HashSet::new();
+        for (tunnel, config) in bundle.tunnels.iter().zip(&configs) {
+            let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM tunnels WHERE id = ?)")
+                .bind(&tunnel.id)
+                .fetch_one(&mut *tx)
+                .await?;
+            if exists {
+                report.skipped_tunnels.push(tunnel.name.clone());
+                continue;
+            }
+
+            let (config_encrypted, config_checksum) = TunnelEntry::seal_config(config, &self.encryption_key[..])?;
+            let socket_path_hash = TunnelEntry::hash_path(&self.socket_path(&tunnel.id)?.to_string_lossy())?;
+            // インポート先ではプロセスが存在しないため、終了していないトンネルも終了済みとして登録する
+            // （実行中として登録すると次のクリーンアップで予期しない終了として通知・再起動の対象になる）
+            // 障害ではないため終了コードは0とする
+            let (status, exit_code) = match TunnelStatus::from_i32(tunnel.status) {
+                Some(TunnelStatus::Exited | TunnelStatus::Error) => (tunnel.status, tunnel.exit_code.unwrap_or(-1)),
+                _ => (TunnelStatus::Exited as i32, 0),
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO tunnels (
+                    id, name, pid, socket_path_hash, status, config_encrypted, config_checksum,
+                    created_at, updated_at, last_activity, exit_code, restart_count, last_exit_code
+                ) VALUES (?, ?, NULL, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(&tunnel.id)
+            .bind(&tunnel.name)
+            .bind(&socket_path_hash)
+            .bind(status)
+            .bind(&config_encrypted)
+            .bind(&config_checksum)
+            .bind(tunnel.created_at)
+            .bind(tunnel.updated_at)
+            .bind(tunnel.last_activity)
+            .bind(exit_code)
+            .bind(tunnel.restart_count)
+            .bind(tunnel.last_exit_code)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to import tunnel {}", tunnel.name))?;
+
+            self.log_audit_action(&mut tx, "CREATE", "tunnels", Some(&tunnel.id), true, None).await?;
+            imported_ids.insert(tunnel.id.as_str());
+            report.imported_tunnels.push(tunnel.name.clone());
+        }
+
+        for session in bundle.sessions.iter().filter(|s| imported_ids.contains(s.tunnel_id.as_str())) {
+            let result = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO sessions (
+                    id, tunnel_id, started_at, ended_at, total_connections,
+                    total_bytes_sent, total_bytes_received, avg_latency_ms, error_count
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(&session.id)
+            .bind(&session.tunnel_id)
+            .bind(session.started_at)
+            .bind(session.ended_at)
+            .bind(session.total_connections)
+            .bind(session.total_bytes_sent)
+            .bind(session.total_bytes_received)
+            .bind(session.avg_latency_ms)
+            .bind(session.error_count)
+            .execute(&mut *tx)
+            .await?;
+            report.imported_sessions += result.rows_affected() as usize;
+        }
+
+        tx.commit().await?;
+        info!("Imported {} tunnels ({} skipped), {} sessions and {} audit entries",
+              report.imported_tunnels.len(), report.skipped_tunnels.len(),
+              report.imported_sessions, report.restored_audit_entries);
+        Ok(report)
+    }
+
+    // バンドル内の設定を取り出してチェックサムを検証する
+    fn bundle_config(bundle: &RegistryBundle, tunnel: &BundleTunnel, identity: Option<&Ed25519KeyPair>) -> Result<TunnelConfig> {
+        let config = match (&tunnel.config, &tunnel.config_sealed) {
+            (Some(config), _) => config.clone(),
+            (None, Some(sealed)) => {
+                let identity = identity
+                    .context("Bundle configs are encrypted; the recipient's secret key is required to import them")?;
+                if bundle.recipient.as_deref() != Some(identity.public_key_base64().as_str()) {
+                    bail!("Bundle was encrypted for a different key ({})", bundle.recipient.as_deref().unwrap_or("unknown"));
+                }
+                let sealed = base64::engine::general_purpose::STANDARD.decode(sealed)
+                    .with_context(|| format!("Invalid encrypted config for tunnel {}", tunnel.name))?;
+                let config_json = identity.open_sealed(&sealed)?;
+                serde_json::from_slice(&config_json)?
+            }
+            (None, None) => bail!("Tunnel {} has no config in the bundle", tunnel.name),
+        };
+
+        if TunnelEntry::checksum_config(&config)? != tunnel.config_checksum {
+            bail!("Config checksum mismatch for tunnel {} ({})", tunnel.name, tunnel.id);
+        }
+        Ok(config)
+    }
+
     // 外部終了プロセスのクリーンアップ
-    pub async fn cleanup_dead_processes(&self) -> Result<Vec<String>> {
-        // 一時的に空のリストを返す
-        Ok(Vec::new())
+    // 終了していないはずのトンネルのうち、プロセスが存在しないものを終了扱いにする
+    // excludeは呼び出し側が自身の子として終了を検出するトンネル（二重に処理・通知しない）
+    pub async fn cleanup_dead_processes(&self, exclude: &HashSet<String>) -> Result<Vec<String>> {
+        let rows: Vec<(String, Option<i32>)> = sqlx::query_as(
+            "SELECT id, pid FROM tunnels WHERE status IN (?, ?, ?)"
+        )
+        .bind(TunnelStatus::Created as i32)
+        .bind(TunnelStatus::Running as i32)
+        .bind(TunnelStatus::Stopping as i32)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut cleaned = Vec::new();
+        for (id, pid) in rows {
+            if exclude.contains(&id) || pid.is_some_and(|pid| Self::process_exists(pid as u32)) {
+                continue;
+            }
+            // 終了コードは取得できないため-1とする
+            if self.update_tunnel_status(&id, TunnelStatus::Exited, Some(-1)).await? {
+                let socket_path = self.socket_path(&id)?;
+                let _ = crate::ipc::cleanup_socket_file(&socket_path).await;
+                info!("Marked dead tunnel process as exited: {}", id);
+                cleaned.push(id);
+            }
+        }
+        Ok(cleaned)
+    }
+
+    // ソケットディレクトリとRegistryの整合性チェック
+    pub async fn check_socket_consistency(&self) -> Result<SocketConsistencyReport> {
+        let entries: Vec<TunnelEntry> = sqlx::query_as(
+            "SELECT * FROM tunnels WHERE status IN (?, ?, ?)"
+        )
+        .bind(TunnelStatus::Created as i32)
+        .bind(TunnelStatus::Running as i32)
+        .bind(TunnelStatus::Stopping as i32)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut report = SocketConsistencyReport::default();
+        for entry in &entries {
+            let socket_path = self.socket_path(&entry.id)?;
+            if !entry.matches_socket_path(&socket_path) {
+                report.hash_mismatches.push(entry.id.clone());
+            }
+            // 起動直後はソケットがまだ作られていないことがある
+            if entry.get_status() != TunnelStatus::Created && !socket_path.exists() {
+                report.missing_sockets.push((entry.id.clone(), entry.name.clone()));
+            }
+        }
+
+        let mut dir = match tokio::fs::read_dir(&self.sockets_dir).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+            Err(e) => return Err(e).context("Failed to read socket directory"),
+        };
+        while let Some(item) = dir.next_entry().await? {
+            let path = item.path();
+            let name = item.file_name().to_string_lossy().into_owned();
+            let socket_path = if item.file_type().await?.is_dir() {
+                path.join(crate::ipc::SOCKET_FILE_NAME)
+            } else if path.extension().is_some_and(|ext| ext == "sock") {
+                // 旧形式（<ソケットディレクトリ>/<ID>.sock）の残骸
+                report.orphaned_sockets.push(path);
+                continue;
+            } else {
+                continue;
+            };
+
+            if socket_path.exists() {
+                report.sockets += 1;
+            }
+            if !entries.iter().any(|entry| entry.id == name) {
+                report.orphaned_sockets.push(if socket_path.exists() { socket_path } else { path });
+            }
+        }
+        report.orphaned_sockets.sort();
+
+        Ok(report)
+    }
+
+    // 整合性チェックで見つかった孤立ソケットの削除
+    pub async fn remove_orphaned_sockets(&self, report: &SocketConsistencyReport) -> Result<usize> {
+        let mut removed = 0;
+        for path in &report.orphaned_sockets {
+            // 中身のないトンネル用ディレクトリも孤立扱いになる
+            let result = if path.is_dir() {
+                tokio::fs::remove_dir(path).await.map_err(anyhow::Error::from)
+            } else {
+                crate::ipc::cleanup_socket_file(path).await
+            };
+            match result {
+                Ok(()) => removed += 1,
+                Err(e) => warn!("Failed to remove orphaned socket {}: {}", path.display(), e),
+            }
+        }
+        Ok(removed)
     }
 
     // プロセス存在確認（マルチプラットフォーム対応）
@@ -226,7 +781,7 @@ impl SqliteRegistry {
     // TunnelEntryからTunnelInfoへの変換
     async fn entry_to_tunnel_info(&self, entry: TunnelEntry) -> Result<TunnelInfo> {
         let config = entry.decrypt_config(&self.encryption_key[..])?;
-        let socket_path = self.resolve_socket_path(&entry.socket_path_hash).await?;
+        let socket_path = self.resolve_socket_path(&entry)?;
         let metrics = self.get_tunnel_metrics(&entry.id).await.unwrap_or_default();
 
         Ok(TunnelInfo {
@@ -240,19 +795,25 @@ impl SqliteRegistry {
             updated_at: entry.updated_at,
             last_activity: entry.last_activity,
             exit_code: entry.exit_code,
+            restart_count: entry.restart_count.max(0) as u32,
+            last_exit_code: entry.last_exit_code,
+            health: entry.get_health(),
             metrics,
         })
     }
 
     // ソケットパスの解決
-    async fn resolve_socket_path(&self, path_hash: &str) -> Result<PathBuf> {
-        // 実際の実装では、ハッシュから元のパスを復元するロジックが必要
-        // ここでは簡略化して標準パスを返す
-        Ok(dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".conduit")
-            .join("sockets")
-            .join(format!("{}.sock", &path_hash[..8])))
+    // パスはIDから求め、登録時のハッシュとの照合は整合性の確認にのみ使う
+    fn resolve_socket_path(&self, entry: &TunnelEntry) -> Result<PathBuf> {
+        let socket_path = self.socket_path(&entry.id)?;
+        if !entry.matches_socket_path(&socket_path) {
+            warn!(
+                "Socket path of tunnel {} does not match the registered hash: {}",
+                entry.id,
+                socket_path.display()
+            );
+        }
+        Ok(socket_path)
     }
 
     // トンネルメトリクスの取得
@@ -293,6 +854,7 @@ impl SqliteRegistry {
             } else {
                 0.0
             },
+            ..Default::default()
         })
     }
 
@@ -306,10 +868,25 @@ impl SqliteRegistry {
         success: bool,
         error_message: Option<&str>,
     ) -> Result<()> {
-        let timestamp = chrono::Utc::now().timestamp();
         let user_context = std::env::var("USER").ok();
+        Self::append_audit_entry(tx, action, target_table, target_id, user_context.as_deref(), success, error_message).await
+    }
 
-        sqlx::query(
+    // 監査ログへの追記とハッシュチェーンへの封印
+    // 先に行を挿入して書き込みロックを取ってから直前のハッシュを読むため、複数プロセスが同時に追記してもチェーンは分岐しない
+    #[allow(clippy::too_many_arguments)]
+    async fn append_audit_entry(
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        action: &str,
+        target_table: &str,
+        target_id: Option<&str>,
+        user_context: Option<&str>,
+        success: bool,
+        error_message: Option<&str>,
+    ) -> Result<()> {
+        let timestamp = chrono::Utc::now().timestamp();
+
+        let id = sqlx::query(
             r#"
             INSERT INTO audit_log (action, target_table, target_id, user_context, timestamp, success, error_message)
             VALUES (?, ?, ?, ?, ?, ?, ?)
@@ -323,54 +900,145 @@ impl SqliteRegistry {
         .bind(success)
         .bind(error_message)
         .execute(&mut **tx)
+        .await?
+        .last_insert_rowid();
+
+        let prev_hash: Option<String> = sqlx::query_scalar(
+            "SELECT entry_hash FROM audit_log WHERE id < ? AND entry_hash IS NOT NULL ORDER BY id DESC LIMIT 1"
+        )
+        .bind(id)
+        .fetch_optional(&mut **tx)
         .await?;
+        let prev_hash = prev_hash.unwrap_or_else(|| AUDIT_GENESIS_HASH.to_string());
+
+        let entry = AuditLogEntry {
+            id,
+            action: action.to_string(),
+            target_table: target_table.to_string(),
+            target_id: target_id.map(str::to_string),
+            user_context: user_context.map(str::to_string),
+            timestamp,
+            success,
+            error_message: error_message.map(str::to_string),
+            prev_hash: None,
+            entry_hash: None,
+        };
+        sqlx::query("UPDATE audit_log SET prev_hash = ?, entry_hash = ? WHERE id = ?")
+            .bind(&prev_hash)
+            .bind(entry.compute_hash(&prev_hash))
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
 
         Ok(())
     }
 
     // 暗号化キーの取得または生成
-    async fn get_or_create_encryption_key(pool: &Pool<Sqlite>) -> Result<[u8; 32]> {
-        // 既存のキーを確認
-        let existing_key: Option<String> = sqlx::query_scalar(
-            "SELECT key_id FROM config_metadata WHERE is_active = TRUE ORDER BY created_at DESC LIMIT 1"
-        )
-        .fetch_optional(pool)
-        .await?;
+    // キーはRegistryと同じ場所の鍵ファイルに保存し、次に開いたときも同じキーで復号できるようにする
+    async fn get_or_create_encryption_key(pool: &Pool<Sqlite>, key_path: &Path, shared: bool) -> Result<[u8; 32]> {
+        if let Some(key) = read_key_file(key_path)? {
+            return Ok(key);
+        }
 
-        if existing_key.is_some() {
-            // 既存のキーが存在する場合は、実際の実装では安全な場所から取得
-            // ここでは簡略化してデフォルトキーを使用
-            warn!("Using default encryption key for demonstration purposes");
-            Ok(*b"0123456789abcdef0123456789abcdef")
+        // 鍵ファイル導入前のRegistryは固定キーで暗号化されているため、そのキーを保存して引き継ぐ
+        let has_tunnels: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM tunnels)")
+            .fetch_one(pool)
+            .await?;
+        let key = if has_tunnels {
+            warn!("Migrating registry encrypted with the legacy built-in key to {}", key_path.display());
+            LEGACY_ENCRYPTION_KEY
         } else {
-            // 新しいキーを生成
             use ring::rand::{SecureRandom, SystemRandom};
             let rng = SystemRandom::new();
             let mut key = [0u8; 32];
             rng.fill(&mut key)
                 .map_err(|_| anyhow::anyhow!("Failed to generate encryption key"))?;
+            key
+        };
 
-            // キーメタデータをデータベースに保存
-            let key_id = uuid::Uuid::new_v4().to_string();
-            let now = chrono::Utc::now().timestamp();
-            let rotation_time = now + (30 * 24 * 60 * 60); // 30日後
+        match write_key_file(key_path, &key, shared) {
+            Ok(()) => {}
+            // 同時に開いた別のプロセスが先に作成した
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return read_key_file(key_path)?
+                    .with_context(|| format!("Registry key file disappeared: {}", key_path.display()));
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to write registry key file: {}", key_path.display()));
+            }
+        }
 
-            sqlx::query(
-                r#"
-                INSERT INTO config_metadata (key_id, algorithm, key_rotation_at, created_at, is_active)
-                VALUES (?, ?, ?, ?, ?)
-                "#
-            )
-            .bind(&key_id)
-            .bind("AES-256-GCM")
-            .bind(rotation_time)
-            .bind(now)
-            .bind(true)
-            .execute(pool)
-            .await?;
+        // キーメタデータをデータベースに保存
+        let key_id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        let rotation_time = now + (30 * 24 * 60 * 60); // 30日後
+
+        sqlx::query(
+            r#"
+            INSERT INTO config_metadata (key_id, algorithm, key_rotation_at, created_at, is_active)
+            VALUES (?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&key_id)
+        .bind("AES-256-GCM")
+        .bind(rotation_time)
+        .bind(now)
+        .bind(true)
+        .execute(pool)
+        .await?;
+
+        info!("Stored registry encryption key {} at {}", key_id, key_path.display());
+        Ok(key)
+    }
+}
+
+// 鍵ファイル導入前のRegistryが使っていた固定キー（既存の設定を復号するためだけに使う）
+const LEGACY_ENCRYPTION_KEY: [u8; 32] = *b"0123456789abcdef0123456789abcdef";
+
+fn read_key_file(key_path: &Path) -> Result<Option<[u8; 32]>> {
+    let encoded = match std::fs::read_to_string(key_path) {
+        Ok(encoded) => encoded,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read registry key file: {}", key_path.display())),
+    };
+    let key = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .with_context(|| format!("Invalid registry key file: {}", key_path.display()))?;
+    Ok(Some(key))
+}
+
+// 書きかけの鍵ファイルを他のプロセスに読ませないよう、一時ファイルに書いてからリンクする
+// （既に存在すればAlreadyExistsを返す）
+fn write_key_file(key_path: &Path, key: &[u8; 32], shared: bool) -> std::io::Result<()> {
+    use std::io::Write;
+    let temp_path = key_path.with_extension(format!("key.{}", std::process::id()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        // システムスコープではRegistry本体と同じくconduitグループにも読ませる
+        options.mode(if shared { 0o640 } else { 0o600 });
+    }
+    #[cfg(not(unix))]
+    let _ = shared;
+    let result = options.open(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(base64::engine::general_purpose::STANDARD.encode(key).as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::hard_link(&temp_path, key_path));
+    let _ = std::fs::remove_file(&temp_path);
+    result
+}
 
-            info!("Generated new encryption key: {}", key_id);
-            Ok(key)
+#[cfg(unix)]
+fn share_with_group(db_path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(db_path).map(|m| m.permissions().mode() & 0o777);
+    if matches!(mode, Ok(mode) if mode != 0o660) {
+        if let Err(e) = std::fs::set_permissions(db_path, std::fs::Permissions::from_mode(0o660)) {
+            warn!("Failed to share registry {} with the conduit group: {}", db_path.display(), e);
         }
     }
 }
@@ -391,41 +1059,274 @@ mod tests {
         assert!(!registry.encryption_key.is_empty());
     }
 
-    #[tokio::test]
-    async fn test_tunnel_crud_operations() {
-        let temp_dir = tempdir().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-        let registry = SqliteRegistry::new(Some(db_path)).await.unwrap();
-
-        let config = TunnelConfig {
+    fn test_config() -> TunnelConfig {
+        TunnelConfig {
             router_addr: "10.2.0.1:9999".to_string(),
             source_addr: "10.2.0.2:8080".to_string(),
             bind_addr: "0.0.0.0:80".to_string(),
             protocol: "tcp".to_string(),
             timeout_seconds: 30,
             max_connections: 100,
-        };
+            restart_policy: Default::default(),
+            restart_on_unhealthy: false,
+            unhealthy_threshold: DEFAULT_UNHEALTHY_THRESHOLD,
+            compression: None,
+            webhooks: Vec::new(),
+            retry: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_read_only_leaves_file_unchanged() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("old.db");
+        {
+            let pool = SqlitePoolOptions::new()
+                .connect(&format!("sqlite:{}?mode=rwc", db_path.display()))
+                .await
+                .unwrap();
+            sqlx::query("CREATE TABLE t (id INTEGER)").execute(&pool).await.unwrap();
+            pool.close().await;
+        }
+        let before = std::fs::read(&db_path).unwrap();
+
+        let (pool, _) = SqliteRegistry::open_read_only(Some(db_path.clone())).await.unwrap();
+        schema::inspect(&pool).await.unwrap();
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode").fetch_one(&pool).await.unwrap();
+        assert_eq!(journal_mode, "delete");
+        assert!(sqlx::query("CREATE TABLE u (id INTEGER)").execute(&pool).await.is_err());
+        pool.close().await;
+
+        assert_eq!(std::fs::read(&db_path).unwrap(), before);
+        assert!(!temp_dir.path().join("old.db-wal").exists());
+    }
+
+    #[tokio::test]
+    async fn test_encryption_key_persists_across_opens() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let registry = SqliteRegistry::new(Some(db_path.clone())).await.unwrap();
+        let socket_path = registry.socket_path("test-id").unwrap();
+        registry.create_tunnel(
+            "test-id".to_string(),
+            "test-tunnel".to_string(),
+            12345,
+            &socket_path.to_string_lossy(),
+            &test_config(),
+        ).await.unwrap();
+        let key = *registry.encryption_key;
+        assert_ne!(key, LEGACY_ENCRYPTION_KEY);
+        drop(registry);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(temp_dir.path().join("test.key")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // 開き直しても同じキーで復号でき、一覧から消えない
+        let registry = SqliteRegistry::new(Some(db_path)).await.unwrap();
+        assert_eq!(*registry.encryption_key, key);
+        let tunnels = registry.list_all_tunnels().await.unwrap();
+        assert_eq!(tunnels.len(), 1);
+        assert_eq!(tunnels[0].config.router_addr, test_config().router_addr);
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_crud_operations() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let registry = SqliteRegistry::new(Some(db_path)).await.unwrap();
+        let config = test_config();
+        let socket_path = registry.socket_path("test-id").unwrap();
 
         // 作成
         registry.create_tunnel(
             "test-id".to_string(),
             "test-tunnel".to_string(),
             12345,
-            "/tmp/test.sock",
+            &socket_path.to_string_lossy(),
             &config,
         ).await.unwrap();
 
-        // 取得
+        // 取得（ソケットパスはIDから解決される）
         let tunnel = registry.get_tunnel("test-id").await.unwrap().unwrap();
         assert_eq!(tunnel.name, "test-tunnel");
         assert_eq!(tunnel.config.router_addr, config.router_addr);
+        assert_eq!(tunnel.socket_path, socket_path);
+        assert!(registry.get_tunnel("missing").await.unwrap().is_none());
 
-        // 更新
-        let updated = registry.update_tunnel_status("test-id", TunnelStatus::Running, None).await.unwrap();
+        // 更新（停止中もPIDは保持する）
+        let updated = registry.update_tunnel_status("test-id", TunnelStatus::Stopping, None).await.unwrap();
         assert!(updated);
+        let tunnel = registry.get_tunnel("test-id").await.unwrap().unwrap();
+        assert_eq!(tunnel.pid, Some(12345));
+        assert_eq!(registry.list_active_tunnels().await.unwrap().len(), 1);
+
+        registry.update_tunnel_status("test-id", TunnelStatus::Exited, Some(0)).await.unwrap();
+        assert!(registry.list_active_tunnels().await.unwrap().is_empty());
+        assert_eq!(registry.list_all_tunnels().await.unwrap().len(), 1);
 
         // 削除
         let deleted = registry.delete_tunnel("test-id").await.unwrap();
         assert!(deleted);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_record_access_denied() {
+        let temp_dir = tempdir().unwrap();
+        let registry = SqliteRegistry::new(Some(temp_dir.path().join("test.db"))).await.unwrap();
+
+        registry.record_access_denied("tunnel-1", "Shutdown", Access::Control, "uid=1001 gid=990", "not the owner")
+            .await.unwrap();
+
+        let (action, context, success, message): (String, String, bool, String) = sqlx::query_as(
+            "SELECT action, user_context, success, error_message FROM audit_log WHERE target_id = 'tunnel-1'"
+        ).fetch_one(&registry.pool).await.unwrap();
+        assert_eq!(action, "UPDATE");
+        assert_eq!(context, "uid=1001 gid=990");
+        assert!(!success);
+        assert_eq!(message, "Shutdown denied: not the owner");
+    }
+
+    #[tokio::test]
+    async fn test_audit_hash_chain() {
+        let temp_dir = tempdir().unwrap();
+        let registry = SqliteRegistry::new(Some(temp_dir.path().join("test.db"))).await.unwrap();
+        let socket_path = registry.socket_path("audit-id").unwrap();
+        registry.create_tunnel("audit-id".to_string(), "audit".to_string(), std::process::id() as i32,
+            &socket_path.to_string_lossy(), &test_config()).await.unwrap();
+        registry.update_tunnel_status("audit-id", TunnelStatus::Running, None).await.unwrap();
+        registry.record_access_denied("audit-id", "Shutdown", Access::Control, "uid=1001", "not the owner").await.unwrap();
+
+        let entries = registry.list_audit_entries(&AuditQuery {
+            target_id: Some("audit-id".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].action, "CREATE");
+        let denied = registry.list_audit_entries(&AuditQuery {
+            action: Some("UPDATE".to_string()),
+            limit: Some(1),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(denied.len(), 1);
+        assert!(!denied[0].success);
+
+        let report = registry.verify_audit_chain(None).await.unwrap();
+        assert!(report.is_intact());
+        assert_eq!(report.verified_entries, 3);
+
+        // 封印済みのエントリはトリガーで保護される
+        // 拒否された文が接続のワーカーで後から再実行されないよう、専用の接続で試して閉じてからトリガーを外す
+        let mut conn = registry.pool.acquire().await.unwrap().detach();
+        let result = sqlx::query("UPDATE audit_log SET success = FALSE WHERE id = ?")
+            .bind(entries[0].id)
+            .execute(&mut conn)
+            .await;
+        assert!(result.is_err());
+        assert!(sqlx::query("DELETE FROM audit_log").execute(&mut conn).await.is_err());
+        sqlx::Connection::close(conn).await.unwrap();
+
+        // トリガーを外して書き換えてもチェーンで検出できる
+        sqlx::query("DROP TRIGGER audit_log_immutable").execute(&registry.pool).await.unwrap();
+        sqlx::query("UPDATE audit_log SET user_context = 'mallory' WHERE id = ?")
+            .bind(entries[1].id)
+            .execute(&registry.pool)
+            .await
+            .unwrap();
+        let report = registry.verify_audit_chain(None).await.unwrap();
+        assert!(!report.is_intact());
+        assert_eq!(report.violations[0].id, entries[1].id);
+    }
+
+    #[tokio::test]
+    async fn test_export_import_bundle() {
+        let temp_dir = tempdir().unwrap();
+        let source = SqliteRegistry::new(Some(temp_dir.path().join("source.db"))).await.unwrap();
+        let config = test_config();
+        let socket_path = source.socket_path("tunnel-1").unwrap();
+        source.create_tunnel("tunnel-1".to_string(), "web".to_string(), 4242, &socket_path.to_string_lossy(), &config).await.unwrap();
+        source.update_tunnel_status("tunnel-1", TunnelStatus::Running, None).await.unwrap();
+
+        let recipient = Ed25519KeyPair::generate().unwrap();
+        let (bundle, skipped) = source.export_bundle(Some(&recipient.public_key_bytes())).await.unwrap();
+        assert!(skipped.is_empty());
+        assert!(bundle.tunnels[0].config.is_none());
+        assert!(!bundle.audit_log.is_empty());
+
+        let restored_dir = tempdir().unwrap();
+        let restored = SqliteRegistry::new(Some(restored_dir.path().join("restored.db"))).await.unwrap();
+
+        // 宛先の秘密鍵が無ければ何も取り込まない
+        let other = Ed25519KeyPair::generate().unwrap();
+        assert!(restored.import_bundle(&bundle, None).await.is_err());
+        assert!(restored.import_bundle(&bundle, Some(&other)).await.is_err());
+
+        // 改ざんされた設定はチェックサムで検出する（バンドル全体のチェックサムを付け直しても検出できる）
+        let mut tampered = bundle.clone();
+        tampered.tunnels[0].config_checksum = "tampered".to_string();
+        assert!(restored.import_bundle(&tampered, Some(&recipient)).await.is_err());
+        tampered.update_checksum().unwrap();
+        assert!(restored.import_bundle(&tampered, Some(&recipient)).await.is_err());
+
+        // 監査ログの書き換えはバンドル全体のチェックサムで検出する
+        let mut tampered = bundle.clone();
+        tampered.audit_log.pop();
+        assert!(restored.import_bundle(&tampered, Some(&recipient)).await.is_err());
+        assert!(restored.list_all_tunnels().await.unwrap().is_empty());
+
+        let report = restored.import_bundle(&bundle, Some(&recipient)).await.unwrap();
+        assert_eq!(report.imported_tunnels, vec!["web".to_string()]);
+        assert_eq!(report.restored_audit_entries, bundle.audit_log.len());
+
+        // 実行中だったトンネルは終了済みとして登録され、クリーンアップの対象にならない
+        // 設定はインポート先の鍵で読める
+        let tunnel = restored.get_tunnel("tunnel-1").await.unwrap().unwrap();
+        assert_eq!(tunnel.status, TunnelStatus::Exited);
+        assert_eq!(tunnel.exit_code, Some(0));
+        assert!(restored.cleanup_dead_processes(&HashSet::new()).await.unwrap().is_empty());
+        assert_eq!(tunnel.config.router_addr, config.router_addr);
+        assert!(restored.verify_audit_chain(None).await.unwrap().is_intact());
+
+        let report = restored.import_bundle(&bundle, Some(&recipient)).await.unwrap();
+        assert_eq!(report.skipped_tunnels, vec!["web".to_string()]);
+        assert_eq!(report.skipped_audit_entries, bundle.audit_log.len());
+    }
+
+    #[tokio::test]
+    async fn test_socket_consistency_check() {
+        let temp_dir = tempdir().unwrap();
+        let registry = SqliteRegistry::new(Some(temp_dir.path().join("test.db"))).await.unwrap();
+
+        // 稼働中でソケットあり・稼働中でソケットなし
+        for id in ["live", "lost"] {
+            let socket_path = registry.socket_path(id).unwrap();
+            registry.create_tunnel(id.to_string(), id.to_string(), std::process::id() as i32,
+                &socket_path.to_string_lossy(), &test_config()).await.unwrap();
+            registry.update_tunnel_status(id, TunnelStatus::Stopping, None).await.unwrap();
+        }
+        let live = registry.socket_path("live").unwrap();
+        std::fs::create_dir_all(live.parent().unwrap()).unwrap();
+        std::fs::write(&live, b"").unwrap();
+
+        // 登録のないトンネルのソケット・旧形式のソケット
+        let orphan = registry.socket_path("gone").unwrap();
+        std::fs::create_dir_all(orphan.parent().unwrap()).unwrap();
+        std::fs::write(&orphan, b"").unwrap();
+        std::fs::write(registry.sockets_dir().join("legacy.sock"), b"").unwrap();
+
+        let report = registry.check_socket_consistency().await.unwrap();
+        assert_eq!(report.sockets, 2);
+        assert_eq!(report.missing_sockets, vec![("lost".to_string(), "lost".to_string())]);
+        assert_eq!(report.orphaned_sockets.len(), 2);
+        assert!(report.hash_mismatches.is_empty());
+
+        assert_eq!(registry.remove_orphaned_sockets(&report).await.unwrap(), 2);
+        assert!(!orphan.parent().unwrap().exists());
+        let report = registry.check_socket_consistency().await.unwrap();
+        assert!(report.orphaned_sockets.is_empty());
+        assert!(live.exists());
+    }
+}
@@ -0,0 +1,202 @@
+// 同じサービスを公開する複数Client間の負荷分散
+//
+// 同一の設定を持つClientインスタンスは同じサービス名でトンネルを登録する。
+// Routerはサービス名でトンネルをまとめ、使えるメンバー（Clientが接続中・ドレイン中でない・
+// ターゲットがdownでない）の中から戦略に従って転送先を選ぶ
+
+use super::state::TunnelCounters;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// 負荷分散設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadBalancingConfig {
+    pub strategy: BalanceStrategy,
+    // ドレイン中のClientの接続が終わるのを待つ上限。過ぎたら残りの接続ごと切断する
+    pub drain_timeout_seconds: u64,
+}
+
+impl Default for LoadBalancingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: BalanceStrategy::default(),
+            drain_timeout_seconds: 30,
+        }
+    }
+}
+
+impl LoadBalancingConfig {
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.drain_timeout_seconds)
+    }
+}
+
+// 転送先の選び方
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceStrategy {
+    #[default]
+    RoundRobin,
+    // 処理中の接続が最も少ないメンバー
+    LeastConnections,
+    // 同じキー（接続元アドレスなど）を同じメンバーへ送る。メンバーの増減で移るのはそのメンバーの分だけ
+    ConsistentHash,
+}
+
+impl BalanceStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BalanceStrategy::RoundRobin => "round_robin",
+            BalanceStrategy::LeastConnections => "least_connections",
+            BalanceStrategy::ConsistentHash => "consistent_hash",
+        }
+    }
+}
+
+impl fmt::Display for BalanceStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// サービスごとの選択状態
+#[derive(Default)]
+pub struct LoadBalancer {
+    strategy: BalanceStrategy,
+    // ラウンドロビンの位置
+    cursors: DashMap<String, AtomicUsize>,
+}
+
+impl LoadBalancer {
+    pub fn new(strategy: BalanceStrategy) -> Self {
+        Self {
+            strategy,
+            cursors: DashMap::new(),
+        }
+    }
+
+    pub fn strategy(&self) -> BalanceStrategy {
+        self.strategy
+    }
+
+    // 使えるメンバーから転送先を選ぶ。キーの無いConsistentHashはラウンドロビンで代用する
+    pub fn pick(
+        &self,
+        service: &str,
+        members: &[Arc<TunnelCounters>],
+        key: Option<&str>,
+    ) -> Option<Arc<TunnelCounters>> {
+        if members.is_empty() {
+            return None;
+        }
+        // 登録順に依存しないよう、メンバーの並びを固定する
+        let mut members: Vec<&Arc<TunnelCounters>> = members.iter().collect();
+        members.sort_by(|a, b| (&a.client_id, &a.tunnel_id).cmp(&(&b.client_id, &b.tunnel_id)));
+
+        let picked = match (self.strategy, key) {
+            (BalanceStrategy::LeastConnections, _) => {
+                // 同数のメンバー間では順に回す
+                let start = self.next_cursor(service);
+                (0..members.len())
+                    .map(|offset| members[(start + offset) % members.len()])
+                    .min_by_key(|m| m.active_connections())
+            }
+            (BalanceStrategy::ConsistentHash, Some(key)) => {
+                // Rendezvous hashing: キーとメンバーの組で最もスコアの高いメンバーを選ぶ
+                members.iter().copied().max_by_key(|m| score(key, &m.client_id))
+            }
+            _ => Some(members[self.next_cursor(service) % members.len()]),
+        };
+        picked.cloned()
+    }
+
+    fn next_cursor(&self, service: &str) -> usize {
+        self.cursors
+            .entry(service.to_string())
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+// メンバーはClient IDで識別し、トンネルを張り直しても同じキーの転送先が変わらないようにする
+fn score(key: &str, member: &str) -> u64 {
+    let mut input = Vec::with_capacity(key.len() + member.len() + 1);
+    input.extend_from_slice(key.as_bytes());
+    input.push(0);
+    input.extend_from_slice(member.as_bytes());
+    let digest = ring::digest::digest(&ring::digest::SHA256, &input);
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest.as_ref()[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::state::RouterState;
+
+    fn members(state: &RouterState, clients: &[&str]) -> Vec<Arc<TunnelCounters>> {
+        clients
+            .iter()
+            .map(|client| {
+                state.register_tunnel(
+                    &format!("{}-web", client),
+                    client,
+                    "web",
+                    "web",
+                    &["10.2.0.2:8080".parse().unwrap()],
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_and_least_connections() {
+        let state = RouterState::new();
+        let members = members(&state, &["client-b", "client-a", "client-c"]);
+
+        let balancer = LoadBalancer::new(BalanceStrategy::RoundRobin);
+        let picked: Vec<String> = (0..4)
+            .map(|_| balancer.pick("web", &members, None).unwrap().client_id.clone())
+            .collect();
+        assert_eq!(picked, vec!["client-a", "client-b", "client-c", "client-a"]);
+
+        let balancer = LoadBalancer::new(BalanceStrategy::LeastConnections);
+        members[0].connection_opened();
+        members[1].connection_opened();
+        members[1].connection_opened();
+        assert_eq!(balancer.pick("web", &members, None).unwrap().client_id, "client-c");
+        assert!(balancer.pick("web", &[], None).is_none());
+    }
+
+    #[test]
+    fn test_consistent_hash() {
+        let state = RouterState::new();
+        let members = members(&state, &["client-a", "client-b", "client-c"]);
+        let balancer = LoadBalancer::new(BalanceStrategy::ConsistentHash);
+
+        let keys: Vec<String> = (0..50).map(|i| format!("192.168.1.{}:50000", i)).collect();
+        let before: Vec<String> = keys
+            .iter()
+            .map(|k| balancer.pick("web", &members, Some(k)).unwrap().client_id.clone())
+            .collect();
+        // 同じキーは同じメンバーへ
+        assert_eq!(balancer.pick("web", &members, Some(&keys[0])).unwrap().client_id, before[0]);
+
+        // client-bが抜けても、他のメンバーに割り当てられていたキーは移らない
+        let remaining: Vec<Arc<TunnelCounters>> =
+            members.iter().filter(|m| m.client_id != "client-b").cloned().collect();
+        for (key, owner) in keys.iter().zip(&before) {
+            let now = balancer.pick("web", &remaining, Some(key)).unwrap();
+            if owner != "client-b" {
+                assert_eq!(&now.client_id, owner);
+            }
+        }
+    }
+}
@@ -0,0 +1,405 @@
+// Client接続の処理
+//
+// TLS接続1本につき1タスクで、登録・トンネル作成・Heartbeat・TunnelDataを処理する。
+// 書き込みは送信タスクに集め、応答と切断要求とストリームの返送が同じ接続へ並行して書かれないようにする
+
+use super::stream::{self, ClientLink, StreamSet};
+use super::{Router, SessionOutcome};
+use crate::common::error::{Error, Result};
+use crate::protocol::messages::{
+    ClientRegister, DisconnectMessage, ErrorMessage, Message, MessagePayload, MessageType, MessageVersion,
+    TunnelCreate, TunnelCreateResponse, TunnelData, TunnelDataResponse,
+};
+use crate::protocol::{CodecError, MessageCodec, Negotiated, PayloadCompressor, StreamAck};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadHalf};
+use tokio::sync::{mpsc, Notify};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+// 1メッセージの上限（Client側のProtocolHandlerConfigの既定値に合わせる）
+pub(crate) const MAX_MESSAGE_SIZE: u32 = 1024 * 1024;
+
+// 接続してからClientRegisterを待つ時間
+const REGISTER_TIMEOUT: Duration = Duration::from_secs(30);
+
+// 送信待ちのメッセージ数。詰まったら受信側も待たせて背圧をかける
+const OUTBOUND_QUEUE: usize = 256;
+
+// 接続が終わった理由。Clientの登録を残すかどうかが変わる
+enum Closed {
+    // 通信が途切れた。再開が有効ならセッションを猶予期間だけ残す
+    Lost,
+    // Clientが切断を通知した、またはRouterが切断を要求した
+    Finished,
+}
+
+pub(super) async fn serve<S>(router: Arc<Router>, stream: S, remote_addr: SocketAddr) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let codec = Arc::new(MessageCodec::new(MAX_MESSAGE_SIZE));
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let (outbound, mut outbound_rx) = mpsc::channel::<Message>(OUTBOUND_QUEUE);
+
+    let writer_codec = Arc::clone(&codec);
+    let writer_task = tokio::spawn(async move {
+        while let Some(message) = outbound_rx.recv().await {
+            if let Err(e) = writer_codec.write_message(&mut writer, &message).await {
+                debug!("Failed to write to {}: {}", remote_addr, e);
+                break;
+            }
+        }
+        let _ = writer.shutdown().await;
+    });
+
+    let result = match register(&router, &codec, &mut reader, outbound, remote_addr).await {
+        Ok(Some(mut connection)) => {
+            let result = connection.run(&codec, &mut reader).await;
+            connection.close(result.as_ref().map_or(&Closed::Lost, |closed| closed)).await;
+            result.map(|_| ())
+        }
+        Ok(None) => Ok(()),
+        Err(e) => Err(e),
+    };
+
+    // 送信側を全て手放してから、送信待ちの応答を書き切るのを待つ
+    let _ = writer_task.await;
+    result
+}
+
+// 最初のメッセージで登録を受け付ける。登録できなければNone
+async fn register<S>(
+    router: &Arc<Router>,
+    codec: &MessageCodec,
+    reader: &mut ReadHalf<S>,
+    outbound: mpsc::Sender<Message>,
+    remote_addr: SocketAddr,
+) -> Result<Option<ClientConnection>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let message = match tokio::time::timeout(REGISTER_TIMEOUT, codec.read_message(reader)).await {
+        Ok(Ok(message)) => message,
+        Ok(Err(CodecError::ConnectionClosed)) => return Ok(None),
+        Ok(Err(e)) => return Err(Error::Protocol(e.to_string())),
+        Err(_) => {
+            debug!("No registration from {} within {:?}", remote_addr, REGISTER_TIMEOUT);
+            return Ok(None);
+        }
+    };
+
+    let register: ClientRegister = match message.payload {
+        MessagePayload::ClientRegister(register) => register,
+        _ => {
+            let error = error_message(
+                message.id,
+                message.version,
+                "REGISTRATION_REQUIRED",
+                format!("Expected ClientRegister, got {:?}", message.message_type),
+            );
+            let _ = outbound.send(error).await;
+            return Ok(None);
+        }
+    };
+
+    // TODO: Ed25519署名の検証（Clientが公開鍵と署名を送るようになってから）
+    let negotiated = router.negotiate_registration(&register);
+    let Ok(agreed) = negotiated.as_ref() else {
+        warn!("Rejected client {} from {}: {:?}", register.client_id, remote_addr, negotiated.as_ref().err());
+        let response = MessagePayload::ClientRegisterResponse(router.build_register_response(&negotiated, None));
+        let response = reply(message.id, MessageVersion::default(), MessageType::ClientRegisterResponse, response);
+        let _ = outbound.send(response).await;
+        return Ok(None);
+    };
+    let agreed = agreed.clone();
+    let client_id = register.client_id.to_string();
+
+    // 切断前のストリームが残っていれば、再開の判定が済むまでその送信を止めておく
+    // （判定から再送までの間に転送先から届いたデータも再送に含めるため）
+    let parked = router.parked.remove(&client_id).map(|(_, streams)| streams);
+    let link = parked.as_ref().map(|streams| Arc::clone(&streams.link));
+    let held = match link.as_ref() {
+        Some(link) => Some(link.hold().await),
+        None => None,
+    };
+
+    let session = router.open_session(&register, &agreed, remote_addr);
+    let response = MessagePayload::ClientRegisterResponse(router.build_register_response(&negotiated, Some(&session)));
+    let response = reply(message.id, agreed.version, MessageType::ClientRegisterResponse, response);
+    let _ = outbound.send(response).await;
+
+    let (streams, disconnect) = match (session, parked, held) {
+        (SessionOutcome::Resumed(resumed), Some(streams), Some(held)) => {
+            info!("Client {} resumed its session; replaying {} frames", client_id, resumed.replay.len());
+            held.attach(outbound.clone(), agreed.version, resumed.replay).await;
+            (streams, resumed.disconnect)
+        }
+        (session, _, _) => {
+            // 引き継げないストリームは転送先との接続ごと閉じる
+            let disconnect = Arc::clone(session.disconnect());
+            if let SessionOutcome::Resumed(resumed) = session {
+                stream::send_replay(&outbound, agreed.version, resumed.replay).await;
+            }
+            let link = ClientLink::new(outbound.clone(), agreed.version, router.state().client_session(&client_id));
+            (StreamSet::new(link), disconnect)
+        }
+    };
+    info!("Client {} ({}) connected from {}", register.client_name, client_id, remote_addr);
+
+    Ok(Some(ClientConnection {
+        router: Arc::clone(router),
+        client_id,
+        remote_addr,
+        negotiated: agreed,
+        outbound,
+        disconnect,
+        streams,
+    }))
+}
+
+// 登録済みClientとの接続
+struct ClientConnection {
+    router: Arc<Router>,
+    client_id: String,
+    remote_addr: SocketAddr,
+    negotiated: Negotiated,
+    outbound: mpsc::Sender<Message>,
+    // 管理APIやドレインからの切断要求
+    disconnect: Arc<Notify>,
+    // 再開時は切断前の接続から引き継ぐ
+    streams: StreamSet,
+}
+
+impl ClientConnection {
+    async fn run<S>(&mut self, codec: &MessageCodec, reader: &mut ReadHalf<S>) -> Result<Closed>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        // ループの外で作っておき、待機していない間のnotify_waitersも取りこぼさない
+        let router = Arc::clone(&self.router);
+        let shutdown = router.shutdown.notified();
+        tokio::pin!(shutdown);
+        let disconnect = Arc::clone(&self.disconnect);
+
+        loop {
+            tokio::select! {
+                read = codec.read_message(reader) => match read {
+                    Ok(message) => {
+                        if !self.handle_message(message).await {
+                            return Ok(Closed::Finished);
+                        }
+                    }
+                    Err(CodecError::ConnectionClosed) => return Ok(Closed::Lost),
+                    Err(e) => return Err(Error::Protocol(e.to_string())),
+                },
+                _ = self.streams.reap() => {}
+                _ = disconnect.notified() => {
+                    self.send_disconnect("Disconnected by router", None).await;
+                    return Ok(Closed::Finished);
+                }
+                _ = &mut shutdown => {
+                    self.send_disconnect("Router is shutting down", Some(5)).await;
+                    return Ok(Closed::Finished);
+                }
+            }
+        }
+    }
+
+    // 接続を続けるならtrue
+    async fn handle_message(&mut self, message: Message) -> bool {
+        match message.payload {
+            MessagePayload::TunnelCreate(create) => {
+                let response = MessagePayload::TunnelCreateResponse(self.create_tunnel(&create));
+                self.send(self.reply(message.id, MessageType::TunnelCreateResponse, response))
+                    .await;
+            }
+            MessagePayload::TunnelData(data) => {
+                let response = MessagePayload::TunnelDataResponse(self.handle_tunnel_data(data).await);
+                self.send(self.reply(message.id, MessageType::TunnelDataResponse, response))
+                    .await;
+            }
+            MessagePayload::TunnelDataResponse(ack) => {
+                // Routerから送ったデータの受信確認
+                self.streams.link.acknowledge(&[StreamAck {
+                    tunnel_id: ack.tunnel_id,
+                    connection_id: ack.connection_id,
+                    sequence: ack.ack_sequence,
+                }]);
+            }
+            MessagePayload::Heartbeat(_) => {
+                let response = MessagePayload::HeartbeatResponse(self.router.build_heartbeat_response(&self.client_id));
+                self.send(self.reply(message.id, MessageType::HeartbeatResponse, response))
+                    .await;
+            }
+            MessagePayload::Disconnect(disconnect) => {
+                info!("Client {} disconnected: {}", self.client_id, disconnect.reason);
+                // 処理中の接続があれば、ドレインが終わって切断を要求するまで接続を残す
+                let busy = self.router.state().client_active_connections(&self.client_id) > 0;
+                return busy && self.router.drain_client(&self.client_id);
+            }
+            MessagePayload::Error(error) => {
+                warn!("Client {} reported an error: {} {}", self.client_id, error.code, error.message);
+            }
+            _ => {
+                self.send(error_message(
+                    message.id,
+                    self.negotiated.version,
+                    "UNEXPECTED_MESSAGE",
+                    format!("Unexpected {:?} from a registered client", message.message_type),
+                ))
+                .await;
+            }
+        }
+        true
+    }
+
+    fn create_tunnel(&mut self, create: &TunnelCreate) -> TunnelCreateResponse {
+        let result = self
+            .router
+            .check_tunnel_create(&self.client_id, create)
+            .map_err(Error::from)
+            .and_then(|create| {
+                // 合意した圧縮はRouterからClientへ返すデータにも使う
+                let compressor = create
+                    .config
+                    .compression_enabled
+                    .then(|| PayloadCompressor::new(create.config.compression.clone()))
+                    .transpose()?;
+                self.router.register_tunnel(&self.client_id, &create)?;
+                Ok((create, compressor))
+            });
+        match result {
+            Ok((create, compressor)) => {
+                if let Some(compressor) = compressor {
+                    self.streams.compressors.insert(create.tunnel_id, Arc::new(Mutex::new(compressor)));
+                }
+                self.streams.tunnels.insert(create.tunnel_id, create.config);
+                info!("Tunnel {} ({}) created for client {}", create.tunnel_name, create.tunnel_id, self.client_id);
+                TunnelCreateResponse { tunnel_id: create.tunnel_id, success: true, router_port: None, error: None }
+            }
+            Err(e) => {
+                warn!("Rejected tunnel {} for client {}: {}", create.tunnel_name, self.client_id, e);
+                TunnelCreateResponse {
+                    tunnel_id: create.tunnel_id,
+                    success: false,
+                    router_port: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        }
+    }
+
+    // Clientからのデータを転送先へ渡し、受信確認を返す。新しいconnection_idなら転送先へ接続する
+    async fn handle_tunnel_data(&mut self, data: TunnelData) -> TunnelDataResponse {
+        let key = (data.tunnel_id, data.connection_id);
+        let mut response = TunnelDataResponse {
+            tunnel_id: data.tunnel_id,
+            connection_id: data.connection_id,
+            data: None,
+            ack_sequence: data.sequence,
+            error: None,
+        };
+
+        let payload = match data.payload(MAX_MESSAGE_SIZE as usize) {
+            Ok(payload) => payload,
+            Err(e) => {
+                response.error = Some(e.to_string());
+                return response;
+            }
+        };
+        // 再開後にClientが再送した、切断前に受け取り済みのデータ
+        if !self.streams.link.record_received(&data) {
+            debug!("Dropping duplicate tunnel data (sequence {})", data.sequence);
+            return response;
+        }
+
+        let stream = match self.streams.get_mut(&key) {
+            Some(stream) => stream,
+            // 開いていないストリームの終端は、既に閉じたストリームの後始末なので何もしない
+            None if payload.is_empty() => return response,
+            None => {
+                let tunnel = self
+                    .router
+                    .state()
+                    .tunnel(&data.tunnel_id.to_string())
+                    .filter(|t| t.client_id == self.client_id);
+                let Some(tunnel) = tunnel else {
+                    response.error = Some(format!("Unknown tunnel: {}", data.tunnel_id));
+                    return response;
+                };
+                // 同じサービスのメンバーから転送先を選ぶ。ConsistentHashではClientごとに同じメンバーへ送る
+                let balance_key = self.remote_addr.ip().to_string();
+                let Some(member) = self.router.select_tunnel(&tunnel.service, Some(&balance_key)) else {
+                    response.error = Some(format!("No available member for service {}", tunnel.service));
+                    return response;
+                };
+                self.streams.open(&self.router, member, key)
+            }
+        };
+        stream.forward(payload).await;
+        response
+    }
+
+    async fn send_disconnect(&self, reason: &str, reconnect_delay_seconds: Option<u64>) {
+        let message = Message::new(
+            MessageType::Disconnect,
+            MessagePayload::Disconnect(DisconnectMessage {
+                reason: reason.to_string(),
+                reconnect_allowed: true,
+                reconnect_delay_seconds,
+            }),
+        )
+        .with_version(self.negotiated.version);
+        self.send(message).await;
+    }
+
+    async fn send(&self, message: Message) {
+        // 送信タスクが終わっていれば、次の読み込みで接続の終了に気付く
+        let _ = self.outbound.send(message).await;
+    }
+
+    fn reply(&self, request_id: Uuid, message_type: MessageType, payload: MessagePayload) -> Message {
+        reply(request_id, self.negotiated.version, message_type, payload)
+    }
+
+    // 接続の後始末。別の接続が同じClientとして登録し直していれば、そちらの状態には触れない
+    // 再開を待つ間は、転送先との接続を残したままストリームを預けておく
+    async fn close(self, closed: &Closed) {
+        let state = self.router.state();
+        if !state.is_current_connection(&self.client_id, &self.disconnect) {
+            return;
+        }
+        match closed {
+            Closed::Lost => {
+                self.streams.link.detach().await;
+                // 再開の要求より先に預けておく（預ける前に再開されるとストリームを失う）
+                self.router.parked.insert(self.client_id.clone(), self.streams);
+                if !self.router.client_disconnected(&self.client_id) {
+                    self.router.parked.remove(&self.client_id);
+                }
+            }
+            Closed::Finished => self.router.unregister_client(&self.client_id),
+        }
+    }
+}
+
+// 応答は要求と同じIDで返す（Clientは保留中の要求をIDで照合する）
+fn reply(request_id: Uuid, version: MessageVersion, message_type: MessageType, payload: MessagePayload) -> Message {
+    let mut message = Message::new(message_type, payload).with_version(version);
+    message.id = request_id;
+    message
+}
+
+// 要求へのエラー応答。応答待ちのClientが待ち続けないよう、これも要求と同じIDで返す
+fn error_message(request_id: Uuid, version: MessageVersion, code: &str, message: String) -> Message {
+    let payload = MessagePayload::Error(ErrorMessage {
+        code: code.to_string(),
+        message,
+        details: None,
+        related_message_id: Some(request_id),
+    });
+    reply(request_id, version, MessageType::Error, payload)
+}
@@ -0,0 +1,463 @@
+// Router側サービス（--source）のヘルスチェック
+//
+// トンネル自体は生きていても転送先サービスが落ちているケースを切り分けるため、
+// Routerから各ターゲットへ定期的にプローブを実行し、結果をClientへ通知する
+
+use crate::protocol::messages::{TargetHealth, TargetState};
+use chrono::Utc;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+// ヘルスチェック設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheckConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_interval_seconds")]
+    pub interval_seconds: u64,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    // 一時的なパケットロスで即Downにしないよう、連続失敗回数で判定する
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+    #[serde(default = "default_healthy_threshold")]
+    pub healthy_threshold: u32,
+    #[serde(default)]
+    pub probe: ProbeKind,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_interval_seconds() -> u64 {
+    10
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_unhealthy_threshold() -> u32 {
+    3
+}
+
+fn default_healthy_threshold() -> u32 {
+    1
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            interval_seconds: default_interval_seconds(),
+            timeout_ms: default_timeout_ms(),
+            unhealthy_threshold: default_unhealthy_threshold(),
+            healthy_threshold: default_healthy_threshold(),
+            probe: ProbeKind::default(),
+        }
+    }
+}
+
+// プローブ種別
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ProbeKind {
+    // TCP接続のみ確認
+    #[default]
+    Tcp,
+    // HTTP GETを送信し、2xx/3xxをUpとみなす
+    Http {
+        #[serde(default = "default_http_path")]
+        path: String,
+    },
+    // TLSハンドシェイクの成否のみ確認（証明書は検証しない）
+    Tls {
+        #[serde(default)]
+        server_name: Option<String>,
+    },
+}
+
+fn default_http_path() -> String {
+    "/".to_string()
+}
+
+// ターゲットごとの判定状態
+#[derive(Debug, Clone)]
+struct TargetEntry {
+    probe: ProbeKind,
+    health: TargetHealth,
+    consecutive_successes: u32,
+}
+
+// Router側サービスのヘルスモニター
+pub struct HealthMonitor {
+    config: HealthCheckConfig,
+    targets: Arc<DashMap<SocketAddr, TargetEntry>>,
+    shutdown: Arc<Notify>,
+}
+
+impl HealthMonitor {
+    pub fn new(config: HealthCheckConfig) -> Self {
+        Self {
+            config,
+            targets: Arc::new(DashMap::new()),
+            shutdown: Arc::new(Notify::new()),
+        }
+    }
+
+    // 監視対象の登録（トンネル確立時に呼ぶ）
+    pub fn register_target(&self, target_addr: SocketAddr, probe: Option<ProbeKind>) {
+        let probe = probe.unwrap_or_else(|| self.config.probe.clone());
+        self.targets.entry(target_addr).or_insert_with(|| TargetEntry {
+            probe,
+            health: TargetHealth::unknown(target_addr),
+            consecutive_successes: 0,
+        });
+        debug!("Registered health check target: {}", target_addr);
+    }
+
+    // 監視対象の解除（同じターゲットを使う全トンネルが閉じた時に呼ぶ）
+    pub fn unregister_target(&self, target_addr: &SocketAddr) {
+        self.targets.remove(target_addr);
+        debug!("Unregistered health check target: {}", target_addr);
+    }
+
+    // in_useに含まれないターゲットの監視をまとめて解除する
+    pub fn retain_targets(&self, in_use: &HashSet<SocketAddr>) {
+        self.targets.retain(|addr, _| {
+            let keep = in_use.contains(addr);
+            if !keep {
+                debug!("Unregistered health check target: {}", addr);
+            }
+            keep
+        });
+    }
+
+    // 単一ターゲットの現在の状態
+    pub fn target_health(&self, target_addr: &SocketAddr) -> Option<TargetHealth> {
+        self.targets.get(target_addr).map(|entry| entry.health.clone())
+    }
+
+    // 全ターゲットの現在の状態
+    pub fn snapshot(&self) -> Vec<TargetHealth> {
+        let mut health: Vec<TargetHealth> = self
+            .targets
+            .iter()
+            .map(|entry| entry.health.clone())
+            .collect();
+        health.sort_by_key(|h| h.target_addr);
+        health
+    }
+
+    // 定期プローブを開始
+    pub fn start(&self) {
+        if !self.config.enabled {
+            info!("Target health checks are disabled");
+            return;
+        }
+
+        let config = self.config.clone();
+        let targets = Arc::clone(&self.targets);
+        let shutdown = Arc::clone(&self.shutdown);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(config.interval_seconds.max(1)));
+            loop {
+                tokio::select! {
+                    _ = shutdown.notified() => {
+                        debug!("Health monitor stopped");
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        Self::probe_all(&config, &targets).await;
+                    }
+                }
+            }
+        });
+    }
+
+    // 定期プローブを停止
+    // プローブ中で待機していない時に呼ばれても取りこぼさないよう、許可を残すnotify_oneを使う
+    pub fn stop(&self) {
+        self.shutdown.notify_one();
+    }
+
+    // 全ターゲットを一巡プローブする
+    pub async fn probe_once(&self) {
+        Self::probe_all(&self.config, &self.targets).await;
+    }
+
+    async fn probe_all(config: &HealthCheckConfig, targets: &DashMap<SocketAddr, TargetEntry>) {
+        // DashMapのロックを跨いでawaitしないよう、対象を先に取り出す
+        let probes: Vec<(SocketAddr, ProbeKind)> = targets
+            .iter()
+            .map(|entry| (*entry.key(), entry.probe.clone()))
+            .collect();
+
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let tasks: Vec<_> = probes
+            .into_iter()
+            .map(|(addr, probe)| {
+                tokio::spawn(async move {
+                    let started = Instant::now();
+                    let result = run_probe(addr, &probe, timeout).await;
+                    (addr, result, started.elapsed())
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            let Ok((addr, result, elapsed)) = task.await else {
+                continue;
+            };
+            if let Some(mut entry) = targets.get_mut(&addr) {
+                Self::apply_result(config, &mut entry, result, elapsed);
+            }
+        }
+    }
+
+    fn apply_result(
+        config: &HealthCheckConfig,
+        entry: &mut TargetEntry,
+        result: Result<(), String>,
+        elapsed: Duration,
+    ) {
+        let previous = entry.health.state;
+        entry.health.checked_at = Utc::now();
+
+        match result {
+            Ok(()) => {
+                entry.consecutive_successes += 1;
+                entry.health.consecutive_failures = 0;
+                entry.health.latency_ms = Some(elapsed.as_secs_f64() * 1000.0);
+                entry.health.error = None;
+                if previous != TargetState::Up && entry.consecutive_successes >= config.healthy_threshold {
+                    entry.health.state = TargetState::Up;
+                }
+            }
+            Err(e) => {
+                entry.consecutive_successes = 0;
+                entry.health.consecutive_failures += 1;
+                entry.health.latency_ms = None;
+                entry.health.error = Some(e);
+                if previous != TargetState::Down && entry.health.consecutive_failures >= config.unhealthy_threshold {
+                    entry.health.state = TargetState::Down;
+                }
+            }
+        }
+
+        if previous != entry.health.state {
+            match entry.health.state {
+                TargetState::Down => warn!(
+                    "Target {} is down: {}",
+                    entry.health.target_addr,
+                    entry.health.error.as_deref().unwrap_or("unknown error")
+                ),
+                _ => info!("Target {} is {}", entry.health.target_addr, entry.health.state.as_str()),
+            }
+        }
+    }
+}
+
+impl Drop for HealthMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// プローブ種別に応じた疎通確認
+pub async fn run_probe(addr: SocketAddr, probe: &ProbeKind, timeout: Duration) -> Result<(), String> {
+    let fut = async {
+        match probe {
+            ProbeKind::Tcp => probe_tcp(addr).await,
+            ProbeKind::Http { path } => probe_http(addr, path).await,
+            ProbeKind::Tls { server_name } => probe_tls(addr, server_name.as_deref()).await,
+        }
+    };
+
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("probe timed out after {}ms", timeout.as_millis())),
+    }
+}
+
+async fn probe_tcp(addr: SocketAddr) -> Result<(), String> {
+    TcpStream::connect(addr)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("connect failed: {}", e))
+}
+
+async fn probe_http(addr: SocketAddr, path: &str) -> Result<(), String> {
+    let mut stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| format!("connect failed: {}", e))?;
+
+    // 依存を増やさないよう、ステータス行だけを読む最小限のHTTP/1.1リクエスト
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: conduit-health/{}\r\nConnection: close\r\n\r\n",
+        path,
+        addr,
+        env!("CARGO_PKG_VERSION")
+    );
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("write failed: {}", e))?;
+
+    let mut buf = [0u8; 64];
+    let n = stream
+        .read(&mut buf)
+        .await
+        .map_err(|e| format!("read failed: {}", e))?;
+
+    let status = parse_http_status(&buf[..n]).ok_or_else(|| "invalid HTTP response".to_string())?;
+    if (200..400).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("HTTP status {}", status))
+    }
+}
+
+fn parse_http_status(response: &[u8]) -> Option<u16> {
+    let line = std::str::from_utf8(response).ok()?.lines().next()?;
+    let mut parts = line.split_whitespace();
+    if !parts.next()?.starts_with("HTTP/") {
+        return None;
+    }
+    parts.next()?.parse().ok()
+}
+
+async fn probe_tls(addr: SocketAddr, server_name: Option<&str>) -> Result<(), String> {
+    let tls_config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(NoCertificateVerification))
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(tls_config));
+
+    let server_name = match server_name {
+        Some(name) => rustls::ServerName::try_from(name).map_err(|e| format!("invalid server name: {}", e))?,
+        None => rustls::ServerName::IpAddress(addr.ip()),
+    };
+
+    let stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| format!("connect failed: {}", e))?;
+    connector
+        .connect(server_name, stream)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("TLS handshake failed: {}", e))
+}
+
+// 到達性の確認が目的なので、ターゲットの証明書の信頼性は問わない
+struct NoCertificateVerification;
+
+impl rustls::client::ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: std::time::SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn closed_port() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_tcp_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(run_probe(addr, &ProbeKind::Tcp, Duration::from_secs(1)).await.is_ok());
+
+        let closed = closed_port().await;
+        assert!(run_probe(closed, &ProbeKind::Tcp, Duration::from_secs(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_http_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 256];
+            let _ = socket.read(&mut buf).await;
+            socket.write_all(b"HTTP/1.1 503 Service Unavailable\r\n\r\n").await.unwrap();
+        });
+
+        let probe = ProbeKind::Http { path: "/healthz".to_string() };
+        let result = run_probe(addr, &probe, Duration::from_secs(1)).await;
+        assert_eq!(result.unwrap_err(), "HTTP status 503");
+    }
+
+    #[test]
+    fn test_parse_http_status() {
+        assert_eq!(parse_http_status(b"HTTP/1.1 200 OK\r\n"), Some(200));
+        assert_eq!(parse_http_status(b"HTTP/1.0 302 Found\r\n"), Some(302));
+        assert_eq!(parse_http_status(b"SSH-2.0-OpenSSH\r\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_threshold() {
+        let config = HealthCheckConfig {
+            unhealthy_threshold: 2,
+            timeout_ms: 500,
+            ..Default::default()
+        };
+        let monitor = HealthMonitor::new(config);
+        let closed = closed_port().await;
+        monitor.register_target(closed, None);
+
+        // 1回目の失敗ではまだDownにしない
+        monitor.probe_once().await;
+        let health = monitor.target_health(&closed).unwrap();
+        assert_eq!(health.state, TargetState::Unknown);
+        assert_eq!(health.consecutive_failures, 1);
+
+        monitor.probe_once().await;
+        let health = monitor.target_health(&closed).unwrap();
+        assert_eq!(health.state, TargetState::Down);
+        assert!(health.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_target_recovers() {
+        let monitor = HealthMonitor::new(HealthCheckConfig::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        monitor.register_target(addr, None);
+
+        monitor.probe_once().await;
+        let snapshot = monitor.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].state, TargetState::Up);
+        assert!(snapshot[0].latency_ms.is_some());
+
+        monitor.unregister_target(&addr);
+        assert!(monitor.snapshot().is_empty());
+    }
+}
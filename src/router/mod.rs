@@ -2,41 +2,1112 @@
 //
 // Client接続を受け入れ、ターゲットサービスにトラフィックを転送するRouter側機能を実装
 
-use crate::common::error::Result;
+pub mod balancer;
+mod connection;
+pub mod health;
+pub mod sources;
+pub mod state;
+mod stream;
+
+pub use balancer::{BalanceStrategy, LoadBalancer, LoadBalancingConfig};
+pub use health::{HealthCheckConfig, HealthMonitor, ProbeKind};
+pub use sources::{OutlierDetectionConfig, SourcePool, SourceStats};
+pub use state::{ResumedSession, RouterState, ServiceSummary, TunnelCounters};
+
+use crate::api::ApiConfig;
+use crate::common::error::{Error, Result};
+use crate::metrics::MetricsConfig;
+use crate::protocol::messages::{ClientRegister, ClientRegisterResponse, HeartbeatResponse, TargetState, TunnelCreate};
+use crate::protocol::{
+    Capability, Negotiated, ProtocolModuleError, ProtocolResult, ProtocolSupport, ResumptionConfig, SessionTicket,
+};
+use crate::security::{AuthManager, TlsConfig, TlsServerConfig};
+use dashmap::DashMap;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::{Notify, RwLock};
+use tokio_rustls::TlsAcceptor;
+
+// TLSハンドシェイクを待つ時間（確立しないまま接続を占有させない）
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
 
+// Routerの設定ファイル（省略したセクションは既定値）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RouterConfig {
     pub bind_addr: SocketAddr,
     pub private_key_path: Option<PathBuf>,
+    pub tls: TlsConfig,
+    pub health_check: HealthCheckConfig,
+    pub api: ApiConfig,
+    pub metrics: MetricsConfig,
+    pub resumption: ResumptionConfig,
+    pub load_balancing: LoadBalancingConfig,
+    pub outlier_detection: OutlierDetectionConfig,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: SocketAddr::from(([0, 0, 0, 0], 9999)),
+            private_key_path: None,
+            tls: TlsConfig::default(),
+            health_check: HealthCheckConfig::default(),
+            api: ApiConfig::default(),
+            metrics: MetricsConfig::default(),
+            resumption: ResumptionConfig::default(),
+            load_balancing: LoadBalancingConfig::default(),
+            outlier_detection: OutlierDetectionConfig::default(),
+        }
+    }
+}
+
+impl RouterConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::config(format!("Failed to read router config {}: {}", path.display(), e)))?;
+        toml::from_str(&content)
+            .map_err(|e| Error::config(format!("Failed to parse router config {}: {}", path.display(), e)))
+    }
+
+    // 起動前に検出できる設定の誤り（証明書の読み込みはstartで行う）
+    pub fn validate(&self) -> Result<()> {
+        if self.tls.cert_file.is_none() || self.tls.key_file.is_none() {
+            return Err(Error::config("Router requires tls.cert_file and tls.key_file"));
+        }
+        self.api.validate()?;
+        if self.metrics.enabled {
+            self.metrics.validate()?;
+        }
+        Ok(())
+    }
+}
+
+// 登録時のセッションの扱い
+#[derive(Debug)]
+pub enum SessionOutcome {
+    // 新しいセッション。再開を合意したClientにはチケットを発行する
+    Started {
+        session_id: uuid::Uuid,
+        ticket: Option<SessionTicket>,
+        disconnect: Arc<Notify>,
+    },
+    // 切断前のセッションを引き継いだ
+    Resumed(ResumedSession),
+}
+
+impl SessionOutcome {
+    pub fn session_id(&self) -> uuid::Uuid {
+        match self {
+            SessionOutcome::Started { session_id, .. } => *session_id,
+            SessionOutcome::Resumed(resumed) => resumed.session_id,
+        }
+    }
+
+    pub fn disconnect(&self) -> &Arc<Notify> {
+        match self {
+            SessionOutcome::Started { disconnect, .. } => disconnect,
+            SessionOutcome::Resumed(resumed) => &resumed.disconnect,
+        }
+    }
 }
 
 pub struct Router {
     config: RouterConfig,
+    state: Arc<RouterState>,
+    auth_manager: Arc<RwLock<AuthManager>>,
+    health_monitor: Arc<HealthMonitor>,
+    balancer: LoadBalancer,
+    protocol: ProtocolSupport,
+    started_at: chrono::DateTime<chrono::Utc>,
+    shutdown: Arc<Notify>,
+    // 再開を待っているClientのストリーム（転送先との接続を猶予期間だけ残す）
+    parked: DashMap<String, stream::StreamSet>,
 }
 
 impl Router {
-    pub fn new(config: RouterConfig) -> Self {
-        Self { config }
+    pub fn new(config: RouterConfig, auth_manager: AuthManager) -> Self {
+        let health_monitor = Arc::new(HealthMonitor::new(config.health_check.clone()));
+        let balancer = LoadBalancer::new(config.load_balancing.strategy);
+        Self {
+            config,
+            state: Arc::new(RouterState::new()),
+            auth_manager: Arc::new(RwLock::new(auth_manager)),
+            health_monitor,
+            balancer,
+            protocol: ProtocolSupport::default(),
+            started_at: chrono::Utc::now(),
+            shutdown: Arc::new(Notify::new()),
+            parked: DashMap::new(),
+        }
     }
-    
-    pub async fn start(&self) -> Result<()> {
+
+    pub async fn start(self: &Arc<Self>) -> Result<()> {
         tracing::info!("Starting Conduit Router on {}", self.config.bind_addr);
-        
-        // TODO: TLS 1.3 + Ed25519認証によるRouterサーバー実装
-        // 1. TLS設定のセットアップ
-        // 2. Client接続の待機開始
-        // 3. トンネル確立要求の処理
-        // 4. アクティブトンネルと接続の管理
-        
+
+        // 証明書やポートの問題は他のサービスを起動する前に返す
+        let acceptor = TlsServerConfig::new(&self.config.tls)
+            .map_err(|e| Error::tls(e.to_string()))?
+            .acceptor();
+        let listener = TcpListener::bind(self.config.bind_addr)
+            .await
+            .map_err(|e| Error::network(format!("Failed to bind {}: {}", self.config.bind_addr, e)))?;
+
+        // ターゲットの死活はトンネルの確立と独立して監視する
+        self.health_monitor.start();
+
+        if self.config.api.enabled {
+            self.start_api()?;
+        }
+        if self.config.metrics.enabled {
+            self.start_metrics()?;
+        }
+        if self.config.resumption.enabled {
+            self.start_session_sweeper();
+        }
+
+        self.start_listener(listener, acceptor);
+        Ok(())
+    }
+
+    // Client接続を受け付け、TLSハンドシェイク後に接続ごとのタスクへ渡す
+    fn start_listener(self: &Arc<Self>, listener: TcpListener, acceptor: TlsAcceptor) {
+        let router = Arc::clone(self);
+
+        tokio::spawn(async move {
+            let shutdown = router.shutdown.notified();
+            tokio::pin!(shutdown);
+            loop {
+                let (stream, remote_addr) = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            // ファイルディスクリプタ不足などで空回りしないよう少し待つ
+                            tracing::warn!("Failed to accept connection: {}", e);
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            continue;
+                        }
+                    },
+                    _ = &mut shutdown => break,
+                };
+
+                let router = Arc::clone(&router);
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let stream = match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => stream,
+                        Ok(Err(e)) => {
+                            if router.config.tls.require_client_cert {
+                                router.state.record_auth_failure();
+                            }
+                            tracing::debug!("TLS handshake with {} failed: {}", remote_addr, e);
+                            return;
+                        }
+                        Err(_) => {
+                            tracing::debug!("TLS handshake with {} timed out", remote_addr);
+                            return;
+                        }
+                    };
+                    if let Err(e) = router.serve_connection(stream, remote_addr).await {
+                        tracing::warn!("Connection from {} closed: {}", remote_addr, e);
+                    }
+                });
+            }
+        });
+    }
+
+    // 1本のClient接続を、切断されるかRouterが停止するまで処理する
+    pub async fn serve_connection<S>(self: Arc<Self>, stream: S, remote_addr: SocketAddr) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        connection::serve(self, stream, remote_addr).await
+    }
+
+    // 猶予期間を過ぎた切断済みセッションを定期的に破棄する
+    fn start_session_sweeper(self: &Arc<Self>) {
+        let router = Arc::clone(self);
+        let interval = (self.config.resumption.grace_period() / 4).max(std::time::Duration::from_secs(1));
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            let shutdown = router.shutdown.notified();
+            tokio::pin!(shutdown);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        // 再開されなかったClientの転送先との接続も閉じる
+                        let expired = router.state.expire_detached_sessions();
+                        for client_id in &expired {
+                            router.parked.remove(client_id);
+                        }
+                        if !expired.is_empty() {
+                            router.release_health_targets();
+                        }
+                    }
+                    _ = &mut shutdown => break,
+                }
+            }
+        });
+    }
+
+    #[cfg(feature = "api")]
+    fn start_api(&self) -> Result<()> {
+        // 起動前に検証し、認証なしの公開設定ならRouter自体を起動させない
+        self.config.api.validate()?;
+
+        let api_config = self.config.api.clone();
+        let api_state = crate::api::ApiState {
+            router_state: Arc::clone(&self.state),
+            auth_manager: Arc::clone(&self.auth_manager),
+            health_monitor: Arc::clone(&self.health_monitor),
+            started_at: self.started_at,
+            bind_addr: self.config.bind_addr,
+        };
+        let shutdown = Arc::clone(&self.shutdown);
+
+        tokio::spawn(async move {
+            if let Err(e) = crate::api::serve(&api_config, api_state, shutdown).await {
+                tracing::error!("Management API failed: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    #[cfg(not(feature = "api"))]
+    fn start_api(&self) -> Result<()> {
+        tracing::warn!("Management API is enabled in config but this build does not include the 'api' feature");
+        Ok(())
+    }
+
+    #[cfg(feature = "metrics")]
+    fn start_metrics(&self) -> Result<()> {
+        self.config.metrics.validate()?;
+
+        let metrics_config = self.config.metrics.clone();
+        let source: Arc<dyn crate::metrics::MetricsSource> = Arc::clone(&self.state) as _;
+        let shutdown = Arc::clone(&self.shutdown);
+
+        tokio::spawn(async move {
+            if let Err(e) = crate::metrics::serve(&metrics_config, source, shutdown).await {
+                tracing::error!("Metrics endpoint failed: {}", e);
+            }
+        });
         Ok(())
     }
-    
+
+    #[cfg(not(feature = "metrics"))]
+    fn start_metrics(&self) -> Result<()> {
+        tracing::warn!("Metrics endpoint is enabled in config but this build does not include the 'metrics' feature");
+        Ok(())
+    }
+
     pub async fn stop(&self) -> Result<()> {
         tracing::info!("Stopping Conduit Router");
-        
+
+        self.health_monitor.stop();
+        self.shutdown.notify_waiters();
+        self.parked.clear();
+
         // TODO: グレースフルシャットダウンロジック実装
-        
+
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn state(&self) -> &Arc<RouterState> {
+        &self.state
+    }
+
+    pub fn auth_manager(&self) -> &Arc<RwLock<AuthManager>> {
+        &self.auth_manager
+    }
+
+    pub fn health_monitor(&self) -> &HealthMonitor {
+        &self.health_monitor
+    }
+
+    // ClientRegisterの対応バージョン・機能から使うプロトコルを決める
+    // ネゴシエーション非対応の旧Clientは1.0として受け入れる
+    pub fn negotiate_registration(&self, register: &ClientRegister) -> ProtocolResult<Negotiated> {
+        self.protocol.negotiate(&register.supported_versions, &register.capabilities)
+    }
+
+    // 認証済みClientを登録する。再開の要求に有効なチケットが付いていれば切断前のセッションを引き継ぐ
+    pub fn open_session(
+        &self,
+        register: &ClientRegister,
+        negotiated: &Negotiated,
+        remote_addr: SocketAddr,
+    ) -> SessionOutcome {
+        let client_id = register.client_id.to_string();
+        let resumable = self.config.resumption.enabled && negotiated.capabilities.contains(Capability::Resumption);
+
+        if let (true, Some(resume)) = (resumable, register.resume.as_ref()) {
+            match self.state.resume_client(&client_id, resume, remote_addr) {
+                Ok(resumed) => {
+                    self.state.set_client_protocol(&client_id, negotiated.clone());
+                    return SessionOutcome::Resumed(resumed);
+                }
+                Err(e) => tracing::warn!("Cannot resume session for client {}: {}", client_id, e),
+            }
+        }
+
+        let disconnect = self.state.register_client(&client_id, &register.client_name, remote_addr);
+        self.state.set_client_protocol(&client_id, negotiated.clone());
+        let session_id = uuid::Uuid::new_v4();
+        let ticket = if resumable {
+            self.state
+                .start_session(&client_id, session_id, self.config.resumption.max_buffered_bytes)
+        } else {
+            None
+        };
+        SessionOutcome::Started { session_id, ticket, disconnect }
+    }
+
+    // 接続が切れたClientのセッションを、再開が有効なら猶予期間だけ残す
+    pub fn client_disconnected(&self, client_id: &str) -> bool {
+        if self.config.resumption.enabled {
+            self.state.detach_client(client_id, self.config.resumption.grace_period())
+        } else {
+            self.unregister_client(client_id);
+            false
+        }
+    }
+
+    // Clientの登録をトンネルごと解除し、どのトンネルも使わなくなった転送先の監視をやめる
+    pub fn unregister_client(&self, client_id: &str) {
+        self.state.unregister_client(client_id);
+        self.release_health_targets();
+    }
+
+    fn release_health_targets(&self) {
+        self.health_monitor.retain_targets(&self.state.source_addrs());
+    }
+
+    // 登録応答。セッションは認証とネゴシエーションが成功した場合のみ渡す
+    pub fn build_register_response(
+        &self,
+        negotiated: &ProtocolResult<Negotiated>,
+        session: Option<&SessionOutcome>,
+    ) -> ClientRegisterResponse {
+        match negotiated {
+            Ok(negotiated) => {
+                let mut response = ClientRegisterResponse {
+                    success: true,
+                    session_id: session.map(SessionOutcome::session_id),
+                    server_public_key: None,
+                    error: None,
+                    server_capabilities: negotiated.capabilities.to_names(),
+                    negotiated_version: Some(negotiated.version),
+                    session_ticket: None,
+                    resume_grace_seconds: None,
+                    resumed: false,
+                    acks: Vec::new(),
+                };
+                match session {
+                    Some(SessionOutcome::Started { ticket: Some(ticket), .. }) => {
+                        response.session_ticket = Some(ticket.clone());
+                        response.resume_grace_seconds = Some(self.config.resumption.grace_seconds);
+                    }
+                    Some(SessionOutcome::Resumed(resumed)) => {
+                        response.session_ticket = Some(resumed.ticket.clone());
+                        response.resume_grace_seconds = Some(self.config.resumption.grace_seconds);
+                        response.resumed = true;
+                        response.acks = resumed.acks.clone();
+                    }
+                    _ => {}
+                }
+                response
+            }
+            Err(e) => ClientRegisterResponse {
+                success: false,
+                session_id: None,
+                server_public_key: None,
+                error: Some(e.to_string()),
+                server_capabilities: self.protocol.capabilities.to_names(),
+                negotiated_version: None,
+                session_ticket: None,
+                resume_grace_seconds: None,
+                resumed: false,
+                acks: Vec::new(),
+            },
+        }
+    }
+
+    // トンネル作成要求を、そのClientと合意した機能の範囲に収める
+    pub fn check_tunnel_create(&self, client_id: &str, create: &TunnelCreate) -> ProtocolResult<TunnelCreate> {
+        let negotiated = self.state.client_protocol(client_id).ok_or_else(|| ProtocolModuleError::Handler {
+            message: format!("Client {} has not negotiated a protocol", client_id),
+        })?;
+        negotiated.check_tunnel_create(create)
+    }
+
+    // 確立したトンネルを登録し、サービス名でまとめて全ての転送先の監視を始める
+    pub fn register_tunnel(&self, client_id: &str, create: &TunnelCreate) -> Result<Arc<TunnelCounters>> {
+        let sources = create.source_endpoints();
+        let tunnel = self.state.register_tunnel(
+            &create.tunnel_id.to_string(),
+            client_id,
+            &create.tunnel_name,
+            create.service_name(),
+            &sources,
+        )?;
+        for source in &sources {
+            self.health_monitor.register_target(*source, None);
+        }
+        Ok(tunnel)
+    }
+
+    // サービスへの新しい接続を割り当てるトンネルを選ぶ
+    // keyはConsistentHashで同じ転送先へ送りたい単位（接続元アドレスなど）
+    pub fn select_tunnel(&self, service: &str, key: Option<&str>) -> Option<Arc<TunnelCounters>> {
+        let members: Vec<Arc<TunnelCounters>> = self
+            .state
+            .service_members(service)
+            .into_iter()
+            .filter(|t| t.sources.addrs().iter().any(|addr| self.is_target_available(addr)))
+            .collect();
+        self.balancer.pick(service, &members, key)
+    }
+
+    // トンネルの次の接続の転送先。ヘルスチェックでdownの転送先と外れ値として外した転送先を避ける
+    // 接続の結果はsource_connected / source_connect_failedで知らせる
+    pub fn select_source(&self, tunnel: &TunnelCounters) -> Option<SocketAddr> {
+        tunnel.sources.pick(|addr| self.is_target_available(addr))
+    }
+
+    pub fn source_connected(&self, tunnel: &TunnelCounters, source: SocketAddr, latency: std::time::Duration) {
+        tunnel.sources.connect_succeeded(source);
+        tunnel.connection_opened();
+        tunnel.observe_connect_latency(latency);
+    }
+
+    // 転送先への接続失敗を記録する。続けて失敗した転送先は一定時間候補から外す
+    pub fn source_connect_failed(&self, tunnel: &TunnelCounters, source: SocketAddr) -> bool {
+        tunnel.sources.connect_failed(source, &self.config.outlier_detection)
+    }
+
+    pub fn source_closed(&self, tunnel: &TunnelCounters, source: SocketAddr) {
+        tunnel.sources.connection_closed(source);
+        tunnel.connection_closed();
+    }
+
+    // 未判定の転送先は使えるものとして扱う
+    fn is_target_available(&self, addr: &SocketAddr) -> bool {
+        self.health_monitor
+            .target_health(addr)
+            .map_or(true, |h| h.state != TargetState::Down)
+    }
+
+    // Clientをドレインする。新しい接続の割り当てを止め、処理中の接続が終わるか
+    // drain_timeout_secondsを過ぎたら切断を要求する。トンネルが無ければfalse
+    pub fn drain_client(&self, client_id: &str) -> bool {
+        if self.state.drain_client(client_id) == 0 {
+            return false;
+        }
+
+        let state = Arc::clone(&self.state);
+        let shutdown = Arc::clone(&self.shutdown);
+        let client_id = client_id.to_string();
+        let deadline = tokio::time::Instant::now() + self.config.load_balancing.drain_timeout();
+        tokio::spawn(async move {
+            while state.client_active_connections(&client_id) > 0 && tokio::time::Instant::now() < deadline {
+                tokio::select! {
+                    _ = tokio::time::sleep(std::time::Duration::from_millis(200)) => {}
+                    _ = shutdown.notified() => return,
+                }
+            }
+            state.disconnect_client(&client_id);
+        });
+        true
+    }
+
+    // Heartbeatへの応答にターゲットのヘルス情報を載せる
+    // 他のClientの転送先は見せず、要求したClientのトンネルが使うものだけを返す
+    pub fn build_heartbeat_response(&self, client_id: &str) -> HeartbeatResponse {
+        let traffic = self.state.traffic_stats();
+        let sources = self.state.client_source_addrs(client_id);
+        let mut target_health = self.health_monitor.snapshot();
+        target_health.retain(|health| sources.contains(&health.target_addr));
+        HeartbeatResponse {
+            server_time: chrono::Utc::now(),
+            connected_clients: traffic.connected_clients as u32,
+            total_tunnels: traffic.tunnels as u32,
+            server_load: 0.0,
+            target_health,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::messages::{
+        DisconnectMessage, Heartbeat, Message, MessagePayload, MessageType, MessageVersion, TunnelConfig, TunnelData,
+    };
+    use crate::protocol::{MessageCodec, SessionResume, StreamAck, SUPPORTED_VERSIONS};
+    use crate::security::{KeyManager, KeyRotationConfig};
+    use tokio::io::DuplexStream;
+    use tokio::task::JoinHandle;
+    use uuid::Uuid;
+
+    fn test_router(dir: &std::path::Path) -> Arc<Router> {
+        let key_manager = KeyManager::new(dir, KeyRotationConfig::default()).unwrap();
+        let config = RouterConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            private_key_path: None,
+            tls: TlsConfig::default(),
+            health_check: HealthCheckConfig::default(),
+            api: ApiConfig::default(),
+            metrics: MetricsConfig::default(),
+            resumption: ResumptionConfig::default(),
+            load_balancing: LoadBalancingConfig::default(),
+            outlier_detection: OutlierDetectionConfig::default(),
+        };
+        let auth_manager = AuthManager::new(key_manager, Duration::from_secs(3600), Duration::from_secs(1800));
+        Arc::new(Router::new(config, auth_manager))
+    }
+
+    fn connect(router: &Arc<Router>) -> (DuplexStream, JoinHandle<Result<()>>) {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let remote_addr = "192.168.1.10:50000".parse().unwrap();
+        (client, tokio::spawn(Arc::clone(router).serve_connection(server, remote_addr)))
+    }
+
+    async fn request(stream: &mut DuplexStream, message: Message) -> Message {
+        let codec = MessageCodec::new(connection::MAX_MESSAGE_SIZE);
+        codec.write_message(stream, &message).await.unwrap();
+        let response = codec.read_message(stream).await.unwrap();
+        assert_eq!(response.id, message.id, "responses reuse the request id");
+        response
+    }
+
+    fn register_message(client_id: Uuid, versions: Vec<MessageVersion>, capabilities: &[&str]) -> Message {
+        Message::new(
+            MessageType::ClientRegister,
+            MessagePayload::ClientRegister(ClientRegister {
+                client_id,
+                client_name: "laptop".to_string(),
+                public_key: String::new(),
+                signature: String::new(),
+                client_version: "test".to_string(),
+                capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+                supported_versions: versions,
+                resume: None,
+            }),
+        )
+    }
+
+    fn tunnel_create_message(compression_enabled: bool) -> (Uuid, Message) {
+        tunnel_create_with_sources(&["10.2.0.2:80".parse().unwrap()], compression_enabled)
+    }
+
+    fn tunnel_create_with_sources(sources: &[SocketAddr], compression_enabled: bool) -> (Uuid, Message) {
+        let tunnel_id = Uuid::new_v4();
+        let message = Message::new(
+            MessageType::TunnelCreate,
+            MessagePayload::TunnelCreate(TunnelCreate {
+                tunnel_id,
+                tunnel_name: "web".to_string(),
+                source_addr: sources[0],
+                bind_addr: "127.0.0.1:8080".parse().unwrap(),
+                protocol: "tcp".to_string(),
+                config: TunnelConfig { compression_enabled, ..TunnelConfig::default() },
+                service: None,
+                source_addrs: if sources.len() > 1 { sources.to_vec() } else { Vec::new() },
+            }),
+        );
+        (tunnel_id, message)
+    }
+
+    fn tunnel_data_message(tunnel_id: Uuid, connection_id: Uuid, sequence: u64, payload: &[u8]) -> Message {
+        let data = TunnelData::new(tunnel_id, connection_id, sequence, payload, None);
+        Message::new(MessageType::TunnelData, MessagePayload::TunnelData(data))
+    }
+
+    // 受け取った接続ごとにエコーを返す転送先
+    async fn echo_source() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = socket.split();
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+        addr
+    }
+
+    // 指定したストリームへ返ってきた次のデータ（受信確認は読み飛ばす）
+    async fn next_data(codec: &MessageCodec, stream: &mut DuplexStream, connection_id: Uuid) -> Vec<u8> {
+        loop {
+            let message = codec.read_message(stream).await.unwrap();
+            match message.payload {
+                MessagePayload::TunnelData(data) if data.connection_id == connection_id => {
+                    return data.payload(1024).unwrap();
+                }
+                MessagePayload::TunnelDataResponse(ack) => assert!(ack.error.is_none(), "{:?}", ack.error),
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_create_tunnel_and_disconnect() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+        let (mut stream, handle) = connect(&router);
+        let client_id = Uuid::new_v4();
+
+        let response = request(&mut stream, register_message(client_id, SUPPORTED_VERSIONS.to_vec(), &["tcp", "heartbeat"])).await;
+        let MessagePayload::ClientRegisterResponse(register) = response.payload else {
+            panic!("unexpected response: {:?}", response.message_type);
+        };
+        assert!(register.success);
+        assert_eq!(register.negotiated_version, Some(SUPPORTED_VERSIONS[1]));
+        assert_eq!(response.version, SUPPORTED_VERSIONS[1]);
+        assert!(router.state().is_client_connected(&client_id.to_string()));
+        assert!(router.state().client_protocol(&client_id.to_string()).is_some());
+
+        // 合意していない圧縮は外したうえでトンネルを作る
+        let (tunnel_id, create) = tunnel_create_message(true);
+        let response = request(&mut stream, create).await;
+        let MessagePayload::TunnelCreateResponse(created) = response.payload else {
+            panic!("unexpected response: {:?}", response.message_type);
+        };
+        assert!(created.success, "{:?}", created.error);
+        assert_eq!(created.tunnel_id, tunnel_id);
+        let tunnels = router.state().list_tunnels();
+        assert_eq!(tunnels.len(), 1);
+        assert_eq!(tunnels[0].tunnel_id, tunnel_id.to_string());
+        assert_eq!(router.health_monitor().snapshot().len(), 1);
+
+        let heartbeat = Message::new(
+            MessageType::Heartbeat,
+            MessagePayload::Heartbeat(Heartbeat {
+                client_id,
+                active_tunnels: 1,
+                active_connections: 0,
+                cpu_usage: 0.0,
+                memory_usage: 0,
+            }),
+        );
+        let response = request(&mut stream, heartbeat).await;
+        let MessagePayload::HeartbeatResponse(heartbeat) = response.payload else {
+            panic!("unexpected response: {:?}", response.message_type);
+        };
+        assert_eq!(heartbeat.connected_clients, 1);
+        assert_eq!(heartbeat.total_tunnels, 1);
+        assert_eq!(heartbeat.target_health.len(), 1);
+
+        // 切断を通知したClientはセッションを残さずに登録を外す
+        let disconnect = Message::new(
+            MessageType::Disconnect,
+            MessagePayload::Disconnect(DisconnectMessage {
+                reason: "shutdown".to_string(),
+                reconnect_allowed: false,
+                reconnect_delay_seconds: None,
+            }),
+        );
+        MessageCodec::new(connection::MAX_MESSAGE_SIZE)
+            .write_message(&mut stream, &disconnect)
+            .await
+            .unwrap();
+        handle.await.unwrap().unwrap();
+        assert!(!router.state().is_client_connected(&client_id.to_string()));
+        assert!(router.state().list_tunnels().is_empty());
+        assert_eq!(router.state().traffic_stats().detached_clients, 0);
+        // どのトンネルも使わなくなった転送先は監視しない
+        assert!(router.health_monitor().snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_reports_only_own_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+        let mut clients = Vec::new();
+        for source in ["10.2.0.2:80", "10.9.0.9:80"] {
+            let (mut stream, handle) = connect(&router);
+            let client_id = Uuid::new_v4();
+            request(&mut stream, register_message(client_id, SUPPORTED_VERSIONS.to_vec(), &["tcp"])).await;
+            let (_, create) = tunnel_create_with_sources(&[source.parse().unwrap()], false);
+            request(&mut stream, create).await;
+            clients.push((client_id, stream, handle));
+        }
+        assert_eq!(router.health_monitor().snapshot().len(), 2);
+
+        let (client_id, stream, _) = &mut clients[0];
+        let heartbeat = Message::new(
+            MessageType::Heartbeat,
+            MessagePayload::Heartbeat(Heartbeat {
+                client_id: *client_id,
+                active_tunnels: 1,
+                active_connections: 0,
+                cpu_usage: 0.0,
+                memory_usage: 0,
+            }),
+        );
+        let response = request(stream, heartbeat).await;
+        let MessagePayload::HeartbeatResponse(heartbeat) = response.payload else {
+            panic!("unexpected response: {:?}", response.message_type);
+        };
+        let targets: Vec<SocketAddr> = heartbeat.target_health.iter().map(|h| h.target_addr).collect();
+        assert_eq!(targets, vec!["10.2.0.2:80".parse::<SocketAddr>().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn test_registration_is_required_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+        let (mut stream, handle) = connect(&router);
+
+        let (_, create) = tunnel_create_message(false);
+        let response = request(&mut stream, create).await;
+        assert_eq!(response.message_type, MessageType::Error);
+        handle.await.unwrap().unwrap();
+        assert!(router.state().list_tunnels().is_empty());
+
+        // 共通のバージョンが無ければ失敗を返し、登録しない
+        let (mut stream, handle) = connect(&router);
+        let client_id = Uuid::new_v4();
+        let response = request(&mut stream, register_message(client_id, vec![MessageVersion::new(2, 0)], &["tcp"])).await;
+        let MessagePayload::ClientRegisterResponse(register) = response.payload else {
+            panic!("unexpected response: {:?}", response.message_type);
+        };
+        assert!(!register.success);
+        assert!(register.error.is_some());
+        handle.await.unwrap().unwrap();
+        assert!(!router.state().is_client_connected(&client_id.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_router_requested_disconnect() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+        let (mut stream, handle) = connect(&router);
+        let client_id = Uuid::new_v4();
+        request(&mut stream, register_message(client_id, SUPPORTED_VERSIONS.to_vec(), &["tcp"])).await;
+
+        assert!(router.state().disconnect_client(&client_id.to_string()));
+        let codec = MessageCodec::new(connection::MAX_MESSAGE_SIZE);
+        let message = codec.read_message(&mut stream).await.unwrap();
+        assert_eq!(message.message_type, MessageType::Disconnect);
+        handle.await.unwrap().unwrap();
+        assert!(!router.state().is_client_connected(&client_id.to_string()));
+
+        // 同じClientが登録し直すと、古い接続は閉じられて新しい接続の状態は残る
+        let (mut first, first_handle) = connect(&router);
+        request(&mut first, register_message(client_id, SUPPORTED_VERSIONS.to_vec(), &["tcp"])).await;
+        let (mut second, _second_handle) = connect(&router);
+        request(&mut second, register_message(client_id, SUPPORTED_VERSIONS.to_vec(), &["tcp"])).await;
+        let message = codec.read_message(&mut first).await.unwrap();
+        assert_eq!(message.message_type, MessageType::Disconnect);
+        first_handle.await.unwrap().unwrap();
+        assert!(router.state().is_client_connected(&client_id.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_router_compresses_data_for_compressed_tunnels() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+        let source = echo_source().await;
+
+        let (mut stream, _handle) = connect(&router);
+        request(&mut stream, register_message(Uuid::new_v4(), SUPPORTED_VERSIONS.to_vec(), &["tcp", "compression"])).await;
+        let (tunnel_id, create) = tunnel_create_with_sources(&[source], true);
+        let response = request(&mut stream, create).await;
+        assert!(matches!(response.payload, MessagePayload::TunnelCreateResponse(ref r) if r.success));
+
+        let codec = MessageCodec::new(connection::MAX_MESSAGE_SIZE);
+        let connection_id = Uuid::new_v4();
+        let payload = vec![b'a'; 512];
+        codec.write_message(&mut stream, &tunnel_data_message(tunnel_id, connection_id, 1, &payload)).await.unwrap();
+
+        // エコーで返ってきたデータは合意した圧縮で届く
+        let mut echoed = Vec::new();
+        let mut compressed = false;
+        while echoed.len() < payload.len() {
+            let message = codec.read_message(&mut stream).await.unwrap();
+            if let MessagePayload::TunnelData(data) = message.payload {
+                compressed |= data.compression.is_some();
+                echoed.extend(data.payload(1024).unwrap());
+            }
+        }
+        assert_eq!(echoed, payload);
+        assert!(compressed);
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_data_balances_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+        // 接続を拒否する転送先（一度バインドしてから解放したポート）
+        let dead = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let live = echo_source().await;
+
+        let (mut stream, _handle) = connect(&router);
+        request(&mut stream, register_message(Uuid::new_v4(), SUPPORTED_VERSIONS.to_vec(), &["tcp", "multi_source"])).await;
+        let (tunnel_id, create) = tunnel_create_with_sources(&[dead, live], false);
+        let response = request(&mut stream, create).await;
+        assert!(matches!(response.payload, MessagePayload::TunnelCreateResponse(ref r) if r.success));
+
+        let codec = MessageCodec::new(connection::MAX_MESSAGE_SIZE);
+        let connection_id = Uuid::new_v4();
+        let hello = tunnel_data_message(tunnel_id, connection_id, 1, b"hello");
+        codec.write_message(&mut stream, &hello).await.unwrap();
+        // 書き込み側を閉じると、エコーの転送先も閉じて終端が返る
+        let eof = tunnel_data_message(tunnel_id, connection_id, 2, b"");
+        codec.write_message(&mut stream, &eof).await.unwrap();
+
+        let mut echoed = Vec::new();
+        let mut acks = Vec::new();
+        loop {
+            let message = codec.read_message(&mut stream).await.unwrap();
+            match message.payload {
+                MessagePayload::TunnelDataResponse(ack) => {
+                    assert!(ack.error.is_none(), "{:?}", ack.error);
+                    acks.push(ack.ack_sequence);
+                }
+                MessagePayload::TunnelData(data) => {
+                    assert_eq!(data.connection_id, connection_id);
+                    let payload = data.payload(1024).unwrap();
+                    if payload.is_empty() {
+                        break;
+                    }
+                    echoed.extend(payload);
+                }
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+        assert_eq!(echoed, b"hello");
+        assert_eq!(acks, vec![1, 2]);
+
+        // 先に選んだ転送先の失敗を記録し、次の転送先で接続した
+        let tunnel = router.state().tunnel(&tunnel_id.to_string()).unwrap();
+        let sources = tunnel.sources.snapshot();
+        assert_eq!(sources.iter().find(|s| s.addr == dead).unwrap().consecutive_failures, 1);
+        let stats = tunnel.snapshot();
+        assert_eq!(stats.total_connections, 1);
+        assert_eq!((stats.bytes_in, stats.bytes_out), (5, 5));
+        for _ in 0..50 {
+            if tunnel.active_connections() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(tunnel.active_connections(), 0);
+
+        // 他のClientのトンネルや未作成のトンネルへのデータはエラーで返す
+        let response = request(&mut stream, tunnel_data_message(Uuid::new_v4(), connection_id, 1, b"x")).await;
+        let MessagePayload::TunnelDataResponse(ack) = response.payload else {
+            panic!("unexpected response: {:?}", response.message_type);
+        };
+        assert!(ack.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_resume_keeps_streams_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+        let live = echo_source().await;
+        let codec = MessageCodec::new(connection::MAX_MESSAGE_SIZE);
+        let client_id = Uuid::new_v4();
+
+        let (mut stream, handle) = connect(&router);
+        let response = request(&mut stream, register_message(client_id, SUPPORTED_VERSIONS.to_vec(), &["tcp", "resumption"])).await;
+        let MessagePayload::ClientRegisterResponse(registered) = response.payload else {
+            panic!("unexpected response: {:?}", response.message_type);
+        };
+        let ticket = registered.session_ticket.expect("ticket for a resumable session");
+        let (tunnel_id, create) = tunnel_create_with_sources(&[live], false);
+        request(&mut stream, create).await;
+
+        // Routerから返ったデータを受信確認せずに接続が切れる
+        let connection_id = Uuid::new_v4();
+        codec
+            .write_message(&mut stream, &tunnel_data_message(tunnel_id, connection_id, 1, b"ping"))
+            .await
+            .unwrap();
+        loop {
+            let message = codec.read_message(&mut stream).await.unwrap();
+            if let MessagePayload::TunnelData(data) = message.payload {
+                assert_eq!(data.payload(1024).unwrap(), b"ping");
+                break;
+            }
+        }
+        drop(stream);
+        handle.await.unwrap().unwrap();
+        assert!(!router.state().is_client_connected(&client_id.to_string()));
+        assert_eq!(router.state().traffic_stats().detached_clients, 1);
+
+        let (mut stream, _handle) = connect(&router);
+        let mut register = register_message(client_id, SUPPORTED_VERSIONS.to_vec(), &["tcp", "resumption"]);
+        if let MessagePayload::ClientRegister(ref mut register) = register.payload {
+            register.resume = Some(SessionResume {
+                session_id: registered.session_id.unwrap(),
+                ticket,
+                acks: Vec::new(),
+            });
+        }
+        let response = request(&mut stream, register).await;
+        let MessagePayload::ClientRegisterResponse(resumed) = response.payload else {
+            panic!("unexpected response: {:?}", response.message_type);
+        };
+        assert!(resumed.resumed);
+        assert_eq!(resumed.acks, vec![StreamAck { tunnel_id, connection_id, sequence: 1 }]);
+
+        // 受信確認していないデータが登録応答の直後に再送される
+        let message = codec.read_message(&mut stream).await.unwrap();
+        let MessagePayload::TunnelData(replayed) = message.payload else {
+            panic!("unexpected message: {:?}", message.message_type);
+        };
+        assert_eq!((replayed.sequence, replayed.payload(1024).unwrap()), (1, b"ping".to_vec()));
+
+        // Clientの再送した重複は転送せず、同じ転送先との接続で続きを中継する
+        for (sequence, payload) in [(1, &b"ping"[..]), (2, b"pong"), (3, b"")] {
+            codec
+                .write_message(&mut stream, &tunnel_data_message(tunnel_id, connection_id, sequence, payload))
+                .await
+                .unwrap();
+        }
+        let mut echoed = Vec::new();
+        loop {
+            let message = codec.read_message(&mut stream).await.unwrap();
+            if let MessagePayload::TunnelData(data) = message.payload {
+                let payload = data.payload(1024).unwrap();
+                if payload.is_empty() {
+                    break;
+                }
+                assert_eq!(data.sequence, 2);
+                echoed.extend(payload);
+            }
+        }
+        assert_eq!(echoed, b"pong");
+        let tunnel = router.state().tunnel(&tunnel_id.to_string()).unwrap();
+        assert_eq!(tunnel.snapshot().total_connections, 1);
+    }
+
+    #[tokio::test]
+    async fn test_service_members_share_streams_and_drain() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = test_router(dir.path());
+        let codec = MessageCodec::new(connection::MAX_MESSAGE_SIZE);
+
+        // 同じサービス名（トンネル名）のトンネルを2つのClientが登録する
+        let mut members = Vec::new();
+        for _ in 0..2 {
+            let client_id = Uuid::new_v4();
+            let (mut stream, handle) = connect(&router);
+            request(&mut stream, register_message(client_id, SUPPORTED_VERSIONS.to_vec(), &["tcp"])).await;
+            let (tunnel_id, create) = tunnel_create_with_sources(&[echo_source().await], false);
+            request(&mut stream, create).await;
+            members.push((client_id, tunnel_id, stream, handle));
+        }
+        let (client_b, tunnel_b, mut stream_b, handle_b) = members.pop().unwrap();
+        let (_, tunnel_a, mut stream_a, _handle_a) = members.pop().unwrap();
+        let counters_a = router.state().tunnel(&tunnel_a.to_string()).unwrap();
+        let counters_b = router.state().tunnel(&tunnel_b.to_string()).unwrap();
+
+        // Aのトンネルへの接続を、ラウンドロビンで両方のメンバーの転送先へ振り分ける
+        let mut connections = Vec::new();
+        for _ in 0..2 {
+            let connection_id = Uuid::new_v4();
+            codec
+                .write_message(&mut stream_a, &tunnel_data_message(tunnel_a, connection_id, 1, b"hello"))
+                .await
+                .unwrap();
+            assert_eq!(next_data(&codec, &mut stream_a, connection_id).await, b"hello");
+            connections.push(connection_id);
+        }
+        assert_eq!((counters_a.active_connections(), counters_b.active_connections()), (1, 1));
+
+        // 処理中の接続があるBの切断はドレインになり、新しい接続はAへ割り当てる
+        let disconnect = Message::new(
+            MessageType::Disconnect,
+            MessagePayload::Disconnect(DisconnectMessage {
+                reason: "shutdown".to_string(),
+                reconnect_allowed: false,
+                reconnect_delay_seconds: None,
+            }),
+        );
+        codec.write_message(&mut stream_b, &disconnect).await.unwrap();
+        let draining = loop {
+            let services = router.state().list_services();
+            if services[0].draining == 1 {
+                break services[0].available;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(draining, 1);
+        assert!(router.state().is_client_connected(&client_b.to_string()));
+
+        let connection_id = Uuid::new_v4();
+        codec
+            .write_message(&mut stream_a, &tunnel_data_message(tunnel_a, connection_id, 1, b"again"))
+            .await
+            .unwrap();
+        assert_eq!(next_data(&codec, &mut stream_a, connection_id).await, b"again");
+        connections.push(connection_id);
+        assert_eq!((counters_a.snapshot().total_connections, counters_b.snapshot().total_connections), (2, 1));
+
+        // 処理中の接続が終わるとBへ切断を要求する
+        for connection_id in connections {
+            codec
+                .write_message(&mut stream_a, &tunnel_data_message(tunnel_a, connection_id, 2, b""))
+                .await
+                .unwrap();
+        }
+        let message = codec.read_message(&mut stream_b).await.unwrap();
+        assert_eq!(message.message_type, MessageType::Disconnect);
+        handle_b.await.unwrap().unwrap();
+        assert!(!router.state().is_client_connected(&client_b.to_string()));
+        assert_eq!(counters_b.active_connections(), 0);
+    }
+
+    #[test]
+    fn test_router_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("router.toml");
+        std::fs::write(&path, r#"
+bind_addr = "127.0.0.1:7000"
+
+[tls]
+cert_file = "router.crt"
+key_file = "router.key"
+
+[api]
+enabled = true
+token = "secret"
+
+[resumption]
+grace_seconds = 5
+"#).unwrap();
+
+        let config = RouterConfig::from_file(&path).unwrap();
+        assert_eq!(config.bind_addr, "127.0.0.1:7000".parse().unwrap());
+        assert_eq!(config.tls.cert_file.as_deref(), Some("router.crt"));
+        assert!(config.api.enabled);
+        assert_eq!(config.resumption.grace_seconds, 5);
+        // 省略したセクションとフィールドは既定値
+        assert!(config.resumption.enabled);
+        assert!(!config.metrics.enabled);
+        assert_eq!(config.health_check.interval_seconds, HealthCheckConfig::default().interval_seconds);
+        config.validate().unwrap();
+
+        // 証明書なしでは起動できない
+        assert!(RouterConfig::default().validate().is_err());
+    }
+
+    #[test]
+    fn test_sample_router_config() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("config/router.toml");
+        RouterConfig::from_file(&path).unwrap().validate().unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+// トンネルの転送先エンドポイント（レプリカ）の選択と外れ値の除外
+//
+// 1つのトンネルが複数の転送先を持つ場合、処理中の接続が少ないエンドポイントへ振り分ける。
+// ヘルスチェック（能動的な監視）とは別に、実際の接続で続けて失敗したエンドポイントを
+// 一定時間だけ候補から外し（ejection）、時間が過ぎたら戻す。戻した直後の失敗は1回で外し直す
+
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// 外れ値の除外設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutlierDetectionConfig {
+    pub enabled: bool,
+    // 候補から外す連続接続失敗回数
+    pub consecutive_failures: u32,
+    // 最初に外す時間。外すたびに倍にする
+    pub ejection_seconds: u64,
+    pub max_ejection_seconds: u64,
+}
+
+impl Default for OutlierDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            consecutive_failures: 3,
+            ejection_seconds: 30,
+            max_ejection_seconds: 300,
+        }
+    }
+}
+
+impl OutlierDetectionConfig {
+    // n回目（1始まり）に外すときの時間
+    fn ejection_duration(&self, ejections: u32) -> Duration {
+        let factor = 1u64 << ejections.saturating_sub(1).min(16);
+        Duration::from_secs(self.ejection_seconds.saturating_mul(factor).min(self.max_ejection_seconds))
+    }
+}
+
+// エンドポイントのスナップショット
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceStats {
+    pub addr: SocketAddr,
+    pub active_connections: u64,
+    pub consecutive_failures: u32,
+    pub ejected: bool,
+}
+
+struct SourceEndpoint {
+    addr: SocketAddr,
+    active_connections: u64,
+    consecutive_failures: u32,
+    // 連続して外された回数（成功で0に戻す）
+    ejections: u32,
+    ejected_until: Option<Instant>,
+}
+
+impl SourceEndpoint {
+    fn is_ejected(&self, now: Instant) -> bool {
+        self.ejected_until.is_some_and(|until| until > now)
+    }
+}
+
+// トンネルの転送先
+pub struct SourcePool {
+    endpoints: Mutex<Vec<SourceEndpoint>>,
+    cursor: AtomicUsize,
+}
+
+impl SourcePool {
+    pub fn new(addrs: &[SocketAddr]) -> Self {
+        let endpoints = addrs
+            .iter()
+            .map(|&addr| SourceEndpoint {
+                addr,
+                active_connections: 0,
+                consecutive_failures: 0,
+                ejections: 0,
+                ejected_until: None,
+            })
+            .collect();
+        Self {
+            endpoints: Mutex::new(endpoints),
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn addrs(&self) -> Vec<SocketAddr> {
+        self.lock().iter().map(|e| e.addr).collect()
+    }
+
+    // 次の接続の転送先を選ぶ。availableはヘルスチェックでdownでないか
+    // 全て外されていたら除外を無視して選ぶ（接続を全て失うよりは試す）
+    pub fn pick(&self, available: impl Fn(&SocketAddr) -> bool) -> Option<SocketAddr> {
+        self.pick_at(Instant::now(), available)
+    }
+
+    fn pick_at(&self, now: Instant, available: impl Fn(&SocketAddr) -> bool) -> Option<SocketAddr> {
+        let mut endpoints = self.lock();
+        for endpoint in endpoints.iter_mut() {
+            if endpoint.ejected_until.is_some_and(|until| until <= now) {
+                endpoint.ejected_until = None;
+                info!("Source {} is back in rotation after ejection", endpoint.addr);
+            }
+        }
+
+        let candidates: Vec<&SourceEndpoint> = endpoints.iter().filter(|e| available(&e.addr)).collect();
+        let admitted: Vec<&SourceEndpoint> = candidates.iter().copied().filter(|e| !e.is_ejected(now)).collect();
+        let candidates = if admitted.is_empty() { candidates } else { admitted };
+        if candidates.is_empty() {
+            return None;
+        }
+
+        // 処理中の接続が同数のエンドポイント間では順に回す
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed);
+        (0..candidates.len())
+            .map(|offset| candidates[(start + offset) % candidates.len()])
+            .min_by_key(|e| e.active_connections)
+            .map(|e| e.addr)
+    }
+
+    pub fn connect_succeeded(&self, addr: SocketAddr) {
+        if let Some(endpoint) = self.lock().iter_mut().find(|e| e.addr == addr) {
+            endpoint.active_connections += 1;
+            endpoint.consecutive_failures = 0;
+            endpoint.ejections = 0;
+        }
+    }
+
+    pub fn connection_closed(&self, addr: SocketAddr) {
+        if let Some(endpoint) = self.lock().iter_mut().find(|e| e.addr == addr) {
+            endpoint.active_connections = endpoint.active_connections.saturating_sub(1);
+        }
+    }
+
+    // 接続の失敗を記録する。候補から外したらtrue
+    pub fn connect_failed(&self, addr: SocketAddr, config: &OutlierDetectionConfig) -> bool {
+        self.connect_failed_at(Instant::now(), addr, config)
+    }
+
+    fn connect_failed_at(&self, now: Instant, addr: SocketAddr, config: &OutlierDetectionConfig) -> bool {
+        let mut endpoints = self.lock();
+        let Some(endpoint) = endpoints.iter_mut().find(|e| e.addr == addr) else {
+            return false;
+        };
+        endpoint.consecutive_failures = endpoint.consecutive_failures.saturating_add(1);
+        if !config.enabled || endpoint.is_ejected(now) {
+            return false;
+        }
+        // 戻した直後（前回外してから成功していない）は1回の失敗で外し直す
+        let threshold = if endpoint.ejections > 0 { 1 } else { config.consecutive_failures.max(1) };
+        if endpoint.consecutive_failures < threshold {
+            return false;
+        }
+
+        endpoint.ejections = endpoint.ejections.saturating_add(1);
+        let duration = config.ejection_duration(endpoint.ejections);
+        endpoint.ejected_until = Some(now + duration);
+        endpoint.consecutive_failures = 0;
+        warn!(
+            "Ejecting source {} for {}s after repeated connection failures",
+            endpoint.addr,
+            duration.as_secs()
+        );
+        true
+    }
+
+    pub fn snapshot(&self) -> Vec<SourceStats> {
+        let now = Instant::now();
+        self.lock()
+            .iter()
+            .map(|e| SourceStats {
+                addr: e.addr,
+                active_connections: e.active_connections,
+                consecutive_failures: e.consecutive_failures,
+                ejected: e.is_ejected(now),
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<SourceEndpoint>> {
+        self.endpoints.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs() -> [SocketAddr; 2] {
+        ["10.2.0.2:8080".parse().unwrap(), "10.2.0.3:8080".parse().unwrap()]
+    }
+
+    #[test]
+    fn test_least_connections_across_sources() {
+        let [a, b] = addrs();
+        let pool = SourcePool::new(&[a, b]);
+
+        let first = pool.pick(|_| true).unwrap();
+        pool.connect_succeeded(first);
+        let second = pool.pick(|_| true).unwrap();
+        assert_ne!(first, second);
+        pool.connect_succeeded(second);
+        pool.connection_closed(first);
+        assert_eq!(pool.pick(|_| true), Some(first));
+
+        // ヘルスチェックでdownのエンドポイントは選ばない
+        assert_eq!(pool.pick(|addr| *addr != first), Some(second));
+        assert_eq!(pool.pick(|_| false), None);
+    }
+
+    #[test]
+    fn test_outlier_ejection_and_cooldown() {
+        let [a, b] = addrs();
+        let pool = SourcePool::new(&[a, b]);
+        let config = OutlierDetectionConfig::default();
+        let now = Instant::now();
+
+        assert!(!pool.connect_failed_at(now, a, &config));
+        assert!(!pool.connect_failed_at(now, a, &config));
+        assert!(pool.connect_failed_at(now, a, &config));
+        for _ in 0..4 {
+            assert_eq!(pool.pick_at(now, |_| true), Some(b));
+        }
+        // 全て外れたら除外を無視する
+        assert_eq!(pool.pick_at(now, |addr| *addr == a), Some(a));
+
+        // 時間が過ぎたら戻し、戻した直後の失敗は1回で倍の時間外す
+        let later = now + Duration::from_secs(31);
+        assert!(pool.pick_at(later, |addr| *addr == a).is_some());
+        assert!(!pool.snapshot()[0].ejected);
+        assert!(pool.connect_failed_at(later, a, &config));
+        assert_eq!(pool.pick_at(later + Duration::from_secs(31), |_| true), Some(b));
+        assert_eq!(pool.pick_at(later + Duration::from_secs(61), |addr| *addr == a), Some(a));
+
+        // 成功したら通常のしきい値に戻る
+        pool.connect_succeeded(a);
+        let recovered = later + Duration::from_secs(200);
+        assert!(!pool.connect_failed_at(recovered, a, &config));
+        assert!(!pool.connect_failed_at(recovered, a, &config));
+        assert!(pool.connect_failed_at(recovered, a, &config));
+    }
+}
@@ -0,0 +1,828 @@
+// Routerの接続・トンネル状態
+//
+// 管理APIやメトリクスから参照されるため、接続処理のタスクとは独立して集計する。
+// カウンタはホットパスから更新されるのでロックを取らずAtomicで持つ
+
+use super::sources::{SourcePool, SourceStats};
+use crate::common::error::{self, Error};
+use crate::metrics::{LatencyHistogram, MetricsSnapshot, RouterSample, TunnelSample};
+use crate::protocol::{Negotiated, ResumableSession, ResumeError, SessionResume, SessionTicket, StreamAck, TunnelData};
+use chrono::{DateTime, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+// 接続中のClient
+struct ClientConnection {
+    client_name: String,
+    remote_addr: SocketAddr,
+    connected_at: DateTime<Utc>,
+    // 管理APIからの強制切断要求（接続ハンドラ側でnotified()を待つ）
+    disconnect: Arc<Notify>,
+    // 登録時に合意したプロトコル（トンネル作成要求の検査に使う）
+    protocol: Option<Negotiated>,
+    // 再開用のセッション（チケットを発行したClientのみ）
+    session: Option<ClientSession>,
+}
+
+struct ClientSession {
+    session_id: Uuid,
+    // チケットそのものは保持せず、ハッシュで照合する
+    ticket_digest: [u8; 32],
+    streams: Arc<Mutex<ResumableSession>>,
+}
+
+// 切断後、猶予期間内の再開を待っているClient（トンネルは残したまま）
+struct DetachedClient {
+    connection: ClientConnection,
+    expires_at: DateTime<Utc>,
+}
+
+// 再開に成功したセッション
+#[derive(Debug)]
+pub struct ResumedSession {
+    pub disconnect: Arc<Notify>,
+    pub session_id: Uuid,
+    // 再開のたびにチケットを更新し、古いチケットでは再開できないようにする
+    pub ticket: SessionTicket,
+    // Clientへ再送するデータ（Clientの受信済み位置より後ろ）
+    pub replay: Vec<TunnelData>,
+    // Routerが受信済みの位置（Clientの再送の起点）
+    pub acks: Vec<StreamAck>,
+}
+
+// トンネル単位のトラフィックカウンタ
+pub struct TunnelCounters {
+    pub tunnel_id: String,
+    pub client_id: String,
+    pub name: String,
+    // 負荷分散でまとめるサービス名（省略時はトンネル名）
+    pub service: String,
+    // 先頭の転送先（ヘルスチェックやメトリクスの代表）
+    pub target_addr: SocketAddr,
+    pub sources: SourcePool,
+    pub created_at: DateTime<Utc>,
+    // 新しい接続を割り当てず、処理中の接続の終了を待っている
+    draining: AtomicBool,
+    active_connections: AtomicU64,
+    total_connections: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    connect_latency: Mutex<LatencyHistogram>,
+}
+
+impl TunnelCounters {
+    pub fn connection_opened(&self) {
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+        self.total_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn connection_closed(&self) {
+        // 二重クローズで負数にならないよう飽和させる
+        let _ = self
+            .active_connections
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| Some(n.saturating_sub(1)));
+    }
+
+    pub fn active_connections(&self) -> u64 {
+        self.active_connections.load(Ordering::Relaxed)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    // Client -> ターゲット方向
+    pub fn add_bytes_in(&self, bytes: u64) {
+        self.bytes_in.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    // ターゲット -> Client方向
+    pub fn add_bytes_out(&self, bytes: u64) {
+        self.bytes_out.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    // ターゲットへの接続確立に要した時間
+    pub fn observe_connect_latency(&self, latency: Duration) {
+        if let Ok(mut histogram) = self.connect_latency.lock() {
+            histogram.observe(latency.as_secs_f64() * 1000.0);
+        }
+    }
+
+    pub fn connect_latency(&self) -> LatencyHistogram {
+        self.connect_latency.lock().map(|h| h.clone()).unwrap_or_default()
+    }
+
+    pub fn snapshot(&self) -> TunnelStats {
+        TunnelStats {
+            tunnel_id: self.tunnel_id.clone(),
+            client_id: self.client_id.clone(),
+            name: self.name.clone(),
+            service: self.service.clone(),
+            target_addr: self.target_addr,
+            sources: self.sources.snapshot(),
+            created_at: self.created_at,
+            draining: self.is_draining(),
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            total_connections: self.total_connections.load(Ordering::Relaxed),
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// トンネル統計のスナップショット
+#[derive(Debug, Clone, Serialize)]
+pub struct TunnelStats {
+    pub tunnel_id: String,
+    pub client_id: String,
+    pub name: String,
+    pub service: String,
+    pub target_addr: SocketAddr,
+    pub sources: Vec<SourceStats>,
+    pub created_at: DateTime<Utc>,
+    pub draining: bool,
+    pub active_connections: u64,
+    pub total_connections: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+// サービス（同じサービス名のトンネルの集まり）のスナップショット
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceSummary {
+    pub service: String,
+    pub members: usize,
+    // Clientが接続中でドレイン中でないメンバー（ターゲットのヘルスは含まない）
+    pub available: usize,
+    pub draining: usize,
+    pub active_connections: u64,
+}
+
+// 接続中Clientのスナップショット
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientSummary {
+    pub client_id: String,
+    pub client_name: String,
+    pub remote_addr: SocketAddr,
+    pub connected_at: DateTime<Utc>,
+    pub protocol_version: Option<String>,
+    pub capabilities: Vec<String>,
+    pub tunnels: usize,
+    pub active_connections: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+// Router全体のトラフィック集計
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TrafficStats {
+    pub connected_clients: usize,
+    pub tunnels: usize,
+    pub active_connections: u64,
+    pub total_connections: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    // 切断後、セッションの再開を待っているClient
+    pub detached_clients: usize,
+}
+
+// Routerの接続状態
+#[derive(Default)]
+pub struct RouterState {
+    clients: DashMap<String, ClientConnection>,
+    detached: DashMap<String, DetachedClient>,
+    tunnels: DashMap<String, Arc<TunnelCounters>>,
+    auth_failures: AtomicU64,
+}
+
+impl RouterState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 認証済みClientの登録。戻り値のNotifyで強制切断要求を受け取る
+    pub fn register_client(&self, client_id: &str, client_name: &str, remote_addr: SocketAddr) -> Arc<Notify> {
+        let disconnect = Arc::new(Notify::new());
+        let previous = self.clients.insert(
+            client_id.to_string(),
+            ClientConnection {
+                client_name: client_name.to_string(),
+                remote_addr,
+                connected_at: Utc::now(),
+                disconnect: Arc::clone(&disconnect),
+                protocol: None,
+                session: None,
+            },
+        );
+        // 同じClientの古い接続がまだ残っていれば閉じさせる
+        if let Some(previous) = previous {
+            previous.disconnect.notify_one();
+        }
+        // 再開せずに新しく登録し直したClientの古いセッションは引き継がない
+        if self.detached.remove(client_id).is_some() {
+            self.tunnels.retain(|_, t| t.client_id != client_id);
+        }
+        info!("Client registered: {} ({})", client_id, remote_addr);
+        disconnect
+    }
+
+    // Client切断時の登録解除（そのClientのトンネルも破棄する）
+    pub fn unregister_client(&self, client_id: &str) {
+        self.clients.remove(client_id);
+        self.detached.remove(client_id);
+        self.tunnels.retain(|_, t| t.client_id != client_id);
+        debug!("Client unregistered: {}", client_id);
+    }
+
+    pub fn register_tunnel(
+        &self,
+        tunnel_id: &str,
+        client_id: &str,
+        name: &str,
+        service: &str,
+        sources: &[SocketAddr],
+    ) -> error::Result<Arc<TunnelCounters>> {
+        let Some(&target_addr) = sources.first() else {
+            return Err(Error::Protocol(format!("Tunnel {} has no source address", name)));
+        };
+        let entry = match self.tunnels.entry(tunnel_id.to_string()) {
+            // 再接続したClientが送り直した要求は、処理中の接続の集計を残したまま受け入れる
+            Entry::Occupied(existing) if existing.get().client_id == client_id => {
+                return Ok(Arc::clone(existing.get()));
+            }
+            Entry::Occupied(_) => {
+                return Err(Error::Protocol(format!("Tunnel ID {} is already in use", tunnel_id)));
+            }
+            Entry::Vacant(entry) => entry,
+        };
+        let counters = Arc::new(TunnelCounters {
+            tunnel_id: tunnel_id.to_string(),
+            client_id: client_id.to_string(),
+            name: name.to_string(),
+            service: service.to_string(),
+            target_addr,
+            sources: SourcePool::new(sources),
+            created_at: Utc::now(),
+            draining: AtomicBool::new(false),
+            active_connections: AtomicU64::new(0),
+            total_connections: AtomicU64::new(0),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            connect_latency: Mutex::new(LatencyHistogram::default()),
+        });
+        entry.insert(Arc::clone(&counters));
+        Ok(counters)
+    }
+
+    pub fn tunnel(&self, tunnel_id: &str) -> Option<Arc<TunnelCounters>> {
+        self.tunnels.get(tunnel_id).map(|t| Arc::clone(t.value()))
+    }
+
+    pub fn unregister_tunnel(&self, tunnel_id: &str) -> Option<Arc<TunnelCounters>> {
+        self.tunnels.remove(tunnel_id).map(|(_, t)| t)
+    }
+
+    // Clientのトンネルを負荷分散の対象から外す。対象にしたトンネルの数を返す
+    pub fn drain_client(&self, client_id: &str) -> usize {
+        let mut drained = 0;
+        for tunnel in self.tunnels.iter().filter(|t| t.client_id == client_id) {
+            tunnel.draining.store(true, Ordering::Relaxed);
+            drained += 1;
+        }
+        if drained > 0 {
+            info!("Draining {} tunnel(s) of client {}", drained, client_id);
+        }
+        drained
+    }
+
+    pub fn client_active_connections(&self, client_id: &str) -> u64 {
+        self.tunnels
+            .iter()
+            .filter(|t| t.client_id == client_id)
+            .map(|t| t.active_connections())
+            .sum()
+    }
+
+    // 登録中のトンネル（再開待ちを含む）が使っている転送先
+    pub fn source_addrs(&self) -> HashSet<SocketAddr> {
+        self.tunnels.iter().flat_map(|t| t.sources.addrs()).collect()
+    }
+
+    // Clientのトンネルが使っている転送先
+    pub fn client_source_addrs(&self, client_id: &str) -> HashSet<SocketAddr> {
+        self.tunnels
+            .iter()
+            .filter(|t| t.client_id == client_id)
+            .flat_map(|t| t.sources.addrs())
+            .collect()
+    }
+
+    // 新しい接続を割り当てられるサービスのメンバー
+    // 切断済み（再開待ちを含む）のClientとドレイン中のトンネルは除く
+    pub fn service_members(&self, service: &str) -> Vec<Arc<TunnelCounters>> {
+        self.tunnels
+            .iter()
+            .filter(|t| t.service == service && !t.is_draining() && self.clients.contains_key(&t.client_id))
+            .map(|t| Arc::clone(t.value()))
+            .collect()
+    }
+
+    pub fn list_services(&self) -> Vec<ServiceSummary> {
+        let mut services: Vec<ServiceSummary> = Vec::new();
+        for tunnel in self.tunnels.iter() {
+            let index = match services.iter().position(|s| s.service == tunnel.service) {
+                Some(index) => index,
+                None => {
+                    services.push(ServiceSummary {
+                        service: tunnel.service.clone(),
+                        members: 0,
+                        available: 0,
+                        draining: 0,
+                        active_connections: 0,
+                    });
+                    services.len() - 1
+                }
+            };
+            let summary = &mut services[index];
+            summary.members += 1;
+            if tunnel.is_draining() {
+                summary.draining += 1;
+            } else if self.clients.contains_key(&tunnel.client_id) {
+                summary.available += 1;
+            }
+            summary.active_connections += tunnel.active_connections();
+        }
+        services.sort_by(|a, b| a.service.cmp(&b.service));
+        services
+    }
+
+    // 登録時のネゴシエーション結果を記録する。接続中でなければfalse
+    pub fn set_client_protocol(&self, client_id: &str, negotiated: Negotiated) -> bool {
+        match self.clients.get_mut(client_id) {
+            Some(mut client) => {
+                client.protocol = Some(negotiated);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn client_protocol(&self, client_id: &str) -> Option<Negotiated> {
+        self.clients.get(client_id).and_then(|c| c.protocol.clone())
+    }
+
+    // 再開用のセッションを開始し、Clientへ渡すチケットを返す。接続中でなければNone
+    pub fn start_session(&self, client_id: &str, session_id: Uuid, max_buffered_bytes: usize) -> Option<SessionTicket> {
+        let mut client = self.clients.get_mut(client_id)?;
+        let ticket = SessionTicket::generate();
+        client.session = Some(ClientSession {
+            session_id,
+            ticket_digest: ticket.digest(),
+            streams: Arc::new(Mutex::new(ResumableSession::new(max_buffered_bytes))),
+        });
+        Some(ticket)
+    }
+
+    // 送受信したTunnelDataを記録するためのストリーム状態
+    pub fn client_session(&self, client_id: &str) -> Option<Arc<Mutex<ResumableSession>>> {
+        self.clients
+            .get(client_id)
+            .and_then(|c| c.session.as_ref().map(|s| Arc::clone(&s.streams)))
+    }
+
+    // 接続が切れたClientのセッションを猶予期間だけ保持する
+    // 再開できない（チケット未発行、バッファ超過）場合はトンネルも破棄してfalseを返す
+    pub fn detach_client(&self, client_id: &str, grace: Duration) -> bool {
+        let Some((_, connection)) = self.clients.remove(client_id) else {
+            return false;
+        };
+        let resumable = connection
+            .session
+            .as_ref()
+            .is_some_and(|s| s.streams.lock().unwrap_or_else(|e| e.into_inner()).is_resumable());
+        if !resumable || grace.is_zero() {
+            self.tunnels.retain(|_, t| t.client_id != client_id);
+            debug!("Client unregistered: {}", client_id);
+            return false;
+        }
+
+        let expires_at = Utc::now() + chrono::Duration::from_std(grace).unwrap_or_else(|_| chrono::Duration::zero());
+        info!("Client {} disconnected; keeping its session until {}", client_id, expires_at);
+        self.detached.insert(client_id.to_string(), DetachedClient { connection, expires_at });
+        true
+    }
+
+    // チケットを照合して切断前のセッションを引き継ぐ
+    pub fn resume_client(
+        &self,
+        client_id: &str,
+        resume: &SessionResume,
+        remote_addr: SocketAddr,
+    ) -> Result<ResumedSession, ResumeError> {
+        let Some((_, detached)) = self.detached.remove(client_id) else {
+            return Err(ResumeError::UnknownSession);
+        };
+        let matches = detached
+            .connection
+            .session
+            .as_ref()
+            .is_some_and(|s| s.session_id == resume.session_id && s.ticket_digest == resume.ticket.digest());
+        if !matches {
+            // 誤ったチケットによる試行で本来のClientのセッションを失わせない
+            self.detached.insert(client_id.to_string(), detached);
+            return Err(ResumeError::InvalidTicket);
+        }
+        if detached.expires_at <= Utc::now() {
+            self.tunnels.retain(|_, t| t.client_id != client_id);
+            return Err(ResumeError::Expired);
+        }
+
+        let mut connection = detached.connection;
+        let Some(session) = connection.session.as_mut() else {
+            return Err(ResumeError::UnknownSession);
+        };
+        let (replay, acks) = {
+            let mut streams = session.streams.lock().unwrap_or_else(|e| e.into_inner());
+            if !streams.is_resumable() {
+                drop(streams);
+                self.tunnels.retain(|_, t| t.client_id != client_id);
+                return Err(ResumeError::BufferOverflow);
+            }
+            (streams.replay(&resume.acks), streams.acks())
+        };
+        let ticket = SessionTicket::generate();
+        session.ticket_digest = ticket.digest();
+        let session_id = session.session_id;
+
+        // 切断前にドレインしていても、戻ってきたClientには再び接続を割り当てる
+        for tunnel in self.tunnels.iter().filter(|t| t.client_id == client_id) {
+            tunnel.draining.store(false, Ordering::Relaxed);
+        }
+
+        let disconnect = Arc::new(Notify::new());
+        connection.remote_addr = remote_addr;
+        connection.disconnect = Arc::clone(&disconnect);
+        self.clients.insert(client_id.to_string(), connection);
+        info!("Client resumed: {} ({}), replaying {} frames", client_id, remote_addr, replay.len());
+
+        Ok(ResumedSession { disconnect, session_id, ticket, replay, acks })
+    }
+
+    // 猶予期間を過ぎたセッションをトンネルごと破棄し、対象のClient IDを返す
+    pub fn expire_detached_sessions(&self) -> Vec<String> {
+        let now = Utc::now();
+        let expired: Vec<String> = self
+            .detached
+            .iter()
+            .filter(|d| d.expires_at <= now)
+            .map(|d| d.key().clone())
+            .collect();
+        for client_id in &expired {
+            self.detached.remove(client_id);
+            self.tunnels.retain(|_, t| &t.client_id != client_id);
+            info!("Session expired for client: {}", client_id);
+        }
+        expired
+    }
+
+    pub fn is_client_connected(&self, client_id: &str) -> bool {
+        self.clients.contains_key(client_id)
+    }
+
+    // 接続ハンドラが今もそのClientの接続か（登録し直した後の古い接続でないか）
+    pub fn is_current_connection(&self, client_id: &str, disconnect: &Arc<Notify>) -> bool {
+        self.clients
+            .get(client_id)
+            .is_some_and(|c| Arc::ptr_eq(&c.disconnect, disconnect))
+    }
+
+    // 接続ハンドラへ切断を要求する。接続中でなければfalse
+    pub fn disconnect_client(&self, client_id: &str) -> bool {
+        match self.clients.get(client_id) {
+            Some(client) => {
+                // ハンドラがまだ待機していなくても取りこぼさないようnotify_oneを使う
+                client.disconnect.notify_one();
+                info!("Disconnect requested for client: {}", client_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_client(&self, client_id: &str) -> Option<ClientSummary> {
+        self.clients.get(client_id).map(|c| self.summarize(c.key(), c.value()))
+    }
+
+    pub fn list_clients(&self) -> Vec<ClientSummary> {
+        let mut clients: Vec<ClientSummary> = self
+            .clients
+            .iter()
+            .map(|c| self.summarize(c.key(), c.value()))
+            .collect();
+        clients.sort_by_key(|c| c.connected_at);
+        clients
+    }
+
+    pub fn list_tunnels(&self) -> Vec<TunnelStats> {
+        let mut tunnels: Vec<TunnelStats> = self.tunnels.iter().map(|t| t.snapshot()).collect();
+        tunnels.sort_by_key(|t| t.created_at);
+        tunnels
+    }
+
+    pub fn traffic_stats(&self) -> TrafficStats {
+        let mut stats = TrafficStats {
+            connected_clients: self.clients.len(),
+            detached_clients: self.detached.len(),
+            ..Default::default()
+        };
+        for tunnel in self.tunnels.iter() {
+            let snapshot = tunnel.snapshot();
+            stats.tunnels += 1;
+            stats.active_connections += snapshot.active_connections;
+            stats.total_connections += snapshot.total_connections;
+            stats.bytes_in += snapshot.bytes_in;
+            stats.bytes_out += snapshot.bytes_out;
+        }
+        stats
+    }
+
+    // 認証に失敗したハンドシェイク（Clientとして登録される前なので個別には保持しない）
+    pub fn record_auth_failure(&self) {
+        self.auth_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn auth_failures(&self) -> u64 {
+        self.auth_failures.load(Ordering::Relaxed)
+    }
+
+    // Prometheusエクスポート用。トンネル名が重複しうるためラベルはトンネル名とIDを組み合わせる
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        let mut tunnels: Vec<TunnelSample> = self
+            .tunnels
+            .iter()
+            .map(|t| {
+                let stats = t.snapshot();
+                TunnelSample {
+                    tunnel: format!("{}/{}", stats.name, stats.tunnel_id),
+                    up: true,
+                    active_connections: stats.active_connections,
+                    total_connections: stats.total_connections,
+                    // Router側から見た方向（Clientへ送信 = bytes_out）
+                    bytes_sent: stats.bytes_out,
+                    bytes_received: stats.bytes_in,
+                    latency: t.connect_latency(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        tunnels.sort_by(|a, b| a.tunnel.cmp(&b.tunnel));
+
+        MetricsSnapshot {
+            tunnels,
+            router: Some(RouterSample {
+                connected_clients: self.clients.len() as u64,
+                auth_failures: self.auth_failures(),
+            }),
+        }
+    }
+
+    fn summarize(&self, client_id: &str, client: &ClientConnection) -> ClientSummary {
+        let mut summary = ClientSummary {
+            client_id: client_id.to_string(),
+            client_name: client.client_name.clone(),
+            remote_addr: client.remote_addr,
+            connected_at: client.connected_at,
+            protocol_version: client.protocol.as_ref().map(|p| p.version.to_string()),
+            capabilities: client.protocol.as_ref().map(|p| p.capabilities.to_names()).unwrap_or_default(),
+            tunnels: 0,
+            active_connections: 0,
+            bytes_in: 0,
+            bytes_out: 0,
+        };
+        for tunnel in self.tunnels.iter().filter(|t| t.client_id == client_id) {
+            let snapshot = tunnel.snapshot();
+            summary.tunnels += 1;
+            summary.active_connections += snapshot.active_connections;
+            summary.bytes_in += snapshot.bytes_in;
+            summary.bytes_out += snapshot.bytes_out;
+        }
+        summary
+    }
+}
+
+#[cfg(feature = "metrics")]
+#[async_trait::async_trait]
+impl crate::metrics::MetricsSource for RouterState {
+    async fn snapshot(&self) -> MetricsSnapshot {
+        self.metrics_snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tunnel_counters() {
+        let state = RouterState::new();
+        state.register_client("client-a", "laptop", "192.168.1.10:50000".parse().unwrap());
+        let counters = state.register_tunnel("t1", "client-a", "web", "web", &["10.2.0.2:8080".parse().unwrap()]).unwrap();
+
+        counters.connection_opened();
+        counters.connection_opened();
+        counters.connection_closed();
+        counters.add_bytes_in(100);
+        counters.add_bytes_out(250);
+
+        let client = state.get_client("client-a").unwrap();
+        assert_eq!(client.tunnels, 1);
+        assert_eq!(client.active_connections, 1);
+        assert_eq!(client.bytes_out, 250);
+
+        let traffic = state.traffic_stats();
+        assert_eq!(traffic.total_connections, 2);
+        assert_eq!(traffic.bytes_in, 100);
+
+        // 余分なクローズで負にならない
+        counters.connection_closed();
+        counters.connection_closed();
+        assert_eq!(state.list_tunnels()[0].active_connections, 0);
+    }
+
+    #[test]
+    fn test_register_tunnel_validation() {
+        let state = RouterState::new();
+        let source: SocketAddr = "10.2.0.2:8080".parse().unwrap();
+
+        // ネットワークから来た不正な要求はエラーで返す
+        assert!(state.register_tunnel("t1", "client-a", "web", "web", &[]).is_err());
+
+        let counters = state.register_tunnel("t1", "client-a", "web", "web", &[source]).unwrap();
+        counters.connection_opened();
+        // 同じClientの送り直しは既存の集計を引き継ぎ、他のClientからは使えない
+        let again = state.register_tunnel("t1", "client-a", "web", "web", &[source]).unwrap();
+        assert!(Arc::ptr_eq(&counters, &again));
+        assert_eq!(again.active_connections(), 1);
+        assert!(state.register_tunnel("t1", "client-b", "web", "web", &[source]).is_err());
+        assert_eq!(state.list_tunnels().len(), 1);
+    }
+
+    #[test]
+    fn test_client_protocol() {
+        use crate::protocol::ProtocolSupport;
+
+        let state = RouterState::new();
+        state.register_client("client-a", "laptop", "192.168.1.10:50000".parse().unwrap());
+        assert!(state.client_protocol("client-a").is_none());
+
+        let negotiated = ProtocolSupport::default().negotiate::<&str>(&[], &["tcp"]).unwrap();
+        assert!(state.set_client_protocol("client-a", negotiated.clone()));
+        assert!(!state.set_client_protocol("unknown", negotiated.clone()));
+        assert_eq!(state.client_protocol("client-a"), Some(negotiated));
+
+        let client = state.get_client("client-a").unwrap();
+        assert_eq!(client.protocol_version.as_deref(), Some("1.0"));
+        assert_eq!(client.capabilities, vec!["tcp"]);
+    }
+
+    #[test]
+    fn test_session_resumption() {
+        let state = RouterState::new();
+        let addr: SocketAddr = "192.168.1.10:50000".parse().unwrap();
+        state.register_client("client-a", "laptop", addr);
+        state.register_tunnel("t1", "client-a", "ssh", "ssh", &["10.2.0.2:22".parse().unwrap()]).unwrap();
+        let session_id = Uuid::new_v4();
+        let ticket = state.start_session("client-a", session_id, 1024).unwrap();
+
+        // Routerから送ったが、Clientが受信したのは1件目まで
+        let (tunnel_id, connection_id) = (Uuid::new_v4(), Uuid::new_v4());
+        {
+            let streams = state.client_session("client-a").unwrap();
+            let mut streams = streams.lock().unwrap();
+            for _ in 0..3 {
+                let sequence = streams.next_sequence(tunnel_id, connection_id);
+                streams.record_sent(&TunnelData::new(tunnel_id, connection_id, sequence, b"banner", None));
+            }
+        }
+
+        assert!(state.detach_client("client-a", Duration::from_secs(60)));
+        assert!(!state.is_client_connected("client-a"));
+        assert_eq!(state.list_tunnels().len(), 1);
+        assert_eq!(state.traffic_stats().detached_clients, 1);
+
+        let mut resume = SessionResume {
+            session_id,
+            ticket: SessionTicket::generate(),
+            acks: vec![StreamAck { tunnel_id, connection_id, sequence: 1 }],
+        };
+        let new_addr: SocketAddr = "192.168.1.20:50001".parse().unwrap();
+        assert_eq!(state.resume_client("client-a", &resume, new_addr).unwrap_err(), ResumeError::InvalidTicket);
+
+        resume.ticket = ticket.clone();
+        let resumed = state.resume_client("client-a", &resume, new_addr).unwrap();
+        assert_eq!(resumed.session_id, session_id);
+        assert_eq!(resumed.replay.iter().map(|d| d.sequence).collect::<Vec<_>>(), vec![2, 3]);
+        assert_ne!(resumed.ticket, ticket);
+        assert_eq!(state.get_client("client-a").unwrap().remote_addr, new_addr);
+        assert_eq!(state.get_client("client-a").unwrap().tunnels, 1);
+
+        // 更新前のチケットでは再開できない
+        state.detach_client("client-a", Duration::from_secs(60));
+        assert_eq!(state.resume_client("client-a", &resume, new_addr).unwrap_err(), ResumeError::InvalidTicket);
+    }
+
+    #[test]
+    fn test_detached_session_expiry() {
+        let state = RouterState::new();
+        state.register_client("client-a", "laptop", "192.168.1.10:50000".parse().unwrap());
+        state.register_tunnel("t1", "client-a", "ssh", "ssh", &["10.2.0.2:22".parse().unwrap()]).unwrap();
+
+        // チケットを発行していないClientは切断時にトンネルを破棄する
+        assert!(!state.detach_client("client-a", Duration::from_secs(60)));
+        assert!(state.list_tunnels().is_empty());
+
+        state.register_client("client-b", "desktop", "192.168.1.11:50000".parse().unwrap());
+        state.register_tunnel("t2", "client-b", "db", "db", &["10.2.0.3:5432".parse().unwrap()]).unwrap();
+        state.start_session("client-b", Uuid::new_v4(), 1024).unwrap();
+        assert!(state.detach_client("client-b", Duration::from_millis(1)));
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(state.expire_detached_sessions(), vec!["client-b".to_string()]);
+        assert!(state.list_tunnels().is_empty());
+        assert_eq!(state.traffic_stats().detached_clients, 0);
+    }
+
+    #[test]
+    fn test_service_members() {
+        let state = RouterState::new();
+        state.register_client("client-a", "web-1", "192.168.1.10:50000".parse().unwrap());
+        state.register_client("client-b", "web-2", "192.168.1.11:50000".parse().unwrap());
+        state.register_tunnel("t1", "client-a", "web", "web", &["10.2.0.2:8080".parse().unwrap()]).unwrap();
+        state.register_tunnel("t2", "client-b", "web-2", "web", &["10.2.0.2:8080".parse().unwrap()]).unwrap();
+        state.register_tunnel("t3", "client-b", "ssh", "ssh", &["10.2.0.2:22".parse().unwrap()]).unwrap();
+        assert_eq!(state.service_members("web").len(), 2);
+
+        // ドレイン中のClientには新しい接続を割り当てない
+        assert_eq!(state.drain_client("client-b"), 2);
+        let members = state.service_members("web");
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].client_id, "client-a");
+        assert!(state.service_members("ssh").is_empty());
+
+        let services = state.list_services();
+        assert_eq!(services.iter().map(|s| s.service.as_str()).collect::<Vec<_>>(), vec!["ssh", "web"]);
+        assert_eq!((services[1].members, services[1].available, services[1].draining), (2, 1, 1));
+
+        // 切断済みのClientも対象外
+        state.unregister_client("client-a");
+        assert!(state.service_members("web").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_client() {
+        let state = RouterState::new();
+        let signal = state.register_client("client-a", "laptop", "192.168.1.10:50000".parse().unwrap());
+        state.register_tunnel("t1", "client-a", "web", "web", &["10.2.0.2:8080".parse().unwrap()]).unwrap();
+
+        assert!(state.disconnect_client("client-a"));
+        assert!(!state.disconnect_client("unknown"));
+        // 待機前に要求されても受け取れる
+        tokio::time::timeout(std::time::Duration::from_secs(1), signal.notified())
+            .await
+            .unwrap();
+
+        state.unregister_client("client-a");
+        assert!(state.list_tunnels().is_empty());
+        assert!(!state.is_client_connected("client-a"));
+    }
+
+    #[test]
+    fn test_metrics_snapshot() {
+        let state = RouterState::new();
+        state.register_client("client-a", "laptop", "192.168.1.10:50000".parse().unwrap());
+        let counters = state.register_tunnel("t1", "client-a", "web", "web", &["10.2.0.2:8080".parse().unwrap()]).unwrap();
+        counters.connection_opened();
+        counters.add_bytes_out(64);
+        counters.observe_connect_latency(Duration::from_millis(8));
+        state.record_auth_failure();
+
+        let snapshot = state.metrics_snapshot();
+        let tunnel = &snapshot.tunnels[0];
+        assert_eq!(tunnel.tunnel, "web/t1");
+        assert_eq!(tunnel.bytes_sent, 64);
+        assert_eq!(tunnel.latency.count, 1);
+
+        let router = snapshot.router.unwrap();
+        assert_eq!(router.connected_clients, 1);
+        assert_eq!(router.auth_failures, 1);
+    }
+}
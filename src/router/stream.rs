@@ -0,0 +1,333 @@
+// Clientからの1接続（ストリーム）と転送先の中継
+//
+// 新しいconnection_idのTunnelDataが届いたら転送先へ接続し、以降は転送先からの受信を
+// TunnelDataでClientへ返す。空のTunnelDataはその向きの終端（half-close）を表す。
+// 再開が有効なセッションでは、Clientとの接続が切れても転送先との接続は猶予期間だけ残し、
+// その間に転送先から届いたデータは再送用バッファに溜めて再開後に送る。
+// 圧縮を合意したトンネルでは、転送先からのデータもそのトンネルの圧縮器で圧縮して返す
+
+use super::{Router, TunnelCounters};
+use crate::protocol::messages::{Message, MessagePayload, MessageType, MessageVersion, TunnelConfig, TunnelData};
+use crate::protocol::{PayloadCompressor, ResumableSession, StreamAck};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+// 転送先への接続を待つ時間
+const SOURCE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 転送先へ書き込み待ちのチャンク数
+const STREAM_QUEUE: usize = 64;
+
+// 1回の読み込みの上限（TunnelDataがメッセージサイズの上限に収まるように）
+const MAX_CHUNK_SIZE: usize = 256 * 1024;
+
+pub(super) type StreamKey = (Uuid, Uuid);
+
+type Outbound = Option<(mpsc::Sender<Message>, MessageVersion)>;
+
+// ストリームからClientへの送信口。再開を待つ間は送らずに再送用バッファへ記録だけする
+pub(super) struct ClientLink {
+    // 接続中の送信キューと合意したバージョン
+    outbound: tokio::sync::Mutex<Outbound>,
+    session: Arc<Mutex<ResumableSession>>,
+    // 送信データを再送用に保持するか（チケットを発行したセッションのみ）
+    resumable: bool,
+}
+
+impl ClientLink {
+    pub(super) fn new(
+        outbound: mpsc::Sender<Message>,
+        version: MessageVersion,
+        session: Option<Arc<Mutex<ResumableSession>>>,
+    ) -> Self {
+        let resumable = session.is_some();
+        Self {
+            outbound: tokio::sync::Mutex::new(Some((outbound, version))),
+            // 再開しないセッションでもsequenceの採番には使う
+            session: session.unwrap_or_else(|| Arc::new(Mutex::new(ResumableSession::new(0)))),
+            resumable,
+        }
+    }
+
+    fn session(&self) -> std::sync::MutexGuard<'_, ResumableSession> {
+        self.session.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn send_data(&self, key: StreamKey, payload: &[u8], compressor: Option<&Mutex<PayloadCompressor>>) {
+        // 付け替え中は待たせ、再送より先に新しいデータが届かないようにする
+        let outbound = self.outbound.lock().await;
+        let data = {
+            let mut session = self.session();
+            let sequence = session.next_sequence(key.0, key.1);
+            // 終端は圧縮しない
+            let data = match compressor.filter(|_| !payload.is_empty()) {
+                Some(compressor) => {
+                    let mut compressor = compressor.lock().unwrap_or_else(|e| e.into_inner());
+                    TunnelData::new(key.0, key.1, sequence, payload, Some(&mut compressor))
+                }
+                None => TunnelData::new(key.0, key.1, sequence, payload, None),
+            };
+            if self.resumable {
+                session.record_sent(&data);
+            }
+            data
+        };
+        if let Some((outbound, version)) = outbound.as_ref() {
+            let message = Message::new(MessageType::TunnelData, MessagePayload::TunnelData(data)).with_version(*version);
+            // 接続が閉じていれば再送用バッファに残ったデータを再開後に送る
+            let _ = outbound.send(message).await;
+        }
+    }
+
+    // Clientからのデータを記録する。再開時にClientが再送した重複ならfalse
+    pub(super) fn record_received(&self, data: &TunnelData) -> bool {
+        !self.resumable || self.session().record_received(data)
+    }
+
+    // Clientが受信済みのデータを再送用バッファから外す
+    pub(super) fn acknowledge(&self, acks: &[StreamAck]) {
+        self.session().acknowledge(acks);
+    }
+
+    // 終わったストリームの採番と再送用バッファを解放する
+    fn close_stream(&self, key: StreamKey) {
+        self.session().close_stream(key.0, key.1);
+    }
+
+    // Clientとの接続が切れた。再開まで送信を止める
+    pub(super) async fn detach(&self) {
+        *self.outbound.lock().await = None;
+    }
+
+    // 新しい接続へ付け替える準備。戻り値を持つ間、ストリームからの送信を止める
+    pub(super) async fn hold(&self) -> HeldLink<'_> {
+        HeldLink(self.outbound.lock().await)
+    }
+}
+
+pub(super) struct HeldLink<'a>(tokio::sync::MutexGuard<'a, Outbound>);
+
+impl HeldLink<'_> {
+    // Clientの受信済み位置より後ろを先に送ってから、ストリームの送信を新しい接続へ向ける
+    pub(super) async fn attach(mut self, outbound: mpsc::Sender<Message>, version: MessageVersion, replay: Vec<TunnelData>) {
+        send_replay(&outbound, version, replay).await;
+        *self.0 = Some((outbound, version));
+    }
+}
+
+pub(super) async fn send_replay(outbound: &mpsc::Sender<Message>, version: MessageVersion, replay: Vec<TunnelData>) {
+    for data in replay {
+        let message = Message::new(MessageType::TunnelData, MessagePayload::TunnelData(data)).with_version(version);
+        let _ = outbound.send(message).await;
+    }
+}
+
+// Clientのストリーム一式。再開時は新しい接続へ引き継ぐ
+pub(super) struct StreamSet {
+    pub link: Arc<ClientLink>,
+    // 作成したトンネルの設定（合意した機能に合わせた後のもの）
+    pub tunnels: HashMap<Uuid, TunnelConfig>,
+    // 圧縮を合意したトンネルの、Clientへ送るデータの圧縮器
+    pub compressors: HashMap<Uuid, Arc<Mutex<PayloadCompressor>>>,
+    streams: HashMap<StreamKey, StreamHandle>,
+    closed_tx: mpsc::UnboundedSender<StreamKey>,
+    closed_rx: mpsc::UnboundedReceiver<StreamKey>,
+}
+
+impl StreamSet {
+    pub(super) fn new(link: ClientLink) -> Self {
+        let (closed_tx, closed_rx) = mpsc::unbounded_channel();
+        Self {
+            link: Arc::new(link),
+            tunnels: HashMap::new(),
+            compressors: HashMap::new(),
+            streams: HashMap::new(),
+            closed_tx,
+            closed_rx,
+        }
+    }
+
+    pub(super) fn get_mut(&mut self, key: &StreamKey) -> Option<&mut StreamHandle> {
+        self.streams.get_mut(key)
+    }
+
+    // 転送先への接続を始める（既に開いていればそのストリームを返す）
+    // tunnelは負荷分散で選んだメンバーで、転送先の候補と接続の集計に使う
+    pub(super) fn open(&mut self, router: &Arc<Router>, tunnel: Arc<TunnelCounters>, key: StreamKey) -> &mut StreamHandle {
+        let entry = match self.streams.entry(key) {
+            Entry::Occupied(entry) => return entry.into_mut(),
+            Entry::Vacant(entry) => entry,
+        };
+        let buffer_size = self
+            .tunnels
+            .get(&key.0)
+            .map_or_else(|| TunnelConfig::default().buffer_size, |config| config.buffer_size);
+        let context = StreamContext {
+            router: Arc::clone(router),
+            tunnel,
+            key,
+            link: Arc::clone(&self.link),
+            compressor: self.compressors.get(&key.0).cloned(),
+            buffer_size,
+            closed: self.closed_tx.clone(),
+        };
+        let (to_source, from_client) = mpsc::channel(STREAM_QUEUE);
+        let task = tokio::spawn(relay(context, from_client));
+        entry.insert(StreamHandle { to_source: Some(to_source), task })
+    }
+
+    // 終わったストリームを待って外す
+    pub(super) async fn reap(&mut self) {
+        if let Some(key) = self.closed_rx.recv().await {
+            self.streams.remove(&key);
+        }
+    }
+}
+
+// 接続ハンドラが持つストリームの送信口
+pub(super) struct StreamHandle {
+    // Clientが終端を送ったらNoneにする（転送先への書き込み側を閉じる）
+    to_source: Option<mpsc::Sender<Vec<u8>>>,
+    task: JoinHandle<()>,
+}
+
+impl StreamHandle {
+    // Clientから受け取ったデータを転送先へ渡す。空なら終端
+    pub(super) async fn forward(&mut self, payload: Vec<u8>) {
+        if payload.is_empty() {
+            self.to_source = None;
+            return;
+        }
+        if let Some(to_source) = &self.to_source {
+            if to_source.send(payload).await.is_err() {
+                self.to_source = None;
+            }
+        }
+    }
+}
+
+impl Drop for StreamHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct StreamContext {
+    router: Arc<Router>,
+    tunnel: Arc<TunnelCounters>,
+    key: StreamKey,
+    link: Arc<ClientLink>,
+    compressor: Option<Arc<Mutex<PayloadCompressor>>>,
+    buffer_size: usize,
+    // 終わったストリームをStreamSetへ知らせる
+    closed: mpsc::UnboundedSender<StreamKey>,
+}
+
+impl StreamContext {
+    async fn send(&self, payload: &[u8]) {
+        self.link.send_data(self.key, payload, self.compressor.as_deref()).await;
+    }
+}
+
+async fn relay(context: StreamContext, mut from_client: mpsc::Receiver<Vec<u8>>) {
+    let Some((connected, socket)) = connect(&context).await else {
+        // どの転送先にも繋がらなければ、Clientへ終端を返して接続を閉じさせる
+        context.send(&[]).await;
+        context.link.close_stream(context.key);
+        let _ = context.closed.send(context.key);
+        return;
+    };
+
+    let (mut source_read, mut source_write) = socket.into_split();
+    let mut buffer = vec![0u8; context.buffer_size.clamp(1024, MAX_CHUNK_SIZE)];
+    let (mut client_open, mut source_open) = (true, true);
+
+    while client_open || source_open {
+        tokio::select! {
+            chunk = from_client.recv(), if client_open => match chunk {
+                Some(chunk) => {
+                    if let Err(e) = source_write.write_all(&chunk).await {
+                        debug!("Failed to write to source {}: {}", connected.source, e);
+                        break;
+                    }
+                    context.tunnel.add_bytes_in(chunk.len() as u64);
+                }
+                None => {
+                    client_open = false;
+                    let _ = source_write.shutdown().await;
+                }
+            },
+            read = source_read.read(&mut buffer), if source_open => match read {
+                Ok(0) | Err(_) => {
+                    source_open = false;
+                    context.send(&[]).await;
+                }
+                Ok(n) => {
+                    context.tunnel.add_bytes_out(n as u64);
+                    context.send(&buffer[..n]).await;
+                }
+            },
+        }
+    }
+    if source_open {
+        context.send(&[]).await;
+    }
+
+    drop(connected);
+    context.link.close_stream(context.key);
+    let _ = context.closed.send(context.key);
+}
+
+// 転送先との接続中の集計。タスクが中断されても閉じた接続として数える
+struct ConnectedSource {
+    router: Arc<Router>,
+    tunnel: Arc<TunnelCounters>,
+    source: SocketAddr,
+}
+
+impl Drop for ConnectedSource {
+    fn drop(&mut self) {
+        self.router.source_closed(&self.tunnel, self.source);
+    }
+}
+
+// 転送先を選んで接続する。失敗したら記録して別の転送先を試す
+async fn connect(context: &StreamContext) -> Option<(ConnectedSource, TcpStream)> {
+    let router = &context.router;
+    let mut tried: Vec<SocketAddr> = Vec::new();
+
+    while let Some(source) = router.select_source(&context.tunnel) {
+        if tried.contains(&source) {
+            break;
+        }
+        tried.push(source);
+
+        let started = Instant::now();
+        match tokio::time::timeout(SOURCE_CONNECT_TIMEOUT, TcpStream::connect(source)).await {
+            Ok(Ok(socket)) => {
+                router.source_connected(&context.tunnel, source, started.elapsed());
+                let connected = ConnectedSource { router: Arc::clone(router), tunnel: Arc::clone(&context.tunnel), source };
+                return Some((connected, socket));
+            }
+            Ok(Err(e)) => debug!("Failed to connect to source {}: {}", source, e),
+            Err(_) => debug!("Connecting to source {} timed out", source),
+        }
+        router.source_connect_failed(&context.tunnel, source);
+    }
+
+    warn!(
+        "No source reachable for tunnel {} (tried {})",
+        context.tunnel.name,
+        tried.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(", ")
+    );
+    None
+}
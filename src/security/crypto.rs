@@ -34,6 +34,9 @@ pub enum Ed25519Error {
     
     #[error("Encoding error: {message}")]
     Encoding { message: String },
+    
+    #[error("Sealed data error: {message}")]
+    Sealing { message: String },
 }
 
 /// Ed25519の結果型
@@ -136,6 +139,33 @@ impl Ed25519KeyPair {
         }
     }
     
+    /// seal_for_public_keyで自分の公開鍵宛てに暗号化されたデータを復号
+    pub fn open_sealed(&self, sealed: &[u8]) -> Ed25519Result<Vec<u8>> {
+        if sealed.len() < PUBLIC_KEY_LENGTH + SEALED_NONCE_LENGTH + ring::aead::AES_256_GCM.tag_len() {
+            return Err(Ed25519Error::Sealing {
+                message: format!("Sealed data is too short: {} bytes", sealed.len())
+            });
+        }
+        
+        let (ephemeral_public, rest) = sealed.split_at(PUBLIC_KEY_LENGTH);
+        let (nonce_bytes, ciphertext) = rest.split_at(SEALED_NONCE_LENGTH);
+        let ephemeral = parse_verifying_key(ephemeral_public)?;
+        let shared = ephemeral.to_montgomery()
+            .mul_clamped(self.signing_key.to_scalar_bytes())
+            .to_bytes();
+        let key = derive_sealing_key(&shared, ephemeral_public, &self.public_key_bytes())?;
+        
+        let nonce = ring::aead::Nonce::try_assume_unique_for_key(nonce_bytes)
+            .map_err(|_| Ed25519Error::Sealing { message: "Invalid nonce length".to_string() })?;
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = key.open_in_place(nonce, ring::aead::Aad::empty(), &mut in_out)
+            .map_err(|_| Ed25519Error::Sealing {
+                message: "Failed to decrypt sealed data (not encrypted for this key or corrupted)".to_string()
+            })?;
+        
+        Ok(plaintext.to_vec())
+    }
+    
     /// 秘密鍵をファイルに保存
     pub fn save_secret_key<P: AsRef<Path>>(&self, path: P) -> Ed25519Result<()> {
         let secret_base64 = self.secret_key_base64();
@@ -287,6 +317,73 @@ pub fn verify_signature(
     }
 }
 
+// 公開鍵宛ての暗号化で使うHKDFのラベルとnonce長
+const SEALED_BOX_INFO: &[u8] = b"conduit sealed box v1";
+const SEALED_NONCE_LENGTH: usize = 12;
+
+/// 公開鍵宛てにデータを暗号化（X25519 + HKDF-SHA256 + AES-256-GCM）
+///
+/// Ed25519の公開鍵をX25519に変換して使い捨ての鍵と鍵共有するため、`conduit init`で生成した
+/// キーペアをそのまま宛先にできます。出力は `一時公開鍵(32) || nonce(12) || 暗号文+タグ` です。
+pub fn seal_for_public_key(public_key_bytes: &[u8], plaintext: &[u8]) -> Ed25519Result<Vec<u8>> {
+    use ring::rand::{SecureRandom, SystemRandom};
+    
+    let recipient = parse_verifying_key(public_key_bytes)?;
+    let ephemeral = SigningKey::generate(&mut OsRng);
+    let ephemeral_public = ephemeral.verifying_key().to_bytes();
+    let shared = recipient.to_montgomery()
+        .mul_clamped(ephemeral.to_scalar_bytes())
+        .to_bytes();
+    let key = derive_sealing_key(&shared, &ephemeral_public, &recipient.to_bytes())?;
+    
+    let mut nonce_bytes = [0u8; SEALED_NONCE_LENGTH];
+    SystemRandom::new().fill(&mut nonce_bytes)
+        .map_err(|_| Ed25519Error::Sealing { message: "Failed to generate nonce".to_string() })?;
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(
+        ring::aead::Nonce::assume_unique_for_key(nonce_bytes),
+        ring::aead::Aad::empty(),
+        &mut in_out,
+    )
+    .map_err(|_| Ed25519Error::Sealing { message: "Failed to encrypt data".to_string() })?;
+    
+    let mut sealed = ephemeral_public.to_vec();
+    sealed.extend_from_slice(&nonce_bytes);
+    sealed.extend_from_slice(&in_out);
+    Ok(sealed)
+}
+
+fn parse_verifying_key(bytes: &[u8]) -> Ed25519Result<VerifyingKey> {
+    let bytes: &[u8; PUBLIC_KEY_LENGTH] = bytes.try_into().map_err(|_| Ed25519Error::KeyParsing {
+        message: format!("Invalid public key length: {} bytes (expected: {} bytes)",
+                       bytes.len(), PUBLIC_KEY_LENGTH)
+    })?;
+    VerifyingKey::from_bytes(bytes).map_err(|e| Ed25519Error::KeyParsing {
+        message: format!("Failed to parse public key: {}", e)
+    })
+}
+
+// 共有秘密から暗号鍵を導出する（両者の公開鍵をsaltに含めて宛先を束縛する）
+fn derive_sealing_key(
+    shared: &[u8; 32],
+    ephemeral_public: &[u8],
+    recipient_public: &[u8],
+) -> Ed25519Result<ring::aead::LessSafeKey> {
+    use ring::{aead, hkdf};
+    
+    // 低位数の点を渡された場合は共有秘密が0になるため拒否する
+    if shared.iter().all(|b| *b == 0) {
+        return Err(Ed25519Error::Sealing { message: "Invalid public key for key agreement".to_string() });
+    }
+    
+    let salt = hkdf::Salt::new(hkdf::HKDF_SHA256, &[ephemeral_public, recipient_public].concat());
+    let prk = salt.extract(shared);
+    let okm = prk
+        .expand(&[SEALED_BOX_INFO], &aead::AES_256_GCM)
+        .map_err(|_| Ed25519Error::Sealing { message: "Failed to derive encryption key".to_string() })?;
+    Ok(aead::LessSafeKey::new(aead::UnboundKey::from(okm)))
+}
+
 /// セキュアな乱数生成
 pub fn generate_random_bytes(length: usize) -> Vec<u8> {
     use rand::RngCore;
@@ -377,6 +474,23 @@ mod tests {
         assert!(is_valid);
     }
 
+    #[test]
+    fn test_seal_for_public_key() {
+        let recipient = Ed25519KeyPair::generate().unwrap();
+        let data = b"tunnel config";
+        
+        let sealed = seal_for_public_key(&recipient.public_key_bytes(), data).unwrap();
+        assert_eq!(recipient.open_sealed(&sealed).unwrap(), data);
+        
+        // 宛先以外の鍵では復号できない
+        let other = Ed25519KeyPair::generate().unwrap();
+        assert!(other.open_sealed(&sealed).is_err());
+        
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(recipient.open_sealed(&tampered).is_err());
+    }
+
     #[test]
     fn test_random_bytes_generation() {
         let bytes1 = generate_random_bytes(32);
@@ -37,6 +37,7 @@ pub enum TlsError {
 pub type TlsResult<T> = Result<T, TlsError>;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct TlsConfig {
     pub cert_file: Option<String>,
     pub key_file: Option<String>,
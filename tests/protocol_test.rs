@@ -2,8 +2,9 @@
 //
 // Router-Client間のコア通信プロトコルの基本テスト
 
-use conduit::protocol::{Message, MessageType, MessagePayload};
+use conduit::protocol::{Message, MessageType, MessagePayload, ProtocolSupport, SUPPORTED_VERSIONS};
 use conduit::protocol::messages::{Heartbeat, ClientRegister};
+use conduit::protocol::negotiation::{Capability, PROTOCOL_V1_0};
 use uuid::Uuid;
 
 #[tokio::test]
@@ -36,6 +37,8 @@ async fn test_client_register_message() {
         signature: "test-signature".to_string(),
         client_version: "1.0.0".to_string(),
         capabilities: vec!["tcp".to_string(), "heartbeat".to_string()],
+        supported_versions: SUPPORTED_VERSIONS.to_vec(),
+        resume: None,
     });
     
     let message = Message::new(MessageType::ClientRegister, register);
@@ -45,4 +48,26 @@ async fn test_client_register_message() {
     
     // サイズ制限超過時のエラーハンドリング検証
     assert!(message.validate(100).is_err());
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_register_from_legacy_client() {
+    // ネゴシエーション導入前のClientはsupported_versionsを送らない
+    let json = r#"{
+        "client_id": "6f1c2e4a-0c8e-4b9e-9f57-2a8f3f6d1b10",
+        "client_name": "old-client",
+        "public_key": "key",
+        "signature": "sig",
+        "client_version": "0.1.0",
+        "capabilities": ["tcp", "heartbeat"]
+    }"#;
+    let register: ClientRegister = serde_json::from_str(json).unwrap();
+    assert!(register.supported_versions.is_empty());
+
+    let negotiated = ProtocolSupport::default()
+        .negotiate(&register.supported_versions, &register.capabilities)
+        .unwrap();
+    assert_eq!(negotiated.version, PROTOCOL_V1_0);
+    assert!(negotiated.supports(Capability::Tcp));
+    assert!(!negotiated.supports(Capability::Udp));
+}